@@ -181,7 +181,8 @@ pub fn find_asset_usages(scenario: ivy::scenario::Scenario, asset_path: &str) ->
             cmd.voice.as_deref(),
         ]
         .iter()
-        .any(|field| field == &Some(asset_path));
+        .any(|field| field == &Some(asset_path))
+            || cmd.layers.iter().any(|layer| layer.image == asset_path);
 
         if matches {
             usages.push(index);
@@ -226,6 +227,11 @@ pub fn find_unused_assets(
                 used_assets.insert(voice.clone());
             }
         }
+        for layer in &cmd.layers {
+            if !layer.image.is_empty() {
+                used_assets.insert(layer.image.clone());
+            }
+        }
     }
 
     let mut unused = Vec::new();
@@ -28,6 +28,7 @@ pub struct EdgeData {
     pub target: String,
     pub edge_type: String,
     pub label: Option<String>,
+    pub annotations: Vec<String>,
 }
 
 #[tauri::command]
@@ -46,8 +47,14 @@ pub fn get_flowchart(scenario: Scenario) -> FlowchartData {
                 NodeType::Choice { options } => {
                     ("choice".to_string(), format!("{} choices", options.len()))
                 }
-                NodeType::Conditional { var, .. } => {
-                    ("conditional".to_string(), format!("if {}", var))
+                NodeType::Conditional { condition } => {
+                    ("conditional".to_string(), format!("if {}", condition))
+                }
+                NodeType::Switch { subject } => {
+                    ("switch".to_string(), format!("switch {}", subject))
+                }
+                NodeType::Random { branches } => {
+                    ("random".to_string(), format!("random ({})", branches))
                 }
                 NodeType::End => ("end".to_string(), "End".to_string()),
             };
@@ -78,6 +85,10 @@ pub fn get_flowchart(scenario: Scenario) -> FlowchartData {
                 EdgeType::Jump => "jump",
                 EdgeType::Choice(_) => "choice",
                 EdgeType::Conditional => "conditional",
+                EdgeType::Else => "else",
+                EdgeType::Case(_) => "case",
+                EdgeType::Default => "default",
+                EdgeType::Random(_) => "random",
             };
 
             EdgeData {
@@ -86,6 +97,7 @@ pub fn get_flowchart(scenario: Scenario) -> FlowchartData {
                 target: format!("node-{}", edge.to.0),
                 edge_type: edge_type.to_string(),
                 label: edge.label.clone(),
+                annotations: edge.annotations.clone(),
             }
         })
         .collect();
@@ -1,6 +1,6 @@
 use chrono::{DateTime, Local, TimeZone};
 use ivy::runtime::save::SaveData;
-use ivy::scenario::parse_scenario;
+use ivy::scenario::{parse_expr, parse_scenario};
 use serde::Serialize;
 use std::collections::HashSet;
 use std::fs;
@@ -108,7 +108,7 @@ pub fn list_save_data(base_dir: &str) -> Result<Vec<SaveDataInfo>, String> {
 
             // Try to parse the save data to get metadata
             let (timestamp, scenario_path) = match fs::read_to_string(&path) {
-                Ok(content) => match serde_json::from_str::<SaveData>(&content) {
+                Ok(content) => match SaveData::from_json(&content) {
                     Ok(save) => (save.timestamp, Some(save.scenario_path)),
                     Err(_) => (0, None),
                 },
@@ -165,14 +165,14 @@ pub fn validate_save_data(save_path: &str, base_dir: Option<&str>) -> SaveDataVa
         }
     };
 
-    // Parse JSON
-    let save_data: SaveData = match serde_json::from_str(&content) {
+    // Parse JSON, migrating older layouts
+    let save_data = match SaveData::from_json(&content) {
         Ok(s) => s,
         Err(e) => {
             issues.push(ValidationIssue {
                 severity: IssueSeverity::Error,
                 code: "JSON_PARSE_ERROR".to_string(),
-                message: "Invalid JSON format".to_string(),
+                message: "Invalid save data".to_string(),
                 details: Some(e.to_string()),
             });
             return SaveDataValidationResult {
@@ -233,10 +233,42 @@ pub fn validate_save_data(save_path: &str, base_dir: Option<&str>) -> SaveDataVa
                         // Variables from set commands
                         if let Some(set) = &cmd.set {
                             scenario_var_refs.insert(set.name.clone());
+                            if let Some(Ok(expr)) = set.expr.as_deref().map(parse_expr) {
+                                for name in expr.variables() {
+                                    scenario_var_refs.insert(name.to_string());
+                                }
+                            }
                         }
                         // Variables from if conditions
                         if let Some(if_cond) = &cmd.if_cond {
-                            scenario_var_refs.insert(if_cond.var.clone());
+                            if let Ok(expr) = if_cond.condition() {
+                                for name in expr.variables() {
+                                    scenario_var_refs.insert(name.to_string());
+                                }
+                            }
+                        }
+                        // Variables from switches
+                        if let Some(switch) = &cmd.switch {
+                            if let Ok(expr) = switch.subject() {
+                                for name in expr.variables() {
+                                    scenario_var_refs.insert(name.to_string());
+                                }
+                            }
+                        }
+                        // Variables from random numbers
+                        if let Some(var) = cmd.random.as_ref().and_then(|r| r.var.as_ref()) {
+                            scenario_var_refs.insert(var.clone());
+                        }
+                        // Variables from choice conditions and assignments
+                        for choice in cmd.choices.iter().flatten() {
+                            if let Some(Ok(expr)) = choice.condition() {
+                                for name in expr.variables() {
+                                    scenario_var_refs.insert(name.to_string());
+                                }
+                            }
+                            for set in &choice.set {
+                                scenario_var_refs.insert(set.name.clone());
+                            }
                         }
                         // Variables from input commands
                         if let Some(input) = &cmd.input {
@@ -29,7 +29,12 @@ pub fn create_empty_scenario(title: String) -> Scenario {
     Scenario {
         title,
         chapters: vec![],
+        characters: std::collections::HashMap::new(),
         modular_characters: std::collections::HashMap::new(),
+        includes: vec![],
+        macros: std::collections::HashMap::new(),
         script: vec![],
+        sources: vec![],
+        origins: vec![],
     }
 }
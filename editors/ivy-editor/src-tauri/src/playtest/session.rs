@@ -6,9 +6,10 @@ use std::sync::Mutex;
 
 use ivy::i18n::LocalizedString;
 use ivy::runtime::display::DisplayState;
+use ivy::runtime::persistent::PERSISTENT_PREFIX;
 use ivy::runtime::save::SaveData;
 use ivy::runtime::state::GameState;
-use ivy::scenario::{CharPosition, Scenario};
+use ivy::scenario::{CharPosition, Scenario, VariableLookup, interpolate_variables};
 use ivy::types::Value;
 use serde::{Deserialize, Serialize};
 
@@ -80,6 +81,8 @@ pub enum PlaytestDisplay {
 pub struct PlaytestChoice {
     pub label: String,
     pub jump: String,
+    /// Why the choice can't be picked, if it's disabled.
+    pub disabled: Option<String>,
 }
 
 /// A history entry for the backlog display.
@@ -357,11 +360,18 @@ impl PlaytestSession {
             .rev()
             .find_map(|cmd| cmd.label.clone());
 
+        let persistent = data
+            .game_state
+            .persistent()
+            .variables()
+            .iter()
+            .map(|(k, v)| (format!("{}{}", PERSISTENT_PREFIX, k), v.clone()));
         let variables: HashMap<String, Value> = data
             .game_state
             .variables()
             .iter()
             .map(|(k, v)| (k.clone(), v.clone()))
+            .chain(persistent)
             .collect();
 
         // Build history entries
@@ -370,11 +380,7 @@ impl PlaytestSession {
             .history()
             .iter()
             .map(|entry| {
-                let speaker = scenario
-                    .script
-                    .get(entry.index)
-                    .and_then(|cmd| cmd.speaker.as_ref())
-                    .map(|s| resolve_localized(s, lang));
+                let speaker = entry.speaker.as_ref().map(|s| resolve_localized(s, lang));
                 PlaytestHistoryEntry {
                     index: entry.index,
                     speaker,
@@ -398,7 +404,7 @@ impl PlaytestSession {
             active: true,
             command_index: data.game_state.current_index(),
             total_commands: scenario.script.len(),
-            display: convert_display(&display, lang),
+            display: convert_display(&display, lang, &data.game_state.script_variables()),
             variables,
             history_count: data.game_state.history().len(),
             can_rollback: data.game_state.can_rollback(),
@@ -432,15 +438,22 @@ fn format_char_pos(pos: CharPosition) -> String {
     }
 }
 
-fn convert_display(display: &DisplayState, lang: &str) -> PlaytestDisplay {
+/// Convert a display state, expanding variable tags the same way the game does.
+fn convert_display(
+    display: &DisplayState,
+    lang: &str,
+    variables: &impl VariableLookup,
+) -> PlaytestDisplay {
+    let interpolate =
+        |s: &LocalizedString| interpolate_variables(&resolve_localized(s, lang), variables);
     match display {
         DisplayState::Text {
             speaker,
             text,
             visual,
         } => PlaytestDisplay::Text {
-            speaker: speaker.as_ref().map(|s| resolve_localized(s, lang)),
-            text: resolve_localized(text, lang),
+            speaker: speaker.as_ref().map(interpolate),
+            text: interpolate(text),
             background: visual.background.clone(),
             character: visual.character.clone(),
             char_pos: Some(format_char_pos(visual.char_pos)),
@@ -454,13 +467,14 @@ fn convert_display(display: &DisplayState, lang: &str) -> PlaytestDisplay {
             timeout,
             default_choice,
         } => PlaytestDisplay::Choices {
-            speaker: speaker.as_ref().map(|s| resolve_localized(s, lang)),
-            text: resolve_localized(text, lang),
+            speaker: speaker.as_ref().map(interpolate),
+            text: interpolate(text),
             choices: choices
                 .iter()
                 .map(|c| PlaytestChoice {
-                    label: resolve_localized(&c.label, lang),
-                    jump: c.jump.clone(),
+                    label: resolve_localized(&c.choice.label, lang),
+                    jump: c.choice.jump.clone(),
+                    disabled: c.disabled.as_ref().map(|r| resolve_localized(r, lang)),
                 })
                 .collect(),
             background: visual.background.clone(),
@@ -239,6 +239,20 @@ export const VariableEditor: React.FC<VariableEditorProps> = ({
                   ))}
                 </select>
               </div>
+              <div className="variable-field">
+                <label>Else jump to</label>
+                <select
+                  value={ifCondition?.else || ""}
+                  onChange={(e) => updateIfCondition({ else: e.target.value || undefined })}
+                >
+                  <option value="">-- Fall through --</option>
+                  {labels.map((label) => (
+                    <option key={label} value={label}>
+                      {label}
+                    </option>
+                  ))}
+                </select>
+              </div>
             </div>
 
             {/* Preview */}
@@ -246,6 +260,7 @@ export const VariableEditor: React.FC<VariableEditorProps> = ({
               <code>
                 if {ifCondition?.var || "var"} == {JSON.stringify(ifCondition?.is ?? "")}{" "}
                 → {ifCondition?.jump || "?"}
+                {ifCondition?.else && ` else → ${ifCondition.else}`}
               </code>
             </div>
           </div>
@@ -31,9 +31,13 @@ function getLocalizedText(text: LocalizedString): string {
 function getCommandPreview(cmd: Command): string {
   if (cmd.label) {return `[${cmd.label}]`;}
   if (cmd.choices) {return `Choices (${cmd.choices.length})`;}
+  if (cmd.call) {return `call ${cmd.call}`;}
+  if (cmd.return) {return "return";}
   if (cmd.jump) {return `-> ${cmd.jump}`;}
-  if (cmd.if) {return `if ${cmd.if.var} == ${cmd.if.is} -> ${cmd.if.jump}`;}
-  if (cmd.set) {return `${cmd.set.name} = ${cmd.set.value}`;}
+  if (cmd.if) {return `if ${cmd.if.expr ?? `${cmd.if.var} == ${cmd.if.is}`} -> ${cmd.if.jump}${cmd.if.else ? ` else -> ${cmd.if.else}` : ""}`;}
+  if (cmd.random) {return cmd.random.var ? `${cmd.random.var} = random ${cmd.random.min}..${cmd.random.max}` : `random (${cmd.random.branches?.length ?? 0})`;}
+  if (cmd.switch) {return `switch ${cmd.switch.expr ?? cmd.switch.var} (${cmd.switch.cases.length} cases)`;}
+  if (cmd.set) {return `${cmd.set.name} ${cmd.set.op && cmd.set.op !== "assign" ? cmd.set.op : "="} ${cmd.set.expr ?? cmd.set.value}`;}
   if (cmd.background) {return `BG: ${cmd.background || "(clear)"}`;}
   if (cmd.character) {return `Char: ${cmd.character || "(clear)"}`;}
   if (cmd.bgm) {return `BGM: ${cmd.bgm || "(stop)"}`;}
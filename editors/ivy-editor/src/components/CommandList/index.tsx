@@ -64,9 +64,9 @@ const typeIcons: Record<string, string> = {
 function getCommandType(cmd: Command): string {
   if (cmd.label) {return "label";}
   if (cmd.choices) {return "choice";}
-  if (cmd.jump) {return "jump";}
-  if (cmd.if) {return "if";}
-  if (cmd.set) {return "set";}
+  if (cmd.jump || cmd.call || cmd.return) {return "jump";}
+  if (cmd.if || cmd.switch || cmd.random?.branches) {return "if";}
+  if (cmd.set || cmd.random) {return "set";}
   if (cmd.background) {return "background";}
   if (cmd.character) {return "character";}
   if (cmd.bgm) {return "bgm";}
@@ -24,6 +24,8 @@ const nodeTypes = {
   label: LabelNode,
   choice: ChoiceNode,
   conditional: ConditionalNode,
+  switch: ConditionalNode,
+  random: ChoiceNode,
   end: EndNode,
 };
 
@@ -32,6 +34,10 @@ const edgeStyles: Record<EdgeType, React.CSSProperties> = {
   jump: { stroke: "#f59e0b", strokeWidth: 2, strokeDasharray: "5,5" },
   choice: { stroke: "#9b59b6", strokeWidth: 2 },
   conditional: { stroke: "#e74c3c", strokeWidth: 2, strokeDasharray: "3,3" },
+  else: { stroke: "#c0392b", strokeWidth: 2, strokeDasharray: "1,3" },
+  case: { stroke: "#e74c3c", strokeWidth: 2, strokeDasharray: "3,3" },
+  default: { stroke: "#c0392b", strokeWidth: 2, strokeDasharray: "1,3" },
+  random: { stroke: "#16a085", strokeWidth: 2, strokeDasharray: "6,2" },
 };
 
 export const FlowchartView: React.FC<Props> = ({ scenario, onNodeClick }) => {
@@ -58,7 +64,7 @@ export const FlowchartView: React.FC<Props> = ({ scenario, onNodeClick }) => {
         id: edge.id,
         source: edge.source,
         target: edge.target,
-        label: edge.label,
+        label: [edge.label, ...edge.annotations].filter(Boolean).join(" / ") || undefined,
         style: edgeStyles[edge.edge_type],
         animated: edge.edge_type === "jump",
       }));
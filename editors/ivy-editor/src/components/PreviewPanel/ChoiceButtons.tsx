@@ -77,10 +77,13 @@ export const ChoiceButtons: React.FC<Props> = ({
         <button
           key={index}
           className={`choice-button ${defaultChoice === index ? "default-choice" : ""}`}
-          disabled={disabled}
+          disabled={disabled || !!choice.disabled}
           onClick={() => handleSelect(index)}
         >
           {choice.label}
+          {choice.disabled && (
+            <span className="disabled-reason"> ({choice.disabled})</span>
+          )}
           {defaultChoice === index && !disabled && timeout !== null && (
             <span className="default-indicator">*</span>
           )}
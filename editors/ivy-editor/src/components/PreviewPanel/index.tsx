@@ -70,7 +70,11 @@ function getPlaytestDisplayInfo(state: PlaytestState) {
         speaker: display.speaker,
         text: display.text,
         nvlMode: false,
-        choices: display.choices.map((c) => ({ label: c.label, jump: c.jump })),
+        choices: display.choices.map((c) => ({
+          label: c.label,
+          jump: c.jump,
+          disabled: c.disabled,
+        })),
         timeout: display.timeout,
         defaultChoice: display.default_choice,
       };
@@ -1,5 +1,5 @@
 import { useState, useMemo } from "react";
-import type { Scenario } from "../../types/scenario";
+import type { Command, Scenario } from "../../types/scenario";
 import "./styles.css";
 
 interface StoryPathAnalyzerProps {
@@ -21,6 +21,34 @@ interface PathIssue {
   label?: string;
 }
 
+/** Labels an `if` (both arms), `switch` (every case and the default) or `random` can jump to. */
+function branchTargets(cmd: Command): string[] {
+  const targets: string[] = [];
+  if (cmd.if) {
+    targets.push(cmd.if.jump);
+    if (cmd.if.else) {
+      targets.push(cmd.if.else);
+    }
+  }
+  if (cmd.switch) {
+    cmd.switch.cases.forEach((c) => targets.push(c.jump));
+    if (cmd.switch.default) {
+      targets.push(cmd.switch.default);
+    }
+  }
+  cmd.random?.branches?.forEach((b) => targets.push(b.jump));
+  return targets;
+}
+
+/** Whether an `if`/`switch`/`random` always jumps instead of falling through. */
+function alwaysBranches(cmd: Command): boolean {
+  return (
+    Boolean(cmd.if?.else) ||
+    Boolean(cmd.switch?.default) ||
+    Boolean(cmd.random?.branches?.length)
+  );
+}
+
 export const StoryPathAnalyzer: React.FC<StoryPathAnalyzerProps> = ({
   scenario,
   onSelectCommand,
@@ -57,14 +85,13 @@ export const StoryPathAnalyzer: React.FC<StoryPathAnalyzerProps> = ({
         }
       }
 
-      // Conditional jump
-      if (cmd.if && typeof cmd.if === "object" && "jump" in cmd.if) {
-        const jump = (cmd.if as { jump: string }).jump;
+      // Conditional and switch jumps
+      branchTargets(cmd).forEach((jump) => {
         const label = labels.get(jump);
         if (label) {
           label.referencedFrom.push(index);
         }
-      }
+      });
 
       // Choice jumps
       if (cmd.choices) {
@@ -98,8 +125,8 @@ export const StoryPathAnalyzer: React.FC<StoryPathAnalyzerProps> = ({
         }
       }
 
-      // If not a jump/choice, flow continues to next command
-      if (!cmd.jump && !cmd.choices) {
+      // If not a jump/choice/exhaustive branch, flow continues to next command
+      if (!cmd.jump && !cmd.choices && !alwaysBranches(cmd)) {
         queue.push(idx + 1);
       }
 
@@ -111,16 +138,13 @@ export const StoryPathAnalyzer: React.FC<StoryPathAnalyzerProps> = ({
         }
       }
 
-      // Handle conditional jump (both paths)
-      if (cmd.if && typeof cmd.if === "object" && "jump" in cmd.if) {
-        const jump = (cmd.if as { jump: string }).jump;
+      // Handle conditional and switch jumps (all arms)
+      branchTargets(cmd).forEach((jump) => {
         const targetLabel = labels.get(jump);
         if (targetLabel) {
           queue.push(targetLabel.definedAt);
         }
-        // Also continue to next command (condition not met)
-        queue.push(idx + 1);
-      }
+      });
 
       // Handle choices
       if (cmd.choices) {
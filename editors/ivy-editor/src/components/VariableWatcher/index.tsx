@@ -53,11 +53,9 @@ export const VariableWatcher: React.FC<VariableWatcherProps> = ({
         }
       }
 
-      // Check for if conditions
-      if (cmd.if) {
-        const varName = typeof cmd.if === "object" && "var" in cmd.if
-          ? (cmd.if as { var: string }).var
-          : "";
+      // Check for if conditions and switches
+      const conditionVars = [cmd.if?.var, cmd.switch?.var];
+      conditionVars.forEach((varName) => {
         if (varName) {
           if (!info.has(varName)) {
             info.set(varName, {
@@ -70,7 +68,7 @@ export const VariableWatcher: React.FC<VariableWatcherProps> = ({
             info.get(varName)!.usedAtIndices.push(index);
           }
         }
-      }
+      });
     });
 
     // Add any variables from runtime that weren't found in static analysis
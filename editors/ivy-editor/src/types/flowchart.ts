@@ -3,9 +3,19 @@ export type NodeType =
   | "label"
   | "choice"
   | "conditional"
+  | "switch"
+  | "random"
   | "end";
 
-export type EdgeType = "sequential" | "jump" | "choice" | "conditional";
+export type EdgeType =
+  | "sequential"
+  | "jump"
+  | "choice"
+  | "conditional"
+  | "else"
+  | "case"
+  | "default"
+  | "random";
 
 export interface NodeData {
   id: string;
@@ -25,6 +35,7 @@ export interface EdgeData {
   target: string;
   edge_type: EdgeType;
   label?: string;
+  annotations: string[];
 }
 
 export interface FlowchartData {
@@ -5,6 +5,7 @@ export type Value = boolean | number | string;
 export interface PlaytestChoice {
   label: string;
   jump: string;
+  disabled: string | null;
 }
 
 // Base visual state fields shared across display types
@@ -1,6 +1,8 @@
 export interface ChoiceInfo {
   label: string;
   jump: string;
+  /** Why the choice can't be picked (playtest only). */
+  disabled?: string | null;
 }
 
 export interface PreviewState {
@@ -59,19 +59,55 @@ export interface Choice {
   label: LocalizedString;
   jump: string;
   default?: boolean;
+  if?: string;
+  disabled_reason?: LocalizedString;
+  once?: boolean;
+  set?: SetVar[];
 }
 
-export type Value = boolean | number | string;
+export type Value = boolean | number | string | Value[] | { [key: string]: Value };
+
+export type SetOp = "assign" | "add" | "sub" | "mul" | "min" | "max" | "concat" | "push" | "remove";
 
 export interface SetVar {
   name: string;
-  value: Value;
+  value?: Value;
+  expr?: string;
+  op?: SetOp;
 }
 
 export interface IfCondition {
-  var: string;
-  is: Value;
+  var?: string;
+  is?: Value;
+  expr?: string;
   jump: string;
+  else?: string;
+}
+
+export interface SwitchCase {
+  is?: Value;
+  min?: Value;
+  max?: Value;
+  jump: string;
+}
+
+export interface RandomBranch {
+  jump: string;
+  weight?: number;
+}
+
+export interface RandomCommand {
+  var?: string;
+  min?: number;
+  max?: number;
+  branches?: RandomBranch[];
+}
+
+export interface Switch {
+  var?: string;
+  expr?: string;
+  cases: SwitchCase[];
+  default?: string;
 }
 
 export interface Input {
@@ -147,6 +183,24 @@ export interface CharIdleAnimation {
   easing?: Easing;
 }
 
+export interface CharacterMove {
+  target?: string;
+  x?: number;
+  y?: number;
+  scale?: number;
+  opacity?: number;
+  tint?: string;
+  duration?: number;
+  easing?: Easing;
+}
+
+export interface LayerCommand {
+  name: string;
+  image: string;
+  z?: number;
+  transition?: CharAnimation;
+}
+
 export interface ModularCharRef {
   name: string;
   [layer: string]: string | number;
@@ -158,13 +212,18 @@ export interface Command {
   text?: LocalizedString;
   choices?: Choice[];
   jump?: string;
+  call?: string;
+  return?: boolean;
   background?: string;
   video_bg?: VideoBackground;
   character?: string;
+  expression?: string;
   char_pos?: CharPosition;
   char_enter?: CharAnimation;
   char_exit?: CharAnimation;
   char_idle?: CharIdleAnimation;
+  move?: CharacterMove[];
+  layers?: LayerCommand[];
   bgm?: string;
   se?: string;
   voice?: string;
@@ -174,6 +233,8 @@ export interface Command {
   shake?: Shake;
   set?: SetVar;
   if?: IfCondition;
+  switch?: Switch;
+  random?: RandomCommand;
   wait?: number;
   timeout?: number;
   input?: Input;
@@ -196,6 +257,14 @@ export interface ChapterDef {
   description?: string;
 }
 
+export interface CharacterDef {
+  name?: LocalizedString;
+  color?: string;
+  sprite?: string;
+  expressions?: Record<string, string>;
+  voice_prefix?: string;
+}
+
 export interface LayerDef {
   name: string;
   images: string[];
@@ -206,10 +275,19 @@ export interface ModularCharDef {
   layers?: LayerDef[];
 }
 
+export interface MacroDef {
+  params?: string[];
+  commands: unknown[];
+}
+
 export interface Scenario {
+  format_version?: number;
   title: string;
   chapters?: ChapterDef[];
+  characters?: Record<string, CharacterDef>;
   modular_characters?: Record<string, ModularCharDef>;
+  includes?: string[];
+  macros?: Record<string, MacroDef>;
   script: Command[];
 }
 
@@ -220,6 +298,8 @@ export interface ValidationIssue {
   message: string;
   command_index?: number;
   label?: string;
+  file?: string;
+  field?: string;
 }
 
 export interface ValidationResult {
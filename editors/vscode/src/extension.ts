@@ -368,7 +368,11 @@ async function runValidation(filePath: string) {
     outputChannel.appendLine(`Validating: ${filePath}`);
     outputChannel.show();
 
-    const validateProcess = cp.spawn(ivyValidatePath, ['--json', filePath], {
+    const args = ['--json', filePath];
+    if (!config.get<boolean>('strict', true)) {
+        args.unshift('--no-strict');
+    }
+    const validateProcess = cp.spawn(ivyValidatePath, args, {
         cwd: path.dirname(filePath)
     });
 
@@ -461,11 +465,15 @@ function startLanguageClient(lspPath: string, context: vscode.ExtensionContext)
     const clientOptions: LanguageClientOptions = {
         documentSelector: [
             { scheme: 'file', language: 'ivy' },
+            { scheme: 'file', language: 'ivy-screenplay' },
             { scheme: 'file', language: 'yaml', pattern: '**/*.ivy.yaml' },
             { scheme: 'file', language: 'yaml', pattern: '**/*.ivy.yml' },
         ],
         synchronize: {
-            fileEvents: vscode.workspace.createFileSystemWatcher('**/*.{ivy.yaml,ivy.yml}'),
+            fileEvents: vscode.workspace.createFileSystemWatcher('**/*.{ivy.yaml,ivy.yml,ivys}'),
+        },
+        initializationOptions: {
+            strict: vscode.workspace.getConfiguration('ivy').get<boolean>('strict', true),
         },
         outputChannel,
     };
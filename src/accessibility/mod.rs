@@ -3,6 +3,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::platform;
+use crate::scenario::markup::strip_markup;
 
 /// Self-voicing mode for screen reader support.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
@@ -67,10 +68,7 @@ impl SelfVoicing {
             return;
         }
 
-        let full_text = match speaker {
-            Some(name) => format!("{}: {}", name, text),
-            None => text.to_string(),
-        };
+        let full_text = spoken_dialogue(speaker, text);
 
         // Avoid repeating the same text
         if full_text == self.last_text {
@@ -102,7 +100,7 @@ impl SelfVoicing {
             choices
                 .iter()
                 .enumerate()
-                .map(|(i, c)| format!("{}. {}", i + 1, c))
+                .map(|(i, c)| format!("{}. {}", i + 1, strip_markup(c)))
                 .collect::<Vec<_>>()
                 .join(", ")
         );
@@ -129,6 +127,15 @@ impl SelfVoicing {
     }
 }
 
+/// Dialogue as read aloud: text tags such as `{b}` or `{w=0.5}` are dropped.
+fn spoken_dialogue(speaker: Option<&str>, text: &str) -> String {
+    let text = strip_markup(text);
+    match speaker {
+        Some(name) => format!("{}: {}", strip_markup(name), text),
+        None => text,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -151,4 +158,13 @@ mod tests {
         assert_eq!(sv.mode(), SelfVoicingMode::Clipboard);
         assert!(sv.mode().is_enabled());
     }
+
+    #[test]
+    fn test_spoken_dialogue_strips_tags() {
+        assert_eq!(
+            spoken_dialogue(Some("Alice"), "Well{w=0.5}... {b}fine{/b}.{nw}"),
+            "Alice: Well... fine."
+        );
+        assert_eq!(spoken_dialogue(None, "{speed=5}Slowly{/speed}"), "Slowly");
+    }
 }
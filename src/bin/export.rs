@@ -0,0 +1,232 @@
+//! CLI tool for exporting ivy scenarios as recording scripts and screenplays.
+//!
+//! Usage:
+//!   ivy-export --lines <scenario.yaml> [-o <directory>]
+//!   ivy-export --fountain <scenario.yaml> [-o <file>]
+//!   ivy-export --html <scenario.yaml> [-o <file>]
+//!
+//! `@key` text is resolved from the language files in the `i18n/` directory
+//! next to the scenario, if there is one.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+use ivy::i18n::Translations;
+use ivy::scenario::{
+    SpokenLine, fountain, html, lines_table, load_scenario, spoken_lines, text_languages,
+};
+
+// ANSI color codes
+const GREEN: &str = "\x1b[32m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+fn print_usage() {
+    eprintln!("ivy-export - Export ivy scenarios for voice recording and proofreading");
+    eprintln!();
+    eprintln!("Usage:");
+    eprintln!("  ivy-export --lines <scenario.yaml> [-o <directory>]  Spoken lines as CSV");
+    eprintln!("  ivy-export --fountain <scenario.yaml> [-o <file>]    Fountain screenplay");
+    eprintln!("  ivy-export --html <scenario.yaml> [-o <file>]        HTML screenplay");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  -h, --help          Show this help message");
+    eprintln!("  -o, --output        Write to a file (a directory with one file per");
+    eprintln!("                      character for --lines) instead of stdout");
+    eprintln!("  --tsv               Write --lines tables as TSV instead of CSV");
+    eprintln!("  --character <id>    Only export the lines of one character");
+    eprintln!("  --lang <code>       Language of screenplay text (default: en)");
+    eprintln!("  --i18n <dir>        Language files for @key text (default: i18n/");
+    eprintln!("                      next to the scenario)");
+    eprintln!("  --no-color          Disable colored output");
+}
+
+/// What to export.
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Lines,
+    Fountain,
+    Html,
+}
+
+/// Write `content` to `path`, or to stdout without one.
+fn write_output(path: Option<&str>, content: &str, use_color: bool) -> Result<(), String> {
+    match path {
+        Some(path) => {
+            fs::write(path, content).map_err(|e| format!("Error writing {}: {}", path, e))?;
+            if use_color {
+                eprintln!("{}{}Wrote{} {}", BOLD, GREEN, RESET, path);
+            } else {
+                eprintln!("Wrote {}", path);
+            }
+            Ok(())
+        }
+        None => {
+            print!("{}", content);
+            Ok(())
+        }
+    }
+}
+
+/// Write one table per character into `dir`.
+fn write_character_tables(
+    dir: &Path,
+    lines: &[SpokenLine],
+    languages: &[String],
+    translations: &Translations,
+    tsv: bool,
+    use_color: bool,
+) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Error creating {}: {}", dir.display(), e))?;
+
+    let mut by_character: BTreeMap<&str, Vec<SpokenLine>> = BTreeMap::new();
+    for line in lines {
+        by_character
+            .entry(&line.character)
+            .or_default()
+            .push(line.clone());
+    }
+    let (separator, extension) = if tsv { ('\t', "tsv") } else { (',', "csv") };
+    for (character, lines) in by_character {
+        let file_name: String = character
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        let path = dir.join(format!("{}.{}", file_name, extension));
+        let table = lines_table(&lines, languages, separator, translations);
+        write_output(Some(&path.to_string_lossy()), &table, use_color)?;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() < 2 {
+        print_usage();
+        return ExitCode::from(1);
+    }
+
+    let mut mode: Option<Mode> = None;
+    let mut output: Option<&str> = None;
+    let mut tsv = false;
+    let mut character: Option<&str> = None;
+    let mut lang = "en";
+    let mut i18n: Option<&str> = None;
+    let mut use_color = true;
+    let mut target: Option<&str> = None;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "-h" | "--help" => {
+                print_usage();
+                return ExitCode::from(0);
+            }
+            "--lines" => mode = Some(Mode::Lines),
+            "--fountain" => mode = Some(Mode::Fountain),
+            "--html" => mode = Some(Mode::Html),
+            "--tsv" => tsv = true,
+            "--no-color" => use_color = false,
+            option @ ("-o" | "--output" | "--character" | "--lang" | "--i18n") => {
+                i += 1;
+                let Some(value) = args.get(i) else {
+                    eprintln!("Missing value after {}", option);
+                    return ExitCode::from(1);
+                };
+                match option {
+                    "--character" => character = Some(value),
+                    "--lang" => lang = value,
+                    "--i18n" => i18n = Some(value),
+                    _ => output = Some(value),
+                }
+            }
+            arg if !arg.starts_with('-') => {
+                target = Some(arg);
+            }
+            arg => {
+                eprintln!("Unknown option: {}", arg);
+                print_usage();
+                return ExitCode::from(1);
+            }
+        }
+        i += 1;
+    }
+
+    let Some(mode) = mode else {
+        eprintln!("Choose what to export with --lines, --fountain or --html");
+        print_usage();
+        return ExitCode::from(1);
+    };
+    let Some(target) = target else {
+        eprintln!("No scenario specified");
+        print_usage();
+        return ExitCode::from(1);
+    };
+
+    let scenario = match load_scenario(target) {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            eprintln!("Error loading {}: {}", target, e);
+            return ExitCode::from(1);
+        }
+    };
+
+    let i18n_dir = match i18n {
+        Some(dir) => Some(Path::new(dir).to_path_buf()),
+        None => Path::new(target)
+            .parent()
+            .map(|dir| dir.join("i18n"))
+            .filter(|dir| dir.is_dir()),
+    };
+    let translations = match i18n_dir {
+        Some(dir) => match Translations::load_dir(&dir) {
+            Ok(translations) => translations,
+            Err(e) => {
+                eprintln!("Error loading translations: {:#}", e);
+                return ExitCode::from(1);
+            }
+        },
+        None => Translations::new(),
+    };
+
+    let result = match mode {
+        Mode::Lines => {
+            let lines: Vec<SpokenLine> = spoken_lines(&scenario, &translations)
+                .into_iter()
+                .filter(|line| character.is_none_or(|id| line.character == id))
+                .collect();
+            let languages = text_languages(&lines, &translations);
+            match output {
+                Some(dir) => write_character_tables(
+                    Path::new(dir),
+                    &lines,
+                    &languages,
+                    &translations,
+                    tsv,
+                    use_color,
+                ),
+                None => {
+                    let separator = if tsv { '\t' } else { ',' };
+                    let table = lines_table(&lines, &languages, separator, &translations);
+                    write_output(None, &table, use_color)
+                }
+            }
+        }
+        Mode::Fountain => {
+            write_output(output, &fountain(&scenario, lang, &translations), use_color)
+        }
+        Mode::Html => write_output(output, &html(&scenario, lang, &translations), use_color),
+    };
+
+    match result {
+        Ok(()) => ExitCode::from(0),
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(1)
+        }
+    }
+}
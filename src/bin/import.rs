@@ -0,0 +1,197 @@
+//! CLI tool for importing Twine (Twee 3) and Ink stories as ivy scenarios.
+//!
+//! Content that can't be converted is reported as warnings.
+//!
+//! Usage:
+//!   ivy-import <story.twee> [-o scenario.yaml]
+//!   ivy-import --format ink <story.txt> [-o scenario.yaml]
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+use ivy::scenario::{
+    ImportFormat, Severity, ValidationIssue, import_scenario, scenario_to_yaml, validate_scenario,
+};
+
+// ANSI color codes
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const GREEN: &str = "\x1b[32m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+fn print_usage() {
+    eprintln!("ivy-import - Convert Twine and Ink stories to ivy scenarios");
+    eprintln!();
+    eprintln!("Usage:");
+    eprintln!("  ivy-import <story.twee> [-o scenario.yaml]  Import a Twee 3 story");
+    eprintln!("  ivy-import <story.ink> [-o scenario.yaml]   Import an Ink story");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  -h, --help       Show this help message");
+    eprintln!("  -o, --output     Write the scenario to a file instead of stdout");
+    eprintln!("  --format <name>  Input format (twee or ink), if not clear from the extension");
+    eprintln!("  --no-color       Disable colored output");
+    eprintln!("  --quiet, -q      Only output errors (suppress warnings)");
+}
+
+fn print_issue(issue: &ValidationIssue, use_color: bool) {
+    let (name, color) = match issue.severity {
+        Severity::Error => ("ERROR", RED),
+        Severity::Warning => ("WARNING", YELLOW),
+    };
+    let location = issue
+        .label
+        .as_ref()
+        .map(|label| format!(" ({})", label))
+        .unwrap_or_default();
+    if use_color {
+        eprintln!(
+            "  {}{}{}{}{}: {}",
+            BOLD, color, name, RESET, location, issue.message
+        );
+    } else {
+        eprintln!("  {}{}: {}", name, location, issue.message);
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() < 2 {
+        print_usage();
+        return ExitCode::from(1);
+    }
+
+    let mut format: Option<ImportFormat> = None;
+    let mut output: Option<&str> = None;
+    let mut use_color = true;
+    let mut quiet_mode = false;
+    let mut input: Option<&str> = None;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "-h" | "--help" => {
+                print_usage();
+                return ExitCode::from(0);
+            }
+            "-o" | "--output" => {
+                i += 1;
+                let Some(path) = args.get(i) else {
+                    eprintln!("Missing file after {}", args[i - 1]);
+                    return ExitCode::from(1);
+                };
+                output = Some(path);
+            }
+            "--format" => {
+                i += 1;
+                let Some(name) = args.get(i) else {
+                    eprintln!("Missing format after --format");
+                    return ExitCode::from(1);
+                };
+                let Some(name) = ImportFormat::from_name(name) else {
+                    eprintln!("Unknown format: {} (expected twee or ink)", name);
+                    return ExitCode::from(1);
+                };
+                format = Some(name);
+            }
+            "--no-color" => {
+                use_color = false;
+            }
+            "-q" | "--quiet" => {
+                quiet_mode = true;
+            }
+            arg if !arg.starts_with('-') => {
+                input = Some(arg);
+            }
+            arg => {
+                eprintln!("Unknown option: {}", arg);
+                print_usage();
+                return ExitCode::from(1);
+            }
+        }
+        i += 1;
+    }
+
+    let Some(input) = input else {
+        eprintln!("No input specified");
+        print_usage();
+        return ExitCode::from(1);
+    };
+    let Some(format) = format.or_else(|| ImportFormat::from_path(Path::new(input))) else {
+        eprintln!(
+            "Error: can't tell the format of {} (use --format twee or --format ink)",
+            input
+        );
+        return ExitCode::from(1);
+    };
+
+    let text = match fs::read_to_string(input) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", input, e);
+            return ExitCode::from(1);
+        }
+    };
+    let imported = match import_scenario(format, &text) {
+        Ok(imported) => imported,
+        Err(e) => {
+            eprintln!("Error converting {}: {}", input, e);
+            return ExitCode::from(1);
+        }
+    };
+    let yaml = match scenario_to_yaml(&imported.scenario) {
+        Ok(yaml) => yaml,
+        Err(e) => {
+            eprintln!("Error writing scenario: {}", e);
+            return ExitCode::from(1);
+        }
+    };
+
+    // Conversion warnings, then problems in the converted scenario
+    let validation = validate_scenario(&imported.scenario);
+    let issues: Vec<&ValidationIssue> = imported
+        .issues
+        .iter()
+        .chain(&validation.issues)
+        .filter(|issue| !quiet_mode || matches!(issue.severity, Severity::Error))
+        .collect();
+    for issue in &issues {
+        print_issue(issue, use_color);
+    }
+
+    match output {
+        Some(path) => {
+            if let Err(e) = fs::write(path, yaml) {
+                eprintln!("Error writing {}: {}", path, e);
+                return ExitCode::from(1);
+            }
+            if use_color {
+                eprintln!(
+                    "{}{}Imported{} {} -> {} ({} command(s), {} issue(s))",
+                    BOLD,
+                    GREEN,
+                    RESET,
+                    input,
+                    path,
+                    imported.scenario.script.len(),
+                    issues.len()
+                );
+            } else {
+                eprintln!(
+                    "Imported {} -> {} ({} command(s), {} issue(s))",
+                    input,
+                    path,
+                    imported.scenario.script.len(),
+                    issues.len()
+                );
+            }
+        }
+        None => print!("{}", yaml),
+    }
+
+    ExitCode::from(0)
+}
@@ -1,6 +1,7 @@
 //! Ivy Language Server Protocol (LSP) implementation.
 //!
-//! Provides IDE features for ivy scenario files:
+//! Provides IDE features for ivy scenario files (YAML and `.ivys`
+//! screenplays):
 //! - Diagnostics (validation errors and warnings)
 //! - Go to Definition (label jumps)
 //! - Find References (label references)
@@ -9,6 +10,7 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use anyhow::Result;
 use tokio::sync::RwLock;
@@ -16,20 +18,37 @@ use tower_lsp::jsonrpc;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
-use ivy::scenario::parser::parse_scenario;
-use ivy::scenario::position::PositionMap;
-use ivy::scenario::validator::{Severity, validate_scenario};
+use ivy::scenario::parser::{parse_scenario_source, resolve_includes};
+use ivy::scenario::position::{LinePosition, PositionMap};
+use ivy::scenario::screenplay::is_screenplay;
+use ivy::scenario::span::{LabelSpan, ScenarioSpans, Span};
+use ivy::scenario::validator::{Severity, validate_scenario, validate_scenario_strict};
 
 /// Document state stored by the server.
 struct DocumentState {
     text: String,
     position_map: PositionMap,
+    /// Whether the document is a screenplay rather than YAML.
+    screenplay: bool,
+}
+
+impl DocumentState {
+    fn new(uri: &Url, text: String) -> Self {
+        Self {
+            position_map: PositionMap::from_source(uri.path(), &text),
+            screenplay: is_screenplay(uri.path()),
+            text,
+        }
+    }
 }
 
 /// Ivy Language Server.
 struct IvyLanguageServer {
     client: Client,
     documents: Arc<RwLock<HashMap<Url, DocumentState>>>,
+    /// Report keys that no field is named after (on unless the client sets
+    /// `strict: false` in its initialization options).
+    strict: AtomicBool,
 }
 
 impl IvyLanguageServer {
@@ -37,39 +56,88 @@ impl IvyLanguageServer {
         Self {
             client,
             documents: Arc::new(RwLock::new(HashMap::new())),
+            strict: AtomicBool::new(true),
         }
     }
 
     /// Validate a document and publish diagnostics.
     async fn validate_and_publish(&self, uri: &Url, text: &str) {
-        let diagnostics = self.get_diagnostics(text);
+        let diagnostics = self.get_diagnostics(uri, text);
         self.client
             .publish_diagnostics(uri.clone(), diagnostics, None)
             .await;
     }
 
     /// Get diagnostics from scenario text.
-    fn get_diagnostics(&self, text: &str) -> Vec<Diagnostic> {
-        let position_map = PositionMap::from_yaml(text);
-
-        match parse_scenario(text) {
+    ///
+    /// Scenarios with `includes` are validated together with their included
+    /// files; only issues in this document are reported.
+    fn get_diagnostics(&self, uri: &Url, text: &str) -> Vec<Diagnostic> {
+        let mut position_map = PositionMap::from_source(uri.path(), text);
+
+        let scenario =
+            parse_scenario_source(uri.path(), text).and_then(|scenario| match uri.to_file_path() {
+                Ok(path) if !scenario.includes.is_empty() => {
+                    resolve_includes(scenario, &path.to_string_lossy())
+                }
+                _ => Ok(scenario),
+            });
+
+        match scenario {
             Ok(scenario) => {
-                let result = validate_scenario(&scenario);
+                // Commands of this document come first in the merged script.
+                let local_len = scenario
+                    .sources
+                    .first()
+                    .map_or(scenario.script.len(), |source| source.len);
+                let root = scenario.sources.first().map(|source| source.name.as_str());
+                // Spans of this document as edited, and of its includes as saved.
+                let spans: Vec<ScenarioSpans> =
+                    std::iter::once(std::mem::take(&mut position_map.spans))
+                        .chain(scenario.sources.iter().skip(1).map(ScenarioSpans::read))
+                        .collect();
+                let result = if self.strict.load(Ordering::Relaxed) {
+                    validate_scenario_strict(&scenario)
+                } else {
+                    validate_scenario(&scenario)
+                };
                 result
                     .issues
                     .iter()
+                    .filter(|issue| match issue.command_index {
+                        Some(index) => index < local_len,
+                        None => issue.file.is_none() || issue.file.as_deref() == root,
+                    })
                     .map(|issue| {
-                        let range = if let Some(index) = issue.command_index {
-                            position_map
-                                .get_command_position(index)
-                                .map(|pos| Range {
-                                    start: Position::new(pos.line, pos.column),
-                                    end: Position::new(pos.line, pos.column + 1),
-                                })
-                                .unwrap_or_default()
-                        } else {
-                            Range::default()
+                        // The field the issue is about, if it is in this document.
+                        let field = issue
+                            .locate(&scenario, &spans)
+                            .filter(|location| {
+                                location.file.is_none() || location.file.as_deref() == root
+                            })
+                            .map(|location| location.field);
+                        // Issues in expanded commands are shown at the macro
+                        // call, with the macro definition as related info.
+                        let (written, definition) = issue
+                            .command_index
+                            .map(|index| position_map.locate_command(&scenario, index))
+                            .unwrap_or_default();
+                        let origin = issue
+                            .command_index
+                            .and_then(|index| scenario.macro_origin(index));
+                        let range = match (origin, field) {
+                            (None, Some(field)) => span_range(text, field.span()),
+                            _ => written.map(|pos| line_range(text, pos)).unwrap_or_default(),
                         };
+                        let related_information = origin.and_then(|origin| {
+                            let range = field
+                                .map(|field| span_range(text, field.span()))
+                                .or(definition.map(|pos| line_range(text, pos)))?;
+                            Some(vec![DiagnosticRelatedInformation {
+                                location: Location::new(uri.clone(), range),
+                                message: format!("expanded from macro '{}'", origin.name),
+                            }])
+                        });
 
                         Diagnostic {
                             range,
@@ -79,6 +147,7 @@ impl IvyLanguageServer {
                             }),
                             source: Some("ivy".to_string()),
                             message: issue.message.clone(),
+                            related_information,
                             ..Default::default()
                         }
                     })
@@ -102,6 +171,55 @@ impl IvyLanguageServer {
     }
 }
 
+/// Convert a position in `text`, whose column counts characters, to an LSP
+/// position, whose character counts UTF-16 code units.
+fn lsp_position(text: &str, pos: LinePosition) -> Position {
+    let line = text.lines().nth(pos.line as usize).unwrap_or_default();
+    let character: usize = line
+        .chars()
+        .take(pos.column as usize)
+        .map(char::len_utf16)
+        .sum();
+    Position::new(pos.line, character as u32)
+}
+
+/// Convert an LSP position in `text` to a position whose column counts
+/// characters.
+fn line_position(text: &str, position: Position) -> LinePosition {
+    let line = text.lines().nth(position.line as usize).unwrap_or_default();
+    let column = line[..byte_offset(line, position.character)]
+        .chars()
+        .count();
+    LinePosition::new(position.line, column as u32)
+}
+
+/// A one-character range at a line position.
+fn line_range(text: &str, pos: LinePosition) -> Range {
+    Range {
+        start: lsp_position(text, pos),
+        end: lsp_position(text, LinePosition::new(pos.line, pos.column + 1)),
+    }
+}
+
+/// The range of a span.
+fn span_range(text: &str, span: Span) -> Range {
+    Range {
+        start: lsp_position(text, span.start),
+        end: lsp_position(text, span.end),
+    }
+}
+
+/// The range of `length` characters from a position.
+fn text_range(text: &str, pos: LinePosition, length: usize) -> Range {
+    let end = LinePosition::new(pos.line, pos.column + length as u32);
+    span_range(text, Span::new(pos, end))
+}
+
+/// The range of a label's field, from its key to the end of the label.
+fn label_range(text: &str, label: &LabelSpan) -> Range {
+    span_range(text, Span::new(label.field.start(), label.field.value.end))
+}
+
 /// Extract line number from serde_yaml error messages.
 fn extract_parse_error_position(error: &str) -> (u32, String) {
     // serde_yaml errors often contain "at line X column Y".
@@ -118,7 +236,16 @@ fn extract_parse_error_position(error: &str) -> (u32, String) {
 
 #[tower_lsp::async_trait]
 impl LanguageServer for IvyLanguageServer {
-    async fn initialize(&self, _: InitializeParams) -> jsonrpc::Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> jsonrpc::Result<InitializeResult> {
+        if let Some(strict) = params
+            .initialization_options
+            .as_ref()
+            .and_then(|options| options.get("strict"))
+            .and_then(|strict| strict.as_bool())
+        {
+            self.strict.store(strict, Ordering::Relaxed);
+        }
+
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
                 text_document_sync: Some(TextDocumentSyncCapability::Options(
@@ -164,13 +291,7 @@ impl LanguageServer for IvyLanguageServer {
         // Store document state.
         {
             let mut docs = self.documents.write().await;
-            docs.insert(
-                uri.clone(),
-                DocumentState {
-                    position_map: PositionMap::from_yaml(&text),
-                    text: text.clone(),
-                },
-            );
+            docs.insert(uri.clone(), DocumentState::new(&uri, text.clone()));
         }
 
         self.validate_and_publish(&uri, &text).await;
@@ -184,13 +305,7 @@ impl LanguageServer for IvyLanguageServer {
             // Update document state.
             {
                 let mut docs = self.documents.write().await;
-                docs.insert(
-                    uri.clone(),
-                    DocumentState {
-                        position_map: PositionMap::from_yaml(&text),
-                        text: text.clone(),
-                    },
-                );
+                docs.insert(uri.clone(), DocumentState::new(&uri, text.clone()));
             }
 
             self.validate_and_publish(&uri, &text).await;
@@ -222,8 +337,27 @@ impl LanguageServer for IvyLanguageServer {
             return Ok(None);
         };
 
+        if !doc.screenplay {
+            let spans = &doc.position_map.spans;
+            let pos = line_position(&doc.text, position);
+            let Some(label) = spans.label_at(pos) else {
+                return Ok(None);
+            };
+            return Ok(spans
+                .labels()
+                .into_iter()
+                .find(|definition| definition.label == label.label)
+                .map(|definition| {
+                    GotoDefinitionResponse::Scalar(Location {
+                        uri: uri.clone(),
+                        range: label_range(&doc.text, &definition),
+                    })
+                }));
+        }
+
         // Find the label at the cursor position.
-        let Some(label) = find_label_at_position(&doc.text, position) else {
+        let Some(label) = find_screenplay_label_at_position(&doc.text, position, &doc.position_map)
+        else {
             return Ok(None);
         };
 
@@ -232,12 +366,10 @@ impl LanguageServer for IvyLanguageServer {
             return Ok(None);
         };
 
+        // "# " + label
         Ok(Some(GotoDefinitionResponse::Scalar(Location {
             uri: uri.clone(),
-            range: Range {
-                start: Position::new(def_pos.line, def_pos.column),
-                end: Position::new(def_pos.line, def_pos.column + label.len() as u32 + 7), // "label: " + label
-            },
+            range: text_range(&doc.text, def_pos, label.chars().count() + 2),
         })))
     }
 
@@ -250,30 +382,40 @@ impl LanguageServer for IvyLanguageServer {
             return Ok(None);
         };
 
-        // Find the label at the cursor position (could be definition or reference).
-        let label = find_label_at_position(&doc.text, position)
-            .or_else(|| find_label_definition_at_position(&doc.text, position));
-
-        let Some(label) = label else {
-            return Ok(None);
-        };
-
-        // Find all references to this label.
-        let Some(refs) = doc.position_map.get_label_references(&label) else {
-            return Ok(None);
+        let locations: Vec<Location> = if doc.screenplay {
+            // Find the label at the cursor position (could be definition or reference).
+            let Some(label) =
+                find_screenplay_label_at_position(&doc.text, position, &doc.position_map)
+            else {
+                return Ok(None);
+            };
+            let Some(refs) = doc.position_map.get_label_references(&label) else {
+                return Ok(None);
+            };
+            refs.iter()
+                .map(|&pos| Location {
+                    uri: uri.clone(),
+                    range: text_range(&doc.text, pos, label.chars().count()),
+                })
+                .collect()
+        } else {
+            // The label defined or referenced at the cursor position.
+            let spans = &doc.position_map.spans;
+            let pos = line_position(&doc.text, position);
+            let Some(label) = spans.label_at(pos) else {
+                return Ok(None);
+            };
+            spans
+                .label_references()
+                .iter()
+                .filter(|reference| reference.label == label.label)
+                .map(|reference| Location {
+                    uri: uri.clone(),
+                    range: label_range(&doc.text, reference),
+                })
+                .collect()
         };
 
-        let locations: Vec<Location> = refs
-            .iter()
-            .map(|pos| Location {
-                uri: uri.clone(),
-                range: Range {
-                    start: Position::new(pos.line, pos.column),
-                    end: Position::new(pos.line, pos.column + label.len() as u32 + 6), // "jump: " + label
-                },
-            })
-            .collect();
-
         if locations.is_empty() {
             Ok(None)
         } else {
@@ -293,10 +435,15 @@ impl LanguageServer for IvyLanguageServer {
             return Ok(None);
         };
 
-        let context = determine_completion_context(&doc.text, position);
+        let context = if doc.screenplay {
+            determine_screenplay_completion_context(&doc.text, position)
+        } else {
+            determine_completion_context(&doc.text, position)
+        };
 
         let items = match context {
             CompletionContext::YamlKey => get_yaml_key_completions(),
+            CompletionContext::Directive => get_directive_completions(),
             CompletionContext::LabelReference => get_label_completions(&doc.position_map),
             CompletionContext::CharPosition => get_char_position_completions(),
             CompletionContext::Easing => get_easing_completions(),
@@ -315,8 +462,15 @@ impl LanguageServer for IvyLanguageServer {
             return Ok(None);
         };
 
-        // Find the YAML key at the cursor position.
-        let Some(key) = find_yaml_key_at_position(&doc.text, position) else {
+        // Find the YAML key (or screenplay directive) at the cursor position.
+        let key = if doc.screenplay {
+            find_directive_at_position(&doc.text, position)
+                .and_then(directive_field)
+                .map(str::to_string)
+        } else {
+            find_yaml_key_at_position(&doc.text, position)
+        };
+        let Some(key) = key else {
             return Ok(None);
         };
 
@@ -334,40 +488,88 @@ impl LanguageServer for IvyLanguageServer {
     }
 }
 
-/// Find a label reference (jump target) at the given position.
-fn find_label_at_position(text: &str, position: Position) -> Option<String> {
-    let lines: Vec<&str> = text.lines().collect();
-    let line = lines.get(position.line as usize)?;
+/// Find a label defined or referenced at the given position of a screenplay.
+fn find_screenplay_label_at_position(
+    text: &str,
+    position: Position,
+    position_map: &PositionMap,
+) -> Option<String> {
+    let line = text.lines().nth(position.line as usize)?;
+    let is_label_char = |c: char| !c.is_whitespace() && c != '#' && c != '>';
+    let column = byte_offset(line, position.character);
+    let start = line[..column]
+        .rfind(|c: char| !is_label_char(c))
+        .map_or(0, |index| index + 1);
+    let end = line[column..]
+        .find(|c: char| !is_label_char(c))
+        .map_or(line.len(), |index| column + index);
+    let word = line.get(start..end)?;
+
+    (position_map.labels.contains_key(word) || position_map.label_references.contains_key(word))
+        .then(|| word.to_string())
+}
 
-    // Check if this line contains a jump reference.
-    if let Some(jump_pos) = line.find("jump:") {
-        let value_start = jump_pos + 5;
-        let value = line[value_start..].trim();
-        let label = value.trim_matches(|c| c == '"' || c == '\'');
-        if !label.is_empty() {
-            return Some(label.to_string());
+/// Convert an LSP character offset (in UTF-16 code units) to a byte offset
+/// into the line, clamped to its end.
+fn byte_offset(line: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (index, c) in line.char_indices() {
+        if units >= character as usize {
+            return index;
         }
+        units += c.len_utf16();
     }
-
-    None
+    line.len()
 }
 
-/// Find a label definition at the given position.
-fn find_label_definition_at_position(text: &str, position: Position) -> Option<String> {
-    let lines: Vec<&str> = text.lines().collect();
-    let line = lines.get(position.line as usize)?;
-
-    // Check if this line contains a label definition.
-    if let Some(label_pos) = line.find("label:") {
-        let value_start = label_pos + 6;
-        let value = line[value_start..].trim();
-        let label = value.trim_matches(|c| c == '"' || c == '\'');
-        if !label.is_empty() {
-            return Some(label.to_string());
-        }
-    }
+/// Find the `@` directive on the line at the given position of a screenplay.
+fn find_directive_at_position(text: &str, position: Position) -> Option<&str> {
+    let line = text.lines().nth(position.line as usize)?;
+    let directive = line.trim_start().strip_prefix('@')?;
+    directive.split_whitespace().next()
+}
 
-    None
+/// Screenplay directives and the YAML fields they set.
+const DIRECTIVES: &[(&str, &str, &str)] = &[
+    ("title", "title", "Scenario title"),
+    ("include", "includes", "Include another scenario file"),
+    ("bg", "background", "Background image path (none = clear)"),
+    (
+        "char",
+        "character",
+        "Character image or id, expression and position",
+    ),
+    ("bgm", "bgm", "Background music file path (none = stop)"),
+    ("se", "se", "Sound effect file path"),
+    ("voice", "voice", "Voice audio file path"),
+    (
+        "transition",
+        "transition",
+        "Screen transition type and duration",
+    ),
+    ("nvl", "nvl", "NVL mode on, off or clear"),
+    (
+        "set",
+        "set",
+        "Set a variable: name = expression (or +=, -=, *=)",
+    ),
+    (
+        "if",
+        "if",
+        "Conditional jump: condition -> label [else label]",
+    ),
+    ("jump", "jump", "Jump to a label"),
+    ("call", "call", "Call a label as a subroutine"),
+    ("return", "return", "Return from a subroutine call"),
+    ("wait", "wait", "Wait for specified seconds"),
+];
+
+/// The YAML field a screenplay directive sets.
+fn directive_field(directive: &str) -> Option<&'static str> {
+    DIRECTIVES
+        .iter()
+        .find(|(name, _, _)| *name == directive)
+        .map(|(_, field, _)| *field)
 }
 
 /// Find the YAML key at the given position.
@@ -394,6 +596,7 @@ enum CompletionContext {
     LabelReference,
     CharPosition,
     Easing,
+    Directive,
     None,
 }
 
@@ -407,7 +610,10 @@ fn determine_completion_context(text: &str, position: Position) -> CompletionCon
     let trimmed = line.trim();
 
     // Check for specific field contexts.
-    if trimmed.starts_with("jump:") || trimmed.contains("jump:") {
+    if ["jump:", "call:", "else:"]
+        .iter()
+        .any(|key| trimmed.contains(key))
+    {
         return CompletionContext::LabelReference;
     }
     if trimmed.starts_with("char_pos:") || trimmed.contains("char_pos:") {
@@ -431,6 +637,38 @@ fn determine_completion_context(text: &str, position: Position) -> CompletionCon
     CompletionContext::None
 }
 
+/// Determine what kind of completion to provide in a screenplay.
+fn determine_screenplay_completion_context(text: &str, position: Position) -> CompletionContext {
+    let Some(line) = text.lines().nth(position.line as usize) else {
+        return CompletionContext::None;
+    };
+    let before = &line[..byte_offset(line, position.character)];
+    let trimmed = before.trim_start();
+
+    if trimmed.contains("->") || trimmed.starts_with("@jump ") || trimmed.starts_with("@call ") {
+        return CompletionContext::LabelReference;
+    }
+    if trimmed.starts_with('@') && !trimmed.contains(' ') {
+        return CompletionContext::Directive;
+    }
+    CompletionContext::None
+}
+
+/// Get completions for screenplay directives.
+fn get_directive_completions() -> Vec<CompletionItem> {
+    DIRECTIVES
+        .iter()
+        .map(|(name, _, doc)| CompletionItem {
+            label: format!("@{}", name),
+            kind: Some(CompletionItemKind::KEYWORD),
+            detail: Some(doc.to_string()),
+            insert_text: Some(format!("{} ", name)),
+            insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
+            ..Default::default()
+        })
+        .collect()
+}
+
 /// Get completions for YAML keys.
 fn get_yaml_key_completions() -> Vec<CompletionItem> {
     let keys = [
@@ -438,9 +676,15 @@ fn get_yaml_key_completions() -> Vec<CompletionItem> {
         ("speaker", "Character name speaking"),
         ("label", "Define a jump target label"),
         ("jump", "Unconditional jump to a label"),
+        ("call", "Call a label as a subroutine"),
+        ("return", "Return from a subroutine call"),
+        ("macro", "Insert the commands of a macro"),
         ("background", "Background image path"),
-        ("character", "Character sprite image path"),
+        ("character", "Character sprite image path or character id"),
+        ("expression", "Expression of the character or speaker"),
         ("char_pos", "Character position (left/center/right)"),
+        ("move", "Tween characters already on screen"),
+        ("layers", "Show, change or hide named image layers"),
         ("choices", "Present choices to the player"),
         ("bgm", "Background music file path"),
         ("se", "Sound effect file path"),
@@ -452,6 +696,8 @@ fn get_yaml_key_completions() -> Vec<CompletionItem> {
         ("nvl_clear", "Clear NVL text buffer"),
         ("set", "Set a variable value"),
         ("if_cond", "Conditional jump based on variable"),
+        ("switch", "Jump to a label chosen by a variable's value"),
+        ("random", "Random number or weighted random jump"),
         ("input", "Get text input from player"),
         ("camera", "Camera pan/zoom/tilt effect"),
         ("ambient", "Ambient audio layers"),
@@ -535,13 +781,19 @@ fn get_field_documentation(field: &str) -> Option<&'static str> {
             "**text** - Display text\n\n\
             Text to show in the text box. Supports:\n\
             - `{color:red}text{/color}` - Colored text\n\
+            - `{b}`, `{i}`, `{size:1.4}` - Bold, italic and scaled text (closed by `{/b}`, `{/i}`, `{/size}`)\n\
+            - `{shake}`, `{wave}` - Animated characters (closed by `{/shake}`, `{/wave}`)\n\
+            - `{w=0.5}`, `{speed=10}...{/speed}`, `{nw}` - Pause, typing speed, advance without waiting\n\
             - `{ruby:漢字:かんじ}` - Ruby (furigana)\n\
-            - `{var:name}` - Variable interpolation\n\n\
+            - `{var:name}` - Variable interpolation (`{var:gold:,}`, `{var:day:03}`, `{var:name:upper}`)\n\
+            - `{if:flag}...{else}...{/if}` - Inline conditional\n\
+            - `{plural:count|# item|# items}` - Pluralization\n\n\
             ```yaml\ntext: \"Hello, world!\"\n```",
         ),
         "speaker" => Some(
             "**speaker** - Character name\n\n\
-            Name displayed above the text box.\n\n\
+            Name displayed above the text box. The id of a character under \
+            `characters:` shows its name in its colour and prefixes `voice`.\n\n\
             ```yaml\nspeaker: \"Alice\"\ntext: \"Hello!\"\n```",
         ),
         "label" => Some(
@@ -563,21 +815,109 @@ fn get_field_documentation(field: &str) -> Option<&'static str> {
         ),
         "character" => Some(
             "**character** - Character sprite\n\n\
-            Display a character sprite.\n\
+            Display a character sprite, by path or by the id of a character \
+            under `characters:`.\n\
             - Omit to keep previous character\n\
             - Use `\"\"` to clear\n\n\
             ```yaml\ncharacter: \"assets/char.png\"\nchar_pos: center\n```",
         ),
+        "expression" => Some(
+            "**expression** - Character expression\n\n\
+            Show one of the `expressions` of the character in `character`, or \
+            without one, change the speaker's sprite wherever it is shown.\n\n\
+            ```yaml\nspeaker: alice\nexpression: smile\ntext: \"Hello!\"\n```",
+        ),
         "char_pos" => Some(
             "**char_pos** - Character position\n\n\
             Position of the character sprite.\n\
             Values: `left`, `center`, `right`\n\n\
             ```yaml\nchar_pos: center\n```",
         ),
+        "move" => Some(
+            "**move** - Move characters\n\n\
+            Tween characters already on screen to a new `x`/`y`, `scale`, \
+            `opacity` or `tint` over `duration` seconds with `easing`. `target` is \
+            an index into `characters`, an image path or a character id; without \
+            one the single `character` moves. The result stays until the character \
+            is replaced.\n\n\
+            ```yaml\nmove:\n  - target: alice\n    x: 0.3\n    opacity: 0.6\n    duration: 1.0\n    easing: ease_out\n```",
+        ),
+        "layers" => Some(
+            "**layers** - Image layers\n\n\
+            In a command, shows, changes or hides named full-screen images such as \
+            event CGs, overlays and foreground frames (under `modular_characters`, \
+            the sprite layers of a character). Layers with a `z` below 0 are drawn \
+            behind the characters, the rest in front of them. An empty `image` \
+            hides the layer; `transition` fades or slides it in and out. Layer \
+            images are unlocked in the gallery.\n\n\
+            ```yaml\nlayers:\n  - name: cg\n    image: \"assets/cg/kiss.png\"\n    transition:\n      type: fade\n      duration: 1.0\n```",
+        ),
         "choices" => Some(
             "**choices** - Player choices\n\n\
-            Present choices to the player.\n\n\
-            ```yaml\nchoices:\n  - label: \"Go left\"\n    jump: left_path\n  - label: \"Go right\"\n    jump: right_path\n```",
+            Present choices to the player. A choice with `if` is only available while \
+            the expression holds (greyed out with `disabled_reason`, hidden otherwise); \
+            `once: true` hides it after it has been picked, and `set` lists assignments \
+            applied when it is picked.\n\n\
+            ```yaml\nchoices:\n  - label: \"Go left\"\n    jump: left_path\n  - label: \"Buy sword\"\n    jump: sword\n    if: \"gold >= 10\"\n    disabled_reason: \"Not enough gold\"\n```",
+        ),
+        "if" => Some(
+            "**if** - Conditional jump\n\n\
+            Jump when a condition is true. Use `var`/`is` for a simple equality \
+            check or `expr` for comparisons and logic (`==`, `!=`, `<`, `<=`, \
+            `>`, `>=`, `and`, `or`, `not`).\n\n\
+            `else` names a label to jump to when the condition is false \
+            (otherwise execution falls through).\n\n\
+            ```yaml\nif:\n  expr: \"affection > 5 and met_alice\"\n  jump: alice_route\n  else: common_route\n```",
+        ),
+        "switch" => Some(
+            "**switch** - Multi-way jump\n\n\
+            Jump to the label of the first case matching `var` (or `expr`). A case \
+            matches an exact value with `is` or an inclusive range with `min`/`max`. \
+            `default` is used when nothing matches; without it execution falls through.\n\n\
+            ```yaml\nswitch:\n  var: affection\n  cases:\n    - { min: 10, jump: best_end }\n    - { min: 5, max: 9, jump: good_end }\n  default: normal_end\n```",
+        ),
+        "random" => Some(
+            "**random** - Random number or random jump\n\n\
+            Store a random integer in `var` (between `min` and `max`, inclusive), or \
+            jump to one of `branches`, picked by `weight` (default 1). The generator \
+            is saved with the game, so loading or rolling back replays the same result.\n\n\
+            ```yaml\nrandom:\n  branches:\n    - { jump: wolf, weight: 3 }\n    - { jump: quiet_night }\n```",
+        ),
+        "call" => Some(
+            "**call** - Call a subroutine\n\n\
+            Jump to a label and remember where to come back. A later `return: true` \
+            resumes after this command.\n\n\
+            ```yaml\n- call: shop_visit\n```",
+        ),
+        "return" => Some(
+            "**return** - Return from a subroutine\n\n\
+            Resume after the most recent `call`.\n\n\
+            ```yaml\n- label: shop_visit\n  text: \"Welcome!\"\n- return: true\n```",
+        ),
+        "macro" => Some(
+            "**macro** - Expand a macro\n\n\
+            Replace this entry with the commands of a macro declared under `macros:`, \
+            substituting `{param}` with the given arguments.\n\n\
+            ```yaml\n- macro:\n    name: scene_change\n    args:\n      bg: room.png\n```",
+        ),
+        "characters" => Some(
+            "**characters** - Characters\n\n\
+            At the top level, defines characters by id with a `name`, a name \
+            `color`, a default `sprite`, `expressions` and a `voice_prefix`. In a \
+            command, lists the character sprites to show, each with an `image`, a \
+            `pos` preset or `x`/`y` screen fractions, `scale`, `flip` and `z` order.\n\n\
+            ```yaml\ncharacters:\n  alice:\n    name: \"Alice\"\n    color: \"#ff8080\"\n    sprite: \"assets/alice.png\"\n    expressions:\n      smile: \"assets/alice_smile.png\"\n```",
+        ),
+        "macros" => Some(
+            "**macros** - Reusable command lists\n\n\
+            Each macro has optional `params` and a list of `commands`.\n\n\
+            ```yaml\nmacros:\n  scene_change:\n    params: [bg]\n    commands:\n      - background: \"{bg}\"\n```",
+        ),
+        "set" => Some(
+            "**set** - Set a variable\n\n\
+            Assign a literal `value`, compute one with `expr`, or update the \
+            current value with `op` (`add`, `sub`, `mul`, `min`, `max`, `concat`).\n\n\
+            ```yaml\nset:\n  name: affection\n  op: add\n  value: 5\n```",
         ),
         "bgm" => Some(
             "**bgm** - Background music\n\n\
@@ -625,3 +965,72 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screenplay_positions_inside_multi_byte_text() {
+        let text = "# 始まり\nアリス \"こんにちは\"\n> はい -> 始まり\n";
+        let position_map = PositionMap::from_source("story.ivys", text);
+
+        // Every offset of the dialogue line, including ones that fall
+        // inside a character as bytes
+        for character in 0..12 {
+            let position = Position::new(1, character);
+            assert_eq!(
+                find_screenplay_label_at_position(text, position, &position_map),
+                None
+            );
+            assert!(matches!(
+                determine_screenplay_completion_context(text, position),
+                CompletionContext::None
+            ));
+        }
+
+        // The label of the choice, found by its UTF-16 offset
+        let position = Position::new(2, 8);
+        assert_eq!(
+            find_screenplay_label_at_position(text, position, &position_map).as_deref(),
+            Some("始まり")
+        );
+        assert!(matches!(
+            determine_screenplay_completion_context(text, position),
+            CompletionContext::LabelReference
+        ));
+
+        // Ranges of the definition and the reference, in UTF-16 units
+        let definition = position_map.get_label_position("始まり").unwrap();
+        assert_eq!(
+            text_range(text, definition, "始まり".chars().count() + 2),
+            Range::new(Position::new(0, 0), Position::new(0, 5))
+        );
+        let reference = position_map.get_label_references("始まり").unwrap()[0];
+        assert_eq!(
+            text_range(text, reference, "始まり".chars().count()),
+            Range::new(Position::new(2, 8), Position::new(2, 11))
+        );
+    }
+
+    #[test]
+    fn test_yaml_ranges_count_utf16_units() {
+        let text = "script:\n  - { text: \"😀\", jump: end }\n  - label: end\n";
+        let spans = ScenarioSpans::parse(text);
+
+        // The emoji is one character but two UTF-16 units
+        let reference = &spans.label_references()[0];
+        assert_eq!(
+            label_range(text, reference),
+            Range::new(Position::new(1, 18), Position::new(1, 27))
+        );
+
+        // And the cursor on the reference, by its UTF-16 offset
+        let pos = line_position(text, Position::new(1, 25));
+        assert_eq!(pos, LinePosition::new(1, 24));
+        assert_eq!(
+            spans.label_at(pos).map(|label| label.label),
+            Some("end".to_string())
+        );
+    }
+}
@@ -20,7 +20,8 @@ use std::thread;
 use std::time::Duration;
 
 use ivy::i18n::{LocalizedString, Translations};
-use ivy::scenario::{Scenario, parse_scenario};
+use ivy::scenario::{Scenario, interpolate_variables, load_scenario};
+use ivy::types::Value;
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::Serialize;
 use tungstenite::{Message, accept};
@@ -37,7 +38,7 @@ struct PreviewState {
     character: Option<String>,
     char_pos: Option<String>,
     choices: Vec<ChoiceInfo>,
-    variables: HashMap<String, String>,
+    variables: HashMap<String, Value>,
     labels: Vec<String>,
     current_label: Option<String>,
     nvl_mode: bool,
@@ -82,7 +83,7 @@ fn resolve_localized(s: &LocalizedString) -> String {
 fn build_preview_state(
     scenario: &Scenario,
     index: usize,
-    variables: &HashMap<String, String>,
+    variables: &HashMap<String, Value>,
 ) -> PreviewState {
     let commands = &scenario.script;
     let total = commands.len();
@@ -101,13 +102,19 @@ fn build_preview_state(
         .rev()
         .find_map(|cmd| cmd.label.clone());
 
-    // Build visual state by scanning commands up to current index
+    // Build visual state and variables by scanning commands up to current index
     let mut background: Option<String> = None;
     let mut character: Option<String> = None;
     let mut char_pos: Option<String> = None;
     let mut nvl_mode = false;
+    let mut variables = variables.clone();
 
     for cmd in commands.iter().take(idx + 1) {
+        if let Some(ref set) = cmd.set
+            && let Ok(value) = set.evaluate(&variables)
+        {
+            variables.insert(set.name.clone(), value);
+        }
         if let Some(ref bg) = cmd.background {
             if bg.is_empty() {
                 background = None;
@@ -118,8 +125,18 @@ fn build_preview_state(
         if let Some(ref ch) = cmd.character {
             if ch.is_empty() {
                 character = None;
-            } else {
-                character = Some(ch.clone());
+            } else if let Some(sprite) = scenario.character_sprite(ch, cmd.expression.as_deref()) {
+                character = Some(sprite);
+            }
+        } else if let Some(ref expression) = cmd.expression {
+            // An expression alone changes the speaker's sprite
+            if let Some(sprite) = cmd
+                .speaker
+                .as_ref()
+                .and_then(|s| scenario.speaker_character(s))
+                .and_then(|(_, def)| def.sprite_for(Some(expression)))
+            {
+                character = Some(sprite.to_string());
             }
         }
         if let Some(ref pos) = cmd.char_pos {
@@ -133,10 +150,10 @@ fn build_preview_state(
     let current_cmd = commands.get(idx);
     let text = current_cmd
         .and_then(|c| c.text.as_ref())
-        .map(resolve_localized);
+        .map(|t| interpolate_variables(&resolve_localized(t), &variables));
     let speaker = current_cmd
         .and_then(|c| c.speaker.as_ref())
-        .map(resolve_localized);
+        .map(|s| interpolate_variables(&resolve_localized(&scenario.speaker_name(s)), &variables));
     let choices: Vec<ChoiceInfo> = current_cmd
         .and_then(|c| c.choices.as_ref())
         .map(|choices| {
@@ -160,7 +177,7 @@ fn build_preview_state(
         character,
         char_pos,
         choices,
-        variables: variables.clone(),
+        variables,
         labels,
         current_label,
         nvl_mode,
@@ -627,7 +644,7 @@ fn send_ws_error(
 fn send_state(
     websocket: &mut tungstenite::WebSocket<TcpStream>,
     scenario: &Arc<Mutex<Scenario>>,
-    state: &Arc<Mutex<(usize, HashMap<String, String>)>>,
+    state: &Arc<Mutex<(usize, HashMap<String, Value>)>>,
 ) -> bool {
     let scn = match scenario.lock() {
         Ok(s) => s,
@@ -654,7 +671,7 @@ fn send_state(
 fn handle_websocket(
     stream: TcpStream,
     scenario: Arc<Mutex<Scenario>>,
-    state: Arc<Mutex<(usize, HashMap<String, String>)>>,
+    state: Arc<Mutex<(usize, HashMap<String, Value>)>>,
     _tx: Sender<()>,
 ) {
     let mut websocket = match accept(stream) {
@@ -795,16 +812,8 @@ fn main() -> ExitCode {
 
     let scenario_dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
 
-    // Load initial scenario
-    let content = match fs::read_to_string(path) {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("Error reading file: {}", e);
-            return ExitCode::from(1);
-        }
-    };
-
-    let initial_scenario = match parse_scenario(&content) {
+    // Load initial scenario (with any included files)
+    let initial_scenario = match load_scenario(target) {
         Ok(s) => s,
         Err(e) => {
             eprintln!("Parse error: {}", e);
@@ -813,7 +822,7 @@ fn main() -> ExitCode {
     };
 
     let scenario = Arc::new(Mutex::new(initial_scenario));
-    let state = Arc::new(Mutex::new((0usize, HashMap::<String, String>::new())));
+    let state = Arc::new(Mutex::new((0usize, HashMap::<String, Value>::new())));
     let (reload_tx, _reload_rx) = channel::<()>();
 
     // File watcher
@@ -839,9 +848,7 @@ fn main() -> ExitCode {
                 Ok(Ok(event)) => {
                     if event.paths.iter().any(|p| p == &watch_path) {
                         thread::sleep(Duration::from_millis(100)); // Debounce
-                        if let Ok(content) = fs::read_to_string(&watch_path)
-                            && let Ok(new_scenario) = parse_scenario(&content)
-                        {
+                        if let Ok(new_scenario) = load_scenario(&watch_path.to_string_lossy()) {
                             match scenario_clone.lock() {
                                 Ok(mut scn) => {
                                     *scn = new_scenario;
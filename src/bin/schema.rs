@@ -0,0 +1,64 @@
+//! CLI tool for writing the JSON Schema of ivy scenario files.
+//!
+//! Usage:
+//!   ivy-schema                 Print the schema
+//!   ivy-schema -o <file>       Write the schema to a file
+//!   ivy-schema --update        Update the schema kept in the repository
+
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+
+use ivy::scenario::schema::{SCHEMA_PATH, scenario_schema_json};
+
+fn print_usage() {
+    eprintln!("ivy-schema - Generate the JSON Schema of ivy scenario files");
+    eprintln!();
+    eprintln!("Usage:");
+    eprintln!("  ivy-schema              Print the schema");
+    eprintln!("  ivy-schema -o <file>    Write the schema to a file");
+    eprintln!("  ivy-schema --update     Update {}", SCHEMA_PATH);
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  -h, --help    Show this help message");
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+    let schema = scenario_schema_json();
+
+    let output = match args.get(1).map(String::as_str) {
+        None => {
+            print!("{}", schema);
+            return ExitCode::from(0);
+        }
+        Some("-h" | "--help") => {
+            print_usage();
+            return ExitCode::from(0);
+        }
+        Some("--update") => SCHEMA_PATH,
+        Some("-o" | "--output") => match args.get(2) {
+            Some(path) => path.as_str(),
+            None => {
+                eprintln!("Missing file after {}", args[1]);
+                return ExitCode::from(1);
+            }
+        },
+        Some(arg) => {
+            eprintln!("Unknown option: {}", arg);
+            print_usage();
+            return ExitCode::from(1);
+        }
+    };
+
+    match fs::write(output, schema) {
+        Ok(()) => {
+            eprintln!("Wrote {}", output);
+            ExitCode::from(0)
+        }
+        Err(e) => {
+            eprintln!("Error writing {}: {}", output, e);
+            ExitCode::from(1)
+        }
+    }
+}
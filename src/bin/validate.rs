@@ -1,21 +1,30 @@
 //! CLI tool for validating ivy scenarios.
 //!
+//! Scenarios are YAML (`.yaml`, `.yml`) or screenplay (`.ivys`) files.
+//!
 //! Usage:
 //!   ivy-validate <scenario.yaml>
 //!   ivy-validate --all <directory>
 //!   ivy-validate --watch <directory>
 //!   ivy-validate --json <scenario.yaml>
+//!   ivy-validate --migrate <scenario.yaml | directory>
 
+use std::collections::HashSet;
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 #[cfg(not(target_arch = "wasm32"))]
 use std::sync::mpsc::channel;
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::Duration;
 
-use ivy::scenario::{Severity, detect_circular_paths, parse_scenario, validate_scenario};
+use ivy::scenario::position::ProjectPositionMap;
+use ivy::scenario::{
+    FORMAT_VERSION, Scenario, Severity, ValidationIssue, ValidationResult, describe_command,
+    detect_circular_paths, is_scenario_file, is_screenplay, load_scenario, migrate_source,
+    validate_scenario, validate_scenario_strict,
+};
 #[cfg(not(target_arch = "wasm32"))]
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::Serialize;
@@ -37,6 +46,25 @@ struct JsonIssue {
     command_index: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    /// 1-indexed line within `file`: of the field the issue is about when
+    /// known, otherwise of the command.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<u32>,
+    /// 1-indexed column of the field the issue is about.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<u32>,
+    /// File of the macro the command was expanded from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    macro_file: Option<String>,
+    /// 1-indexed line of the field (or the command) within the macro
+    /// definition.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    macro_line: Option<u32>,
+    /// 1-indexed column of the field within the macro definition.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    macro_column: Option<u32>,
 }
 
 /// JSON output structure for a file's validation result.
@@ -58,40 +86,226 @@ struct JsonOutput {
     results: Vec<JsonFileResult>,
 }
 
+/// Checks chosen on the command line.
+#[derive(Clone, Copy)]
+struct Checks {
+    /// Detect circular jump paths.
+    cycles: bool,
+    /// Report keys that no field is named after.
+    strict: bool,
+}
+
+impl Checks {
+    fn validate(self, scenario: &Scenario) -> ValidationResult {
+        if self.strict {
+            validate_scenario_strict(scenario)
+        } else {
+            validate_scenario(scenario)
+        }
+    }
+}
+
 fn print_usage() {
     eprintln!("ivy-validate - Validate ivy scenario files");
     eprintln!();
     eprintln!("Usage:");
     eprintln!("  ivy-validate <scenario.yaml>     Validate a single scenario file");
-    eprintln!("  ivy-validate --all <directory>   Validate all .yaml files in directory");
+    eprintln!("  ivy-validate --all <directory>   Validate all .yaml and .ivys files in directory");
     #[cfg(not(target_arch = "wasm32"))]
     eprintln!("  ivy-validate --watch <directory> Watch directory and validate on changes");
     eprintln!();
     eprintln!("Options:");
     eprintln!("  -h, --help    Show this help message");
-    eprintln!("  --all         Validate all YAML files in the specified directory");
+    eprintln!("  --all         Validate all scenario files in the specified directory");
     #[cfg(not(target_arch = "wasm32"))]
     eprintln!("  --watch       Watch for file changes and re-validate automatically");
     eprintln!("  --cycles      Also detect circular jump paths");
+    eprintln!("  --no-strict   Don't report unknown fields (such as typos in field names)");
+    eprintln!("  --migrate     Rewrite YAML files in an older format in the current one first");
     eprintln!("  --no-color    Disable colored output");
     eprintln!("  --json        Output results in JSON format (for CI/tooling integration)");
     eprintln!("  --quiet, -q   Only output errors (suppress warnings and info)");
 }
 
-fn validate_file(path: &Path, check_cycles: bool, use_color: bool) -> (usize, usize) {
-    let content = match fs::read_to_string(path) {
-        Ok(c) => c,
+/// Describe where an issue is, e.g.
+/// " (command 3 of chapter2.yaml, line 12, column 5)".
+///
+/// The line and column are those of the field the issue is about when it
+/// is known, otherwise the line of the command. Issues in commands expanded
+/// from a macro also name where the field is in the macro definition.
+fn issue_location(
+    issue: &ValidationIssue,
+    scenario: &Scenario,
+    positions: &ProjectPositionMap,
+) -> String {
+    let field = issue
+        .locate(scenario, positions.spans())
+        .map(|location| location.field.start());
+    let Some(index) = issue.command_index else {
+        let mut parts: Vec<String> = issue.file.iter().cloned().collect();
+        if let Some(pos) = field {
+            parts.push(format!("line {}, column {}", pos.line + 1, pos.column + 1));
+        }
+        return if parts.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", parts.join(", "))
+        };
+    };
+    let mut location = describe_command(scenario, index);
+    let command_line = positions
+        .get_command_position(index)
+        .map(|(_, pos)| pos.line + 1);
+    match (positions.get_macro_command_position(index), field) {
+        (Some((file, pos)), field) => {
+            if let Some(line) = command_line {
+                location.push_str(&format!(", line {}", line));
+            }
+            match field {
+                Some(field) => location.push_str(&format!(
+                    ", macro at {} line {}, column {}",
+                    file,
+                    field.line + 1,
+                    field.column + 1
+                )),
+                None => location.push_str(&format!(", macro at {} line {}", file, pos.line + 1)),
+            }
+        }
+        (None, Some(pos)) => location.push_str(&format!(
+            ", line {}, column {}",
+            pos.line + 1,
+            pos.column + 1
+        )),
+        (None, None) => {
+            if let Some(line) = command_line {
+                location.push_str(&format!(", line {}", line));
+            }
+        }
+    }
+    format!(" ({})", location)
+}
+
+/// List the scenario files in a directory, skipping files that are included
+/// by another scenario (they are validated as part of it).
+fn scenario_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| is_scenario_file(path))
+        .collect();
+    files.sort();
+
+    let mut included: HashSet<PathBuf> = HashSet::new();
+    for file in &files {
+        if let Ok(scenario) = load_scenario(&file.to_string_lossy()) {
+            for source in scenario.sources.iter().skip(1) {
+                if let Ok(path) = fs::canonicalize(&source.path) {
+                    included.insert(path);
+                }
+            }
+        }
+    }
+
+    files
+        .into_iter()
+        .filter(|file| fs::canonicalize(file).map_or(true, |path| !included.contains(&path)))
+        .collect()
+}
+
+/// Rewrite the YAML scenario files at `path` (a file, or every one in a
+/// directory) and the files they include that are in an older format.
+/// Returns the number of failures.
+fn migrate_files(path: &Path, use_color: bool) -> usize {
+    let files: Vec<PathBuf> = if path.is_dir() {
+        let Ok(entries) = fs::read_dir(path) else {
+            eprintln!("Error reading directory: {}", path.display());
+            return 1;
+        };
+        let mut files: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut seen = HashSet::new();
+    let mut failures = 0;
+    for file in files {
+        if !is_scenario_file(&file) {
+            continue;
+        }
+        failures += migrate_file(&file, &mut seen, use_color);
+        // Included files, found once the including file reads in the
+        // current format
+        if let Ok(scenario) = load_scenario(&file.to_string_lossy()) {
+            for source in scenario.sources.iter().skip(1) {
+                failures += migrate_file(Path::new(&source.path), &mut seen, use_color);
+            }
+        }
+    }
+    failures
+}
+
+/// Rewrite a YAML scenario file in an older format, unless it is in `seen`.
+/// Returns the number of failures (0 or 1).
+fn migrate_file(file: &Path, seen: &mut HashSet<PathBuf>, use_color: bool) -> usize {
+    let name = file.to_string_lossy();
+    let key = fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+    if is_screenplay(&name) || !seen.insert(key) {
+        return 0;
+    }
+    let result = fs::read_to_string(file)
+        .map_err(anyhow::Error::from)
+        .and_then(|yaml| migrate_source(&yaml))
+        .and_then(|migrated| {
+            if let Some(migrated) = &migrated {
+                fs::write(file, &migrated.yaml)?;
+            }
+            Ok(migrated)
+        });
+    match result {
+        Ok(Some(migrated)) => {
+            let message = if migrated.from == u64::from(FORMAT_VERSION) {
+                format!("{} (added format_version {})", name, FORMAT_VERSION)
+            } else {
+                format!(
+                    "{} (format_version {} -> {})",
+                    name, migrated.from, FORMAT_VERSION
+                )
+            };
+            if use_color {
+                eprintln!("{}{}Migrated{} {}", BOLD, GREEN, RESET, message);
+            } else {
+                eprintln!("Migrated {}", message);
+            }
+            0
+        }
+        Ok(None) => 0,
         Err(e) => {
             if use_color {
-                eprintln!("  {}{}Error{} reading file: {}", BOLD, RED, RESET, e);
+                eprintln!("{}{}Error{} migrating {}: {}", BOLD, RED, RESET, name, e);
             } else {
-                eprintln!("  Error reading file: {}", e);
+                eprintln!("Error migrating {}: {}", name, e);
             }
-            return (1, 0);
+            1
         }
-    };
+    }
+}
 
-    let scenario = match parse_scenario(&content) {
+fn validate_file(path: &Path, checks: Checks, use_color: bool) -> (usize, usize) {
+    if let Err(e) = fs::metadata(path) {
+        if use_color {
+            eprintln!("  {}{}Error{} reading file: {}", BOLD, RED, RESET, e);
+        } else {
+            eprintln!("  Error reading file: {}", e);
+        }
+        return (1, 0);
+    }
+
+    let scenario = match load_scenario(&path.to_string_lossy()) {
         Ok(s) => s,
         Err(e) => {
             if use_color {
@@ -103,15 +317,13 @@ fn validate_file(path: &Path, check_cycles: bool, use_color: bool) -> (usize, us
         }
     };
 
-    let result = validate_scenario(&scenario);
+    let result = checks.validate(&scenario);
+    let positions = ProjectPositionMap::from_scenario(&scenario);
     let mut errors = 0;
     let mut warnings = 0;
 
     for issue in &result.issues {
-        let location = match issue.command_index {
-            Some(idx) => format!(" (command {})", idx + 1),
-            None => String::new(),
-        };
+        let location = issue_location(issue, &scenario, &positions);
 
         match issue.severity {
             Severity::Error => {
@@ -139,7 +351,7 @@ fn validate_file(path: &Path, check_cycles: bool, use_color: bool) -> (usize, us
         }
     }
 
-    if check_cycles {
+    if checks.cycles {
         let cycles = detect_circular_paths(&scenario);
         for cycle in &cycles {
             if use_color {
@@ -166,30 +378,33 @@ fn validate_file(path: &Path, check_cycles: bool, use_color: bool) -> (usize, us
 }
 
 /// Validate a file and return JSON-compatible result.
-fn validate_file_json(path: &Path, check_cycles: bool) -> JsonFileResult {
+fn validate_file_json(path: &Path, checks: Checks) -> JsonFileResult {
     let mut issues = Vec::new();
     let mut errors = 0;
     let mut warnings = 0;
 
-    let content = match fs::read_to_string(path) {
-        Ok(c) => c,
-        Err(e) => {
-            issues.push(JsonIssue {
-                severity: "error".to_string(),
-                message: format!("Error reading file: {}", e),
-                command_index: None,
-                label: None,
-            });
-            return JsonFileResult {
-                file: path.display().to_string(),
-                errors: 1,
-                warnings: 0,
-                issues,
-            };
-        }
-    };
+    if let Err(e) = fs::metadata(path) {
+        issues.push(JsonIssue {
+            severity: "error".to_string(),
+            message: format!("Error reading file: {}", e),
+            command_index: None,
+            label: None,
+            file: None,
+            line: None,
+            column: None,
+            macro_file: None,
+            macro_line: None,
+            macro_column: None,
+        });
+        return JsonFileResult {
+            file: path.display().to_string(),
+            errors: 1,
+            warnings: 0,
+            issues,
+        };
+    }
 
-    let scenario = match parse_scenario(&content) {
+    let scenario = match load_scenario(&path.to_string_lossy()) {
         Ok(s) => s,
         Err(e) => {
             issues.push(JsonIssue {
@@ -197,6 +412,12 @@ fn validate_file_json(path: &Path, check_cycles: bool) -> JsonFileResult {
                 message: format!("Parse error: {}", e),
                 command_index: None,
                 label: None,
+                file: None,
+                line: None,
+                column: None,
+                macro_file: None,
+                macro_line: None,
+                macro_column: None,
             });
             return JsonFileResult {
                 file: path.display().to_string(),
@@ -207,9 +428,25 @@ fn validate_file_json(path: &Path, check_cycles: bool) -> JsonFileResult {
         }
     };
 
-    let result = validate_scenario(&scenario);
+    let result = checks.validate(&scenario);
+    let positions = ProjectPositionMap::from_scenario(&scenario);
 
     for issue in &result.issues {
+        let position = issue
+            .command_index
+            .and_then(|index| positions.get_command_position(index))
+            .map(|(_, pos)| pos);
+        let macro_position = issue
+            .command_index
+            .and_then(|index| positions.get_macro_command_position(index));
+        let field = issue
+            .locate(&scenario, positions.spans())
+            .map(|location| location.field.start());
+        // The field is in the macro definition for expanded commands
+        let (field, macro_field) = match macro_position {
+            Some(_) => (None, field),
+            None => (field, None),
+        };
         let severity = match issue.severity {
             Severity::Error => {
                 errors += 1;
@@ -226,10 +463,18 @@ fn validate_file_json(path: &Path, check_cycles: bool) -> JsonFileResult {
             message: issue.message.clone(),
             command_index: issue.command_index,
             label: issue.label.clone(),
+            file: issue.file.clone(),
+            line: field.or(position).map(|pos| pos.line + 1),
+            column: field.map(|pos| pos.column + 1),
+            macro_file: macro_position.map(|(file, _)| file.to_string()),
+            macro_line: macro_field
+                .or(macro_position.map(|(_, pos)| pos))
+                .map(|pos| pos.line + 1),
+            macro_column: macro_field.map(|pos| pos.column + 1),
         });
     }
 
-    if check_cycles {
+    if checks.cycles {
         let cycles = detect_circular_paths(&scenario);
         for cycle in &cycles {
             warnings += 1;
@@ -242,6 +487,12 @@ fn validate_file_json(path: &Path, check_cycles: bool) -> JsonFileResult {
                 ),
                 command_index: None,
                 label: cycle.first().cloned(),
+                file: None,
+                line: None,
+                column: None,
+                macro_file: None,
+                macro_line: None,
+                macro_column: None,
             });
         }
     }
@@ -254,41 +505,33 @@ fn validate_file_json(path: &Path, check_cycles: bool) -> JsonFileResult {
     }
 }
 
-fn validate_directory(path: &Path, check_cycles: bool, use_color: bool) -> (usize, usize, usize) {
-    let entries = match fs::read_dir(path) {
-        Ok(e) => e,
-        Err(e) => {
-            eprintln!("Error reading directory: {}", e);
-            return (0, 0, 0);
-        }
-    };
+fn validate_directory(path: &Path, checks: Checks, use_color: bool) -> (usize, usize, usize) {
+    if let Err(e) = fs::read_dir(path) {
+        eprintln!("Error reading directory: {}", e);
+        return (0, 0, 0);
+    }
 
     let mut total_errors = 0;
     let mut total_warnings = 0;
     let mut files_checked = 0;
 
-    for entry in entries.flatten() {
-        let file_path = entry.path();
-        if let Some(ext) = file_path.extension()
-            && (ext == "yaml" || ext == "yml")
-        {
-            if use_color {
-                eprintln!("{}Validating:{} {}", CYAN, RESET, file_path.display());
-            } else {
-                eprintln!("Validating: {}", file_path.display());
-            }
-            let (errors, warnings) = validate_file(&file_path, check_cycles, use_color);
-            total_errors += errors;
-            total_warnings += warnings;
-            files_checked += 1;
+    for file_path in scenario_files(path) {
+        if use_color {
+            eprintln!("{}Validating:{} {}", CYAN, RESET, file_path.display());
+        } else {
+            eprintln!("Validating: {}", file_path.display());
         }
+        let (errors, warnings) = validate_file(&file_path, checks, use_color);
+        total_errors += errors;
+        total_warnings += warnings;
+        files_checked += 1;
     }
 
     (total_errors, total_warnings, files_checked)
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-fn watch_directory(path: &Path, check_cycles: bool, use_color: bool) -> Result<(), notify::Error> {
+fn watch_directory(path: &Path, checks: Checks, use_color: bool) -> Result<(), notify::Error> {
     let (tx, rx) = channel();
 
     let mut watcher = RecommendedWatcher::new(tx, Config::default())?;
@@ -310,27 +553,23 @@ fn watch_directory(path: &Path, check_cycles: bool, use_color: bool) -> Result<(
     }
 
     // Initial validation
-    let (errors, warnings, files) = validate_directory(path, check_cycles, use_color);
+    let (errors, warnings, files) = validate_directory(path, checks, use_color);
     print_summary(errors, warnings, files, use_color);
 
     loop {
         match rx.recv_timeout(Duration::from_millis(500)) {
             Ok(result) => {
                 if let Ok(event) = result {
-                    // Check if any YAML files were modified
-                    let yaml_changed = event.paths.iter().any(|p| {
-                        p.extension()
-                            .is_some_and(|ext| ext == "yaml" || ext == "yml")
-                    });
+                    // Check if any scenario files were modified
+                    let scenario_changed = event.paths.iter().any(|p| is_scenario_file(p));
 
-                    if yaml_changed {
+                    if scenario_changed {
                         if use_color {
                             eprintln!("\n{}--- File changed, re-validating ---{}", CYAN, RESET);
                         } else {
                             eprintln!("\n--- File changed, re-validating ---");
                         }
-                        let (errors, warnings, files) =
-                            validate_directory(path, check_cycles, use_color);
+                        let (errors, warnings, files) = validate_directory(path, checks, use_color);
                         print_summary(errors, warnings, files, use_color);
                     }
                 }
@@ -371,13 +610,17 @@ fn main() -> ExitCode {
         return ExitCode::from(1);
     }
 
-    let mut check_cycles = false;
+    let mut checks = Checks {
+        cycles: false,
+        strict: true,
+    };
     let mut all_mode = false;
     #[cfg(not(target_arch = "wasm32"))]
     let mut watch_mode = false;
     let mut use_color = true;
     let mut json_mode = false;
     let mut quiet_mode = false;
+    let mut migrate = false;
     let mut target: Option<&str> = None;
 
     let mut i = 1;
@@ -388,7 +631,10 @@ fn main() -> ExitCode {
                 return ExitCode::from(0);
             }
             "--cycles" => {
-                check_cycles = true;
+                checks.cycles = true;
+            }
+            "--no-strict" => {
+                checks.strict = false;
             }
             "--all" => {
                 all_mode = true;
@@ -406,6 +652,9 @@ fn main() -> ExitCode {
             "-q" | "--quiet" => {
                 quiet_mode = true;
             }
+            "--migrate" => {
+                migrate = true;
+            }
             arg if !arg.starts_with('-') => {
                 target = Some(arg);
             }
@@ -429,37 +678,44 @@ fn main() -> ExitCode {
 
     let path = Path::new(target);
 
+    // The target is checked the same way in every mode: a directory with
+    // --all (or --watch), a file otherwise
+    #[cfg(not(target_arch = "wasm32"))]
+    let wants_dir = all_mode || watch_mode;
+    #[cfg(target_arch = "wasm32")]
+    let wants_dir = all_mode;
+    if wants_dir && !path.is_dir() {
+        eprintln!("Error: {} is not a directory", target);
+        return ExitCode::from(1);
+    }
+    if !wants_dir && !path.is_file() {
+        if path.is_dir() {
+            eprintln!("Error: {} is a directory (use --all)", target);
+        } else {
+            eprintln!("Error: {} is not a file", target);
+        }
+        return ExitCode::from(1);
+    }
+
+    if migrate && migrate_files(path, use_color) > 0 {
+        return ExitCode::from(1);
+    }
+
     // JSON mode
     if json_mode {
         let mut results = Vec::new();
         let mut total_errors = 0;
         let mut total_warnings = 0;
 
-        if all_mode || path.is_dir() {
-            if !path.is_dir() {
-                eprintln!("Error: {} is not a directory", target);
-                return ExitCode::from(1);
-            }
-
-            if let Ok(entries) = fs::read_dir(path) {
-                for entry in entries.flatten() {
-                    let file_path = entry.path();
-                    if let Some(ext) = file_path.extension()
-                        && (ext == "yaml" || ext == "yml")
-                    {
-                        let result = validate_file_json(&file_path, check_cycles);
-                        total_errors += result.errors;
-                        total_warnings += result.warnings;
-                        results.push(result);
-                    }
-                }
+        if all_mode {
+            for file_path in scenario_files(path) {
+                let result = validate_file_json(&file_path, checks);
+                total_errors += result.errors;
+                total_warnings += result.warnings;
+                results.push(result);
             }
         } else {
-            if !path.is_file() {
-                eprintln!("Error: {} is not a file", target);
-                return ExitCode::from(1);
-            }
-            let result = validate_file_json(path, check_cycles);
+            let result = validate_file_json(path, checks);
             total_errors += result.errors;
             total_warnings += result.warnings;
             results.push(result);
@@ -487,12 +743,7 @@ fn main() -> ExitCode {
     // Watch mode
     #[cfg(not(target_arch = "wasm32"))]
     if watch_mode {
-        if !path.is_dir() {
-            eprintln!("Error: --watch requires a directory");
-            return ExitCode::from(1);
-        }
-
-        if let Err(e) = watch_directory(path, check_cycles, use_color) {
+        if let Err(e) = watch_directory(path, checks, use_color) {
             eprintln!("Watch error: {}", e);
             return ExitCode::from(1);
         }
@@ -500,17 +751,8 @@ fn main() -> ExitCode {
     }
 
     let (total_errors, total_warnings, files_checked) = if all_mode {
-        if !path.is_dir() {
-            eprintln!("Error: {} is not a directory", target);
-            return ExitCode::from(1);
-        }
-        validate_directory(path, check_cycles, use_color)
+        validate_directory(path, checks, use_color)
     } else {
-        if !path.is_file() {
-            eprintln!("Error: {} is not a file", target);
-            return ExitCode::from(1);
-        }
-
         if !quiet_mode {
             if use_color {
                 eprintln!("{}Validating:{} {}", CYAN, RESET, path.display());
@@ -518,7 +760,7 @@ fn main() -> ExitCode {
                 eprintln!("Validating: {}", path.display());
             }
         }
-        let (errors, warnings) = validate_file(path, check_cycles, use_color);
+        let (errors, warnings) = validate_file(path, checks, use_color);
         (errors, warnings, 1)
     };
 
@@ -532,3 +774,43 @@ fn main() -> ExitCode {
         ExitCode::from(0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_follows_includes() {
+        let dir = std::env::temp_dir().join(format!("ivy_migrate_includes_{}", std::process::id()));
+        fs::create_dir_all(dir.join("chapters")).unwrap();
+        let main = dir.join("main.yaml");
+        fs::write(
+            &main,
+            "title: Test\nincludes:\n  - chapters/one.yaml\nscript:\n  - text: \"Main\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("chapters/one.yaml"),
+            "includes:\n  - two.yaml\nscript:\n  - text: \"One\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("chapters/two.yaml"),
+            "script:\n  - text: \"Two\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(migrate_files(&main, false), 0);
+        for file in ["main.yaml", "chapters/one.yaml", "chapters/two.yaml"] {
+            let yaml = fs::read_to_string(dir.join(file)).unwrap();
+            assert!(
+                yaml.starts_with(&format!("format_version: {}\n", FORMAT_VERSION)),
+                "{}: {}",
+                file,
+                yaml
+            );
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
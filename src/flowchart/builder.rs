@@ -29,14 +29,10 @@ pub fn build_flowchart(scenario: &Scenario) -> Flowchart {
     }
 
     // First pass: collect all label positions
-    let mut label_to_index: HashMap<String, usize> = HashMap::new();
-    for (i, cmd) in scenario.script.iter().enumerate() {
-        if let Some(label) = &cmd.label {
-            label_to_index.insert(label.clone(), i);
-        }
-    }
+    let label_to_index = scenario.label_index();
 
-    // Second pass: identify significant nodes (labels, choices, conditionals)
+    // Second pass: identify significant nodes (labels, choices, conditionals, switches,
+    // random branches)
     let mut significant_indices: Vec<usize> = Vec::new();
     significant_indices.push(0); // Start node
 
@@ -53,6 +49,16 @@ pub fn build_flowchart(scenario: &Scenario) -> Flowchart {
         if cmd.if_cond.is_some() && !significant_indices.contains(&i) {
             significant_indices.push(i);
         }
+        // Switch nodes
+        if cmd.switch.is_some() && !significant_indices.contains(&i) {
+            significant_indices.push(i);
+        }
+        // Random branch nodes
+        if cmd.random.as_ref().is_some_and(|r| !r.branches.is_empty())
+            && !significant_indices.contains(&i)
+        {
+            significant_indices.push(i);
+        }
     }
 
     // Add end node if the script doesn't jump away at the end
@@ -79,13 +85,20 @@ pub fn build_flowchart(scenario: &Scenario) -> Flowchart {
             NodeType::Label {
                 name: label.clone(),
             }
+        } else if let Some(random) = cmd.random.as_ref().filter(|r| !r.branches.is_empty()) {
+            NodeType::Random {
+                branches: random.branches.len(),
+            }
         } else if let Some(choices) = &cmd.choices {
             let options: Vec<String> = choices.iter().map(|c| get_default_text(&c.label)).collect();
             NodeType::Choice { options }
         } else if let Some(if_cond) = &cmd.if_cond {
             NodeType::Conditional {
-                var: if_cond.var.clone(),
-                value: format!("{:?}", if_cond.is),
+                condition: if_cond.describe(),
+            }
+        } else if let Some(switch) = &cmd.switch {
+            NodeType::Switch {
+                subject: switch.describe(),
             }
         } else if idx == last_idx && cmd.jump.is_none() {
             NodeType::End
@@ -121,79 +134,116 @@ pub fn build_flowchart(scenario: &Scenario) -> Flowchart {
         let cmd = &scenario.script[idx];
         let from_id = flowchart.index_to_node[&idx];
 
+        // Random branch edges (drawn before anything else on the command)
+        if let Some(random) = cmd.random.as_ref().filter(|r| !r.branches.is_empty()) {
+            for (branch_idx, branch) in random.branches.iter().enumerate() {
+                add_label_edge(
+                    &mut flowchart,
+                    &significant_indices,
+                    &label_to_index,
+                    from_id,
+                    &branch.jump,
+                    EdgeType::Random(branch_idx),
+                    Some(random.describe_branch(branch_idx)),
+                    Vec::new(),
+                );
+            }
+            continue; // A random branch always jumps
+        }
+
         // Choice edges
         if let Some(choices) = &cmd.choices {
             for (choice_idx, choice) in choices.iter().enumerate() {
-                if let Some(&target_idx) = label_to_index.get(&choice.jump) {
-                    // Find the node at or after target_idx
-                    if let Some(&to_id) = flowchart.index_to_node.get(&target_idx) {
-                        flowchart.edges.push(FlowchartEdge {
-                            from: from_id,
-                            to: to_id,
-                            edge_type: EdgeType::Choice(choice_idx),
-                            label: Some(get_default_text(&choice.label)),
-                        });
-                    } else if let Some(&to_id) =
-                        find_nearest_node(&flowchart, &significant_indices, target_idx)
-                    {
-                        flowchart.edges.push(FlowchartEdge {
-                            from: from_id,
-                            to: to_id,
-                            edge_type: EdgeType::Choice(choice_idx),
-                            label: Some(get_default_text(&choice.label)),
-                        });
-                    }
-                }
+                add_label_edge(
+                    &mut flowchart,
+                    &significant_indices,
+                    &label_to_index,
+                    from_id,
+                    &choice.jump,
+                    EdgeType::Choice(choice_idx),
+                    Some(get_default_text(&choice.label)),
+                    choice.set.iter().map(|set| set.describe()).collect(),
+                );
             }
             continue; // Choices don't have sequential flow
         }
 
         // Jump edge
         if let Some(jump) = &cmd.jump {
-            if let Some(&target_idx) = label_to_index.get(jump) {
-                if let Some(&to_id) = flowchart.index_to_node.get(&target_idx) {
-                    flowchart.edges.push(FlowchartEdge {
-                        from: from_id,
-                        to: to_id,
-                        edge_type: EdgeType::Jump,
-                        label: None,
-                    });
-                } else if let Some(&to_id) =
-                    find_nearest_node(&flowchart, &significant_indices, target_idx)
-                {
-                    flowchart.edges.push(FlowchartEdge {
-                        from: from_id,
-                        to: to_id,
-                        edge_type: EdgeType::Jump,
-                        label: None,
-                    });
-                }
-            }
+            add_label_edge(
+                &mut flowchart,
+                &significant_indices,
+                &label_to_index,
+                from_id,
+                jump,
+                EdgeType::Jump,
+                None,
+                Vec::new(),
+            );
             continue; // Jump doesn't have sequential flow
         }
 
-        // Conditional edge
-        if let Some(if_cond) = &cmd.if_cond
-            && let Some(&target_idx) = label_to_index.get(&if_cond.jump)
-        {
-            if let Some(&to_id) = flowchart.index_to_node.get(&target_idx) {
-                flowchart.edges.push(FlowchartEdge {
-                    from: from_id,
-                    to: to_id,
-                    edge_type: EdgeType::Conditional,
-                    label: Some(format!("{} == {:?}", if_cond.var, if_cond.is)),
-                });
-            } else if let Some(&to_id) =
-                find_nearest_node(&flowchart, &significant_indices, target_idx)
-            {
-                flowchart.edges.push(FlowchartEdge {
-                    from: from_id,
-                    to: to_id,
-                    edge_type: EdgeType::Conditional,
-                    label: Some(format!("{} == {:?}", if_cond.var, if_cond.is)),
-                });
+        // Conditional edges
+        let mut falls_through = true;
+        if let Some(if_cond) = &cmd.if_cond {
+            add_label_edge(
+                &mut flowchart,
+                &significant_indices,
+                &label_to_index,
+                from_id,
+                &if_cond.jump,
+                EdgeType::Conditional,
+                Some(if_cond.describe()),
+                Vec::new(),
+            );
+            // Without an else branch, a false condition falls through
+            if let Some(else_jump) = &if_cond.else_jump {
+                add_label_edge(
+                    &mut flowchart,
+                    &significant_indices,
+                    &label_to_index,
+                    from_id,
+                    else_jump,
+                    EdgeType::Else,
+                    Some("else".to_string()),
+                    Vec::new(),
+                );
+                falls_through = false;
+            }
+        }
+
+        // Switch edges
+        if let Some(switch) = &cmd.switch {
+            for (case_idx, case) in switch.cases.iter().enumerate() {
+                add_label_edge(
+                    &mut flowchart,
+                    &significant_indices,
+                    &label_to_index,
+                    from_id,
+                    &case.jump,
+                    EdgeType::Case(case_idx),
+                    Some(case.describe()),
+                    Vec::new(),
+                );
+            }
+            // Without a default, an unmatched value falls through
+            if let Some(default) = &switch.default {
+                add_label_edge(
+                    &mut flowchart,
+                    &significant_indices,
+                    &label_to_index,
+                    from_id,
+                    default,
+                    EdgeType::Default,
+                    Some("default".to_string()),
+                    Vec::new(),
+                );
+                falls_through = false;
             }
-            // Conditional also has sequential flow (else branch)
+        }
+
+        if !falls_through {
+            continue;
         }
 
         // Sequential edge to next significant node
@@ -205,6 +255,7 @@ pub fn build_flowchart(scenario: &Scenario) -> Flowchart {
                 to: to_id,
                 edge_type: EdgeType::Sequential,
                 label: None,
+                annotations: Vec::new(),
             });
         }
     }
@@ -212,6 +263,32 @@ pub fn build_flowchart(scenario: &Scenario) -> Flowchart {
     flowchart
 }
 
+/// Add an edge from a node to the node of a jump target label.
+#[allow(clippy::too_many_arguments)]
+fn add_label_edge(
+    flowchart: &mut Flowchart,
+    significant_indices: &[usize],
+    label_to_index: &HashMap<String, usize>,
+    from: NodeId,
+    target: &str,
+    edge_type: EdgeType,
+    label: Option<String>,
+    annotations: Vec<String>,
+) {
+    let Some(&target_idx) = label_to_index.get(target) else {
+        return;
+    };
+    if let Some(&to) = find_nearest_node(flowchart, significant_indices, target_idx) {
+        flowchart.edges.push(FlowchartEdge {
+            from,
+            to,
+            edge_type,
+            label,
+            annotations,
+        });
+    }
+}
+
 /// Find the nearest significant node at or after the given index.
 fn find_nearest_node<'a>(
     flowchart: &'a Flowchart,
@@ -242,10 +319,17 @@ mod tests {
     fn create_test_scenario() -> Scenario {
         // Minimal scenario for testing
         Scenario {
+            format_version: crate::scenario::FORMAT_VERSION,
             title: "Test".to_string(),
             chapters: vec![],
+            characters: std::collections::HashMap::new(),
             modular_characters: std::collections::HashMap::new(),
+            includes: vec![],
+            macros: std::collections::HashMap::new(),
             script: vec![],
+            sources: vec![],
+            origins: vec![],
+            unknown_fields: vec![],
         }
     }
 
@@ -256,4 +340,95 @@ mod tests {
         assert!(flowchart.nodes.is_empty());
         assert!(flowchart.edges.is_empty());
     }
+
+    #[test]
+    fn test_choice_edges_show_assignments() {
+        let yaml = r#"
+title: Test
+script:
+  - text: "Buy?"
+    choices:
+      - label: "Yes"
+        jump: bought
+        set:
+          - { name: gold, op: sub, value: 10 }
+          - { name: has_sword, value: true }
+      - label: "No"
+        jump: bought
+  - label: bought
+    text: "Done"
+"#;
+        let scenario = crate::scenario::parse_scenario(yaml).unwrap();
+        let flowchart = build_flowchart(&scenario);
+
+        let yes = flowchart
+            .edges
+            .iter()
+            .find(|e| e.edge_type == EdgeType::Choice(0))
+            .unwrap();
+        assert_eq!(yes.annotations, vec!["gold -= 10", "has_sword = true"]);
+        let no = flowchart
+            .edges
+            .iter()
+            .find(|e| e.edge_type == EdgeType::Choice(1))
+            .unwrap();
+        assert!(no.annotations.is_empty());
+    }
+
+    #[test]
+    fn test_branch_arms_are_separate_edges() {
+        let yaml = r#"
+title: Test
+script:
+  - if:
+      var: met
+      is: true
+      jump: friend
+      else: stranger
+  - switch:
+      var: day
+      cases:
+        - is: 1
+          jump: friend
+        - min: 2
+          max: 5
+          jump: stranger
+  - text: "Weekend"
+  - label: friend
+    text: "Friend"
+  - label: stranger
+    text: "Stranger"
+"#;
+        let scenario = crate::scenario::parse_scenario(yaml).unwrap();
+        let flowchart = build_flowchart(&scenario);
+
+        let start = flowchart.index_to_node[&0];
+        let types: Vec<EdgeType> = flowchart
+            .edges_from(start)
+            .iter()
+            .map(|e| e.edge_type)
+            .collect();
+        // An `if` with `else` has no sequential flow
+        assert_eq!(types, vec![EdgeType::Conditional, EdgeType::Else]);
+
+        let switch = flowchart.index_to_node[&1];
+        assert!(matches!(
+            flowchart.nodes[switch.0].node_type,
+            NodeType::Switch { .. }
+        ));
+        let edges = flowchart.edges_from(switch);
+        let labels: Vec<(EdgeType, Option<&str>)> = edges
+            .iter()
+            .map(|e| (e.edge_type, e.label.as_deref()))
+            .collect();
+        // Without a default, an unmatched value falls through
+        assert_eq!(
+            labels,
+            vec![
+                (EdgeType::Case(0), Some("== 1")),
+                (EdgeType::Case(1), Some("2..5")),
+                (EdgeType::Sequential, None),
+            ]
+        );
+    }
 }
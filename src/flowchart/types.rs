@@ -14,11 +14,11 @@ pub enum NodeType {
     /// Choice point with multiple options.
     Choice { options: Vec<String> },
     /// Conditional branch (if statement).
-    Conditional {
-        var: String,
-        #[allow(dead_code)]
-        value: String,
-    },
+    Conditional { condition: String },
+    /// Multi-way branch (switch statement).
+    Switch { subject: String },
+    /// Random branch with the given number of labels.
+    Random { branches: usize },
     /// End of scenario.
     End,
 }
@@ -45,6 +45,14 @@ pub enum EdgeType {
     Choice(usize),
     /// Conditional jump (if true).
     Conditional,
+    /// Conditional jump (if false).
+    Else,
+    /// Switch case match.
+    Case(usize),
+    /// Switch default (no case matched).
+    Default,
+    /// Random branch pick.
+    Random(usize),
 }
 
 /// An edge connecting two nodes.
@@ -55,6 +63,8 @@ pub struct FlowchartEdge {
     pub edge_type: EdgeType,
     /// Label for the edge (e.g., choice text).
     pub label: Option<String>,
+    /// Side effects of following the edge (e.g., a choice's `gold -= 10`).
+    pub annotations: Vec<String>,
 }
 
 /// Complete flowchart graph.
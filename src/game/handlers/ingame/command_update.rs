@@ -4,8 +4,7 @@ use macroquad::prelude::*;
 
 use crate::game::GameContext;
 use crate::render::{character::AnimationDirection, ParticleType};
-use crate::runtime::{CameraState, DisplayState, GameState};
-use crate::scenario::CharPosition;
+use crate::runtime::{CameraState, CharacterKey, DisplayState, GameState};
 
 /// Called when the current command index changes.
 pub async fn on_command_change(ctx: &mut GameContext, state: &mut GameState) {
@@ -26,6 +25,9 @@ pub async fn on_command_change(ctx: &mut GameContext, state: &mut GameState) {
             for char_state in &visual.characters {
                 ctx.unlocks.unlock_image(&char_state.path);
             }
+            for layer in &visual.layers {
+                ctx.unlocks.unlock_image(&layer.path);
+            }
         }
         DisplayState::End => {}
     }
@@ -37,7 +39,9 @@ pub async fn on_command_change(ctx: &mut GameContext, state: &mut GameState) {
     ctx.audio_manager.play_se(state.current_se()).await;
 
     // Play voice
-    ctx.audio_manager.play_voice(state.current_voice()).await;
+    ctx.audio_manager
+        .play_voice(state.current_voice().as_ref())
+        .await;
 
     // Start ambient tracks
     for track in state.current_ambient() {
@@ -100,60 +104,51 @@ pub async fn on_command_change(ctx: &mut GameContext, state: &mut GameState) {
 
     // Handle multiple character animations
     if let Some(visual) = visual {
-        // Track which positions are currently active
-        let active_positions: std::collections::HashSet<_> =
-            visual.characters.iter().map(|c| c.position).collect();
-
-        // Clear animations for positions no longer in use
-        for pos in [
-            CharPosition::Left,
-            CharPosition::Center,
-            CharPosition::Right,
-        ] {
-            if !active_positions.contains(&pos) {
-                if let Some(anim) = ctx.char_anim_states.get_mut(&pos) {
-                    anim.reset();
-                }
-                if let Some(idle) = ctx.char_idle_states.get_mut(&pos) {
-                    idle.stop();
-                }
-                ctx.pending_idles.remove(&pos);
-            }
-        }
+        // Animations belong to sprites, not list entries, so they follow a
+        // character when others are added, removed or reordered
+        let keys = visual.character_keys();
+        ctx.char_anim_states.retain(|key, _| keys.contains(key));
+        ctx.char_idle_states.retain(|key, _| keys.contains(key));
+        ctx.pending_idles.retain(|key, _| keys.contains(key));
 
         // Start animations for each character
-        for char_state in &visual.characters {
-            let pos = char_state.position;
-
+        for (key, char_state) in keys.into_iter().zip(&visual.characters) {
             // Initialize state if not exists
-            ctx.char_anim_states.entry(pos).or_default();
-            ctx.char_idle_states.entry(pos).or_default();
+            let anim_state = ctx.char_anim_states.entry(key.clone()).or_default();
+            let idle_state = ctx.char_idle_states.entry(key.clone()).or_default();
 
             // Start enter animation if specified
             if let Some(enter) = &char_state.enter {
-                ctx.char_anim_states
-                    .get_mut(&pos)
-                    .unwrap()
-                    .start_enter(enter);
+                anim_state.start_enter(enter);
                 // Store pending idle to start after enter completes
                 if let Some(idle) = &char_state.idle {
-                    ctx.pending_idles.insert(pos, idle.clone());
+                    ctx.pending_idles.insert(key.clone(), idle.clone());
                 }
-                ctx.char_idle_states.get_mut(&pos).unwrap().stop();
+                idle_state.stop();
             } else if let Some(idle) = &char_state.idle {
                 // No enter animation, start idle directly
-                ctx.char_idle_states.get_mut(&pos).unwrap().start(idle);
+                idle_state.start(idle);
             }
 
             // Start exit animation if specified
             if let Some(exit) = &char_state.exit {
-                ctx.char_anim_states.get_mut(&pos).unwrap().start_exit(exit);
-                ctx.char_idle_states.get_mut(&pos).unwrap().stop();
-                ctx.pending_idles.remove(&pos);
+                anim_state.start_exit(exit);
+                idle_state.stop();
+                ctx.pending_idles.remove(&key);
             }
         }
     }
 
+    // Start character moves
+    for tween in state.current_moves() {
+        ctx.char_tweens.start(&tween);
+    }
+
+    // Start layer show/hide transitions
+    for change in state.current_layer_changes() {
+        ctx.layer_transitions.start(&change);
+    }
+
     // Update particles if specified
     if let Some((particles, intensity)) = state.current_particles() {
         if particles.is_empty() {
@@ -247,19 +242,19 @@ pub fn update_animations(ctx: &mut GameContext) {
     }
 
     // Check if enter animations completed and start pending idles
-    let completed_positions: Vec<CharPosition> = ctx
+    let completed: Vec<CharacterKey> = ctx
         .char_anim_states
         .iter()
         .filter(|(_, anim_state)| {
             !anim_state.is_active()
                 && anim_state.direction() == Some(AnimationDirection::Enter)
         })
-        .map(|(pos, _)| *pos)
+        .map(|(key, _)| key.clone())
         .collect();
 
-    for pos in completed_positions {
-        if let Some(idle) = ctx.pending_idles.remove(&pos)
-            && let Some(idle_state) = ctx.char_idle_states.get_mut(&pos)
+    for key in completed {
+        if let Some(idle) = ctx.pending_idles.remove(&key)
+            && let Some(idle_state) = ctx.char_idle_states.get_mut(&key)
         {
             idle_state.start(&idle);
         }
@@ -270,6 +265,12 @@ pub fn update_animations(ctx: &mut GameContext) {
         idle_state.update();
     }
 
+    // Update character move animations
+    ctx.char_tweens.update();
+
+    // Update layer transitions
+    ctx.layer_transitions.update();
+
     // Update camera animation state
     ctx.camera_anim_state.update(get_frame_time());
     ctx.camera_state = ctx.camera_anim_state.current();
@@ -8,10 +8,11 @@ use crate::render::{
     CameraTransform, InputSource, TextBoxConfig, count_nvl_chars, count_visible_chars,
     draw_backlog, draw_choices_with_timer, draw_continue_indicator_with_font, draw_input,
     draw_nvl_text_box, draw_speaker_name, draw_text_box_typewriter, draw_text_box_with_font,
-    interpolate_variables, pop_camera_transform, push_camera_transform,
+    parse_color, pop_camera_transform, push_camera_transform,
 };
-use crate::runtime::{GameState, Value, VisualState};
-use crate::scenario::{Choice, Input};
+use crate::runtime::{ChoiceOption, GameState, Value, VisualState};
+use crate::scenario::markup::text_pacing;
+use crate::scenario::{Input, interpolate_variables};
 
 use super::draw_visual;
 
@@ -50,6 +51,8 @@ pub async fn handle_text(
         &ctx.char_idle_state,
         &ctx.char_anim_states,
         &ctx.char_idle_states,
+        &ctx.char_tweens,
+        &ctx.layer_transitions,
         &ctx.modular_char_defs,
         &ctx.video_bg_state,
     )
@@ -60,23 +63,27 @@ pub async fn handle_text(
     let resolved_text = ctx.language_config.resolve(&text);
 
     // Interpolate variables in text
-    let interpolated_text = interpolate_variables(&resolved_text, state.variables());
+    let interpolated_text = interpolate_variables(&resolved_text, &state.script_variables());
 
     // Resolve speaker name
     let resolved_speaker = speaker.as_ref().map(|name| {
-        interpolate_variables(&ctx.language_config.resolve(name), state.variables())
+        interpolate_variables(&ctx.language_config.resolve(name), &state.script_variables())
     });
+    let speaker_color = state.current_speaker_color().and_then(parse_color);
 
     // Reset typewriter if text changed
     if ctx.last_text.as_ref() != Some(&interpolated_text) {
+        let pacing = text_pacing(&interpolated_text);
         if is_nvl_mode {
             // In NVL mode, count all accumulated chars plus current text
             let total_chars = count_nvl_chars(&ctx.nvl_state, &interpolated_text);
-            ctx.typewriter_state.reset(total_chars);
+            let earlier_chars = total_chars - count_visible_chars(&interpolated_text);
+            ctx.typewriter_state
+                .reset_paced(total_chars, pacing.shifted(earlier_chars));
         } else {
-            // In ADV mode, count visible characters (excluding color tags)
+            // In ADV mode, count visible characters (excluding tags)
             let total_chars = count_visible_chars(&interpolated_text);
-            ctx.typewriter_state.reset(total_chars);
+            ctx.typewriter_state.reset_paced(total_chars, pacing);
         }
         ctx.last_text = Some(interpolated_text.clone());
     }
@@ -90,6 +97,7 @@ pub async fn handle_text(
             &ctx.nvl_config,
             &ctx.nvl_state,
             resolved_speaker.as_deref(),
+            speaker_color,
             &interpolated_text,
             font,
             char_limit,
@@ -97,7 +105,7 @@ pub async fn handle_text(
     } else {
         // ADV mode: draw speaker name if present
         if let Some(ref name) = resolved_speaker {
-            draw_speaker_name(text_config, name, font);
+            draw_speaker_name(text_config, name, speaker_color, font);
         }
 
         // Draw text box with typewriter effect
@@ -126,9 +134,9 @@ pub async fn handle_text(
         let mut auto_advance = false;
         if ctx.auto_mode && ctx.typewriter_state.is_complete() {
             ctx.auto_timer += get_frame_time() as f64;
-            // Wait time based on text length, adjusted by auto speed setting
+            // Wait time based on visible text length, adjusted by auto speed setting
             // Higher speed = shorter wait time
-            let base_wait = 2.0 + resolved_text.len() as f64 * 0.05;
+            let base_wait = 2.0 + count_visible_chars(&interpolated_text) as f64 * 0.05;
             let wait_time = base_wait / ctx.settings.auto_speed as f64;
             if ctx.auto_timer >= wait_time {
                 auto_advance = true;
@@ -136,8 +144,9 @@ pub async fn handle_text(
             }
         }
 
-        // Handle click/Advance keybind
+        // Handle click/Advance keybind; {nw} text advances by itself once shown
         let input_pressed = input.advance_pressed;
+        let no_wait_advance = ctx.typewriter_state.is_complete() && ctx.typewriter_state.no_wait();
 
         if skip_active || auto_advance {
             // Check if we can skip (skip_unread=true or text is read)
@@ -149,8 +158,11 @@ pub async fn handle_text(
                 ctx.typewriter_state.complete();
                 // In NVL mode, add completed text to buffer before advancing
                 if is_nvl_mode {
-                    ctx.nvl_state
-                        .push(resolved_speaker.clone(), interpolated_text.clone());
+                    ctx.nvl_state.push(
+                        resolved_speaker.clone(),
+                        speaker_color,
+                        interpolated_text.clone(),
+                    );
                 }
                 ctx.read_state
                     .mark_read(SCENARIO_PATH, state.current_index());
@@ -161,13 +173,16 @@ pub async fn handle_text(
                 ctx.skip_mode = false;
                 eprintln!("Skip mode stopped (unread text)");
             }
-        } else if input_pressed {
+        } else if input_pressed || no_wait_advance {
             if ctx.typewriter_state.is_complete() {
                 // Text is complete, advance to next
                 // In NVL mode, add completed text to buffer before advancing
                 if is_nvl_mode {
-                    ctx.nvl_state
-                        .push(resolved_speaker.clone(), interpolated_text.clone());
+                    ctx.nvl_state.push(
+                        resolved_speaker.clone(),
+                        speaker_color,
+                        interpolated_text.clone(),
+                    );
                 }
                 ctx.read_state
                     .mark_read(SCENARIO_PATH, state.current_index());
@@ -213,7 +228,7 @@ pub async fn handle_choices(
     state: &mut GameState,
     speaker: Option<LocalizedString>,
     text: LocalizedString,
-    choices: Vec<Choice>,
+    choices: Vec<ChoiceOption>,
     visual: VisualState,
     timeout: Option<f32>,
     default_choice: Option<usize>,
@@ -239,6 +254,8 @@ pub async fn handle_choices(
         &ctx.char_idle_state,
         &ctx.char_anim_states,
         &ctx.char_idle_states,
+        &ctx.char_tweens,
+        &ctx.layer_transitions,
         &ctx.modular_char_defs,
         &ctx.video_bg_state,
     )
@@ -249,19 +266,21 @@ pub async fn handle_choices(
     let resolved_text = ctx.language_config.resolve(&text);
 
     // Interpolate variables in text
-    let interpolated_text = interpolate_variables(&resolved_text, state.variables());
+    let interpolated_text = interpolate_variables(&resolved_text, &state.script_variables());
 
     // Draw speaker name if present (also interpolate variables)
     if let Some(ref name) = speaker {
         let resolved_name = ctx.language_config.resolve(name);
-        let interpolated_name = interpolate_variables(&resolved_name, state.variables());
-        draw_speaker_name(text_config, &interpolated_name, font);
+        let interpolated_name = interpolate_variables(&resolved_name, &state.script_variables());
+        let speaker_color = state.current_speaker_color().and_then(parse_color);
+        draw_speaker_name(text_config, &interpolated_name, speaker_color, font);
     }
 
     // Reset typewriter if text changed
     if ctx.last_text.as_ref() != Some(&interpolated_text) {
         let total_chars = count_visible_chars(&interpolated_text);
-        ctx.typewriter_state.reset(total_chars);
+        ctx.typewriter_state
+            .reset_paced(total_chars, text_pacing(&interpolated_text));
         ctx.last_text = Some(interpolated_text.clone());
         // Reset choice timer and navigation state when text changes
         ctx.choice_timer = timeout;
@@ -355,6 +374,7 @@ pub async fn handle_choices(
             } else if ctx.choice_nav_state.input_source == InputSource::Gamepad
                 && input.choice_nav == Some(ChoiceNavAction::Confirm)
                 && let Some(idx) = ctx.choice_nav_state.focus_index
+                && choices.get(idx).is_some_and(ChoiceOption::is_enabled)
             {
                 // Gamepad A button
                 Some(idx)
@@ -399,6 +419,8 @@ pub async fn handle_wait(
         &ctx.char_idle_state,
         &ctx.char_anim_states,
         &ctx.char_idle_states,
+        &ctx.char_tweens,
+        &ctx.layer_transitions,
         &ctx.modular_char_defs,
         &ctx.video_bg_state,
     )
@@ -462,6 +484,8 @@ pub async fn handle_input(
         &ctx.char_idle_state,
         &ctx.char_anim_states,
         &ctx.char_idle_states,
+        &ctx.char_tweens,
+        &ctx.layer_transitions,
         &ctx.modular_char_defs,
         &ctx.video_bg_state,
     )
@@ -11,7 +11,7 @@ use macroquad::prelude::*;
 
 use crate::game::{GameContext, GameMode, InputDetector, PlayerAction, QUICK_SAVE_PATH, SCENARIO_PATH};
 use crate::render::{calculate_camera_transform, draw_achievement, draw_debug};
-use crate::runtime::{DisplayState, GameState, SaveData};
+use crate::runtime::{DisplayState, GameState, PersistentVariables, SaveData};
 use crate::scenario::load_scenario;
 
 use super::HandlerResult;
@@ -81,7 +81,7 @@ fn load_game_from(path: &str) -> Option<GameState> {
     };
 
     eprintln!("Game loaded from {}", path);
-    Some(GameState::from_save_data(&save_data, scenario))
+    Some(GameState::restore(&save_data, scenario, PersistentVariables::load()))
 }
 
 /// Load game state from quick save slot.
@@ -172,17 +172,11 @@ pub async fn handle_ingame(ctx: &mut GameContext, font: Option<&Font>) -> Handle
                 transition_to_flowchart = true;
                 ctx.flowchart_state.dirty = true;
             }
-            PlayerAction::Rollback => {
-                if !ctx.show_backlog && state.can_rollback() {
-                    state.rollback();
-                }
+            PlayerAction::Rollback if !ctx.show_backlog && state.can_rollback() => {
+                state.rollback();
             }
             PlayerAction::Screenshot => save_screenshot(),
-            PlayerAction::ReturnToTitle => {
-                if !state.is_ended() {
-                    return_to_title = true;
-                }
-            }
+            PlayerAction::ReturnToTitle if !state.is_ended() => return_to_title = true,
             _ => {}
         }
     }
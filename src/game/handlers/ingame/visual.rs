@@ -3,14 +3,17 @@
 use std::collections::HashMap;
 
 use crate::cache::TextureCache;
+use macroquad::prelude::*;
+
 use crate::render::{
-    CharAnimationState, CharIdleState, VideoBackgroundState, draw_background_with_offset,
-    draw_character_animated, draw_modular_char,
+    CharAnimationState, CharIdleState, CharPlacement, CharTweenState, LayerTransitionState,
+    VideoBackgroundState, draw_background_with_offset, draw_character_animated,
+    draw_layer_with_offset, draw_modular_char,
 };
-use crate::runtime::VisualState;
-use crate::scenario::{CharPosition, ModularCharDef};
+use crate::runtime::{CharacterKey, CharacterSlot, LayerState, VisualState};
+use crate::scenario::ModularCharDef;
 
-/// Draw visual elements (background and character) with shake offset and character animation.
+/// Draw visual elements (background, layers and characters) with shake offset and character animation.
 #[allow(clippy::too_many_arguments)]
 pub async fn draw_visual(
     visual: &VisualState,
@@ -18,8 +21,10 @@ pub async fn draw_visual(
     offset: (f32, f32),
     char_anim: &CharAnimationState,
     char_idle: &CharIdleState,
-    char_anim_states: &HashMap<CharPosition, CharAnimationState>,
-    char_idle_states: &HashMap<CharPosition, CharIdleState>,
+    char_anim_states: &HashMap<CharacterKey, CharAnimationState>,
+    char_idle_states: &HashMap<CharacterKey, CharIdleState>,
+    char_tweens: &CharTweenState,
+    layer_transitions: &LayerTransitionState,
     modular_char_defs: &HashMap<String, ModularCharDef>,
     video_bg_state: &VideoBackgroundState,
 ) {
@@ -33,25 +38,54 @@ pub async fn draw_visual(
         draw_background_with_offset(&texture, offset);
     }
 
+    // Layers below 0 go between the background and the characters
+    let (back_layers, front_layers): (Vec<_>, Vec<_>) = layer_transitions
+        .draw_list(visual.layer_draw_order())
+        .into_iter()
+        .partition(|(layer, _)| layer.is_behind_characters());
+    draw_layers(&back_layers, cache, offset).await;
+
     // Draw modular character (if specified)
     if let Some(modular) = &visual.modular_char {
         if let Some(def) = modular_char_defs.get(&modular.name) {
             draw_modular_char(modular, def, cache, offset).await;
         }
     } else if !visual.characters.is_empty() {
-        // Draw multiple characters (if specified)
-        for char_state in &visual.characters {
+        // Draw multiple characters (if specified), back to front
+        let keys = visual.character_keys();
+        for index in visual.character_draw_order() {
+            let char_state = &visual.characters[index];
             if let Some(texture) = cache.get(&char_state.path).await {
-                let pos = char_state.position;
-                let anim = char_anim_states.get(&pos).cloned().unwrap_or_default();
-                let idle = char_idle_states.get(&pos).cloned().unwrap_or_default();
-                draw_character_animated(&texture, pos, offset, &anim, &idle);
+                let key = &keys[index];
+                let anim = char_anim_states.get(key).cloned().unwrap_or_default();
+                let idle = char_idle_states.get(key).cloned().unwrap_or_default();
+                let slot = CharacterSlot::Index(index);
+                let placement = char_tweens.placement(slot, CharPlacement::of(char_state));
+                draw_character_animated(&texture, placement, offset, &anim, &idle);
             }
         }
-    } else if let Some(char_path) = &visual.character {
+    } else if let Some(char_state) = visual.character_state(CharacterSlot::Single) {
         // Draw single character with animation
-        if let Some(texture) = cache.get(char_path).await {
-            draw_character_animated(&texture, visual.char_pos, offset, char_anim, char_idle);
+        if let Some(texture) = cache.get(&char_state.path).await {
+            let slot = CharacterSlot::Single;
+            let placement = char_tweens.placement(slot, CharPlacement::of(&char_state));
+            draw_character_animated(&texture, placement, offset, char_anim, char_idle);
+        }
+    }
+
+    draw_layers(&front_layers, cache, offset).await;
+}
+
+/// Draw image layers with their show/hide transitions.
+async fn draw_layers(
+    layers: &[(&LayerState, CharAnimationState)],
+    cache: &mut TextureCache,
+    offset: (f32, f32),
+) {
+    for (layer, anim) in layers {
+        if let Some(texture) = cache.get(&layer.path).await {
+            let color = Color::new(1.0, 1.0, 1.0, anim.alpha());
+            draw_layer_with_offset(&texture, (offset.0 + anim.offset_x(), offset.1), color);
         }
     }
 }
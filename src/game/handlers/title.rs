@@ -4,7 +4,7 @@ use macroquad::prelude::*;
 
 use crate::game::{GameContext, GameMode, QUICK_SAVE_PATH};
 use crate::render::{ChapterSelectState, TitleMenuItem, draw_title_screen};
-use crate::runtime::{Action, SaveData};
+use crate::runtime::{Action, PersistentVariables, SaveData};
 
 use super::HandlerResult;
 
@@ -29,7 +29,11 @@ fn load_game_from(path: &str) -> Option<crate::runtime::GameState> {
     };
 
     eprintln!("Game loaded from {}", path);
-    Some(crate::runtime::GameState::from_save_data(&save_data, scenario))
+    Some(crate::runtime::GameState::restore(
+        &save_data,
+        scenario,
+        PersistentVariables::load(),
+    ))
 }
 
 /// Load game state from quick save slot.
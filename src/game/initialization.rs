@@ -10,17 +10,17 @@ use crate::i18n::LanguageConfig;
 use crate::input::GamepadState;
 use crate::render::{
     AchievementConfig, BacklogConfig, BacklogState, ChapterSelectConfig, ChapterSelectState,
-    CharAnimationState, CharIdleState, ChoiceButtonConfig, ChoiceNavState, CinematicState,
-    DebugConfig, DebugState, FlowchartConfig, FlowchartState, GalleryConfig, GalleryState,
-    GameSettings, InputConfig, InputState, NvlConfig, NvlState, ParticleState, SettingsConfig,
-    ShakeState, TextBoxConfig, TitleConfig, TransitionState, TypewriterState, VideoBackgroundState,
-    VideoState,
+    CharAnimationState, CharIdleState, CharTweenState, ChoiceButtonConfig, ChoiceNavState,
+    CinematicState, DebugConfig, DebugState, FlowchartConfig, FlowchartState, GalleryConfig,
+    GalleryState, GameSettings, InputConfig, InputState, LayerTransitionState, NvlConfig, NvlState,
+    ParticleState, SettingsConfig, ShakeState, TextBoxConfig, TitleConfig, TransitionState,
+    TypewriterState, VideoBackgroundState, VideoState,
 };
 use crate::runtime::{
     AchievementNotifier, Achievements, CameraAnimationState, CameraState, Chapter, ChapterManager,
-    GameState, ReadState, Unlocks,
+    CharacterKey, GameState, PersistentVariables, ReadState, Unlocks,
 };
-use crate::scenario::{CharPosition, ModularCharDef, Scenario, load_scenario};
+use crate::scenario::{ModularCharDef, Scenario, load_scenario};
 
 use super::{FONT_PATH, SCENARIO_PATH};
 
@@ -99,10 +99,16 @@ pub struct GameContext {
     pub char_idle_state: CharIdleState,
     pub pending_idle: Option<crate::scenario::CharIdleAnimation>,
 
-    // Character animation states (multiple characters)
-    pub char_anim_states: HashMap<CharPosition, CharAnimationState>,
-    pub char_idle_states: HashMap<CharPosition, CharIdleState>,
-    pub pending_idles: HashMap<CharPosition, crate::scenario::CharIdleAnimation>,
+    // Character animation states (multiple characters, by sprite)
+    pub char_anim_states: HashMap<CharacterKey, CharAnimationState>,
+    pub char_idle_states: HashMap<CharacterKey, CharIdleState>,
+    pub pending_idles: HashMap<CharacterKey, crate::scenario::CharIdleAnimation>,
+
+    // Character move animations
+    pub char_tweens: CharTweenState,
+
+    // Image layer show/hide transitions
+    pub layer_transitions: LayerTransitionState,
 
     // Particle and cinematic states
     pub particle_state: ParticleState,
@@ -271,6 +277,8 @@ impl GameContext {
                 char_anim_states: HashMap::new(),
                 char_idle_states: HashMap::new(),
                 pending_idles: HashMap::new(),
+                char_tweens: CharTweenState::default(),
+                layer_transitions: LayerTransitionState::default(),
 
                 // Particle and cinematic states
                 particle_state: ParticleState::default(),
@@ -312,7 +320,10 @@ impl GameContext {
     /// Start a new game.
     pub fn start_new_game(&mut self) -> anyhow::Result<()> {
         let new_scenario = load_scenario(SCENARIO_PATH)?;
-        self.game_state = Some(GameState::new(new_scenario));
+        self.game_state = Some(GameState::with_persistent(
+            new_scenario,
+            PersistentVariables::load(),
+        ));
         self.reset_game_state();
         Ok(())
     }
@@ -320,7 +331,7 @@ impl GameContext {
     /// Start game from a specific chapter.
     pub fn start_from_chapter(&mut self, start_label: &str) -> anyhow::Result<()> {
         let new_scenario = load_scenario(SCENARIO_PATH)?;
-        let mut new_state = GameState::new(new_scenario);
+        let mut new_state = GameState::with_persistent(new_scenario, PersistentVariables::load());
         new_state.jump_to_label(start_label);
         self.game_state = Some(new_state);
         self.reset_game_state();
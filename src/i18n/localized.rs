@@ -179,6 +179,24 @@ impl Serialize for LocalizedString {
     }
 }
 
+/// Schema matching the forms accepted by the deserializer.
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for LocalizedString {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "LocalizedString".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "Text in one language, a map of language codes to text, or a translation key starting with @.",
+            "anyOf": [
+                { "type": "string" },
+                { "type": "object", "additionalProperties": { "type": "string" } }
+            ]
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
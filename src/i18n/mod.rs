@@ -44,6 +44,11 @@ mod localized;
 pub use localized::LocalizedString;
 
 use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_yaml::Value;
 
 /// Translation storage for key-based localization.
 #[derive(Debug, Clone, Default)]
@@ -104,6 +109,66 @@ impl Translations {
     pub fn languages(&self) -> Vec<String> {
         self.data.keys().cloned().collect()
     }
+
+    /// Load the language files of a directory, such as `i18n/en.yaml` and
+    /// `i18n/ja.yaml`: one file per language, named by its code, whose
+    /// nested keys are joined with dots (`intro.welcome`).
+    pub fn load_dir(dir: &Path) -> Result<Self> {
+        let mut translations = Self::new();
+        let entries =
+            fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if !path
+                .extension()
+                .is_some_and(|ext| ext == "yaml" || ext == "yml")
+            {
+                continue;
+            }
+            let Some(lang) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let value: Value = serde_yaml::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            let mut keys = HashMap::new();
+            flatten_keys("", &value, &mut keys);
+            translations.add_language(lang, keys);
+        }
+        Ok(translations)
+    }
+}
+
+/// Collect the texts of a language file under their dotted key paths.
+fn flatten_keys(prefix: &str, value: &Value, keys: &mut HashMap<String, String>) {
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                let key = match key {
+                    Value::String(key) => key.clone(),
+                    Value::Number(key) => key.to_string(),
+                    _ => continue,
+                };
+                let path = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_keys(&path, value, keys);
+            }
+        }
+        Value::String(text) => {
+            keys.insert(prefix.to_string(), text.clone());
+        }
+        Value::Number(number) => {
+            keys.insert(prefix.to_string(), number.to_string());
+        }
+        Value::Bool(flag) => {
+            keys.insert(prefix.to_string(), flag.to_string());
+        }
+        _ => {}
+    }
 }
 
 /// Language configuration for the game.
@@ -24,6 +24,22 @@ pub fn file_exists(path: &str) -> bool {
     std::path::Path::new(path).exists()
 }
 
+/// Seed for the random number generator, taken from the clock.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn random_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Seed for the random number generator (WASM: `Math.random` and the clock).
+#[cfg(target_arch = "wasm32")]
+pub fn random_seed() -> u64 {
+    let random = (js_sys::Math::random() * (1u64 << 53) as f64) as u64;
+    random ^ (js_sys::Date::now() as u64).rotate_left(32)
+}
+
 // WASM implementations using localStorage
 #[cfg(target_arch = "wasm32")]
 fn get_local_storage() -> Option<web_sys::Storage> {
@@ -1,7 +1,9 @@
 use macroquad::prelude::*;
 
 use crate::i18n::LanguageConfig;
+use crate::render::text::parse_color;
 use crate::runtime::HistoryEntry;
+use crate::scenario::markup::strip_markup;
 
 /// Configuration for backlog display.
 pub struct BacklogConfig {
@@ -14,6 +16,8 @@ pub struct BacklogConfig {
     pub padding: f32,
     pub background_color: Color,
     pub text_color: Color,
+    /// Speaker name colour for characters without their own.
+    pub speaker_color: Color,
 }
 
 impl Default for BacklogConfig {
@@ -28,6 +32,7 @@ impl Default for BacklogConfig {
             padding: 20.0,
             background_color: Color::new(0.0, 0.0, 0.0, 0.9),
             text_color: Color::new(1.0, 1.0, 1.0, 1.0),
+            speaker_color: YELLOW,
         }
     }
 }
@@ -96,7 +101,7 @@ pub fn draw_backlog(
     let total_height = entries.len() as f32 * config.line_height;
 
     for (i, entry) in entries.iter().enumerate() {
-        let resolved_text = lang.resolve(&entry.text);
+        let resolved_text = strip_markup(&lang.resolve(&entry.text));
         if resolved_text.is_empty() {
             continue;
         }
@@ -117,13 +122,20 @@ pub fn draw_backlog(
             resolved_text
         };
 
-        draw_text(
-            &display_text,
-            config.x + config.padding,
-            y_pos,
-            config.font_size,
-            config.text_color,
-        );
+        // Speaker name in the character's colour, then the text
+        let mut x = config.x + config.padding;
+        if let Some(speaker) = &entry.speaker {
+            let name = format!("{}: ", lang.resolve(speaker));
+            let color = entry
+                .speaker_color
+                .as_deref()
+                .and_then(parse_color)
+                .unwrap_or(config.speaker_color);
+            draw_text(&name, x, y_pos, config.font_size, color);
+            x += measure_text(&name, None, config.font_size as u16, 1.0).width;
+        }
+
+        draw_text(&display_text, x, y_pos, config.font_size, config.text_color);
     }
 
     // Draw scroll indicator if needed
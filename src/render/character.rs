@@ -1,8 +1,10 @@
 use macroquad::prelude::*;
 
-use crate::scenario::{
-    CharAnimation, CharAnimationType, CharIdleAnimation, CharIdleType, CharPosition, Easing,
-};
+use std::collections::HashMap;
+
+use crate::render::text::parse_color;
+use crate::runtime::{CharacterSlot, CharacterState, CharacterTween};
+use crate::scenario::{CharAnimation, CharAnimationType, CharIdleAnimation, CharIdleType, Easing};
 
 /// Character animation direction.
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -91,7 +93,7 @@ impl CharAnimationState {
     }
 
     /// Get the current X offset for slide animations.
-    pub fn offset_x(&self, position: CharPosition) -> f32 {
+    pub fn offset_x(&self) -> f32 {
         if !self.active {
             return 0.0;
         }
@@ -100,21 +102,21 @@ impl CharAnimationState {
         let screen_width = screen_width();
         let slide_distance = screen_width * 0.5;
 
-        match (self.animation_type, self.direction, position) {
+        match (self.animation_type, self.direction) {
             // Enter from left: start off-screen left, slide to position
-            (CharAnimationType::SlideLeft, Some(AnimationDirection::Enter), _) => {
+            (CharAnimationType::SlideLeft, Some(AnimationDirection::Enter)) => {
                 -slide_distance * (1.0 - progress)
             }
             // Enter from right: start off-screen right, slide to position
-            (CharAnimationType::SlideRight, Some(AnimationDirection::Enter), _) => {
+            (CharAnimationType::SlideRight, Some(AnimationDirection::Enter)) => {
                 slide_distance * (1.0 - progress)
             }
             // Exit to left: slide off-screen left
-            (CharAnimationType::SlideLeft, Some(AnimationDirection::Exit), _) => {
+            (CharAnimationType::SlideLeft, Some(AnimationDirection::Exit)) => {
                 -slide_distance * progress
             }
             // Exit to right: slide off-screen right
-            (CharAnimationType::SlideRight, Some(AnimationDirection::Exit), _) => {
+            (CharAnimationType::SlideRight, Some(AnimationDirection::Exit)) => {
                 slide_distance * progress
             }
             _ => 0.0,
@@ -240,16 +242,125 @@ impl CharIdleState {
     }
 }
 
+/// Where and how a character sprite is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CharPlacement {
+    /// Horizontal centre as a fraction of the screen width.
+    pub x: f32,
+    /// Bottom edge as a fraction of the screen height.
+    pub y: f32,
+    /// Size relative to the default sprite height (80% of the screen).
+    pub scale: f32,
+    /// Mirror the sprite horizontally.
+    pub flip: bool,
+    /// Opacity (0.0 to 1.0).
+    pub opacity: f32,
+    /// Colour multiplied into the sprite.
+    pub tint: Color,
+}
+
+impl CharPlacement {
+    /// Placement of a character on screen.
+    pub fn of(state: &CharacterState) -> Self {
+        let transform = &state.transform;
+        Self {
+            x: state.anchor_x(),
+            y: state.anchor_y(),
+            scale: transform.scale,
+            flip: state.flip,
+            opacity: transform.opacity,
+            tint: transform
+                .tint
+                .as_deref()
+                .and_then(parse_color)
+                .unwrap_or(WHITE),
+        }
+    }
+
+    /// Placement between `self` (t = 0.0) and `to` (t = 1.0).
+    pub fn lerp(&self, to: &Self, t: f32) -> Self {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Self {
+            x: mix(self.x, to.x),
+            y: mix(self.y, to.y),
+            scale: mix(self.scale, to.scale),
+            flip: to.flip,
+            opacity: mix(self.opacity, to.opacity),
+            tint: Color::new(
+                mix(self.tint.r, to.tint.r),
+                mix(self.tint.g, to.tint.g),
+                mix(self.tint.b, to.tint.b),
+                mix(self.tint.a, to.tint.a),
+            ),
+        }
+    }
+}
+
+/// A `move` being animated.
+#[derive(Clone)]
+struct ActiveTween {
+    from: CharPlacement,
+    to: CharPlacement,
+    duration: f32,
+    elapsed: f32,
+    easing: Easing,
+}
+
+/// Character `move` animations in progress, by character.
+#[derive(Clone, Default)]
+pub struct CharTweenState {
+    tweens: HashMap<CharacterSlot, ActiveTween>,
+}
+
+impl CharTweenState {
+    /// Start animating a character from its old to its new placement.
+    pub fn start(&mut self, tween: &CharacterTween) {
+        self.tweens.insert(
+            tween.slot,
+            ActiveTween {
+                from: CharPlacement::of(&tween.from),
+                to: CharPlacement::of(&tween.to),
+                duration: tween.duration,
+                elapsed: 0.0,
+                easing: tween.easing,
+            },
+        );
+    }
+
+    /// Advance the animations, dropping finished ones.
+    pub fn update(&mut self) {
+        let delta = get_frame_time();
+        self.tweens.retain(|_, tween| {
+            tween.elapsed += delta;
+            tween.elapsed < tween.duration
+        });
+    }
+
+    /// Placement to draw a character at, given where it ends up.
+    ///
+    /// A tween only applies while the character still ends up where the
+    /// tween was heading.
+    pub fn placement(&self, slot: CharacterSlot, target: CharPlacement) -> CharPlacement {
+        match self.tweens.get(&slot) {
+            Some(tween) if tween.to == target && tween.duration > 0.0 => {
+                let progress = (tween.elapsed / tween.duration).clamp(0.0, 1.0);
+                tween.from.lerp(&target, tween.easing.apply(progress))
+            }
+            _ => target,
+        }
+    }
+}
+
 /// Draw a character with animation effects.
 pub fn draw_character_animated(
     texture: &Texture2D,
-    position: CharPosition,
+    placement: CharPlacement,
     base_offset: (f32, f32),
     anim_state: &CharAnimationState,
     idle_state: &CharIdleState,
 ) {
     let screen_height = screen_height();
-    let char_height = screen_height * 0.8;
+    let char_height = screen_height * 0.8 * placement.scale;
     let scale = char_height / texture.height();
     let char_width = texture.width() * scale;
 
@@ -258,26 +369,26 @@ pub fn draw_character_animated(
     let final_char_height = char_height * idle_scale;
     let final_char_width = char_width * idle_scale;
 
-    // Calculate base X position
-    let base_x = match position {
-        CharPosition::Left => screen_width() * 0.2 - final_char_width / 2.0,
-        CharPosition::Center => (screen_width() - final_char_width) / 2.0,
-        CharPosition::Right => screen_width() * 0.8 - final_char_width / 2.0,
-    };
-
-    let y = screen_height - final_char_height;
+    // Anchor at the bottom centre
+    let base_x = screen_width() * placement.x - final_char_width / 2.0;
+    let y = screen_height * placement.y - final_char_height;
 
     // Apply animation offsets
-    let anim_offset_x = anim_state.offset_x(position);
+    let anim_offset_x = anim_state.offset_x();
     let idle_offset_x = idle_state.offset_x();
     let idle_offset_y = idle_state.offset_y();
 
     let x = base_x + base_offset.0 + anim_offset_x + idle_offset_x;
     let final_y = y + base_offset.1 + idle_offset_y;
 
-    // Apply animation alpha
-    let alpha = anim_state.alpha();
-    let color = Color::new(1.0, 1.0, 1.0, alpha);
+    // Apply animation alpha, opacity and tint
+    let alpha = anim_state.alpha() * placement.opacity;
+    let color = Color::new(
+        placement.tint.r,
+        placement.tint.g,
+        placement.tint.b,
+        placement.tint.a * alpha,
+    );
 
     draw_texture_ex(
         texture,
@@ -286,6 +397,7 @@ pub fn draw_character_animated(
         color,
         DrawTextureParams {
             dest_size: Some(Vec2::new(final_char_width, final_char_height)),
+            flip_x: placement.flip,
             ..Default::default()
         },
     );
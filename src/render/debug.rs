@@ -1,6 +1,6 @@
 use macroquad::prelude::*;
 
-use crate::runtime::{GameState, Variables};
+use crate::runtime::{GameState, Value, Variables};
 
 /// Debug overlay configuration.
 pub struct DebugConfig {
@@ -187,12 +187,12 @@ pub fn draw_debug(
             ..Default::default()
         }
     };
-    draw_text_ex("VARIABLES:", text_x, draw_y, vars_title_params);
+    draw_text_ex("VARIABLES:", text_x, draw_y, vars_title_params.clone());
     draw_y += config.line_height + 3.0;
 
     // Draw variables (with scroll)
     let variables = game_state.variables();
-    draw_variables(
+    draw_y = draw_variables(
         config,
         variables,
         text_x,
@@ -200,9 +200,22 @@ pub fn draw_debug(
         config.width - config.padding * 2.0,
         font,
     );
+    draw_y += 10.0;
+
+    // Persistent variables, shared by every playthrough
+    draw_text_ex("PERSISTENT:", text_x, draw_y, vars_title_params);
+    draw_y += config.line_height + 3.0;
+    draw_variables(
+        config,
+        game_state.persistent().variables(),
+        text_x,
+        draw_y,
+        config.width - config.padding * 2.0,
+        font,
+    );
 }
 
-/// Draw variables list.
+/// Draw variables list, returning the y position below it.
 fn draw_variables(
     config: &DebugConfig,
     variables: &Variables,
@@ -210,7 +223,7 @@ fn draw_variables(
     start_y: f32,
     max_width: f32,
     font: Option<&Font>,
-) {
+) -> f32 {
     let mut y = start_y;
 
     // Get all variables and sort by name
@@ -233,7 +246,7 @@ fn draw_variables(
             }
         };
         draw_text_ex("(no variables)", x, y, empty_params);
-        return;
+        return y + config.line_height;
     }
 
     for (name, value) in vars {
@@ -266,11 +279,7 @@ fn draw_variables(
             }
         };
 
-        let value_str = match value {
-            crate::runtime::Value::String(s) => format!("\"{}\"", s),
-            crate::runtime::Value::Int(i) => i.to_string(),
-            crate::runtime::Value::Bool(b) => b.to_string(),
-        };
+        let value_str = format_value(value);
 
         let name_text = format!("{}: ", name);
         draw_text_ex(&name_text, x, y, name_params);
@@ -300,4 +309,27 @@ fn draw_variables(
         draw_text_ex(&display_value, x + name_width, y, value_params);
         y += config.line_height;
     }
+
+    y
+}
+
+/// Format a variable value for the overlay, quoting strings and bracketing
+/// collections so their types are visible.
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("\"{}\"", s),
+        Value::Float(x) => format!("{:?}", x),
+        Value::List(items) => {
+            let items: Vec<String> = items.iter().map(format_value).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Map(map) => {
+            let entries: Vec<String> = map
+                .iter()
+                .map(|(key, value)| format!("{}: {}", key, format_value(value)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        other => other.to_string(),
+    }
 }
@@ -17,6 +17,8 @@ pub struct FlowchartConfig {
     pub choice_edge_colors: Vec<Color>,
     pub jump_edge_color: Color,
     pub conditional_edge_color: Color,
+    pub else_edge_color: Color,
+    pub annotation_color: Color,
     pub title_font_size: f32,
     pub node_font_size: f32,
     pub padding: f32,
@@ -43,6 +45,8 @@ impl Default for FlowchartConfig {
             ],
             jump_edge_color: Color::new(0.7, 0.7, 0.7, 0.8),
             conditional_edge_color: Color::new(1.0, 1.0, 0.5, 0.8),
+            else_edge_color: Color::new(0.8, 0.6, 0.3, 0.8),
+            annotation_color: Color::new(0.8, 0.8, 0.85, 0.9),
             title_font_size: 32.0,
             node_font_size: 14.0,
             padding: 40.0,
@@ -232,12 +236,13 @@ pub fn draw_flowchart(
         let edge_color = match edge.edge_type {
             EdgeType::Sequential => config.edge_color,
             EdgeType::Jump => config.jump_edge_color,
-            EdgeType::Choice(idx) => config
+            EdgeType::Choice(idx) | EdgeType::Case(idx) | EdgeType::Random(idx) => config
                 .choice_edge_colors
                 .get(idx % config.choice_edge_colors.len())
                 .copied()
                 .unwrap_or(config.edge_color),
             EdgeType::Conditional => config.conditional_edge_color,
+            EdgeType::Else | EdgeType::Default => config.else_edge_color,
         };
 
         // Draw edge line
@@ -292,6 +297,38 @@ pub fn draw_flowchart(
                 );
             }
         }
+
+        // Draw side effects (e.g., a choice's assignments) below the label
+        if !edge.annotations.is_empty() {
+            let mid_x = (from_x + to_x) / 2.0;
+            let mid_y = (from_y + to_y) / 2.0 + 14.0;
+            let text = edge.annotations.join(", ");
+            let truncated = if text.chars().count() > 24 {
+                format!("{}...", text.chars().take(24).collect::<String>())
+            } else {
+                text
+            };
+
+            let text_dim = measure_text(&truncated, None, 11, 1.0);
+            draw_rectangle(
+                mid_x - text_dim.width / 2.0 - 2.0,
+                mid_y - 8.0,
+                text_dim.width + 4.0,
+                13.0,
+                Color::new(0.1, 0.1, 0.15, 0.9),
+            );
+            draw_text_ex(
+                &truncated,
+                mid_x - text_dim.width / 2.0,
+                mid_y + 3.0,
+                TextParams {
+                    font,
+                    font_size: 11,
+                    color: config.annotation_color,
+                    ..Default::default()
+                },
+            );
+        }
     }
 
     // Draw nodes
@@ -333,8 +370,14 @@ pub fn draw_flowchart(
             NodeType::Choice { options } => {
                 format!("Choice ({})", options.len())
             }
-            NodeType::Conditional { var, .. } => {
-                format!("if {}", var)
+            NodeType::Conditional { condition } => {
+                format!("if {}", condition)
+            }
+            NodeType::Switch { subject } => {
+                format!("switch {}", subject)
+            }
+            NodeType::Random { branches } => {
+                format!("Random ({})", branches)
             }
         };
 
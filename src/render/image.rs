@@ -25,6 +25,21 @@ pub fn draw_background_with_offset(texture: &Texture2D, offset: (f32, f32)) {
     );
 }
 
+/// Draw an image layer over the whole screen, like a background, with a
+/// tint (for fading) and an offset (for shake and slide transitions).
+pub fn draw_layer_with_offset(texture: &Texture2D, offset: (f32, f32), color: Color) {
+    draw_texture_ex(
+        texture,
+        offset.0,
+        offset.1,
+        color,
+        DrawTextureParams {
+            dest_size: Some(Vec2::new(SCREEN_WIDTH, SCREEN_HEIGHT)),
+            ..Default::default()
+        },
+    );
+}
+
 /// Draw a character sprite at the specified position.
 pub fn draw_character(texture: &Texture2D, position: CharPosition) {
     draw_character_with_offset(texture, position, (0.0, 0.0));
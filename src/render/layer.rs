@@ -0,0 +1,133 @@
+//! Show/hide transitions of named image layers.
+
+use std::collections::HashMap;
+
+use crate::render::character::CharAnimationState;
+use crate::runtime::{LayerChange, LayerState};
+
+/// Layers whose show or hide transition is in progress.
+#[derive(Clone, Default)]
+pub struct LayerTransitionState {
+    /// Enter animations of shown layers, by layer name.
+    showing: HashMap<String, CharAnimationState>,
+    /// Replaced or hidden layers, drawn until their exit animation ends.
+    hiding: Vec<(LayerState, CharAnimationState)>,
+}
+
+impl LayerTransitionState {
+    /// Start the transitions of a layer shown, changed or hidden.
+    pub fn start(&mut self, change: &LayerChange) {
+        self.showing.remove(&change.name);
+        self.hiding.retain(|(layer, _)| layer.name != change.name);
+
+        let Some(transition) = &change.transition else {
+            return;
+        };
+        if change.from == change.to {
+            return;
+        }
+        if let Some(from) = &change.from {
+            let mut anim = CharAnimationState::default();
+            anim.start_exit(transition);
+            self.hiding.push((from.clone(), anim));
+        }
+        if change.to.is_some() {
+            let mut anim = CharAnimationState::default();
+            anim.start_enter(transition);
+            self.showing.insert(change.name.clone(), anim);
+        }
+    }
+
+    /// Advance the transitions, dropping finished ones.
+    pub fn update(&mut self) {
+        for anim in self.showing.values_mut() {
+            anim.update();
+        }
+        self.showing.retain(|_, anim| anim.is_active());
+        for (_, anim) in &mut self.hiding {
+            anim.update();
+        }
+        self.hiding.retain(|(_, anim)| anim.is_active());
+    }
+
+    /// Layers to draw, back to front: the visible ones and those still
+    /// transitioning out, with the animation to draw each with.
+    pub fn draw_list<'a>(
+        &'a self,
+        visible: Vec<&'a LayerState>,
+    ) -> Vec<(&'a LayerState, CharAnimationState)> {
+        let mut list: Vec<_> = visible
+            .into_iter()
+            .map(|layer| {
+                let anim = self.showing.get(&layer.name).cloned().unwrap_or_default();
+                (layer, anim)
+            })
+            .chain(
+                self.hiding
+                    .iter()
+                    .map(|(layer, anim)| (layer, anim.clone())),
+            )
+            .collect();
+        list.sort_by_key(|(layer, _)| layer.z);
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::{CharAnimation, CharAnimationType, Easing};
+
+    fn layer(name: &str, path: &str, z: i32) -> LayerState {
+        LayerState {
+            name: name.to_string(),
+            path: path.to_string(),
+            z,
+        }
+    }
+
+    fn fade() -> Option<CharAnimation> {
+        Some(CharAnimation {
+            animation_type: CharAnimationType::Fade,
+            duration: 1.0,
+            easing: Easing::Linear,
+        })
+    }
+
+    #[test]
+    fn test_changed_layer_crossfades() {
+        let mut state = LayerTransitionState::default();
+        let new = layer("cg", "b.png", 0);
+        state.start(&LayerChange {
+            name: "cg".to_string(),
+            from: Some(layer("cg", "a.png", 0)),
+            to: Some(new.clone()),
+            transition: fade(),
+        });
+        let other = layer("frame", "frame.png", 1);
+        let list = state.draw_list(vec![&new, &other]);
+        let paths: Vec<_> = list.iter().map(|(l, _)| l.path.as_str()).collect();
+        assert_eq!(paths, ["b.png", "a.png", "frame.png"]);
+        assert!(list[0].1.is_active() && list[1].1.is_active());
+        assert!(!list[2].1.is_active());
+    }
+
+    #[test]
+    fn test_change_without_transition_is_instant() {
+        let mut state = LayerTransitionState::default();
+        state.start(&LayerChange {
+            name: "cg".to_string(),
+            from: None,
+            to: Some(layer("cg", "a.png", 0)),
+            transition: fade(),
+        });
+        state.start(&LayerChange {
+            name: "cg".to_string(),
+            from: Some(layer("cg", "a.png", 0)),
+            to: None,
+            transition: None,
+        });
+        assert!(state.draw_list(Vec::new()).is_empty());
+        assert!(state.showing.is_empty());
+    }
+}
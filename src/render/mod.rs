@@ -11,6 +11,7 @@
 //!
 //! ## Visual Effects
 //! - `image`: Background rendering
+//! - `layer`: Named image layer transitions
 //! - `character`: Character sprite rendering with animations
 //! - `modular_char`: Layered character sprite compositing
 //! - `transition`: Scene transition effects
@@ -48,6 +49,7 @@ pub mod camera;
 pub mod character;
 pub mod cinematic;
 pub mod image;
+pub mod layer;
 pub mod lipsync;
 pub mod modular_char;
 pub mod particles;
@@ -77,15 +79,18 @@ pub use input::{InputConfig, InputState, draw_input};
 pub use nvl::{NvlConfig, NvlState, count_nvl_chars, draw_nvl_text_box};
 pub use text::{
     TextBoxConfig, count_visible_chars, draw_continue_indicator_with_font, draw_speaker_name,
-    draw_text_box_typewriter, draw_text_box_with_font, interpolate_variables,
+    draw_text_box_typewriter, draw_text_box_with_font, parse_color,
 };
 pub use ui::{ChoiceButtonConfig, ChoiceNavState, InputSource, draw_choices_with_timer};
 
 // Visual Effects
 pub use camera::{CameraTransform, calculate_camera_transform, pop_camera_transform, push_camera_transform};
-pub use character::{CharAnimationState, CharIdleState, draw_character_animated};
+pub use character::{
+    CharAnimationState, CharIdleState, CharPlacement, CharTweenState, draw_character_animated,
+};
 pub use cinematic::CinematicState;
-pub use image::draw_background_with_offset;
+pub use image::{draw_background_with_offset, draw_layer_with_offset};
+pub use layer::LayerTransitionState;
 pub use modular_char::draw_modular_char;
 pub use particles::{ParticleState, ParticleType};
 pub use shake::ShakeState;
@@ -1,10 +1,14 @@
 use macroquad::prelude::*;
 
+use crate::scenario::markup::{count_visible_chars, strip_markup};
+
 /// Entry in the NVL text buffer.
 #[derive(Debug, Clone)]
 pub struct NvlEntry {
     /// Speaker name (optional).
     pub speaker: Option<String>,
+    /// Speaker name colour, overriding the configured one.
+    pub speaker_color: Option<Color>,
     /// Text content.
     pub text: String,
 }
@@ -30,8 +34,12 @@ impl NvlState {
     }
 
     /// Add a new entry to the buffer.
-    pub fn push(&mut self, speaker: Option<String>, text: String) {
-        self.entries.push(NvlEntry { speaker, text });
+    pub fn push(&mut self, speaker: Option<String>, speaker_color: Option<Color>, text: String) {
+        self.entries.push(NvlEntry {
+            speaker,
+            speaker_color,
+            text,
+        });
     }
 
     /// Get all entries.
@@ -95,11 +103,13 @@ impl Default for NvlConfig {
 }
 
 /// Draw the NVL mode text box.
+/// Text tags are stripped (NVL text is drawn unstyled).
 /// Returns the total number of visible characters for typewriter effect.
 pub fn draw_nvl_text_box(
     config: &NvlConfig,
     state: &NvlState,
     current_speaker: Option<&str>,
+    current_speaker_color: Option<Color>,
     current_text: &str,
     font: Option<&Font>,
     char_limit: usize,
@@ -143,14 +153,19 @@ pub fn draw_nvl_text_box(
                 box_x,
                 y,
                 config.font_size,
-                config.speaker_color,
+                entry.speaker_color.unwrap_or(config.speaker_color),
                 font,
             );
             y += config.line_height;
         }
 
         // Draw text with word wrapping
-        let lines = wrap_text(&entry.text, config.width, config.font_size, font);
+        let lines = wrap_text(
+            &strip_markup(&entry.text),
+            config.width,
+            config.font_size,
+            font,
+        );
         for line in &lines {
             let line_chars = line.chars().count();
             draw_text_line(line, box_x, y, config.font_size, config.text_color, font);
@@ -180,14 +195,19 @@ pub fn draw_nvl_text_box(
                 box_x,
                 y,
                 config.font_size,
-                config.speaker_color,
+                current_speaker_color.unwrap_or(config.speaker_color),
                 font,
             );
             y += config.line_height;
         }
 
         // Draw current text with character limit
-        let lines = wrap_text(current_text, config.width, config.font_size, font);
+        let lines = wrap_text(
+            &strip_markup(current_text),
+            config.width,
+            config.font_size,
+            font,
+        );
         let mut remaining_chars = char_limit.saturating_sub(chars_displayed);
 
         for line in &lines {
@@ -275,9 +295,9 @@ pub fn count_nvl_chars(state: &NvlState, current_text: &str) -> usize {
     let mut total = 0;
 
     for entry in state.entries() {
-        total += entry.text.chars().count();
+        total += count_visible_chars(&entry.text);
     }
 
-    total += current_text.chars().count();
+    total += count_visible_chars(current_text);
     total
 }
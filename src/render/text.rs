@@ -1,107 +1,54 @@
 use macroquad::prelude::*;
 
-use crate::runtime::Variables;
-
-/// Count visible characters in text (excluding color tags).
-pub fn count_visible_chars(text: &str) -> usize {
-    let mut count = 0;
-    let mut chars = text.chars().peekable();
-
-    while let Some(ch) = chars.next() {
-        if ch == '{' {
-            // Check for color tag
-            let mut tag = String::new();
-            while let Some(&next_ch) = chars.peek() {
-                if next_ch == '}' {
-                    chars.next();
-                    break;
-                }
-                tag.push(chars.next().unwrap());
-            }
+use crate::scenario::markup::{TextEffect, TextStyle, parse_markup};
+
+pub use crate::scenario::markup::count_visible_chars;
+
+/// Slant of `{i}` text in radians (fonts are drawn rotated, not oblique).
+const ITALIC_SLANT: f32 = 0.12;
+
+/// How the characters of a text element are drawn.
+#[derive(Debug, Clone, Copy)]
+struct Glyph {
+    color: Color,
+    bold: bool,
+    italic: bool,
+    /// Font size multiplier.
+    scale: f32,
+    effect: Option<TextEffect>,
+}
 
-            // Only skip recognized tags
-            if !tag.starts_with("color:") && tag != "/color" {
-                // Not a color tag, count the braces and content
-                count += 2 + tag.chars().count();
-            }
-        } else {
-            count += 1;
+impl Glyph {
+    fn new(style: &TextStyle, default_color: Color) -> Self {
+        Self {
+            color: style
+                .color
+                .as_deref()
+                .and_then(parse_color)
+                .unwrap_or(default_color),
+            bold: style.bold,
+            italic: style.italic,
+            scale: style.size,
+            effect: style.effect,
         }
     }
-
-    count
-}
-
-/// A segment of text with color and ruby information.
-#[derive(Debug, Clone)]
-struct TextSegment {
-    text: String,
-    color: Option<Color>,
-    /// Ruby text (furigana) to display above the base text.
-    ruby: Option<String>,
 }
 
 /// A text element that can be a single character or a ruby group.
 #[derive(Debug, Clone)]
 enum TextElement {
-    /// Single character with color.
-    Char(char, Color),
+    /// Single character.
+    Char(char, Glyph),
     /// Ruby group: base text with reading above.
     Ruby {
         base: String,
         reading: String,
-        color: Color,
+        glyph: Glyph,
     },
 }
 
-/// Interpolate variables in text.
-/// Replaces {var:name} with the variable value.
-pub fn interpolate_variables(text: &str, variables: &Variables) -> String {
-    let mut result = String::new();
-    let mut chars = text.chars().peekable();
-
-    while let Some(ch) = chars.next() {
-        if ch == '{' {
-            // Check for var tag
-            let mut tag = String::new();
-            while let Some(&next_ch) = chars.peek() {
-                if next_ch == '}' {
-                    chars.next();
-                    break;
-                }
-                tag.push(chars.next().unwrap());
-            }
-
-            // Check if it's a variable reference
-            if let Some(var_name) = tag.strip_prefix("var:") {
-                if let Some(value) = variables.get(var_name) {
-                    match value {
-                        crate::runtime::Value::String(s) => result.push_str(s),
-                        crate::runtime::Value::Int(i) => result.push_str(&i.to_string()),
-                        crate::runtime::Value::Bool(b) => result.push_str(&b.to_string()),
-                    }
-                } else {
-                    // Variable not found, keep original tag
-                    result.push('{');
-                    result.push_str(&tag);
-                    result.push('}');
-                }
-            } else {
-                // Not a var tag, keep as-is
-                result.push('{');
-                result.push_str(&tag);
-                result.push('}');
-            }
-        } else {
-            result.push(ch);
-        }
-    }
-
-    result
-}
-
 /// Parse color name or hex code to Color.
-fn parse_color(name: &str) -> Option<Color> {
+pub fn parse_color(name: &str) -> Option<Color> {
     // Check for hex color
     if name.starts_with('#') && name.len() == 7 {
         let r = u8::from_str_radix(&name[1..3], 16).ok()?;
@@ -135,112 +82,6 @@ fn parse_color(name: &str) -> Option<Color> {
     }
 }
 
-/// Parse rich text with color and ruby tags into segments.
-/// Supports: {color:red}, {color:#ff0000}, {/color}, {ruby:base:reading}
-fn parse_rich_text(text: &str, default_color: Color) -> Vec<TextSegment> {
-    let mut segments = Vec::new();
-    let mut current_text = String::new();
-    let mut current_color: Option<Color> = None;
-    let mut color_stack: Vec<Color> = Vec::new();
-
-    let mut chars = text.chars().peekable();
-
-    while let Some(ch) = chars.next() {
-        if ch == '{' {
-            // Check for tag
-            let mut tag = String::new();
-            while let Some(&next_ch) = chars.peek() {
-                if next_ch == '}' {
-                    chars.next();
-                    break;
-                }
-                tag.push(chars.next().unwrap());
-            }
-
-            // Process tag
-            if let Some(color_name) = tag.strip_prefix("color:") {
-                // Save current segment if not empty
-                if !current_text.is_empty() {
-                    segments.push(TextSegment {
-                        text: current_text.clone(),
-                        color: current_color,
-                        ruby: None,
-                    });
-                    current_text.clear();
-                }
-
-                // Parse and push new color
-                if let Some(color) = parse_color(color_name) {
-                    if let Some(old_color) = current_color {
-                        color_stack.push(old_color);
-                    } else {
-                        color_stack.push(default_color);
-                    }
-                    current_color = Some(color);
-                }
-            } else if tag == "/color" {
-                // Save current segment if not empty
-                if !current_text.is_empty() {
-                    segments.push(TextSegment {
-                        text: current_text.clone(),
-                        color: current_color,
-                        ruby: None,
-                    });
-                    current_text.clear();
-                }
-
-                // Pop color from stack
-                current_color = color_stack.pop();
-            } else if let Some(ruby_content) = tag.strip_prefix("ruby:") {
-                // Ruby tag: {ruby:base:reading}
-                // Save current segment if not empty
-                if !current_text.is_empty() {
-                    segments.push(TextSegment {
-                        text: current_text.clone(),
-                        color: current_color,
-                        ruby: None,
-                    });
-                    current_text.clear();
-                }
-
-                // Parse ruby tag
-                if let Some(colon_pos) = ruby_content.find(':') {
-                    let base = &ruby_content[..colon_pos];
-                    let reading = &ruby_content[colon_pos + 1..];
-                    segments.push(TextSegment {
-                        text: base.to_string(),
-                        color: current_color,
-                        ruby: Some(reading.to_string()),
-                    });
-                } else {
-                    // Malformed ruby tag, treat as plain text
-                    current_text.push('{');
-                    current_text.push_str(&tag);
-                    current_text.push('}');
-                }
-            } else {
-                // Not a recognized tag, keep as literal text
-                current_text.push('{');
-                current_text.push_str(&tag);
-                current_text.push('}');
-            }
-        } else {
-            current_text.push(ch);
-        }
-    }
-
-    // Add remaining text
-    if !current_text.is_empty() {
-        segments.push(TextSegment {
-            text: current_text,
-            color: current_color,
-            ruby: None,
-        });
-    }
-
-    segments
-}
-
 /// Configuration for text box rendering.
 pub struct TextBoxConfig {
     pub x: f32,
@@ -313,45 +154,8 @@ pub fn draw_text_box_typewriter(
     draw_text_box_internal(config, text, font, Some(char_limit))
 }
 
-/// Strip color and ruby tags from text for measurement purposes.
-fn strip_tags(text: &str) -> String {
-    let mut result = String::new();
-    let mut chars = text.chars().peekable();
-
-    while let Some(ch) = chars.next() {
-        if ch == '{' {
-            let mut tag = String::new();
-            while let Some(&next_ch) = chars.peek() {
-                if next_ch == '}' {
-                    chars.next();
-                    break;
-                }
-                tag.push(chars.next().unwrap());
-            }
-
-            // Handle ruby tags specially - extract base text
-            if let Some(ruby_content) = tag.strip_prefix("ruby:") {
-                if let Some(colon_pos) = ruby_content.find(':') {
-                    let base = &ruby_content[..colon_pos];
-                    result.push_str(base);
-                }
-            } else if !tag.starts_with("color:") && tag != "/color" {
-                // Unknown tags are kept as-is
-                result.push('{');
-                result.push_str(&tag);
-                result.push('}');
-            }
-            // color tags and /color are simply skipped
-        } else {
-            result.push(ch);
-        }
-    }
-
-    result
-}
-
 /// Draw a text box with the given text and optional custom font.
-/// Supports rich text color tags: {color:red}, {color:#ff0000}, {/color}
+/// Supports the rich text tags of [`crate::scenario::markup`].
 pub fn draw_text_box_with_font(config: &TextBoxConfig, text: &str, font: Option<&Font>) {
     draw_text_box_internal(config, text, font, None);
 }
@@ -376,9 +180,6 @@ fn draw_text_box_internal(
     // Draw border
     draw_rectangle_lines(config.x, config.y, config.width, config.height, 2.0, WHITE);
 
-    // Parse rich text into segments
-    let segments = parse_rich_text(text, config.text_color);
-
     // Draw text with word wrapping
     let text_x = config.x + config.padding;
     // Add extra space for ruby text at the top
@@ -386,20 +187,20 @@ fn draw_text_box_internal(
     let text_y = config.y + config.padding + config.font_size + ruby_space;
     let max_width = config.width - config.padding * 2.0;
 
-    // Build element list with colors and ruby for proper wrapping
+    // Build element list with styles and ruby for proper wrapping
     let mut elements: Vec<TextElement> = Vec::new();
-    for segment in &segments {
-        let color = segment.color.unwrap_or(config.text_color);
-        if let Some(ref ruby) = segment.ruby {
+    for span in parse_markup(text) {
+        let glyph = Glyph::new(&span.style, config.text_color);
+        if let Some(reading) = span.ruby {
             // Ruby group counts as base text length for display count
             elements.push(TextElement::Ruby {
-                base: segment.text.clone(),
-                reading: ruby.clone(),
-                color,
+                base: span.text,
+                reading,
+                glyph,
             });
         } else {
-            for ch in segment.text.chars() {
-                elements.push(TextElement::Char(ch, color));
+            for ch in span.text.chars() {
+                elements.push(TextElement::Char(ch, glyph));
             }
         }
     }
@@ -493,18 +294,63 @@ fn draw_text_box_internal(
 
 /// Measure the width of a line of text elements.
 fn measure_line_width(elements: &[TextElement], font_size: f32, font: Option<&Font>) -> f32 {
-    let mut width = 0.0;
-    for element in elements {
-        let text = match element {
-            TextElement::Char(ch, _) => ch.to_string(),
-            TextElement::Ruby { base, .. } => base.clone(),
-        };
-        width += measure_text(&text, font, font_size as u16, 1.0).width;
+    elements
+        .iter()
+        .map(|element| element_width(element, font_size, font))
+        .sum()
+}
+
+/// Advance of one text element.
+fn element_width(element: &TextElement, font_size: f32, font: Option<&Font>) -> f32 {
+    let (width, glyph) = match element {
+        TextElement::Char(ch, glyph) => (
+            measure_glyphs(&ch.to_string(), font_size, glyph, font),
+            glyph,
+        ),
+        TextElement::Ruby { base, glyph, .. } => {
+            (measure_glyphs(base, font_size, glyph, font), glyph)
+        }
+    };
+    // Faux bold is drawn twice, one pixel apart
+    if glyph.bold { width + 1.0 } else { width }
+}
+
+fn measure_glyphs(text: &str, font_size: f32, glyph: &Glyph, font: Option<&Font>) -> f32 {
+    measure_text(text, font, (font_size * glyph.scale) as u16, 1.0).width
+}
+
+/// Offset of a glyph drawn at `x` for its animated effect.
+fn effect_offset(effect: Option<TextEffect>, x: f32, font_size: f32) -> (f32, f32) {
+    let time = get_time() as f32;
+    match effect {
+        Some(TextEffect::Shake) => {
+            let amount = font_size * 0.06;
+            (
+                (time * 47.0 + x * 12.9898).sin() * amount,
+                (time * 53.0 + x * 78.233).cos() * amount,
+            )
+        }
+        Some(TextEffect::Wave) => (0.0, (time * 6.0 - x * 0.05).sin() * font_size * 0.15),
+        None => (0.0, 0.0),
     }
-    width
 }
 
-/// Draw a line of text elements with ruby support.
+/// Draw text in a glyph style at an explicit font size.
+fn draw_glyphs(text: &str, x: f32, y: f32, font_size: f32, glyph: &Glyph, font: Option<&Font>) {
+    let params = TextParams {
+        font,
+        font_size: font_size as u16,
+        color: glyph.color,
+        rotation: if glyph.italic { ITALIC_SLANT } else { 0.0 },
+        ..Default::default()
+    };
+    if glyph.bold {
+        draw_text_ex(text, x + 1.0, y, params.clone());
+    }
+    draw_text_ex(text, x, y, params);
+}
+
+/// Draw a line of text elements with ruby, style and effect support.
 fn draw_line_with_ruby(
     elements: &[TextElement],
     start_x: f32,
@@ -512,85 +358,43 @@ fn draw_line_with_ruby(
     font_size: f32,
     font: Option<&Font>,
 ) {
-    let ruby_font_size = (font_size * 0.5).max(10.0);
-    let ruby_offset = font_size * 0.6;
-
     let mut x = start_x;
     for element in elements {
+        let width = element_width(element, font_size, font);
         match element {
-            TextElement::Char(ch, color) => {
-                let text = ch.to_string();
-                if let Some(f) = font {
-                    draw_text_ex(
-                        &text,
-                        x,
-                        y,
-                        TextParams {
-                            font: Some(f),
-                            font_size: font_size as u16,
-                            color: *color,
-                            ..Default::default()
-                        },
-                    );
-                    x += measure_text(&text, Some(f), font_size as u16, 1.0).width;
-                } else {
-                    draw_text(&text, x, y, font_size, *color);
-                    x += measure_text(&text, None, font_size as u16, 1.0).width;
-                }
+            TextElement::Char(ch, glyph) => {
+                let size = font_size * glyph.scale;
+                let (dx, dy) = effect_offset(glyph.effect, x, size);
+                draw_glyphs(&ch.to_string(), x + dx, y + dy, size, glyph, font);
             }
             TextElement::Ruby {
                 base,
                 reading,
-                color,
+                glyph,
             } => {
-                // Measure base text width
-                let base_width = measure_text(base, font, font_size as u16, 1.0).width;
+                let size = font_size * glyph.scale;
+                let ruby_font_size = (size * 0.5).max(10.0);
+                let ruby_offset = size * 0.6;
+                let (dx, dy) = effect_offset(glyph.effect, x, size);
 
                 // Draw base text
-                if let Some(f) = font {
-                    draw_text_ex(
-                        base,
-                        x,
-                        y,
-                        TextParams {
-                            font: Some(f),
-                            font_size: font_size as u16,
-                            color: *color,
-                            ..Default::default()
-                        },
-                    );
-                } else {
-                    draw_text(base, x, y, font_size, *color);
-                }
-
-                // Measure ruby text width
-                let ruby_width = measure_text(reading, font, ruby_font_size as u16, 1.0).width;
+                draw_glyphs(base, x + dx, y + dy, size, glyph, font);
 
                 // Center ruby above base text
-                let ruby_x = x + (base_width - ruby_width) / 2.0;
-                let ruby_y = y - ruby_offset;
+                let ruby_width = measure_text(reading, font, ruby_font_size as u16, 1.0).width;
+                let ruby_x = x + dx + (width - ruby_width) / 2.0;
+                let ruby_y = y + dy - ruby_offset;
 
                 // Draw ruby text (slightly transparent)
-                let ruby_color = Color::new(color.r, color.g, color.b, color.a * 0.9);
-                if let Some(f) = font {
-                    draw_text_ex(
-                        reading,
-                        ruby_x,
-                        ruby_y,
-                        TextParams {
-                            font: Some(f),
-                            font_size: ruby_font_size as u16,
-                            color: ruby_color,
-                            ..Default::default()
-                        },
-                    );
-                } else {
-                    draw_text(reading, ruby_x, ruby_y, ruby_font_size, ruby_color);
-                }
-
-                x += base_width;
+                let color = glyph.color;
+                let ruby_glyph = Glyph {
+                    color: Color::new(color.r, color.g, color.b, color.a * 0.9),
+                    ..*glyph
+                };
+                draw_glyphs(reading, ruby_x, ruby_y, ruby_font_size, &ruby_glyph, font);
             }
         }
+        x += width;
     }
 }
 
@@ -638,8 +442,15 @@ fn draw_colored_line(
     }
 }
 
-/// Draw the speaker name box above the text box.
-pub fn draw_speaker_name(config: &TextBoxConfig, speaker: &str, font: Option<&Font>) {
+/// Draw the speaker name box above the text box, in the character's name
+/// colour if it has one.
+pub fn draw_speaker_name(
+    config: &TextBoxConfig,
+    speaker: &str,
+    color: Option<Color>,
+    font: Option<&Font>,
+) {
+    let name_color = color.unwrap_or(YELLOW);
     let speaker_height = 30.0;
     let speaker_y = config.y - speaker_height - 5.0;
     let speaker_padding = 10.0;
@@ -672,7 +483,7 @@ pub fn draw_speaker_name(config: &TextBoxConfig, speaker: &str, font: Option<&Fo
             TextParams {
                 font: Some(f),
                 font_size: config.font_size as u16,
-                color: YELLOW,
+                color: name_color,
                 ..Default::default()
             },
         );
@@ -682,7 +493,7 @@ pub fn draw_speaker_name(config: &TextBoxConfig, speaker: &str, font: Option<&Fo
             config.x + speaker_padding,
             speaker_y + speaker_height - 8.0,
             config.font_size,
-            YELLOW,
+            name_color,
         );
     }
 }
@@ -723,180 +534,6 @@ pub fn draw_continue_indicator_with_font(config: &TextBoxConfig, font: Option<&F
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_count_visible_chars_plain_text() {
-        assert_eq!(count_visible_chars("hello"), 5);
-        assert_eq!(count_visible_chars("hello world"), 11);
-        assert_eq!(count_visible_chars(""), 0);
-    }
-
-    #[test]
-    fn test_count_visible_chars_with_color_tags() {
-        // Color tags should not be counted
-        assert_eq!(count_visible_chars("{color:red}hello{/color}"), 5);
-        assert_eq!(count_visible_chars("{color:#ff0000}test{/color}"), 4);
-        assert_eq!(count_visible_chars("{color:blue}hello{/color} world"), 11);
-    }
-
-    #[test]
-    fn test_count_visible_chars_nested_colors() {
-        assert_eq!(
-            count_visible_chars("{color:red}a{color:blue}b{/color}c{/color}"),
-            3
-        );
-    }
-
-    #[test]
-    fn test_count_visible_chars_unrecognized_tags() {
-        // Unrecognized tags should be counted (braces + content)
-        assert_eq!(count_visible_chars("{unknown}text"), 13);
-    }
-
-    #[test]
-    fn test_interpolate_variables() {
-        let mut vars = Variables::new();
-        vars.set("name", crate::types::Value::String("Alice".to_string()));
-        vars.set("count", crate::types::Value::Int(42));
-        vars.set("flag", crate::types::Value::Bool(true));
-
-        assert_eq!(
-            interpolate_variables("Hello {var:name}!", &vars),
-            "Hello Alice!"
-        );
-        assert_eq!(
-            interpolate_variables("Count: {var:count}", &vars),
-            "Count: 42"
-        );
-        assert_eq!(
-            interpolate_variables("Flag: {var:flag}", &vars),
-            "Flag: true"
-        );
-        assert_eq!(
-            interpolate_variables("{var:unknown}", &vars),
-            "{var:unknown}"
-        );
-    }
-
-    #[test]
-    fn test_interpolate_variables_preserves_other_tags() {
-        let vars = Variables::new();
-        assert_eq!(
-            interpolate_variables("{color:red}text{/color}", &vars),
-            "{color:red}text{/color}"
-        );
-    }
-
-    #[test]
-    fn test_count_visible_chars_deeply_nested_colors() {
-        // Multiple levels of nesting
-        assert_eq!(
-            count_visible_chars("{color:red}{color:blue}{color:green}ABC{/color}{/color}{/color}"),
-            3
-        );
-    }
-
-    #[test]
-    fn test_count_visible_chars_mixed_content() {
-        // Mix of color tags, regular text, and unrecognized tags
-        // "hello" (5) + " " (1) + "{unknown}" (9) + " " (1) + "world" (5) = 21
-        assert_eq!(
-            count_visible_chars("{color:red}hello{/color} {unknown} {color:blue}world{/color}"),
-            21
-        );
-    }
-
-    #[test]
-    fn test_interpolate_variables_combined_with_color() {
-        let mut vars = Variables::new();
-        vars.set("name", crate::types::Value::String("Alice".to_string()));
-
-        // Variable inside color tag should be expanded
-        assert_eq!(
-            interpolate_variables("{color:blue}Hello {var:name}!{/color}", &vars),
-            "{color:blue}Hello Alice!{/color}"
-        );
-    }
-
-    #[test]
-    fn test_interpolate_variables_multiple_vars() {
-        let mut vars = Variables::new();
-        vars.set("a", crate::types::Value::String("X".to_string()));
-        vars.set("b", crate::types::Value::String("Y".to_string()));
-        vars.set("c", crate::types::Value::String("Z".to_string()));
-
-        assert_eq!(
-            interpolate_variables("{var:a}-{var:b}-{var:c}", &vars),
-            "X-Y-Z"
-        );
-    }
-
-    #[test]
-    fn test_interpolate_variables_empty_text() {
-        let vars = Variables::new();
-        assert_eq!(interpolate_variables("", &vars), "");
-    }
-
-    #[test]
-    fn test_interpolate_variables_no_tags() {
-        let vars = Variables::new();
-        assert_eq!(
-            interpolate_variables("plain text without tags", &vars),
-            "plain text without tags"
-        );
-    }
-
-    #[test]
-    fn test_count_visible_chars_long_text() {
-        // Performance test with long text
-        let long_text = "a".repeat(10000);
-        assert_eq!(count_visible_chars(&long_text), 10000);
-    }
-
-    #[test]
-    fn test_count_visible_chars_long_text_with_tags() {
-        // Long text with many color tags
-        let segment = "{color:red}hello{/color}";
-        let long_text = segment.repeat(1000);
-        assert_eq!(count_visible_chars(&long_text), 5000); // 5 chars * 1000
-    }
-
-    #[test]
-    fn test_count_visible_chars_malformed_tags() {
-        // Unclosed tag - the implementation consumes all remaining chars as tag content
-        // so an unclosed tag results in 0 visible chars
-        assert_eq!(count_visible_chars("{color:red"), 0);
-        // Empty braces - "{}" is not a recognized tag, so braces + empty content = 2
-        assert_eq!(count_visible_chars("{}text"), 6);
-    }
-
-    #[test]
-    fn test_strip_tags_basic() {
-        assert_eq!(strip_tags("{color:red}hello{/color}"), "hello");
-        assert_eq!(strip_tags("{color:#ff0000}test{/color}"), "test");
-    }
-
-    #[test]
-    fn test_strip_tags_with_ruby() {
-        assert_eq!(strip_tags("{ruby:漢字:かんじ}"), "漢字");
-        assert_eq!(
-            strip_tags("text {ruby:東京:とうきょう} more"),
-            "text 東京 more"
-        );
-    }
-
-    #[test]
-    fn test_strip_tags_combined() {
-        assert_eq!(
-            strip_tags("{color:red}{ruby:漢字:かんじ}{/color}"),
-            "漢字"
-        );
-    }
-
-    #[test]
-    fn test_strip_tags_unknown_tags_preserved() {
-        assert_eq!(strip_tags("{unknown}text"), "{unknown}text");
-    }
-
     #[test]
     fn test_parse_color_named() {
         assert!(parse_color("red").is_some());
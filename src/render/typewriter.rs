@@ -1,5 +1,7 @@
 use macroquad::prelude::*;
 
+use crate::scenario::markup::{PaceChange, TextPacing};
+
 /// Manages typewriter text animation state.
 #[derive(Default)]
 pub struct TypewriterState {
@@ -7,53 +9,104 @@ pub struct TypewriterState {
     total_chars: usize,
     /// Characters displayed so far.
     displayed_chars: usize,
-    /// Elapsed time since text started.
-    elapsed: f32,
+    /// Characters revealed so far, including the part of the next one.
+    progress: f32,
     /// Whether text is fully displayed.
     complete: bool,
+    /// Inline `{w=...}`, `{speed=...}` and `{nw}` tags of the current text.
+    pacing: TextPacing,
+    /// Pace marks already applied.
+    next_mark: usize,
+    /// Seconds left of the current `{w=...}` pause.
+    pause: f32,
+    /// Speed set by `{speed=...}`, overriding the player's setting unless
+    /// they chose instant text.
+    speed: Option<f32>,
 }
 
 impl TypewriterState {
-    /// Reset for new text.
-    pub fn reset(&mut self, total_chars: usize) {
-        self.total_chars = total_chars;
-        self.displayed_chars = 0;
-        self.elapsed = 0.0;
-        self.complete = total_chars == 0;
+    /// Reset for new text with inline pacing tags (see
+    /// [`crate::scenario::markup::text_pacing`]).
+    pub fn reset_paced(&mut self, total_chars: usize, pacing: TextPacing) {
+        *self = Self {
+            total_chars,
+            pacing,
+            ..Self::default()
+        };
+        self.complete = total_chars == 0 && self.pacing.marks.is_empty();
     }
 
     /// Update the typewriter state.
     /// Returns the number of characters to display.
     pub fn update(&mut self, cps: f32) -> usize {
-        if self.complete {
-            return self.total_chars;
-        }
-
-        // CPS of 0 means instant display
-        if cps <= 0.0 {
-            self.complete = true;
-            self.displayed_chars = self.total_chars;
-            return self.total_chars;
-        }
+        self.advance(get_frame_time(), cps)
+    }
 
-        self.elapsed += get_frame_time();
+    /// Advance by `dt` seconds at `cps` characters per second (unless a
+    /// `{speed=...}` tag overrides it). A speed of 0 shows text instantly,
+    /// ignoring `{speed=...}` tags, but `{w=...}` pauses are still kept.
+    /// Returns the number of characters to display.
+    pub fn advance(&mut self, dt: f32, cps: f32) -> usize {
+        let mut time = dt;
+        while !self.complete {
+            // Apply the tags reached so far (all of them at the end)
+            while let Some(mark) = self.pacing.marks.get(self.next_mark)
+                && (mark.at <= self.displayed_chars || self.displayed_chars >= self.total_chars)
+            {
+                match mark.change {
+                    PaceChange::Pause(seconds) => self.pause += seconds,
+                    PaceChange::Speed(speed) => self.speed = speed,
+                }
+                self.next_mark += 1;
+            }
 
-        // Calculate how many characters should be displayed
-        let chars_to_display = (self.elapsed * cps) as usize;
-        self.displayed_chars = chars_to_display.min(self.total_chars);
+            if self.pause > 0.0 {
+                if time <= 0.0 {
+                    break;
+                }
+                let waited = self.pause.min(time);
+                self.pause -= waited;
+                time -= waited;
+                continue;
+            }
 
-        if self.displayed_chars >= self.total_chars {
-            self.complete = true;
-            self.displayed_chars = self.total_chars;
+            // Reveal up to the next tag or the end of the text
+            let target = self
+                .pacing
+                .marks
+                .get(self.next_mark)
+                .map_or(self.total_chars, |mark| mark.at);
+            let cps = match self.speed {
+                Some(speed) if cps > 0.0 => speed,
+                _ => cps,
+            };
+            let needed = target as f32 - self.progress;
+            if cps <= 0.0 || time * cps >= needed {
+                if cps > 0.0 {
+                    time -= needed / cps;
+                }
+                self.progress = target as f32;
+                self.displayed_chars = target;
+                if target >= self.total_chars && self.next_mark >= self.pacing.marks.len() {
+                    self.complete = true;
+                }
+            } else {
+                self.progress += time * cps;
+                self.displayed_chars = self.progress as usize;
+                break;
+            }
         }
 
         self.displayed_chars
     }
 
-    /// Complete the text instantly (on click).
+    /// Complete the text instantly (on click), skipping any pauses.
     pub fn complete(&mut self) {
         self.complete = true;
         self.displayed_chars = self.total_chars;
+        self.progress = self.total_chars as f32;
+        self.next_mark = self.pacing.marks.len();
+        self.pause = 0.0;
     }
 
     /// Check if text is fully displayed.
@@ -65,4 +118,88 @@ impl TypewriterState {
     pub fn displayed_chars(&self) -> usize {
         self.displayed_chars
     }
+
+    /// Whether the text asked to advance as soon as it is shown (`{nw}`).
+    pub fn no_wait(&self) -> bool {
+        self.pacing.no_wait
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::markup::{count_visible_chars, text_pacing};
+
+    fn typewriter(text: &str) -> TypewriterState {
+        let mut state = TypewriterState::default();
+        state.reset_paced(count_visible_chars(text), text_pacing(text));
+        state
+    }
+
+    #[test]
+    fn test_plain_text() {
+        let mut state = typewriter("Hello");
+        assert_eq!(state.advance(0.2, 10.0), 2);
+        assert_eq!(state.advance(0.3, 10.0), 5);
+        assert!(state.is_complete());
+        assert!(!state.no_wait());
+    }
+
+    #[test]
+    fn test_pause_tag() {
+        let mut state = typewriter("Ah{w=1}.");
+        assert_eq!(state.advance(0.5, 10.0), 2);
+        // Paused after "Ah"
+        assert_eq!(state.advance(0.5, 10.0), 2);
+        assert!(!state.is_complete());
+        assert_eq!(state.advance(0.5, 10.0), 3);
+        assert!(state.is_complete());
+    }
+
+    #[test]
+    fn test_pause_with_instant_text() {
+        let mut state = typewriter("Ah{w=1}...");
+        assert_eq!(state.advance(0.0, 0.0), 2);
+        assert_eq!(state.advance(0.5, 0.0), 2);
+        assert_eq!(state.advance(0.5, 0.0), 5);
+        assert!(state.is_complete());
+    }
+
+    #[test]
+    fn test_speed_tags() {
+        let mut state = typewriter("ab{speed=2}cd{/speed}ef");
+        assert_eq!(state.advance(0.2, 10.0), 2);
+        // Two characters at 2 cps take a second
+        assert_eq!(state.advance(0.5, 10.0), 3);
+        assert_eq!(state.advance(0.5, 10.0), 4);
+        assert_eq!(state.advance(0.2, 10.0), 6);
+        assert!(state.is_complete());
+    }
+
+    #[test]
+    fn test_speed_tags_with_instant_text() {
+        let mut state = typewriter("ab{speed=2}cd{/speed}ef");
+        assert_eq!(state.advance(0.0, 0.0), 6);
+        assert!(state.is_complete());
+    }
+
+    #[test]
+    fn test_complete_skips_pauses() {
+        let mut state = typewriter("Wait{w=5} for it{nw}");
+        state.advance(1.0, 10.0);
+        assert!(!state.is_complete());
+        state.complete();
+        assert!(state.is_complete());
+        assert_eq!(state.displayed_chars(), 11);
+        assert!(state.no_wait());
+    }
+
+    #[test]
+    fn test_trailing_pause_before_complete() {
+        let mut state = typewriter("Done{w=1}{nw}");
+        assert_eq!(state.advance(1.0, 10.0), 4);
+        assert!(!state.is_complete());
+        state.advance(1.0, 10.0);
+        assert!(state.is_complete());
+    }
 }
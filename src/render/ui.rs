@@ -1,7 +1,7 @@
 use macroquad::prelude::*;
 
 use crate::i18n::LanguageConfig;
-use crate::scenario::Choice;
+use crate::runtime::ChoiceOption;
 
 /// Input source for choice navigation.
 #[derive(Default, Clone, Copy, PartialEq)]
@@ -32,6 +32,8 @@ pub struct ChoiceButtonConfig {
     pub bg_color: Color,
     pub hover_color: Color,
     pub text_color: Color,
+    pub disabled_bg_color: Color,
+    pub disabled_text_color: Color,
     pub font_size: f32,
 }
 
@@ -46,6 +48,8 @@ impl Default for ChoiceButtonConfig {
             bg_color: Color::new(0.2, 0.2, 0.4, 0.9),
             hover_color: Color::new(0.3, 0.3, 0.6, 0.9),
             text_color: WHITE,
+            disabled_bg_color: Color::new(0.15, 0.15, 0.2, 0.8),
+            disabled_text_color: Color::new(0.55, 0.55, 0.6, 1.0),
             font_size: 20.0,
         }
     }
@@ -59,7 +63,7 @@ pub struct ChoiceResult {
 /// Draw choice buttons and return which one was clicked.
 pub fn draw_choices(
     config: &ChoiceButtonConfig,
-    choices: &[Choice],
+    choices: &[ChoiceOption],
     lang: &LanguageConfig,
     nav_state: &ChoiceNavState,
 ) -> ChoiceResult {
@@ -69,7 +73,7 @@ pub fn draw_choices(
 /// Draw choice buttons with optional timer display.
 pub fn draw_choices_with_timer(
     config: &ChoiceButtonConfig,
-    choices: &[Choice],
+    choices: &[ChoiceOption],
     remaining_time: Option<f32>,
     default_choice: Option<usize>,
     lang: &LanguageConfig,
@@ -119,11 +123,13 @@ pub fn draw_choices_with_timer(
 
     let mut selected = None;
 
-    for (i, choice) in choices.iter().enumerate() {
+    for (i, option) in choices.iter().enumerate() {
         let y = config.start_y + (config.height + config.spacing) * i as f32;
+        let enabled = option.is_enabled();
 
         // Check if mouse is hovering
-        let is_hover = mouse_pos.0 >= config.x
+        let is_hover = enabled
+            && mouse_pos.0 >= config.x
             && mouse_pos.0 <= config.x + config.width
             && mouse_pos.1 >= y
             && mouse_pos.1 <= y + config.height;
@@ -135,8 +141,10 @@ pub fn draw_choices_with_timer(
         // Check if this is the default choice
         let is_default = default_choice == Some(i);
 
-        // Determine background color (hover or focus)
-        let bg_color = if is_hover || is_focused {
+        // Determine background color (disabled, hover or focus)
+        let bg_color = if !enabled {
+            config.disabled_bg_color
+        } else if is_hover || is_focused {
             config.hover_color
         } else if is_default && remaining_time.is_some() {
             // Highlight default choice when timer is active
@@ -152,6 +160,8 @@ pub fn draw_choices_with_timer(
         let border_color = if is_focused {
             // Cyan border for gamepad focus
             Color::new(0.4, 0.8, 1.0, 1.0)
+        } else if !enabled {
+            GRAY
         } else if is_default && remaining_time.is_some() {
             YELLOW
         } else {
@@ -159,18 +169,25 @@ pub fn draw_choices_with_timer(
         };
         draw_rectangle_lines(config.x, y, config.width, config.height, 2.0, border_color);
 
-        // Draw button text (centered)
-        let resolved_label = lang.resolve(&choice.label);
-        let label = if is_default && remaining_time.is_some() {
-            format!("{} [Default]", resolved_label)
+        // Draw button text (centered), with the reason on disabled choices
+        let resolved_label = lang.resolve(&option.choice.label);
+        let label = match &option.disabled {
+            Some(reason) => format!("{} ({})", resolved_label, lang.resolve(reason)),
+            None if is_default && remaining_time.is_some() => {
+                format!("{} [Default]", resolved_label)
+            }
+            None => resolved_label,
+        };
+        let text_color = if enabled {
+            config.text_color
         } else {
-            resolved_label
+            config.disabled_text_color
         };
         let text_width = measure_text(&label, None, config.font_size as u16, 1.0).width;
         let text_x = config.x + (config.width - text_width) / 2.0;
         let text_y = y + (config.height + config.font_size) / 2.0 - 4.0;
 
-        draw_text(&label, text_x, text_y, config.font_size, config.text_color);
+        draw_text(&label, text_x, text_y, config.font_size, text_color);
 
         // Check for click
         if is_hover && mouse_clicked {
@@ -1,7 +1,9 @@
+use std::collections::BTreeSet;
+
 use serde::Serialize;
 
 use crate::i18n::LocalizedString;
-use crate::runtime::VisualState;
+use crate::runtime::{Rng, Variables, VisualState};
 use crate::scenario::{Choice, Input};
 
 /// History entry for rollback functionality.
@@ -10,6 +12,40 @@ pub struct HistoryEntry {
     pub index: usize,
     pub visual: VisualState,
     pub text: LocalizedString,
+    /// Speaker name as displayed (a character's name for a character id).
+    pub speaker: Option<LocalizedString>,
+    /// Name colour of the speaking character, if it has one.
+    pub speaker_color: Option<String>,
+    /// Call stack at this point, restored on rollback.
+    pub call_stack: Vec<usize>,
+    /// Once-only choices picked before this point, restored on rollback.
+    pub picked_choices: BTreeSet<(usize, usize)>,
+    /// Variables at this point, restored on rollback.
+    pub variables: Variables,
+    /// Random generator state at this point, restored on rollback.
+    pub rng: Rng,
+    /// Persistent writes made stepping on from this point, as (command
+    /// index, field), so that playing them again after a rollback doesn't
+    /// repeat them. Persistent variables are not rolled back.
+    #[serde(skip)]
+    pub persistent_writes: Vec<(usize, String)>,
+}
+
+/// A choice as offered to the player.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChoiceOption {
+    #[serde(flatten)]
+    pub choice: Choice,
+    /// Why the choice can't be picked; `None` if it can.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<LocalizedString>,
+}
+
+impl ChoiceOption {
+    /// Check whether the player can pick this choice.
+    pub fn is_enabled(&self) -> bool {
+        self.disabled.is_none()
+    }
 }
 
 /// Current display state of the game.
@@ -25,11 +61,14 @@ pub enum DisplayState {
     Choices {
         speaker: Option<LocalizedString>,
         text: LocalizedString,
-        choices: Vec<Choice>,
+        /// Choices offered, without hidden ones. `select_choice` takes an
+        /// index into this list.
+        choices: Vec<ChoiceOption>,
         visual: VisualState,
         /// Optional timeout in seconds for timed choices.
         timeout: Option<f32>,
-        /// Index of the default choice (selected on timeout).
+        /// Index of the default choice (selected on timeout). Always an
+        /// enabled choice; `None` if there is none.
         default_choice: Option<usize>,
     },
     /// Waiting for a specified duration.
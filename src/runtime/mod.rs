@@ -2,6 +2,8 @@ pub mod achievements;
 pub mod chapters;
 pub mod display;
 pub mod keybinds;
+pub mod persistent;
+pub mod random;
 pub mod read_state;
 pub mod save;
 pub mod state;
@@ -11,12 +13,19 @@ pub mod visual;
 
 pub use achievements::{AchievementNotifier, Achievements};
 pub use chapters::{Chapter, ChapterManager};
-pub use display::{DisplayState, HistoryEntry};
+pub use display::{ChoiceOption, DisplayState, HistoryEntry};
 pub use keybinds::{Action, KeyBindings};
+#[allow(unused_imports)]
+pub use persistent::{PERSISTENT_PREFIX, PersistentVariables, ScriptVariables};
+pub use random::Rng;
 pub use read_state::ReadState;
-pub use save::SaveData;
+#[allow(unused_imports)]
+pub use save::{SAVE_FORMAT_VERSION, SaveData};
 pub use state::GameState;
 pub use unlocks::Unlocks;
 pub use variables::{Value, Variables};
 #[allow(unused_imports)]
-pub use visual::{CameraAnimationState, CameraState, CharacterState, VisualState};
+pub use visual::{
+    CameraAnimationState, CameraState, CharTransform, CharacterKey, CharacterSlot, CharacterState,
+    CharacterTween, LayerChange, LayerState, VisualState,
+};
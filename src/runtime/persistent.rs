@@ -0,0 +1,137 @@
+use anyhow::Result;
+
+use crate::platform;
+use crate::runtime::{Value, Variables};
+use crate::scenario::VariableLookup;
+
+const PERSISTENT_PATH: &str = "saves/persistent.json";
+
+/// Prefix of variable names that live in the persistent store.
+pub const PERSISTENT_PREFIX: &str = "persistent.";
+
+/// Variables shared by every playthrough (New Game+ flags, endings seen).
+///
+/// Names are stored without the `persistent.` prefix. Unlike per-save
+/// variables they are written out as soon as they change and are not undone
+/// by rollback or loading a save.
+#[derive(Debug, Clone, Default)]
+pub struct PersistentVariables {
+    variables: Variables,
+    /// File written on every change; `None` keeps the variables in memory.
+    path: Option<String>,
+}
+
+impl PersistentVariables {
+    /// Create an empty in-memory store (nothing is written to disk).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load persistent variables from file, starting empty if there are none.
+    pub fn load() -> Self {
+        let variables = match Self::load_internal() {
+            Ok(variables) => variables,
+            Err(_) => Variables::new(),
+        };
+        Self {
+            variables,
+            path: Some(PERSISTENT_PATH.to_string()),
+        }
+    }
+
+    fn load_internal() -> Result<Variables> {
+        let content = platform::read_file(PERSISTENT_PATH)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Save persistent variables to file (in-memory stores are left alone).
+    pub fn save(&self) {
+        if let Err(e) = self.save_internal() {
+            eprintln!("Failed to save persistent variables: {}", e);
+        }
+    }
+
+    fn save_internal(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            let json = serde_json::to_string_pretty(&self.variables)?;
+            platform::write_file(path, &json)?;
+        }
+        Ok(())
+    }
+
+    /// Get a variable by its name without the prefix.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.variables.get(name)
+    }
+
+    /// Set a variable by its name without the prefix, saving if it changed.
+    pub fn set(&mut self, name: &str, value: Value) {
+        if self.variables.get(name) != Some(&value) {
+            self.variables.set(name, value);
+            self.save();
+        }
+    }
+
+    /// All persistent variables (names without the prefix).
+    pub fn variables(&self) -> &Variables {
+        &self.variables
+    }
+
+    /// Replace all variables, saving if anything changed.
+    pub fn replace(&mut self, variables: Variables) {
+        if variables.all() != self.variables.all() {
+            self.variables = variables;
+            self.save();
+        }
+    }
+}
+
+/// Variable lookup seen by scripts: per-save variables plus the persistent
+/// store under `persistent.`.
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptVariables<'a> {
+    pub variables: &'a Variables,
+    pub persistent: &'a Variables,
+}
+
+impl VariableLookup for ScriptVariables<'_> {
+    fn lookup(&self, name: &str) -> Option<&Value> {
+        match name.strip_prefix(PERSISTENT_PREFIX) {
+            Some(name) => self.persistent.get(name),
+            None => self.variables.get(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_variables_lookup() {
+        let mut variables = Variables::new();
+        variables.set("gold", Value::Int(5));
+        let mut persistent = Variables::new();
+        persistent.set("true_end", Value::Bool(true));
+        let lookup = ScriptVariables {
+            variables: &variables,
+            persistent: &persistent,
+        };
+
+        assert_eq!(lookup.lookup("gold"), Some(&Value::Int(5)));
+        assert_eq!(
+            lookup.lookup("persistent.true_end"),
+            Some(&Value::Bool(true))
+        );
+        assert_eq!(lookup.lookup("true_end"), None);
+        assert_eq!(lookup.lookup("persistent.gold"), None);
+    }
+
+    #[test]
+    fn test_in_memory_store_is_not_saved() {
+        let mut store = PersistentVariables::new();
+        store.set("runs", Value::Int(1));
+        assert_eq!(store.get("runs"), Some(&Value::Int(1)));
+        assert!(store.path.is_none());
+    }
+}
@@ -0,0 +1,119 @@
+//! Seeded pseudo-random numbers for `random:` commands.
+//!
+//! The generator state is part of the game state, its saves and its rollback
+//! history, so replaying from the same point draws the same numbers.
+
+use serde::{Deserialize, Serialize};
+
+use crate::platform;
+
+/// SplitMix64 generator; small, fast and plenty for story randomness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Create a generator from a fixed seed.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Create a generator seeded from the clock.
+    pub fn from_entropy() -> Self {
+        Self::new(platform::random_seed())
+    }
+
+    /// Next raw 64-bit value.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform float in `0.0..1.0`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform integer in `min..=max` (`min` if the range is empty).
+    pub fn range_inclusive(&mut self, min: i64, max: i64) -> i64 {
+        if max <= min {
+            return min;
+        }
+        let span = (max as i128 - min as i128 + 1) as u128;
+        let offset = (self.next_u64() as u128 * span) >> 64;
+        (min as i128 + offset as i128) as i64
+    }
+
+    /// Pick an index with probability proportional to its weight.
+    ///
+    /// Non-positive weights are never picked; returns `None` if no weight is
+    /// positive.
+    pub fn pick_weighted(&mut self, weights: &[f64]) -> Option<usize> {
+        let total: f64 = weights.iter().filter(|w| **w > 0.0).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut roll = self.next_f64() * total;
+        for (index, &weight) in weights.iter().enumerate() {
+            if weight <= 0.0 {
+                continue;
+            }
+            if roll < weight {
+                return Some(index);
+            }
+            roll -= weight;
+        }
+        // Rounding left the roll past the end; take the last candidate
+        weights.iter().rposition(|w| *w > 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn test_range_inclusive_bounds() {
+        let mut rng = Rng::new(7);
+        let mut seen = [false; 6];
+        for _ in 0..1000 {
+            let n = rng.range_inclusive(1, 6);
+            assert!((1..=6).contains(&n));
+            seen[(n - 1) as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+        assert_eq!(rng.range_inclusive(5, 5), 5);
+        // The full range doesn't overflow
+        assert_ne!(
+            rng.range_inclusive(i64::MIN, i64::MAX),
+            rng.range_inclusive(i64::MIN, i64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_pick_weighted() {
+        let mut rng = Rng::new(3);
+        let mut counts = [0; 3];
+        for _ in 0..4000 {
+            counts[rng.pick_weighted(&[3.0, 0.0, 1.0]).unwrap()] += 1;
+        }
+        assert_eq!(counts[1], 0);
+        assert!(counts[0] > counts[2] * 2);
+        assert_eq!(rng.pick_weighted(&[0.0, -1.0]), None);
+        assert_eq!(rng.pick_weighted(&[]), None);
+    }
+}
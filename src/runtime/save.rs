@@ -1,12 +1,30 @@
-use anyhow::Result;
+use std::collections::BTreeSet;
+
+use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 
 use crate::platform;
-use crate::runtime::{Variables, VisualState};
+use crate::runtime::{Rng, Variables, VisualState};
+use crate::scenario::migrate::{Migration, VERSION_KEY, upgrade};
+
+/// Migrations of save files; entry `i` upgrades version `i + 1` to `i + 2`.
+/// See `scenario::migrate`.
+const MIGRATIONS: &[Migration<serde_json::Value>] = &[];
+
+/// Format version of save files written by this version of ivy.
+pub const SAVE_FORMAT_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+fn default_format_version() -> u32 {
+    SAVE_FORMAT_VERSION
+}
 
 /// Save data format.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SaveData {
+    /// Version of the save layout. Saves without one are version 1 and are
+    /// migrated when loaded.
+    #[serde(default = "default_format_version")]
+    pub format_version: u32,
     pub scenario_path: String,
     pub current_index: usize,
     pub visual: VisualState,
@@ -14,6 +32,15 @@ pub struct SaveData {
     pub timestamp: i64,
     #[serde(default)]
     pub variables: Variables,
+    /// Return addresses of active `call`s.
+    #[serde(default)]
+    pub call_stack: Vec<usize>,
+    /// `once` choices already picked, as (command index, choice index).
+    #[serde(default)]
+    pub picked_choices: BTreeSet<(usize, usize)>,
+    /// State of the generator for `random` commands.
+    #[serde(default)]
+    pub rng: Option<Rng>,
 }
 
 impl SaveData {
@@ -27,8 +54,23 @@ impl SaveData {
     /// Load from a JSON file (or localStorage on WASM).
     pub fn load(path: &str) -> Result<Self> {
         let content = platform::read_file(path)?;
-        let save: SaveData = serde_json::from_str(&content)?;
-        Ok(save)
+        Self::from_json(&content)
+    }
+
+    /// Parse a save, migrating saves written in an older layout.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut doc: serde_json::Value = serde_json::from_str(json)?;
+        let version = match doc.get(VERSION_KEY) {
+            None => 1,
+            Some(version) => version
+                .as_u64()
+                .ok_or_else(|| anyhow!("{} must be a whole number", VERSION_KEY))?,
+        };
+        upgrade(&mut doc, version, MIGRATIONS)?;
+        if let Some(map) = doc.as_object_mut() {
+            map.insert(VERSION_KEY.into(), SAVE_FORMAT_VERSION.into());
+        }
+        Ok(serde_json::from_value(doc)?)
     }
 
     /// Get the path for a specific save slot.
@@ -1,15 +1,25 @@
-use std::collections::{HashMap, VecDeque};
+use std::cell::OnceCell;
+use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::runtime::display::{DisplayState, HistoryEntry};
-use crate::runtime::save::SaveData;
-use crate::runtime::variables::Variables;
-use crate::runtime::visual::{CharacterState, VisualState};
-use crate::scenario::Scenario;
+use crate::runtime::display::{ChoiceOption, DisplayState, HistoryEntry};
+use crate::runtime::persistent::{PERSISTENT_PREFIX, PersistentVariables, ScriptVariables};
+use crate::runtime::random::Rng;
+use crate::runtime::save::{SAVE_FORMAT_VERSION, SaveData};
+use crate::runtime::variables::{Value, Variables};
+use crate::runtime::visual::{
+    CharTransform, CharacterSlot, CharacterState, CharacterTween, LayerChange, LayerState,
+    VisualState,
+};
+use crate::scenario::types::{Command, SetVar};
+use crate::scenario::{CharacterDef, Expr, ExprError, Scenario, ScenarioSpans, VariableLookup};
 
 /// Maximum number of history entries for rollback.
 const MAX_HISTORY_SIZE: usize = 50;
 
+/// Maximum nesting depth of `call` (guards against runaway recursion).
+const MAX_CALL_DEPTH: usize = 64;
+
 /// Runtime state for the visual novel engine.
 #[derive(Debug)]
 pub struct GameState {
@@ -18,31 +28,75 @@ pub struct GameState {
     visual: VisualState,
     history: VecDeque<HistoryEntry>,
     variables: Variables,
-    /// Label to index mapping for O(1) lookup.
+    /// Return addresses of active `call`s (innermost last).
+    call_stack: Vec<usize>,
+    /// `once` choices already picked, as (command index, choice index).
+    picked_choices: BTreeSet<(usize, usize)>,
+    /// Generator for `random` commands.
+    rng: Rng,
+    /// Variables shared across playthroughs (`persistent.` names).
+    persistent: PersistentVariables,
+    /// Label to index mapping for O(1) lookup (bare and `file#label` keys).
     label_index: HashMap<String, usize>,
-}
-
-/// Build label index from scenario.
-fn build_label_index(scenario: &Scenario) -> HashMap<String, usize> {
-    scenario
-        .script
-        .iter()
-        .enumerate()
-        .filter_map(|(i, cmd)| cmd.label.as_ref().map(|label| (label.clone(), i)))
-        .collect()
+    /// Displayed command whose `set` has already been applied, so that
+    /// repeated `display_state` calls don't apply it again.
+    applied_index: Option<usize>,
+    /// Choice conditions already reported as failing, as (command index,
+    /// choice index), so each is reported once rather than every frame.
+    failed_conditions: BTreeSet<(usize, usize)>,
+    /// Parsed `if` conditions of choices, as (command index, choice index),
+    /// so each is parsed once rather than every frame.
+    choice_conditions: HashMap<(usize, usize), Option<Result<Expr, ExprError>>>,
+    /// Persistent writes of the steps rolled back over, most recent last,
+    /// each with the command index the step started from.
+    undone_writes: Vec<(usize, Vec<(usize, String)>)>,
+    /// Persistent writes of the step being played again after a rollback,
+    /// skipped as they come up.
+    replayed_writes: Vec<(usize, String)>,
+    /// Spans of the scenario's files, read the first time a warning names
+    /// where a field was written.
+    spans: OnceCell<Vec<ScenarioSpans>>,
 }
 
 impl GameState {
     /// Create a new game state from a scenario.
+    ///
+    /// `persistent.` variables live in an empty in-memory store; use
+    /// [`GameState::with_persistent`] to share them across playthroughs.
     pub fn new(scenario: Scenario) -> Self {
-        let label_index = build_label_index(&scenario);
+        Self::start(scenario, Rng::from_entropy(), PersistentVariables::new())
+    }
+
+    /// Create a new game state whose `random` commands draw from a fixed seed.
+    pub fn with_seed(scenario: Scenario, seed: u64) -> Self {
+        Self::start(scenario, Rng::new(seed), PersistentVariables::new())
+    }
+
+    /// Create a new game state that reads and writes the given persistent
+    /// variables (e.g. `PersistentVariables::load()`).
+    pub fn with_persistent(scenario: Scenario, persistent: PersistentVariables) -> Self {
+        Self::start(scenario, Rng::from_entropy(), persistent)
+    }
+
+    fn start(scenario: Scenario, rng: Rng, persistent: PersistentVariables) -> Self {
+        let label_index = scenario.label_index();
         let mut state = Self {
             scenario,
             current_index: 0,
             visual: VisualState::default(),
             history: VecDeque::new(),
             variables: Variables::new(),
+            call_stack: Vec::new(),
+            picked_choices: BTreeSet::new(),
+            rng,
+            persistent,
             label_index,
+            applied_index: None,
+            failed_conditions: BTreeSet::new(),
+            choice_conditions: HashMap::new(),
+            undone_writes: Vec::new(),
+            replayed_writes: Vec::new(),
+            spans: OnceCell::new(),
         };
         state.skip_labels();
         state
@@ -56,25 +110,47 @@ impl GameState {
             .unwrap_or(0);
 
         SaveData {
+            format_version: SAVE_FORMAT_VERSION,
             scenario_path: scenario_path.to_string(),
             current_index: self.current_index,
             visual: self.current_visual(),
             timestamp,
             variables: self.variables.clone(),
+            call_stack: self.call_stack.clone(),
+            picked_choices: self.picked_choices.clone(),
+            rng: Some(self.rng),
         }
     }
 
     /// Restore from save data.
     pub fn from_save_data(save: &SaveData, scenario: Scenario) -> Self {
+        Self::restore(save, scenario, PersistentVariables::new())
+    }
+
+    /// Restore from save data, reading and writing the given persistent
+    /// variables.
+    pub fn restore(save: &SaveData, scenario: Scenario, persistent: PersistentVariables) -> Self {
         let current_index = save.current_index.min(scenario.script.len());
-        let label_index = build_label_index(&scenario);
+        let label_index = scenario.label_index();
         let mut state = Self {
             scenario,
             current_index,
             visual: save.visual.clone(),
             history: VecDeque::new(),
             variables: save.variables.clone(),
+            call_stack: save.call_stack.clone(),
+            picked_choices: save.picked_choices.clone(),
+            // Saves from before `random` existed carry no generator state
+            rng: save.rng.unwrap_or_else(Rng::from_entropy),
+            persistent,
             label_index,
+            // Saved variables already include the current command's `set`
+            applied_index: Some(current_index),
+            failed_conditions: BTreeSet::new(),
+            choice_conditions: HashMap::new(),
+            undone_writes: Vec::new(),
+            replayed_writes: Vec::new(),
+            spans: OnceCell::new(),
         };
         state.skip_labels();
         state
@@ -88,28 +164,39 @@ impl GameState {
             return DisplayState::End;
         }
 
+        let offered = self.offered_choices();
         let command = &self.scenario.script[self.current_index];
         let visual = self.current_visual();
-        let speaker = command.speaker.clone();
+        let speaker = command
+            .speaker
+            .as_ref()
+            .map(|speaker| self.scenario.speaker_name(speaker));
 
-        if let Some(choices) = &command.choices {
+        // With every choice hidden or disabled, the command shows as plain
+        // text (empty if it has none), so there is always something to click
+        if offered.iter().any(|(_, option)| option.is_enabled()) {
             let text = command.text.clone().unwrap_or_default();
-            // Find default choice index
-            let default_choice = choices.iter().position(|c| c.default).or(Some(0)); // Default to first choice if none marked
+            let choices: Vec<ChoiceOption> =
+                offered.into_iter().map(|(_, option)| option).collect();
+            // The marked default if it's available, else the first enabled choice
+            let default_choice = choices
+                .iter()
+                .position(|c| c.choice.default && c.is_enabled())
+                .or_else(|| choices.iter().position(ChoiceOption::is_enabled));
             return DisplayState::Choices {
                 speaker,
                 text,
-                choices: choices.clone(),
+                choices,
                 visual,
                 timeout: command.timeout,
                 default_choice,
             };
         }
 
-        if let Some(text) = &command.text {
+        if command.text.is_some() || command.choices.is_some() {
             return DisplayState::Text {
                 speaker,
-                text: text.clone(),
+                text: command.text.clone().unwrap_or_default(),
                 visual,
             };
         }
@@ -141,10 +228,106 @@ impl GameState {
         DisplayState::End
     }
 
+    /// Choices of the current command that are offered to the player, with
+    /// their index in the command's `choices` list.
+    ///
+    /// Hides picked `once` choices and choices whose `if` doesn't hold, unless
+    /// they have a `disabled_reason`, in which case they are offered disabled.
+    fn offered_choices(&mut self) -> Vec<(usize, ChoiceOption)> {
+        let Some(choices) = self
+            .scenario
+            .script
+            .get(self.current_index)
+            .and_then(|cmd| cmd.choices.as_ref())
+        else {
+            return Vec::new();
+        };
+
+        let mut offered = Vec::new();
+        for (index, choice) in choices.iter().enumerate() {
+            if choice.once && self.picked_choices.contains(&(self.current_index, index)) {
+                continue;
+            }
+            let condition = self
+                .choice_conditions
+                .entry((self.current_index, index))
+                .or_insert_with(|| choice.condition());
+            let lookup = ScriptVariables {
+                variables: &self.variables,
+                persistent: self.persistent.variables(),
+            };
+            let result = condition.as_ref().map(|condition| {
+                condition
+                    .as_ref()
+                    .map_err(ExprError::clone)
+                    .and_then(|expr| expr.eval_bool(&lookup))
+            });
+            let available = match result {
+                None => true,
+                Some(Ok(result)) => result,
+                Some(Err(e)) => {
+                    if self.failed_conditions.insert((self.current_index, index)) {
+                        eprintln!(
+                            "Warning: choice condition '{}' at {} failed: {}",
+                            choice.if_cond.as_deref().unwrap_or_default(),
+                            self.describe_field(&format!("choices[{}].if", index)),
+                            e
+                        );
+                    }
+                    false
+                }
+            };
+            let disabled = match (available, &choice.disabled_reason) {
+                (true, _) => None,
+                (false, Some(reason)) => Some(reason.clone()),
+                (false, None) => continue,
+            };
+            offered.push((
+                index,
+                ChoiceOption {
+                    choice: choice.clone(),
+                    disabled,
+                },
+            ));
+        }
+        offered
+    }
+
     /// Get the current visual state, applying command overrides.
     fn current_visual(&self) -> VisualState {
+        self.command_visual().0
+    }
+
+    /// Get the `move`s of the current command, with each character's state
+    /// before and after.
+    pub fn current_moves(&self) -> Vec<CharacterTween> {
+        self.command_visual().1
+    }
+
+    /// Get the layers the current command shows, changes or hides, before and
+    /// after.
+    pub fn current_layer_changes(&self) -> Vec<LayerChange> {
+        let Some(command) = self.scenario.script.get(self.current_index) else {
+            return Vec::new();
+        };
+        let visual = self.current_visual();
+        command
+            .layers
+            .iter()
+            .map(|layer| LayerChange {
+                name: layer.name.clone(),
+                from: self.visual.layer(&layer.name).cloned(),
+                to: visual.layer(&layer.name).cloned(),
+                transition: layer.transition.clone(),
+            })
+            .collect()
+    }
+
+    /// Apply the current command's overrides to the visual state, returning
+    /// it with the tweens its `move`s start.
+    fn command_visual(&self) -> (VisualState, Vec<CharacterTween>) {
         if self.current_index >= self.scenario.script.len() {
-            return self.visual.clone();
+            return (self.visual.clone(), Vec::new());
         }
 
         let command = &self.scenario.script[self.current_index];
@@ -159,35 +342,76 @@ impl GameState {
             };
         }
 
+        // Apply layer changes (empty image = hide)
+        for layer in &command.layers {
+            let existing = visual.layers.iter().position(|l| l.name == layer.name);
+            match existing {
+                _ if layer.image.is_empty() => {
+                    visual.layers.retain(|l| l.name != layer.name);
+                }
+                Some(index) => {
+                    let state = &mut visual.layers[index];
+                    state.path = layer.image.clone();
+                    state.z = layer.z.unwrap_or(state.z);
+                }
+                None => visual.layers.push(LayerState {
+                    name: layer.name.clone(),
+                    path: layer.image.clone(),
+                    z: layer.z.unwrap_or(0),
+                }),
+            }
+        }
+
         // Apply multiple characters override
         if let Some(chars) = &command.characters {
+            // Characters without a sprite for their expression are left out
             visual.characters = chars
                 .iter()
-                .map(|c| CharacterState {
-                    path: c.image.clone(),
-                    position: c.pos,
-                    enter: c.enter.clone(),
-                    exit: c.exit.clone(),
-                    idle: c.idle.clone(),
+                .filter_map(|c| {
+                    Some(CharacterState {
+                        path: self
+                            .scenario
+                            .character_sprite(&c.image, c.expression.as_deref())?,
+                        position: c.pos,
+                        transform: CharTransform {
+                            x: c.x,
+                            y: c.y,
+                            scale: c.scale,
+                            ..CharTransform::default()
+                        },
+                        flip: c.flip,
+                        z: c.z,
+                        enter: c.enter.clone(),
+                        exit: c.exit.clone(),
+                        idle: c.idle.clone(),
+                    })
                 })
                 .collect();
             // Clear single character when using multiple
             visual.character = None;
         } else {
-            // Apply single character override (empty string = clear)
+            // Apply single character override (empty string = clear); a
+            // character without a sprite for the expression keeps the previous
             if let Some(ch) = &command.character {
-                visual.character = if ch.is_empty() {
-                    None
-                } else {
-                    Some(ch.clone())
-                };
-                // Clear multiple characters when using single
-                visual.characters.clear();
+                if ch.is_empty() {
+                    visual.character = None;
+                    visual.characters.clear();
+                } else if let Some(path) = self
+                    .scenario
+                    .character_sprite(ch, command.expression.as_deref())
+                {
+                    visual.character = Some(path);
+                    // Clear multiple characters when using single
+                    visual.characters.clear();
+                }
+            } else if let Some(expression) = &command.expression {
+                self.apply_speaker_expression(command, expression, &mut visual);
             }
 
-            // Apply position override
+            // Apply position override (replacing a moved position)
             if let Some(pos) = command.char_pos {
                 visual.char_pos = pos;
+                visual.char_transform.x = None;
             }
         }
 
@@ -208,49 +432,282 @@ impl GameState {
             visual.characters.clear();
         }
 
+        // A cleared single character loses its placement
+        if visual.character.is_none() {
+            visual.char_transform = CharTransform::default();
+        }
+
+        // Apply moves to the characters now on screen
+        let mut tweens = Vec::new();
+        for mv in &command.move_ {
+            let Some(slot) = self.move_slot(mv.target.as_deref(), &visual) else {
+                continue;
+            };
+            let Some(from) = visual.character_state(slot) else {
+                continue;
+            };
+            if let Some(transform) = visual.transform_mut(slot) {
+                transform.apply(mv);
+            }
+            if let Some(to) = visual.character_state(slot) {
+                tweens.push(CharacterTween {
+                    slot,
+                    from,
+                    to,
+                    duration: mv.duration,
+                    easing: mv.easing,
+                });
+            }
+        }
+
+        (visual, tweens)
+    }
+
+    /// Find the character on screen a `move` targets: an index into
+    /// `characters`, an image path or a character id, or without a target,
+    /// the single character.
+    fn move_slot(&self, target: Option<&str>, visual: &VisualState) -> Option<CharacterSlot> {
+        let Some(target) = target else {
+            return visual.character.is_some().then_some(CharacterSlot::Single);
+        };
+        if let Ok(index) = target.parse::<usize>() {
+            return (index < visual.characters.len()).then_some(CharacterSlot::Index(index));
+        }
+        let def = self.scenario.characters.get(target);
+        let matches = |path: &str| path == target || def.is_some_and(|def| def.owns_sprite(path));
+        if visual.character.as_deref().is_some_and(matches) {
+            return Some(CharacterSlot::Single);
+        }
         visual
+            .characters
+            .iter()
+            .position(|character| matches(&character.path))
+            .map(CharacterSlot::Index)
+    }
+
+    /// Warn about the current command's `move`s whose target isn't on
+    /// screen, which are ignored.
+    fn check_move_targets(&self) {
+        let Some(command) = self.scenario.script.get(self.current_index) else {
+            return;
+        };
+        if command.move_.is_empty() {
+            return;
+        }
+        let visual = self.current_visual();
+        for (index, mv) in command.move_.iter().enumerate() {
+            if self.move_slot(mv.target.as_deref(), &visual).is_none() {
+                eprintln!(
+                    "Warning: move of '{}' at {} was ignored: it is not on screen",
+                    mv.target.as_deref().unwrap_or("character"),
+                    self.describe_field(&format!("move[{}]", index))
+                );
+            }
+        }
+    }
+
+    /// Change the speaker's sprite to an expression wherever the speaker is
+    /// shown, showing them as the single character if they aren't.
+    fn apply_speaker_expression(
+        &self,
+        command: &Command,
+        expression: &str,
+        visual: &mut VisualState,
+    ) {
+        let Some((_, def)) = command
+            .speaker
+            .as_ref()
+            .and_then(|speaker| self.scenario.speaker_character(speaker))
+        else {
+            return;
+        };
+        let Some(sprite) = def.sprite_for(Some(expression)) else {
+            return;
+        };
+
+        let mut shown = false;
+        if visual
+            .character
+            .as_deref()
+            .is_some_and(|path| def.owns_sprite(path))
+        {
+            visual.character = Some(sprite.to_string());
+            shown = true;
+        }
+        for character in &mut visual.characters {
+            if def.owns_sprite(&character.path) {
+                character.path = sprite.to_string();
+                shown = true;
+            }
+        }
+        if !shown {
+            visual.character = Some(sprite.to_string());
+            visual.characters.clear();
+        }
+    }
+
+    /// Describe the current command for warnings, with where `field` was
+    /// written when it is known, e.g. "command 3 (main.yaml:12:5)".
+    fn describe_field(&self, field: &str) -> String {
+        let spans = self
+            .spans
+            .get_or_init(|| ScenarioSpans::load(&self.scenario));
+        match self.scenario.locate_field(spans, self.current_index, field) {
+            Some(location) => format!("command {} ({})", self.current_index, location),
+            None => format!("command {}", self.current_index),
+        }
     }
 
     /// Process set command for current index.
     fn process_set(&mut self) {
-        if let Some(set) = self
+        let Some(set) = self
             .scenario
             .script
             .get(self.current_index)
             .and_then(|cmd| cmd.set.as_ref())
-        {
-            self.variables.set(set.name.clone(), set.value.clone());
+        else {
+            return;
+        };
+
+        match set.evaluate(&self.script_variables()) {
+            Ok(value) => {
+                let name = set.name.clone();
+                self.assign(&name, value, "set");
+            }
+            Err(e) => eprintln!(
+                "Warning: set '{}' at {} failed: {}",
+                set.name,
+                self.describe_field("set"),
+                e
+            ),
         }
     }
 
-    /// Check if condition for current index and return jump target if true.
-    fn check_condition(&self) -> Option<String> {
-        let if_cond = self
+    /// Store a variable set by `field` of the current command, routing
+    /// `persistent.` names to the persistent store.
+    fn assign(&mut self, name: &str, value: Value, field: &str) {
+        match name.strip_prefix(PERSISTENT_PREFIX) {
+            Some(name) => {
+                if self.first_persistent_write(field) {
+                    self.persistent.set(name, value);
+                }
+            }
+            None => self.variables.set(name, value),
+        }
+    }
+
+    /// Record a persistent write by `field` of the current command, and
+    /// check whether it is new rather than one already made before a
+    /// rollback.
+    fn first_persistent_write(&mut self, field: &str) -> bool {
+        let write = (self.current_index, field.to_string());
+        let replayed = self.replayed_writes.iter().position(|w| *w == write);
+        if let Some(position) = replayed {
+            self.replayed_writes.remove(position);
+        }
+        if let Some(entry) = self.history.back_mut() {
+            entry.persistent_writes.push(write);
+        }
+        replayed.is_none()
+    }
+
+    /// Draw the current command's `random`: store its number, or return the
+    /// label it picked.
+    fn process_random(&mut self) -> Option<String> {
+        let random = self
             .scenario
             .script
             .get(self.current_index)
-            .and_then(|cmd| cmd.if_cond.as_ref())?;
+            .and_then(|cmd| cmd.random.as_ref())?;
+
+        if let Err(e) = random.check() {
+            eprintln!(
+                "Warning: random at {} is invalid: {}",
+                self.describe_field("random"),
+                e
+            );
+            return None;
+        }
+        if let (Some(var), Some(min), Some(max)) = (&random.var, random.min, random.max) {
+            let var = var.clone();
+            let value = self.rng.range_inclusive(min, max);
+            self.assign(&var, Value::Int(value), "random");
+            return None;
+        }
+        let weights: Vec<f64> = random.branches.iter().map(|b| b.weight).collect();
+        let picked = self.rng.pick_weighted(&weights)?;
+        Some(random.branches[picked].jump.clone())
+    }
 
-        if self.variables.equals(&if_cond.var, &if_cond.is) {
-            Some(if_cond.jump.clone())
-        } else {
-            None
+    /// Check the `if` or `switch` of the current command and return its jump
+    /// target, if it takes one.
+    fn check_condition(&self) -> Option<String> {
+        let cmd = self.scenario.script.get(self.current_index)?;
+
+        if let Some(if_cond) = &cmd.if_cond {
+            let matched = match (&if_cond.expr, &if_cond.var, &if_cond.is) {
+                (None, Some(var), Some(is)) => self.script_variables().lookup(var) == Some(is),
+                _ => match if_cond
+                    .condition()
+                    .and_then(|expr| expr.eval_bool(&self.script_variables()))
+                {
+                    Ok(result) => result,
+                    Err(e) => {
+                        eprintln!(
+                            "Warning: condition '{}' at {} failed: {}",
+                            if_cond.describe(),
+                            self.describe_field("if"),
+                            e
+                        );
+                        false
+                    }
+                },
+            };
+
+            let target = if matched {
+                Some(&if_cond.jump)
+            } else {
+                if_cond.else_jump.as_ref()
+            };
+            if let Some(target) = target {
+                return Some(target.clone());
+            }
+        }
+
+        let switch = cmd.switch.as_ref()?;
+        match switch.select(&self.script_variables()) {
+            Ok(target) => target.map(str::to_string),
+            Err(e) => {
+                eprintln!(
+                    "Warning: switch on '{}' at {} failed: {}",
+                    switch.describe(),
+                    self.describe_field("switch"),
+                    e
+                );
+                switch.default.clone()
+            }
         }
     }
 
     /// Push current state to history for rollback.
     fn push_history(&mut self) {
-        let text = self
-            .scenario
-            .script
-            .get(self.current_index)
-            .and_then(|cmd| cmd.text.clone())
-            .unwrap_or_default();
+        let command = self.scenario.script.get(self.current_index);
+        let text = command.and_then(|cmd| cmd.text.clone()).unwrap_or_default();
+        let speaker = command
+            .and_then(|cmd| cmd.speaker.as_ref())
+            .map(|speaker| self.scenario.speaker_name(speaker));
 
         let entry = HistoryEntry {
             index: self.current_index,
             visual: self.visual.clone(),
             text,
+            speaker,
+            speaker_color: self.current_speaker_color().map(str::to_string),
+            call_stack: self.call_stack.clone(),
+            picked_choices: self.picked_choices.clone(),
+            variables: self.variables.clone(),
+            rng: self.rng,
+            persistent_writes: Vec::new(),
         };
 
         self.history.push_back(entry);
@@ -259,6 +716,16 @@ impl GameState {
         if self.history.len() > MAX_HISTORY_SIZE {
             self.history.pop_front();
         }
+
+        // Stepping on from where a rolled back step started plays it again;
+        // going anywhere else leaves the undone steps behind
+        self.replayed_writes = match self.undone_writes.pop() {
+            Some((index, writes)) if index == self.current_index => writes,
+            _ => {
+                self.undone_writes.clear();
+                Vec::new()
+            }
+        };
     }
 
     /// Advance to the next command (for text display).
@@ -273,12 +740,8 @@ impl GameState {
         // Update visual state before advancing
         self.visual = self.current_visual();
 
-        // Clone jump target before mutating self
-        let jump_target = self.scenario.script[self.current_index].jump.clone();
-
-        // If there's an unconditional jump, follow it
-        if let Some(jump_label) = jump_target {
-            self.jump_to(&jump_label);
+        // Follow call/return/jump if present
+        if self.follow_flow() {
             return;
         }
 
@@ -286,27 +749,127 @@ impl GameState {
         self.skip_labels();
     }
 
-    /// Select a choice and jump to the target label.
+    /// Follow the current command's `call`, `return` or `jump`.
+    ///
+    /// Returns true if control moved elsewhere.
+    fn follow_flow(&mut self) -> bool {
+        // Clone targets before mutating self
+        let command = &self.scenario.script[self.current_index];
+        let call_target = command.call.clone();
+        let is_return = command.return_;
+        let jump_target = command.jump.clone();
+
+        if let Some(label) = call_target {
+            // Without a return address the next `return` would resume in
+            // the wrong place, so a call too deep isn't made at all.
+            if self.call_stack.len() >= MAX_CALL_DEPTH {
+                eprintln!(
+                    "Warning: call to '{}' at {} exceeds the maximum depth of {} and was skipped",
+                    label,
+                    self.describe_field("call"),
+                    MAX_CALL_DEPTH
+                );
+                return false;
+            }
+            self.call_stack.push(self.current_index + 1);
+            self.jump_to(&label);
+            return true;
+        }
+
+        if is_return {
+            match self.call_stack.pop() {
+                Some(return_index) => {
+                    self.current_index = return_index;
+                    self.applied_index = None;
+                    self.skip_labels();
+                    return true;
+                }
+                None => eprintln!(
+                    "Warning: return at {} without a matching call",
+                    self.describe_field("return")
+                ),
+            }
+        }
+
+        if let Some(label) = jump_target {
+            self.jump_to(&label);
+            return true;
+        }
+
+        false
+    }
+
+    /// Select a choice, apply its `set` assignments and jump to the target label.
+    ///
+    /// `choice_index` is an index into the choices of `DisplayState::Choices`.
+    /// Disabled choices can't be selected.
     pub fn select_choice(&mut self, choice_index: usize) {
         if self.current_index >= self.scenario.script.len() {
             return;
         }
 
+        let Some((index, option)) = self.offered_choices().into_iter().nth(choice_index) else {
+            return;
+        };
+        if !option.is_enabled() {
+            return;
+        }
+
         // Save current state for rollback
         self.push_history();
 
         // Update visual state before jumping
         self.visual = self.current_visual();
 
-        // Clone jump target before mutating self
-        let jump_target = self.scenario.script[self.current_index]
-            .choices
-            .as_ref()
-            .and_then(|choices| choices.get(choice_index))
-            .map(|choice| choice.jump.clone());
+        if option.choice.once {
+            self.picked_choices.insert((self.current_index, index));
+        }
+        self.apply_choice_sets(&option.choice.set, index);
+        self.jump_to(&option.choice.jump);
+    }
 
-        if let Some(label) = jump_target {
-            self.jump_to(&label);
+    /// Apply a choice's assignments all together: each sees the results of
+    /// the previous ones, and if any fails none of them are applied.
+    fn apply_choice_sets(&mut self, sets: &[SetVar], choice_index: usize) {
+        let mut variables = self.variables.clone();
+        let mut persistent = self.persistent.variables().clone();
+        let mut persistent_fields = Vec::new();
+        for (set_index, set) in sets.iter().enumerate() {
+            let lookup = ScriptVariables {
+                variables: &variables,
+                persistent: &persistent,
+            };
+            match set.evaluate(&lookup) {
+                Ok(value) => match set.name.strip_prefix(PERSISTENT_PREFIX) {
+                    Some(name) => {
+                        let field = format!("choices[{}].set[{}]", choice_index, set_index);
+                        let write = (self.current_index, field);
+                        if !self.replayed_writes.contains(&write) {
+                            persistent.set(name, value);
+                        }
+                        persistent_fields.push(write.1);
+                    }
+                    None => variables.set(set.name.clone(), value),
+                },
+                Err(e) => {
+                    eprintln!(
+                        "Warning: set '{}' on choice {} at {} failed, so none of the choice's assignments were applied: {}",
+                        set.name,
+                        choice_index + 1,
+                        self.describe_field(&format!(
+                            "choices[{}].set[{}]",
+                            choice_index, set_index
+                        )),
+                        e
+                    );
+                    return;
+                }
+            }
+        }
+        self.variables = variables;
+        self.persistent.replace(persistent);
+        for field in persistent_fields {
+            self.first_persistent_write(&field);
         }
     }
 
@@ -324,7 +887,7 @@ impl GameState {
 
         // Set the variable if this is an input command
         if let Some(name) = var_name {
-            self.variables.set(name, crate::types::Value::String(value));
+            self.assign(&name, Value::String(value), "input");
         }
 
         // Advance to the next command
@@ -340,6 +903,7 @@ impl GameState {
     fn jump_to(&mut self, label: &str) {
         if let Some(&index) = self.label_index.get(label) {
             self.current_index = index;
+            self.applied_index = None;
             self.skip_labels();
         } else {
             // Label not found, go to end
@@ -360,7 +924,16 @@ impl GameState {
             };
 
             if has_displayable {
-                self.process_set();
+                if self.applied_index != Some(self.current_index) {
+                    self.check_move_targets();
+                    self.process_set();
+                    let random_jump = self.process_random();
+                    self.applied_index = Some(self.current_index);
+                    if let Some(jump_label) = random_jump {
+                        self.jump_to(&jump_label);
+                        return;
+                    }
+                }
                 // Check conditional jump before displaying
                 if let Some(jump_label) = self.check_condition() {
                     self.jump_to(&jump_label);
@@ -370,23 +943,26 @@ impl GameState {
             }
 
             // Update visual state for skipped commands
+            self.check_move_targets();
             self.visual = self.current_visual();
 
             // Process set command
             self.process_set();
 
+            // Draw random numbers and branches
+            if let Some(jump_label) = self.process_random() {
+                self.jump_to(&jump_label);
+                return;
+            }
+
             // Check conditional jump first
             if let Some(jump_label) = self.check_condition() {
                 self.jump_to(&jump_label);
                 return;
             }
 
-            // Clone unconditional jump target (scope the borrow)
-            let jump_target = self.scenario.script[self.current_index].jump.clone();
-
-            // If command has unconditional jump, follow it
-            if let Some(jump_label) = jump_target {
-                self.jump_to(&jump_label);
+            // If command has call/return/jump, follow it
+            if self.follow_flow() {
                 return;
             }
 
@@ -408,7 +984,15 @@ impl GameState {
     pub fn rollback(&mut self) -> bool {
         if let Some(entry) = self.history.pop_back() {
             self.current_index = entry.index;
+            self.applied_index = Some(entry.index);
             self.visual = entry.visual;
+            self.call_stack = entry.call_stack;
+            self.picked_choices = entry.picked_choices;
+            self.variables = entry.variables;
+            self.rng = entry.rng;
+            self.undone_writes
+                .push((entry.index, entry.persistent_writes));
+            self.replayed_writes.clear();
             true
         } else {
             false
@@ -436,12 +1020,34 @@ impl GameState {
             .and_then(|cmd| cmd.se.as_ref())
     }
 
-    /// Get current voice command.
-    pub fn current_voice(&self) -> Option<&String> {
+    /// Get current voice command, after the speaking character's
+    /// `voice_prefix`.
+    pub fn current_voice(&self) -> Option<String> {
+        let command = self.scenario.script.get(self.current_index)?;
+        let voice = command.voice.as_ref()?;
+        let prefix = self
+            .current_speaker()
+            .and_then(|(_, def)| def.voice_prefix.as_deref());
+        match prefix {
+            Some(prefix) if !voice.is_empty() => Some(format!("{}{}", prefix, voice)),
+            _ => Some(voice.clone()),
+        }
+    }
+
+    /// Get the character speaking the current command, with its id.
+    pub fn current_speaker(&self) -> Option<(&str, &CharacterDef)> {
         self.scenario
             .script
             .get(self.current_index)
-            .and_then(|cmd| cmd.voice.as_ref())
+            .and_then(|cmd| cmd.speaker.as_ref())
+            .and_then(|speaker| self.scenario.speaker_character(speaker))
+    }
+
+    /// Get the name colour of the current speaker, if it is a character
+    /// with one.
+    pub fn current_speaker_color(&self) -> Option<&str> {
+        self.current_speaker()
+            .and_then(|(_, def)| def.color.as_deref())
     }
 
     /// Get current script index.
@@ -454,9 +1060,31 @@ impl GameState {
         &self.variables
     }
 
-    /// Set a variable value.
+    /// Get the variables shared across playthroughs.
+    pub fn persistent(&self) -> &PersistentVariables {
+        &self.persistent
+    }
+
+    /// Variables as seen by scripts, including `persistent.` names.
+    pub fn script_variables(&self) -> ScriptVariables<'_> {
+        ScriptVariables {
+            variables: &self.variables,
+            persistent: self.persistent.variables(),
+        }
+    }
+
+    /// Get the return addresses of active calls (innermost last).
+    pub fn call_stack(&self) -> &[usize] {
+        &self.call_stack
+    }
+
+    /// Set a variable value (`persistent.` names go to the persistent store).
     pub fn set_variable(&mut self, name: impl Into<String>, value: crate::types::Value) {
-        self.variables.set(name, value);
+        let name = name.into();
+        match name.strip_prefix(PERSISTENT_PREFIX) {
+            Some(name) => self.persistent.set(name, value),
+            None => self.variables.set(name, value),
+        }
     }
 
     /// Get current transition command.
@@ -588,7 +1216,7 @@ impl GameState {
         let old_label = self.current_label();
         let old_index = self.current_index;
 
-        self.label_index = build_label_index(&scenario);
+        self.label_index = scenario.label_index();
         self.scenario = scenario;
 
         // Try to jump to the same label first
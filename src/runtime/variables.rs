@@ -2,6 +2,7 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
+use crate::scenario::VariableLookup;
 pub use crate::types::Value;
 
 /// Variable storage for game state.
@@ -55,3 +56,9 @@ impl Variables {
         self.data = data;
     }
 }
+
+impl VariableLookup for Variables {
+    fn lookup(&self, name: &str) -> Option<&Value> {
+        self.data.get(name)
+    }
+}
@@ -2,13 +2,91 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-use crate::scenario::{CameraFocus, CharAnimation, CharIdleAnimation, CharPosition, Easing};
+use crate::scenario::{
+    CameraFocus, CharAnimation, CharIdleAnimation, CharPosition, CharacterMove, Easing,
+};
+
+/// Placement, opacity and tint of a character sprite; what `move` tweens.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CharTransform {
+    /// Horizontal centre as a fraction of the screen width, overriding the
+    /// position preset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<f32>,
+    /// Bottom edge as a fraction of the screen height (1.0 = screen bottom).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<f32>,
+    /// Size relative to the default sprite height.
+    #[serde(default = "default_one", skip_serializing_if = "is_one")]
+    pub scale: f32,
+    /// Opacity (0.0 = invisible, 1.0 = opaque).
+    #[serde(default = "default_one", skip_serializing_if = "is_one")]
+    pub opacity: f32,
+    /// Colour multiplied into the sprite (color name or `#rrggbb`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tint: Option<String>,
+}
+
+impl Default for CharTransform {
+    fn default() -> Self {
+        Self {
+            x: None,
+            y: None,
+            scale: 1.0,
+            opacity: 1.0,
+            tint: None,
+        }
+    }
+}
+
+impl CharTransform {
+    /// Check if nothing differs from the default placement.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Set the properties a `move` gives.
+    pub fn apply(&mut self, mv: &CharacterMove) {
+        if mv.x.is_some() {
+            self.x = mv.x;
+        }
+        if mv.y.is_some() {
+            self.y = mv.y;
+        }
+        if let Some(scale) = mv.scale {
+            self.scale = scale;
+        }
+        if let Some(opacity) = mv.opacity {
+            self.opacity = opacity.clamp(0.0, 1.0);
+        }
+        if let Some(tint) = &mv.tint {
+            self.tint = Some(tint.clone());
+        }
+    }
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+fn is_one(value: &f32) -> bool {
+    *value == 1.0
+}
 
 /// Single character state for multi-character support.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CharacterState {
     pub path: String,
     pub position: CharPosition,
+    /// Placement, opacity and tint.
+    #[serde(flatten)]
+    pub transform: CharTransform,
+    /// Mirror the sprite horizontally.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub flip: bool,
+    /// Drawing order; higher is drawn in front.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub z: i32,
     /// Enter animation for this character (optional).
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub enter: Option<CharAnimation>,
@@ -20,6 +98,51 @@ pub struct CharacterState {
     pub idle: Option<CharIdleAnimation>,
 }
 
+impl CharacterState {
+    /// Horizontal centre as a fraction of the screen width.
+    pub fn anchor_x(&self) -> f32 {
+        self.transform.x.unwrap_or_else(|| self.position.anchor_x())
+    }
+
+    /// Bottom edge as a fraction of the screen height.
+    pub fn anchor_y(&self) -> f32 {
+        self.transform.y.unwrap_or(1.0)
+    }
+}
+
+/// Identity of an entry of `characters`, for state that belongs to the
+/// sprite rather than to its place in the list (such as its animations).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CharacterKey {
+    pub path: String,
+    /// Number of earlier entries showing the same image.
+    pub occurrence: usize,
+}
+
+/// A character on screen, as targeted by `move`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CharacterSlot {
+    /// The single `character`.
+    Single,
+    /// An entry of `characters`.
+    Index(usize),
+}
+
+/// A `move` of the current command: the character before and after it.
+#[derive(Debug, Clone)]
+pub struct CharacterTween {
+    pub slot: CharacterSlot,
+    pub from: CharacterState,
+    pub to: CharacterState,
+    /// Animation duration in seconds.
+    pub duration: f32,
+    pub easing: Easing,
+}
+
+fn is_zero(z: &i32) -> bool {
+    *z == 0
+}
+
 /// Camera state for dynamic camera effects.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CameraState {
@@ -108,6 +231,34 @@ impl CameraAnimationState {
     }
 }
 
+/// A named image layer on screen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayerState {
+    pub name: String,
+    pub path: String,
+    /// Drawing order; below 0 is behind the characters.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub z: i32,
+}
+
+impl LayerState {
+    /// Check if the layer is drawn behind the characters.
+    pub fn is_behind_characters(&self) -> bool {
+        self.z < 0
+    }
+}
+
+/// A layer shown, changed or hidden by the current command.
+#[derive(Debug, Clone)]
+pub struct LayerChange {
+    pub name: String,
+    /// The layer before the command (None = not shown).
+    pub from: Option<LayerState>,
+    /// The layer after the command (None = hidden).
+    pub to: Option<LayerState>,
+    pub transition: Option<CharAnimation>,
+}
+
 /// Modular character state for layered sprite compositing.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ModularCharState {
@@ -127,6 +278,9 @@ pub struct VisualState {
     pub character: Option<String>,
     #[serde(default)]
     pub char_pos: CharPosition,
+    /// Placement of the single character, changed by `move`.
+    #[serde(default, skip_serializing_if = "CharTransform::is_default")]
+    pub char_transform: CharTransform,
     /// Multiple characters (used when `characters` field is set in command).
     #[serde(default)]
     pub characters: Vec<CharacterState>,
@@ -136,4 +290,73 @@ pub struct VisualState {
     /// Modular character (layered sprite compositing).
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub modular_char: Option<ModularCharState>,
+    /// Named image layers, in the order they were first shown.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<LayerState>,
+}
+
+impl VisualState {
+    /// Indices of `characters` in drawing order, back to front: by `z`, with
+    /// ties in list order.
+    pub fn character_draw_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.characters.len()).collect();
+        order.sort_by_key(|&index| self.characters[index].z);
+        order
+    }
+
+    /// Identity of each entry of `characters`, which stays with the sprite
+    /// when entries are added, removed or reordered.
+    pub fn character_keys(&self) -> Vec<CharacterKey> {
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        self.characters
+            .iter()
+            .map(|character| {
+                let occurrence = seen.entry(&character.path).or_default();
+                let key = CharacterKey {
+                    path: character.path.clone(),
+                    occurrence: *occurrence,
+                };
+                *occurrence += 1;
+                key
+            })
+            .collect()
+    }
+
+    /// A layer on screen by name.
+    pub fn layer(&self, name: &str) -> Option<&LayerState> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    /// Layers in drawing order, back to front: by `z`, with ties in the order
+    /// they were first shown.
+    pub fn layer_draw_order(&self) -> Vec<&LayerState> {
+        let mut order: Vec<&LayerState> = self.layers.iter().collect();
+        order.sort_by_key(|layer| layer.z);
+        order
+    }
+
+    /// A character on screen as a `CharacterState`; the single character
+    /// takes its position and placement from `char_pos` and `char_transform`.
+    pub fn character_state(&self, slot: CharacterSlot) -> Option<CharacterState> {
+        match slot {
+            CharacterSlot::Single => self.character.as_ref().map(|path| CharacterState {
+                path: path.clone(),
+                position: self.char_pos,
+                transform: self.char_transform.clone(),
+                ..CharacterState::default()
+            }),
+            CharacterSlot::Index(index) => self.characters.get(index).cloned(),
+        }
+    }
+
+    /// Placement of a character on screen, for changing it.
+    pub fn transform_mut(&mut self, slot: CharacterSlot) -> Option<&mut CharTransform> {
+        match slot {
+            CharacterSlot::Single => self.character.is_some().then_some(&mut self.char_transform),
+            CharacterSlot::Index(index) => self
+                .characters
+                .get_mut(index)
+                .map(|character| &mut character.transform),
+        }
+    }
 }
@@ -0,0 +1,468 @@
+//! Scripts for voice actors and proofreaders, exported from a [`Scenario`].
+//!
+//! [`spoken_lines`] lists every line a speaker says, for per-character
+//! CSV/TSV tables ([`lines_table`]). [`fountain`] and [`html`] write the whole
+//! script as a readable screenplay, with labels as scenes and choices and
+//! jumps as branches.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::i18n::{LocalizedString, Translations};
+use crate::scenario::markup::strip_markup;
+use crate::scenario::types::{Command, Scenario};
+
+/// Language used to name speakers and resolve text when none is given.
+const DEFAULT_LANGUAGE: &str = "en";
+
+/// Extension of generated voice filenames.
+const VOICE_EXTENSION: &str = "ogg";
+
+/// A line of dialogue, as listed for recording.
+#[derive(Debug, Clone)]
+pub struct SpokenLine {
+    /// Character id, or the speaker as written if it isn't a character.
+    pub character: String,
+    /// Last label at or before the line in its file.
+    pub label: Option<String>,
+    /// Index of the command in the script.
+    pub index: usize,
+    pub text: LocalizedString,
+    /// Voice file of the line, after the character's `voice_prefix`.
+    pub voice: String,
+    /// Whether `voice` was generated because the line has none.
+    pub generated_voice: bool,
+}
+
+/// Every line with a speaker and text, in script order. Speakers written as
+/// `@key` are named from `translations`.
+pub fn spoken_lines(scenario: &Scenario, translations: &Translations) -> Vec<SpokenLine> {
+    let mut lines = Vec::new();
+    let mut label = None;
+    // Where the block of commands under `label` starts
+    let mut block_start = 0;
+    // Included file the commands are from, none for the root file
+    let mut file = None;
+    for (index, command) in scenario.script.iter().enumerate() {
+        if let Some(position) = scenario
+            .sources
+            .iter()
+            .position(|source| source.start == index)
+        {
+            label = None;
+            block_start = index;
+            file = (position > 0).then(|| scenario.sources[position].name.as_str());
+        }
+        if command.label.is_some() {
+            label = command.label.clone();
+            block_start = index;
+        }
+        let (Some(speaker), Some(text)) = (&command.speaker, &command.text) else {
+            continue;
+        };
+
+        let definition = scenario.speaker_character(speaker);
+        let character = match definition {
+            Some((id, _)) => id.to_string(),
+            None => speaker.resolve(DEFAULT_LANGUAGE, translations),
+        };
+        let prefix = definition
+            .and_then(|(_, def)| def.voice_prefix.clone())
+            .unwrap_or_default();
+        let (voice, generated_voice) = match command.voice.as_deref() {
+            Some(voice) if !voice.is_empty() => (format!("{}{}", prefix, voice), false),
+            _ => (
+                generated_voice_path(
+                    &prefix,
+                    &character,
+                    file,
+                    label.as_deref(),
+                    index - block_start,
+                ),
+                true,
+            ),
+        };
+
+        lines.push(SpokenLine {
+            character,
+            label: label.clone(),
+            index,
+            text: text.clone(),
+            voice,
+            generated_voice,
+        });
+    }
+    lines
+}
+
+/// Voice filename for a line without one, e.g. `alice/start_0012.ogg`
+/// (under the character's `voice_prefix` if it has one), or
+/// `alice/chapter2/start_0012.ogg` for a line of the included file
+/// `chapter2.yaml`, so that labels shared by files don't share names.
+///
+/// `offset` is the line's place in the block under its label, so that
+/// editing other blocks or files doesn't rename it.
+fn generated_voice_path(
+    prefix: &str,
+    character: &str,
+    file: Option<&str>,
+    label: Option<&str>,
+    offset: usize,
+) -> String {
+    let mut folder = if prefix.is_empty() {
+        format!("{}/", file_name_part(character))
+    } else {
+        prefix.to_string()
+    };
+    if let Some(file) = file {
+        let stem = Path::new(file).with_extension("");
+        folder.push_str(&file_name_part(&stem.to_string_lossy()));
+        folder.push('/');
+    }
+    format!(
+        "{}{}_{:04}.{}",
+        folder,
+        file_name_part(label.unwrap_or("line")),
+        offset,
+        VOICE_EXTENSION
+    )
+}
+
+/// Replace characters that don't belong in a filename.
+fn file_name_part(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Languages of the text of the given lines, sorted: those of inline
+/// localized text, and those of `translations` if a line is an `@key`.
+pub fn text_languages(lines: &[SpokenLine], translations: &Translations) -> Vec<String> {
+    let mut languages: BTreeSet<String> = lines
+        .iter()
+        .filter_map(|line| match &line.text {
+            LocalizedString::Localized(map) => Some(map.keys()),
+            _ => None,
+        })
+        .flatten()
+        .cloned()
+        .collect();
+    if lines
+        .iter()
+        .any(|line| matches!(line.text, LocalizedString::Key(_)))
+    {
+        languages.extend(translations.languages());
+    }
+    languages.into_iter().collect()
+}
+
+/// A CSV (`,`) or TSV (`\t`) table of lines, with one text column per
+/// language (a single `text` column if `languages` is empty). `@key` text is
+/// resolved from `translations`.
+pub fn lines_table(
+    lines: &[SpokenLine],
+    languages: &[String],
+    separator: char,
+    translations: &Translations,
+) -> String {
+    let mut header = vec!["character".to_string(), "label".into(), "index".into()];
+    if languages.is_empty() {
+        header.push("text".into());
+    } else {
+        header.extend(languages.iter().map(|lang| format!("text_{}", lang)));
+    }
+    header.extend(["voice".into(), "voice_generated".into()]);
+
+    let mut table = table_row(&header, separator);
+    for line in lines {
+        let mut row = vec![
+            line.character.clone(),
+            line.label.clone().unwrap_or_default(),
+            line.index.to_string(),
+        ];
+        if languages.is_empty() {
+            row.push(strip_markup(
+                &line.text.resolve(DEFAULT_LANGUAGE, translations),
+            ));
+        } else {
+            row.extend(
+                languages
+                    .iter()
+                    .map(|lang| strip_markup(&line.text.resolve(lang, translations))),
+            );
+        }
+        row.extend([line.voice.clone(), line.generated_voice.to_string()]);
+        table.push_str(&table_row(&row, separator));
+    }
+    table
+}
+
+/// One table row, quoting CSV fields or flattening TSV fields as needed.
+fn table_row(fields: &[String], separator: char) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if separator == '\t' {
+                field.replace(['\t', '\n', '\r'], " ")
+            } else if field.contains([separator, '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect();
+    format!("{}\n", fields.join(&separator.to_string()))
+}
+
+/// A step of the script as shown in a screenplay.
+enum Beat {
+    Scene(String),
+    Dialogue {
+        speaker: String,
+        text: String,
+    },
+    Narration(String),
+    /// Choice text, the label it jumps to and its condition.
+    Choices(Vec<(String, String, Option<String>)>),
+    /// A branch to a label, e.g. `jump` or `if x > 1`.
+    Branch {
+        kind: String,
+        target: String,
+    },
+    Return,
+}
+
+/// The script as screenplay beats, with text in the given language.
+fn beats(scenario: &Scenario, lang: &str, translations: &Translations) -> Vec<Beat> {
+    let resolve = |text: &LocalizedString| strip_markup(&text.resolve(lang, translations));
+    let mut beats = Vec::new();
+    for command in &scenario.script {
+        if let Some(label) = &command.label {
+            beats.push(Beat::Scene(label.clone()));
+        }
+        if let Some(text) = &command.text {
+            let text = resolve(text);
+            match &command.speaker {
+                Some(speaker) => beats.push(Beat::Dialogue {
+                    speaker: resolve(&scenario.speaker_name(speaker)),
+                    text,
+                }),
+                None if !text.is_empty() => beats.push(Beat::Narration(text)),
+                None => {}
+            }
+        }
+        if let Some(choices) = &command.choices {
+            beats.push(Beat::Choices(
+                choices
+                    .iter()
+                    .map(|choice| {
+                        (
+                            resolve(&choice.label),
+                            choice.jump.clone(),
+                            choice.if_cond.clone(),
+                        )
+                    })
+                    .collect(),
+            ));
+        }
+        branches(command, &mut beats);
+    }
+    beats
+}
+
+/// Add the jumps a command can make.
+fn branches(command: &Command, beats: &mut Vec<Beat>) {
+    let mut branch = |kind: String, target: &str| {
+        beats.push(Beat::Branch {
+            kind,
+            target: target.to_string(),
+        })
+    };
+    if let Some(if_cond) = &command.if_cond {
+        branch(format!("if {}", if_cond.describe()), &if_cond.jump);
+        if let Some(else_jump) = &if_cond.else_jump {
+            branch("else".to_string(), else_jump);
+        }
+    }
+    if let Some(switch) = &command.switch {
+        for case in &switch.cases {
+            branch(
+                format!("switch {} {}", switch.describe(), case.describe()),
+                &case.jump,
+            );
+        }
+        if let Some(default) = &switch.default {
+            branch(format!("switch {} default", switch.describe()), default);
+        }
+    }
+    if let Some(random) = &command.random {
+        for (index, random_branch) in random.branches.iter().enumerate() {
+            branch(
+                format!("random {}", random.describe_branch(index)),
+                &random_branch.jump,
+            );
+        }
+    }
+    if let Some(call) = &command.call {
+        branch("call".to_string(), call);
+    }
+    if let Some(jump) = &command.jump {
+        branch("jump".to_string(), jump);
+    }
+    if command.return_ {
+        beats.push(Beat::Return);
+    }
+}
+
+/// The script as a Fountain screenplay, with text in the given language.
+///
+/// Labels are scene headings, choices and jumps centered notes and
+/// transitions.
+pub fn fountain(scenario: &Scenario, lang: &str, translations: &Translations) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "Title: {}\n", scenario.title);
+    for beat in beats(scenario, lang, translations) {
+        let _ = match beat {
+            Beat::Scene(label) => writeln!(out, ".{}\n", label.to_uppercase()),
+            Beat::Dialogue { speaker, text } => {
+                writeln!(out, "@{}\n{}\n", speaker.to_uppercase(), text)
+            }
+            Beat::Narration(text) => writeln!(out, "!{}\n", text),
+            Beat::Choices(choices) => {
+                let _ = writeln!(out, "> CHOICE <");
+                for (text, target, condition) in choices {
+                    let condition = condition
+                        .map(|condition| format!(" [[if {}]]", condition))
+                        .unwrap_or_default();
+                    let _ = writeln!(
+                        out,
+                        "> {} -> {}{} <",
+                        text,
+                        target.to_uppercase(),
+                        condition
+                    );
+                }
+                writeln!(out)
+            }
+            Beat::Branch { kind, target } => {
+                writeln!(out, "> {} {}\n", kind.to_uppercase(), target.to_uppercase())
+            }
+            Beat::Return => writeln!(out, "> RETURN\n"),
+        };
+    }
+    out
+}
+
+/// The script as a standalone HTML page, with text in the given language.
+///
+/// Labels are sections that choices and jumps link to.
+pub fn html(scenario: &Scenario, lang: &str, translations: &Translations) -> String {
+    let mut out = String::new();
+    let title = escape_html(&scenario.title);
+    let _ = writeln!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>",
+        escape_html(lang),
+        title
+    );
+    let _ = writeln!(out, "<style>{}</style>\n</head>\n<body>", HTML_STYLE);
+    let _ = writeln!(out, "<h1>{}</h1>", title);
+    for beat in beats(scenario, lang, translations) {
+        let _ = match beat {
+            Beat::Scene(label) => writeln!(out, "<h2 id=\"{0}\">{0}</h2>", escape_html(&label)),
+            Beat::Dialogue { speaker, text } => writeln!(
+                out,
+                "<p class=\"line\"><span class=\"speaker\">{}</span>{}</p>",
+                escape_html(&speaker),
+                escape_html(&text)
+            ),
+            Beat::Narration(text) => {
+                writeln!(out, "<p class=\"narration\">{}</p>", escape_html(&text))
+            }
+            Beat::Choices(choices) => {
+                let _ = writeln!(out, "<ul class=\"choices\">");
+                for (text, target, condition) in choices {
+                    let condition = condition
+                        .map(|condition| {
+                            format!(
+                                " <span class=\"condition\">if {}</span>",
+                                escape_html(&condition)
+                            )
+                        })
+                        .unwrap_or_default();
+                    let _ = writeln!(
+                        out,
+                        "<li>{} &rarr; <a href=\"#{}\">{}</a>{}</li>",
+                        escape_html(&text),
+                        escape_html(&target),
+                        escape_html(&target),
+                        condition
+                    );
+                }
+                writeln!(out, "</ul>")
+            }
+            Beat::Branch { kind, target } => writeln!(
+                out,
+                "<p class=\"branch\">{} &rarr; <a href=\"#{}\">{}</a></p>",
+                escape_html(&kind),
+                escape_html(&target),
+                escape_html(&target)
+            ),
+            Beat::Return => writeln!(out, "<p class=\"branch\">return</p>"),
+        };
+    }
+    let _ = writeln!(out, "</body>\n</html>");
+    out
+}
+
+const HTML_STYLE: &str = "body{font-family:sans-serif;max-width:40em;margin:2em auto;line-height:1.5}\
+h2{border-bottom:1px solid #ccc;font-size:1.1em;text-transform:uppercase}\
+.speaker{display:block;font-weight:bold;text-transform:uppercase}\
+.narration{font-style:italic}\
+.choices,.branch{color:#555}\
+.condition{font-size:.9em;color:#888}";
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_row_quoting() {
+        let fields = ["a,b".to_string(), "say \"hi\"".into(), "tab\there".into()];
+        assert_eq!(
+            table_row(&fields, ','),
+            "\"a,b\",\"say \"\"hi\"\"\",tab\there\n"
+        );
+        assert_eq!(table_row(&fields, '\t'), "a,b\tsay \"hi\"\ttab here\n");
+    }
+
+    #[test]
+    fn test_generated_voice_path() {
+        assert_eq!(
+            generated_voice_path("", "alice", None, Some("day 1"), 12),
+            "alice/day_1_0012.ogg"
+        );
+        assert_eq!(
+            generated_voice_path("voice/a/", "alice", None, None, 3),
+            "voice/a/line_0003.ogg"
+        );
+        assert_eq!(
+            generated_voice_path("", "alice", Some("part 2/day.yaml"), Some("day"), 0),
+            "alice/part_2_day/day_0000.ogg"
+        );
+    }
+}
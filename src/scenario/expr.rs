@@ -0,0 +1,912 @@
+//! Expression language used by scenario conditions and assignments.
+//!
+//! Grammar (lowest to highest precedence):
+//!
+//! ```text
+//! expr       := or
+//! or         := and (("or" | "||") and)*
+//! and        := not (("and" | "&&") not)*
+//! not        := ("not" | "!") not | comparison
+//! comparison := sum (("==" | "!=" | "<" | "<=" | ">" | ">=") sum)?
+//! sum        := product (("+" | "-") product)*
+//! product    := unary (("*" | "/" | "%") unary)*
+//! unary      := "-" unary | primary
+//! primary    := number | string | "true" | "false" | identifier
+//!             | identifier "(" (expr ("," expr)*)? ")" | "(" expr ")"
+//! ```
+//!
+//! Identifiers refer to variables and may contain dots (`persistent.flag`).
+//! Strings may use single or double quotes,
+//! and `+` concatenates two strings. Arithmetic mixing integers and floats
+//! produces a float. Built-in functions are `min`, `max`,
+//! `clamp(value, low, high)`, `len(collection)` and `contains(collection, item)`
+//! (list element, map key or substring).
+
+use std::collections::HashMap;
+
+use crate::types::Value;
+
+/// Error produced while parsing or evaluating an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExprError {
+    /// Human-readable description of the problem.
+    pub message: String,
+    /// Byte offset in the source where the error was detected (parse errors only).
+    pub position: Option<usize>,
+}
+
+impl ExprError {
+    /// Create an error with an optional source position.
+    pub fn new(message: impl Into<String>, position: Option<usize>) -> Self {
+        Self {
+            message: message.into(),
+            position,
+        }
+    }
+}
+
+impl std::fmt::Display for ExprError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some(pos) => write!(f, "{} at column {}", self.message, pos + 1),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+/// Binary operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    /// Operator symbol as written in scripts.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::And => "and",
+            BinaryOp::Or => "or",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+        }
+    }
+
+    /// Whether this is a comparison operator (`==`, `<`, ...).
+    pub fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge
+        )
+    }
+}
+
+/// Parsed expression tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Literal value.
+    Literal(Value),
+    /// Variable reference.
+    Var(String),
+    /// Logical negation.
+    Not(Box<Expr>),
+    /// Arithmetic negation.
+    Neg(Box<Expr>),
+    /// Built-in function call.
+    Call { name: String, args: Vec<Expr> },
+    /// Binary operation.
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+}
+
+/// Source of variable values for expression evaluation.
+pub trait VariableLookup {
+    /// Look up a variable by name.
+    fn lookup(&self, name: &str) -> Option<&Value>;
+}
+
+impl VariableLookup for HashMap<String, Value> {
+    fn lookup(&self, name: &str) -> Option<&Value> {
+        self.get(name)
+    }
+}
+
+impl Expr {
+    /// Evaluate the expression against the given variables.
+    pub fn eval(&self, vars: &impl VariableLookup) -> Result<Value, ExprError> {
+        match self {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Var(name) => vars
+                .lookup(name)
+                .cloned()
+                .ok_or_else(|| ExprError::new(format!("undefined variable '{}'", name), None)),
+            Expr::Not(inner) => {
+                let value = inner.eval(vars)?;
+                Ok(Value::Bool(!expect_bool(&value, "not")?))
+            }
+            Expr::Neg(inner) => match inner.eval(vars)? {
+                Value::Int(i) => i
+                    .checked_neg()
+                    .map(Value::Int)
+                    .ok_or_else(|| ExprError::new("integer overflow", None)),
+                Value::Float(x) => Ok(Value::Float(-x)),
+                other => Err(ExprError::new(format!("cannot negate {:?}", other), None)),
+            },
+            Expr::Call { name, args } => {
+                let values = args
+                    .iter()
+                    .map(|arg| arg.eval(vars))
+                    .collect::<Result<Vec<_>, _>>()?;
+                call_function(name, &values)
+            }
+            Expr::Binary { op, lhs, rhs } => match op {
+                // Short-circuit logical operators.
+                BinaryOp::And => {
+                    if !expect_bool(&lhs.eval(vars)?, "and")? {
+                        return Ok(Value::Bool(false));
+                    }
+                    Ok(Value::Bool(expect_bool(&rhs.eval(vars)?, "and")?))
+                }
+                BinaryOp::Or => {
+                    if expect_bool(&lhs.eval(vars)?, "or")? {
+                        return Ok(Value::Bool(true));
+                    }
+                    Ok(Value::Bool(expect_bool(&rhs.eval(vars)?, "or")?))
+                }
+                _ => {
+                    let left = lhs.eval(vars)?;
+                    let right = rhs.eval(vars)?;
+                    apply_binary(*op, &left, &right)
+                }
+            },
+        }
+    }
+
+    /// Evaluate the expression and require a boolean result.
+    pub fn eval_bool(&self, vars: &impl VariableLookup) -> Result<bool, ExprError> {
+        let value = self.eval(vars)?;
+        expect_bool(&value, "condition")
+    }
+
+    /// Collect the names of all variables referenced by this expression.
+    pub fn variables(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_variables(&mut names);
+        names
+    }
+
+    fn collect_variables<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Expr::Literal(_) => {}
+            Expr::Var(name) => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+            Expr::Not(inner) | Expr::Neg(inner) => inner.collect_variables(names),
+            Expr::Call { args, .. } => {
+                for arg in args {
+                    arg.collect_variables(names);
+                }
+            }
+            Expr::Binary { lhs, rhs, .. } => {
+                lhs.collect_variables(names);
+                rhs.collect_variables(names);
+            }
+        }
+    }
+}
+
+fn expect_bool(value: &Value, context: &str) -> Result<bool, ExprError> {
+    value.as_bool().ok_or_else(|| {
+        ExprError::new(
+            format!("'{}' expects a boolean, got {:?}", context, value),
+            None,
+        )
+    })
+}
+
+/// Apply a non-logical binary operator to two values.
+pub fn apply_binary(op: BinaryOp, left: &Value, right: &Value) -> Result<Value, ExprError> {
+    if op.is_comparison() {
+        return compare(op, left, right).map(Value::Bool);
+    }
+
+    let result = match (op, left, right) {
+        (BinaryOp::Add, Value::String(a), Value::String(b)) => {
+            return Ok(Value::String(format!("{}{}", a, b)));
+        }
+        (BinaryOp::Add, Value::Int(a), Value::Int(b)) => a.checked_add(*b),
+        (BinaryOp::Sub, Value::Int(a), Value::Int(b)) => a.checked_sub(*b),
+        (BinaryOp::Mul, Value::Int(a), Value::Int(b)) => a.checked_mul(*b),
+        (BinaryOp::Div | BinaryOp::Rem, Value::Int(_), Value::Int(0)) => {
+            return Err(ExprError::new("division by zero", None));
+        }
+        (BinaryOp::Div, Value::Int(a), Value::Int(b)) => a.checked_div(*b),
+        (BinaryOp::Rem, Value::Int(a), Value::Int(b)) => a.checked_rem(*b),
+        (_, Value::Float(a), Value::Float(b)) => return apply_float(op, *a, *b),
+        (_, Value::Int(a), Value::Float(b)) => return apply_float(op, *a as f64, *b),
+        (_, Value::Float(a), Value::Int(b)) => return apply_float(op, *a, *b as f64),
+        _ => {
+            return Err(ExprError::new(
+                format!(
+                    "cannot apply '{}' to {:?} and {:?}",
+                    op.symbol(),
+                    left,
+                    right
+                ),
+                None,
+            ));
+        }
+    };
+
+    result
+        .map(Value::Int)
+        .ok_or_else(|| ExprError::new("integer overflow", None))
+}
+
+fn apply_float(op: BinaryOp, a: f64, b: f64) -> Result<Value, ExprError> {
+    let result = match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Sub => a - b,
+        BinaryOp::Mul => a * b,
+        BinaryOp::Div | BinaryOp::Rem if b == 0.0 => {
+            return Err(ExprError::new("division by zero", None));
+        }
+        BinaryOp::Div => a / b,
+        BinaryOp::Rem => a % b,
+        _ => {
+            return Err(ExprError::new(
+                format!("cannot apply '{}' to {} and {}", op.symbol(), a, b),
+                None,
+            ));
+        }
+    };
+    if result.is_finite() {
+        Ok(Value::Float(result))
+    } else {
+        Err(ExprError::new("float overflow", None))
+    }
+}
+
+/// Call a built-in function (`min`, `max`, `clamp`, `len`, `contains`).
+pub fn call_function(name: &str, args: &[Value]) -> Result<Value, ExprError> {
+    match name {
+        "min" | "max" => {
+            let (first, rest) = args.split_first().ok_or_else(|| {
+                ExprError::new(format!("'{}' expects at least one argument", name), None)
+            })?;
+            let mut best = first.clone();
+            for arg in rest {
+                let op = if name == "min" {
+                    BinaryOp::Lt
+                } else {
+                    BinaryOp::Gt
+                };
+                if compare(op, arg, &best)? {
+                    best = arg.clone();
+                }
+            }
+            Ok(best)
+        }
+        "clamp" => match args {
+            [value, low, high] => {
+                if compare(BinaryOp::Gt, low, high)? {
+                    return Err(ExprError::new(
+                        format!("clamp bounds are reversed ({:?} > {:?})", low, high),
+                        None,
+                    ));
+                }
+                if compare(BinaryOp::Lt, value, low)? {
+                    Ok(low.clone())
+                } else if compare(BinaryOp::Gt, value, high)? {
+                    Ok(high.clone())
+                } else {
+                    Ok(value.clone())
+                }
+            }
+            _ => Err(ExprError::new(
+                format!("'clamp' expects 3 arguments, got {}", args.len()),
+                None,
+            )),
+        },
+        "len" => match args {
+            [Value::List(items)] => Ok(Value::Int(items.len() as i64)),
+            [Value::Map(map)] => Ok(Value::Int(map.len() as i64)),
+            [Value::String(s)] => Ok(Value::Int(s.chars().count() as i64)),
+            [other] => Err(ExprError::new(
+                format!("'len' expects a list, map or string, got {:?}", other),
+                None,
+            )),
+            _ => Err(ExprError::new(
+                format!("'len' expects 1 argument, got {}", args.len()),
+                None,
+            )),
+        },
+        "contains" => match args {
+            [Value::List(items), item] => {
+                Ok(Value::Bool(items.iter().any(|x| values_equal(x, item))))
+            }
+            [Value::Map(map), Value::String(key)] => Ok(Value::Bool(map.contains_key(key))),
+            [Value::String(s), Value::String(part)] => Ok(Value::Bool(s.contains(part.as_str()))),
+            [collection, item] => Err(ExprError::new(
+                format!("cannot check whether {:?} contains {:?}", collection, item),
+                None,
+            )),
+            _ => Err(ExprError::new(
+                format!("'contains' expects 2 arguments, got {}", args.len()),
+                None,
+            )),
+        },
+        _ => Err(ExprError::new(format!("unknown function '{}'", name), None)),
+    }
+}
+
+/// Whether two values are equal as `==` compares them: integers and floats
+/// by numeric value.
+pub fn values_equal(left: &Value, right: &Value) -> bool {
+    compare(BinaryOp::Eq, left, right) == Ok(true)
+}
+
+fn compare(op: BinaryOp, left: &Value, right: &Value) -> Result<bool, ExprError> {
+    // Integers and floats compare by numeric value.
+    let numeric = match (left, right) {
+        (Value::Int(_), Value::Float(_)) | (Value::Float(_), Value::Int(_)) => {
+            left.as_float().zip(right.as_float())
+        }
+        _ => None,
+    };
+    match op {
+        BinaryOp::Eq => return Ok(numeric.map_or(left == right, |(a, b)| a == b)),
+        BinaryOp::Ne => return Ok(numeric.map_or(left != right, |(a, b)| a != b)),
+        _ => {}
+    }
+
+    let ordering = match (left, right) {
+        (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => left
+            .as_float()
+            .zip(right.as_float())
+            .and_then(|(a, b)| a.partial_cmp(&b)),
+        _ => None,
+    };
+    let Some(ordering) = ordering else {
+        return Err(ExprError::new(
+            format!("cannot compare {:?} {} {:?}", left, op.symbol(), right),
+            None,
+        ));
+    };
+
+    Ok(match op {
+        BinaryOp::Lt => ordering.is_lt(),
+        BinaryOp::Le => ordering.is_le(),
+        BinaryOp::Gt => ordering.is_gt(),
+        BinaryOp::Ge => ordering.is_ge(),
+        _ => unreachable!("equality handled above"),
+    })
+}
+
+/// Parse an expression from source text.
+pub fn parse_expr(source: &str) -> Result<Expr, ExprError> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: source.len(),
+    };
+    let expr = parser.parse_or()?;
+    if let Some((token, offset)) = parser.tokens.get(parser.pos) {
+        return Err(ExprError::new(
+            format!("unexpected {}", token.describe()),
+            Some(*offset),
+        ));
+    }
+    Ok(expr)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Ident(String),
+    Op(BinaryOp),
+    Not,
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Int(i) => format!("number {}", i),
+            Token::Float(x) => format!("number {}", x),
+            Token::Str(s) => format!("string \"{}\"", s),
+            Token::Ident(name) => format!("identifier '{}'", name),
+            Token::Op(op) => format!("operator '{}'", op.symbol()),
+            Token::Not => "'not'".to_string(),
+            Token::LParen => "'('".to_string(),
+            Token::RParen => "')'".to_string(),
+            Token::Comma => "','".to_string(),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, ch)) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        if ch.is_ascii_digit() {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !c.is_ascii_digit() {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            // A '.' followed by a digit continues a float literal.
+            let mut is_float = false;
+            if source[end..].starts_with('.')
+                && source[end + 1..].starts_with(|c: char| c.is_ascii_digit())
+            {
+                is_float = true;
+                chars.next();
+                while let Some(&(i, c)) = chars.peek() {
+                    if !c.is_ascii_digit() {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+            }
+            let token = if is_float {
+                Token::Float(
+                    source[start..end]
+                        .parse()
+                        .map_err(|_| ExprError::new("invalid float literal", Some(start)))?,
+                )
+            } else {
+                Token::Int(
+                    source[start..end]
+                        .parse()
+                        .map_err(|_| ExprError::new("integer literal out of range", Some(start)))?,
+                )
+            };
+            tokens.push((token, start));
+            continue;
+        }
+
+        if ch.is_alphabetic() || ch == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                // A '.' followed by a letter continues a dotted name
+                // (`persistent.flag`).
+                let dotted = c == '.'
+                    && source[i + 1..]
+                        .chars()
+                        .next()
+                        .is_some_and(|next| next.is_alphabetic() || next == '_');
+                if !(c.is_alphanumeric() || c == '_' || dotted) {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let token = match &source[start..end] {
+                "and" => Token::Op(BinaryOp::And),
+                "or" => Token::Op(BinaryOp::Or),
+                "not" => Token::Not,
+                word => Token::Ident(word.to_string()),
+            };
+            tokens.push((token, start));
+            continue;
+        }
+
+        if ch == '"' || ch == '\'' {
+            chars.next();
+            let mut text = String::new();
+            let mut closed = false;
+            while let Some((_, c)) = chars.next() {
+                match c {
+                    '\\' => match chars.next() {
+                        Some((_, 'n')) => text.push('\n'),
+                        Some((_, escaped)) => text.push(escaped),
+                        None => break,
+                    },
+                    c if c == ch => {
+                        closed = true;
+                        break;
+                    }
+                    c => text.push(c),
+                }
+            }
+            if !closed {
+                return Err(ExprError::new("unterminated string", Some(start)));
+            }
+            tokens.push((Token::Str(text), start));
+            continue;
+        }
+
+        chars.next();
+        let next = chars.peek().map(|&(_, c)| c);
+        let (token, two_chars) = match (ch, next) {
+            ('=', Some('=')) => (Token::Op(BinaryOp::Eq), true),
+            ('!', Some('=')) => (Token::Op(BinaryOp::Ne), true),
+            ('<', Some('=')) => (Token::Op(BinaryOp::Le), true),
+            ('>', Some('=')) => (Token::Op(BinaryOp::Ge), true),
+            ('&', Some('&')) => (Token::Op(BinaryOp::And), true),
+            ('|', Some('|')) => (Token::Op(BinaryOp::Or), true),
+            ('<', _) => (Token::Op(BinaryOp::Lt), false),
+            ('>', _) => (Token::Op(BinaryOp::Gt), false),
+            ('!', _) => (Token::Not, false),
+            ('(', _) => (Token::LParen, false),
+            (')', _) => (Token::RParen, false),
+            (',', _) => (Token::Comma, false),
+            ('+', _) => (Token::Op(BinaryOp::Add), false),
+            ('-', _) => (Token::Op(BinaryOp::Sub), false),
+            ('*', _) => (Token::Op(BinaryOp::Mul), false),
+            ('/', _) => (Token::Op(BinaryOp::Div), false),
+            ('%', _) => (Token::Op(BinaryOp::Rem), false),
+            ('=', _) => {
+                return Err(ExprError::new(
+                    "unexpected '=' (use '==' for comparison)",
+                    Some(start),
+                ));
+            }
+            _ => {
+                return Err(ExprError::new(
+                    format!("unexpected character '{}'", ch),
+                    Some(start),
+                ));
+            }
+        };
+        if two_chars {
+            chars.next();
+        }
+        tokens.push((token, start));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    /// Source length, used as the error position at end of input.
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|(_, offset)| *offset)
+            .unwrap_or(self.end)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(token, _)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.parse_and()?;
+        while self.peek() == Some(&Token::Op(BinaryOp::Or)) {
+            self.next();
+            let rhs = self.parse_and()?;
+            lhs = binary(BinaryOp::Or, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.parse_not()?;
+        while self.peek() == Some(&Token::Op(BinaryOp::And)) {
+            self.next();
+            let rhs = self.parse_not()?;
+            lhs = binary(BinaryOp::And, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, ExprError> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            let inner = self.parse_not()?;
+            return Ok(Expr::Not(Box::new(inner)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, ExprError> {
+        let lhs = self.parse_sum()?;
+        match self.peek() {
+            Some(Token::Op(op)) if op.is_comparison() => {
+                let op = *op;
+                self.next();
+                let rhs = self.parse_sum()?;
+                if let Some(Token::Op(next)) = self.peek()
+                    && next.is_comparison()
+                {
+                    return Err(ExprError::new(
+                        "comparisons cannot be chained (use 'and')",
+                        Some(self.offset()),
+                    ));
+                }
+                Ok(binary(op, lhs, rhs))
+            }
+            _ => Ok(lhs),
+        }
+    }
+
+    fn parse_sum(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.parse_product()?;
+        while let Some(Token::Op(op @ (BinaryOp::Add | BinaryOp::Sub))) = self.peek() {
+            let op = *op;
+            self.next();
+            let rhs = self.parse_product()?;
+            lhs = binary(op, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn parse_product(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.parse_unary()?;
+        while let Some(Token::Op(op @ (BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem))) =
+            self.peek()
+        {
+            let op = *op;
+            self.next();
+            let rhs = self.parse_unary()?;
+            lhs = binary(op, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ExprError> {
+        if self.peek() == Some(&Token::Op(BinaryOp::Sub)) {
+            self.next();
+            return match self.parse_unary()? {
+                // Fold negative literals so `-5` stays a plain value.
+                Expr::Literal(Value::Int(i)) => Ok(Expr::Literal(Value::Int(-i))),
+                Expr::Literal(Value::Float(x)) => Ok(Expr::Literal(Value::Float(-x))),
+                inner => Ok(Expr::Neg(Box::new(inner))),
+            };
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ExprError> {
+        let offset = self.offset();
+        match self.next() {
+            Some(Token::Int(i)) => Ok(Expr::Literal(Value::Int(i))),
+            Some(Token::Float(x)) => Ok(Expr::Literal(Value::Float(x))),
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::String(s))),
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::LParen) => {
+                self.next();
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RParen) {
+                    loop {
+                        args.push(self.parse_or()?);
+                        if self.peek() != Some(&Token::Comma) {
+                            break;
+                        }
+                        self.next();
+                    }
+                }
+                if self.peek() != Some(&Token::RParen) {
+                    return Err(ExprError::new("expected ')'", Some(self.offset())));
+                }
+                self.next();
+                Ok(Expr::Call { name, args })
+            }
+            Some(Token::Ident(name)) => Ok(match name.as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                _ => Expr::Var(name),
+            }),
+            Some(Token::LParen) => {
+                let inner = self.parse_or()?;
+                if self.peek() != Some(&Token::RParen) {
+                    return Err(ExprError::new("expected ')'", Some(self.offset())));
+                }
+                self.next();
+                Ok(inner)
+            }
+            Some(token) => Err(ExprError::new(
+                format!("unexpected {}", token.describe()),
+                Some(offset),
+            )),
+            None => Err(ExprError::new("unexpected end of expression", Some(offset))),
+        }
+    }
+}
+
+fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+    Expr::Binary {
+        op,
+        lhs: Box::new(lhs),
+        rhs: Box::new(rhs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> HashMap<String, Value> {
+        let mut vars = HashMap::new();
+        vars.insert("affection_alice".to_string(), Value::Int(7));
+        vars.insert("affection_bob".to_string(), Value::Int(3));
+        vars.insert("met_alice".to_string(), Value::Bool(true));
+        vars.insert("name".to_string(), Value::String("Alice".to_string()));
+        vars.insert("progress".to_string(), Value::Float(0.25));
+        vars.insert(
+            "visited".to_string(),
+            Value::List(vec![
+                Value::String("beach".to_string()),
+                Value::String("forest".to_string()),
+            ]),
+        );
+        vars
+    }
+
+    fn eval(source: &str) -> Result<Value, ExprError> {
+        parse_expr(source).unwrap().eval(&vars())
+    }
+
+    #[test]
+    fn test_comparison_between_variables() {
+        assert_eq!(
+            eval("affection_alice > affection_bob and met_alice"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(eval("affection_alice <= 6"), Ok(Value::Bool(false)));
+        assert_eq!(eval("affection_bob != 3"), Ok(Value::Bool(false)));
+        assert_eq!(eval("name == 'Alice'"), Ok(Value::Bool(true)));
+        assert_eq!(eval("affection_bob >= -1"), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_logical_operators_and_precedence() {
+        assert_eq!(eval("not met_alice or true"), Ok(Value::Bool(true)));
+        assert_eq!(eval("!(met_alice && false)"), Ok(Value::Bool(true)));
+        assert_eq!(eval("false and true or met_alice"), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_short_circuit_skips_undefined() {
+        assert_eq!(eval("false and missing"), Ok(Value::Bool(false)));
+        assert_eq!(eval("true or missing"), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_eval_errors() {
+        assert!(eval("missing == 1").is_err());
+        assert!(eval("name < 3").is_err());
+        assert!(eval("affection_alice and met_alice").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors_report_position() {
+        let err = parse_expr("a = 1").unwrap_err();
+        assert_eq!(err.position, Some(2));
+
+        let err = parse_expr("(a > 1").unwrap_err();
+        assert_eq!(err.position, Some(6));
+
+        assert!(parse_expr("").is_err());
+        assert!(parse_expr("a < b < c").is_err());
+        assert!(parse_expr("'open").is_err());
+        assert!(parse_expr("a b").is_err());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(eval("affection_alice + 2 * 3"), Ok(Value::Int(13)));
+        assert_eq!(eval("(affection_alice - 1) / 4"), Ok(Value::Int(1)));
+        assert_eq!(eval("affection_alice % 4"), Ok(Value::Int(3)));
+        assert_eq!(eval("-affection_bob"), Ok(Value::Int(-3)));
+        assert_eq!(
+            eval("affection_alice - affection_bob > 3"),
+            Ok(Value::Bool(true))
+        );
+        assert!(eval("affection_alice / 0").is_err());
+    }
+
+    #[test]
+    fn test_string_concat() {
+        assert_eq!(
+            eval("name + ' Smith'"),
+            Ok(Value::String("Alice Smith".to_string()))
+        );
+        assert!(eval("name + 1").is_err());
+    }
+
+    #[test]
+    fn test_functions() {
+        assert_eq!(eval("min(affection_alice, 5)"), Ok(Value::Int(5)));
+        assert_eq!(eval("max(affection_bob, 5, 1)"), Ok(Value::Int(5)));
+        assert_eq!(
+            eval("clamp(affection_alice + 10, 0, 10)"),
+            Ok(Value::Int(10))
+        );
+        assert!(eval("clamp(1, 2)").is_err());
+        assert!(eval("unknown(1)").is_err());
+    }
+
+    #[test]
+    fn test_float_arithmetic() {
+        assert_eq!(eval("progress * 2"), Ok(Value::Float(0.5)));
+        assert_eq!(eval("1.5 + 1"), Ok(Value::Float(2.5)));
+        assert_eq!(eval("-progress"), Ok(Value::Float(-0.25)));
+        assert_eq!(eval("progress < 0.5"), Ok(Value::Bool(true)));
+        assert_eq!(eval("2.0 == 2"), Ok(Value::Bool(true)));
+        assert!(eval("progress / 0").is_err());
+    }
+
+    #[test]
+    fn test_collection_functions() {
+        assert_eq!(eval("contains(visited, 'beach')"), Ok(Value::Bool(true)));
+        assert_eq!(eval("contains(visited, 'cave')"), Ok(Value::Bool(false)));
+        assert_eq!(eval("len(visited)"), Ok(Value::Int(2)));
+        assert_eq!(eval("contains(name, 'lic')"), Ok(Value::Bool(true)));
+        assert!(eval("contains(affection_bob, 3)").is_err());
+    }
+
+    #[test]
+    fn test_variables() {
+        let expr = parse_expr("a > b and not (c or a == 1)").unwrap();
+        assert_eq!(expr.variables(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_dotted_identifiers() {
+        let expr = parse_expr("persistent.runs >= 2 and persistent.seen_end").unwrap();
+        assert_eq!(
+            expr.variables(),
+            vec!["persistent.runs", "persistent.seen_end"]
+        );
+        // A dot before a digit is still a float literal
+        assert!(parse_expr("x > 1.5").is_ok());
+    }
+}
@@ -0,0 +1,651 @@
+//! A practical subset of Ink.
+//!
+//! Supported are knots (`=== name ===`) and stitches (`= name`), which
+//! become labels (`knot` and `knot.stitch`); text lines; diverts
+//! (`-> target`, with `END` and `DONE` ending the story); `VAR`/`CONST`
+//! declarations and `~` assignments; `*` and `+` choices with conditions,
+//! `[bracketed]` text, bodies and gathers (`-`); and `{variable}` and
+//! `{condition: a|b}` in text. Functions, tunnels, threads, lists, tags,
+//! nested choices and other inline logic are reported as warnings.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use super::{END_LABEL, Imported, ScriptBuilder};
+
+/// Convert an Ink story.
+pub fn import_ink(text: &str) -> Result<Imported> {
+    let lines = strip_comments(text);
+    let mut converter = Converter {
+        locals: local_labels(&lines),
+        ..Converter::default()
+    };
+
+    let mut title = String::new();
+    // Global variables are set before the story starts
+    for (line_num, line) in &lines {
+        if let Some(declaration) = line
+            .strip_prefix("VAR ")
+            .or_else(|| line.strip_prefix("CONST "))
+        {
+            match declaration.split_once('=') {
+                Some((name, value)) => {
+                    let value = convert_expr(value.trim());
+                    converter.builder.set(name.trim(), &value, None);
+                }
+                None => converter
+                    .builder
+                    .warn(*line_num, format!("Could not convert '{}'", line)),
+            }
+        } else if let Some(value) = line
+            .strip_prefix('#')
+            .and_then(|tag| tag.trim().strip_prefix("title:"))
+        {
+            title = value.trim().to_string();
+        }
+    }
+
+    for (line_num, line) in &lines {
+        converter.line(*line_num, line);
+    }
+    converter.end_choices(None);
+    converter.builder.finish(&title)
+}
+
+/// Remove `//` and `/* */` comments, returning the remaining non-empty lines
+/// (trimmed) with their line numbers.
+fn strip_comments(text: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut in_block = false;
+    for (line_num, line) in text.lines().enumerate() {
+        let mut kept = String::new();
+        let mut rest = line;
+        loop {
+            if in_block {
+                match rest.find("*/") {
+                    Some(end) => {
+                        rest = &rest[end + 2..];
+                        in_block = false;
+                    }
+                    None => break,
+                }
+            }
+            let line_comment = rest.find("//");
+            let block_comment = rest.find("/*");
+            match (line_comment, block_comment) {
+                (Some(line), Some(block)) if block < line => {
+                    kept.push_str(&rest[..block]);
+                    rest = &rest[block + 2..];
+                    in_block = true;
+                }
+                (None, Some(block)) => {
+                    kept.push_str(&rest[..block]);
+                    rest = &rest[block + 2..];
+                    in_block = true;
+                }
+                (Some(line), _) => {
+                    kept.push_str(&rest[..line]);
+                    break;
+                }
+                (None, None) => {
+                    kept.push_str(rest);
+                    break;
+                }
+            }
+        }
+        let kept = kept.trim();
+        if !kept.is_empty() {
+            lines.push((line_num, kept.to_string()));
+        }
+    }
+    lines
+}
+
+/// Map each knot's stitches and named gathers to their full labels, so
+/// `-> name` inside a knot can find them.
+fn local_labels(lines: &[(usize, String)]) -> HashMap<String, HashMap<String, String>> {
+    let mut locals: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut knot = String::new();
+    let mut stitch: Option<String> = None;
+    for (_, line) in lines {
+        if let Some(name) = knot_name(line) {
+            // Without parameters, as in `Converter::line`
+            knot = name
+                .split('(')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            stitch = None;
+        } else if let Some(name) = stitch_name(line) {
+            locals
+                .entry(knot.clone())
+                .or_default()
+                .insert(name.to_string(), scoped(&knot, None, name));
+            stitch = Some(name.to_string());
+        } else if let Some((_, rest)) = markers(line, '-')
+            && let Some((name, _)) = label_prefix(rest)
+        {
+            locals
+                .entry(knot.clone())
+                .or_default()
+                .insert(name.to_string(), scoped(&knot, stitch.as_deref(), name));
+        }
+    }
+    locals
+}
+
+/// Name of a knot from its `=== name ===` header.
+fn knot_name(line: &str) -> Option<&str> {
+    if !line.starts_with("==") {
+        return None;
+    }
+    Some(line.trim_matches('=').trim())
+}
+
+/// Name of a stitch from its `= name` header.
+fn stitch_name(line: &str) -> Option<&str> {
+    let name = line.strip_prefix('=')?;
+    (!name.starts_with('=')).then(|| name.trim())
+}
+
+/// Full label of `name` inside a knot and stitch.
+fn scoped(knot: &str, stitch: Option<&str>, name: &str) -> String {
+    [knot, stitch.unwrap_or_default(), name]
+        .iter()
+        .filter(|part| !part.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Count leading choice (`*`, `+`) or gather (`-`) markers, returning the
+/// depth and the rest of the line.
+fn markers(line: &str, marker: char) -> Option<(usize, &str)> {
+    if marker == '-' && line.starts_with("->") {
+        return None;
+    }
+    let mut depth = 0;
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        if c == marker || (marker != '-' && (c == '*' || c == '+')) {
+            depth += 1;
+        } else if !c.is_whitespace() || rest.starts_with("->") {
+            break;
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    (depth > 0).then_some((depth, rest))
+}
+
+/// Split a `(label)` off the start of a choice or gather.
+fn label_prefix(text: &str) -> Option<(&str, &str)> {
+    let inner = text.strip_prefix('(')?;
+    let (name, rest) = inner.split_once(')')?;
+    Some((name.trim(), rest.trim_start()))
+}
+
+/// A choice of the choice block being read.
+struct InkChoice {
+    line: usize,
+    /// Text shown in the choice list.
+    label: String,
+    /// Text printed once the choice is picked.
+    output: String,
+    condition: Option<String>,
+    /// `*` choices can only be picked once; `+` choices are sticky.
+    once: bool,
+    target: Option<String>,
+    body: Vec<(usize, String)>,
+}
+
+#[derive(Default)]
+struct Converter {
+    builder: ScriptBuilder,
+    /// Stitches and named gathers of each knot (see [`local_labels`]).
+    locals: HashMap<String, HashMap<String, String>>,
+    knot: String,
+    stitch: Option<String>,
+    /// Choices read since the last gather.
+    choices: Vec<InkChoice>,
+    /// Counter for generated labels.
+    generated: usize,
+    /// Inside a function, which is skipped.
+    in_function: bool,
+    /// Inside a nested choice, which is skipped.
+    in_nested: bool,
+    /// Open braces of a multi-line `{...}` block being skipped.
+    open_braces: usize,
+}
+
+impl Converter {
+    fn line(&mut self, line_num: usize, line: &str) {
+        if self.open_braces > 0 {
+            self.open_braces = brace_depth(self.open_braces, line);
+            return;
+        }
+
+        if let Some(name) = knot_name(line) {
+            self.end_choices(None);
+            self.in_function = false;
+            let name = if let Some(function) = name.strip_prefix("function ") {
+                self.builder.section = Some(function.to_string());
+                self.builder.warn(
+                    line_num,
+                    format!("Functions are not supported; '{}' was skipped", function),
+                );
+                self.in_function = true;
+                return;
+            } else if let Some((name, _)) = name.split_once('(') {
+                self.builder.warn(
+                    line_num,
+                    format!("Parameters of knot '{}' are not supported", name.trim()),
+                );
+                name.trim()
+            } else {
+                name
+            };
+            self.knot = name.to_string();
+            self.stitch = None;
+            self.builder.section = Some(name.to_string());
+            self.builder.label(name);
+            return;
+        }
+        if self.in_function {
+            return;
+        }
+
+        if let Some(name) = stitch_name(line) {
+            self.end_choices(None);
+            let label = scoped(&self.knot, None, name);
+            self.stitch = Some(name.to_string());
+            self.builder.section = Some(label.clone());
+            self.builder.label(label);
+            return;
+        }
+
+        for keyword in ["VAR ", "CONST "] {
+            if line.starts_with(keyword) {
+                return;
+            }
+        }
+        for keyword in ["LIST ", "INCLUDE ", "EXTERNAL "] {
+            if line.starts_with(keyword) {
+                self.builder
+                    .warn(line_num, format!("{} is not supported", keyword.trim_end()));
+                return;
+            }
+        }
+
+        if let Some((depth, rest)) = markers(line, '*') {
+            if depth > 1 {
+                self.builder.warn(
+                    line_num,
+                    "Nested choices are not supported and were skipped",
+                );
+                self.in_nested = true;
+                return;
+            }
+            self.in_nested = false;
+            let once = line.starts_with('*');
+            if let Some(choice) = self.parse_choice(line_num, rest, once) {
+                self.choices.push(choice);
+            }
+            return;
+        }
+
+        if let Some((depth, rest)) = markers(line, '-') {
+            if depth > 1 {
+                if !self.in_nested {
+                    self.builder.warn(
+                        line_num,
+                        "Nested gathers are not supported and were skipped",
+                    );
+                }
+                return;
+            }
+            self.in_nested = false;
+            let (label, rest) = match label_prefix(rest) {
+                Some((name, rest)) => (self.scoped(name), rest),
+                None => (self.generated_label("gather"), rest),
+            };
+            self.end_choices(Some(label));
+            if !rest.is_empty() {
+                self.content(line_num, rest);
+            }
+            return;
+        }
+
+        if self.in_nested {
+            return;
+        }
+        match self.choices.last_mut() {
+            Some(choice) => choice.body.push((line_num, line.to_string())),
+            None => self.content(line_num, line),
+        }
+    }
+
+    /// Parse a choice after its `*` or `+` markers.
+    fn parse_choice(&mut self, line_num: usize, text: &str, once: bool) -> Option<InkChoice> {
+        let mut text = label_prefix(text).map_or(text, |(_, rest)| rest);
+
+        let mut conditions = Vec::new();
+        while text.starts_with('{')
+            && let Some(end) = matching_brace(text)
+        {
+            conditions.push(convert_expr(text[1..end - 1].trim()));
+            text = text[end..].trim_start();
+        }
+
+        let text = self.strip_tags(line_num, text);
+        let (text, target) = self.split_divert(line_num, &text);
+        let (label, output) = match text.find('[').zip(text.find(']')) {
+            Some((open, close)) if open < close => (
+                format!("{}{}", &text[..open], &text[open + 1..close]),
+                format!("{}{}", &text[..open], &text[close + 1..]),
+            ),
+            _ => (text.clone(), text.clone()),
+        };
+        let label = collapse_spaces(&self.inline(line_num, &label));
+        if label.is_empty() {
+            self.builder.warn(
+                line_num,
+                "Fallback choices are not supported and were skipped",
+            );
+            return None;
+        }
+
+        Some(InkChoice {
+            line: line_num,
+            label,
+            output: collapse_spaces(&output),
+            condition: (!conditions.is_empty()).then(|| conditions.join(" and ")),
+            once,
+            target,
+            body: Vec::new(),
+        })
+    }
+
+    /// Close the current choice block, continuing at the gather `label`.
+    fn end_choices(&mut self, gather: Option<String>) {
+        let choices = std::mem::take(&mut self.choices);
+        if choices.is_empty() {
+            if let Some(gather) = gather {
+                self.builder.label(gather);
+            }
+            return;
+        }
+
+        // Choices that print text or have a body go to a label of their own
+        let mut values = Vec::new();
+        let mut bodies = Vec::new();
+        for choice in choices {
+            let target = match &choice.target {
+                Some(target) if choice.output.is_empty() && choice.body.is_empty() => {
+                    target.clone()
+                }
+                _ => self.generated_label("choice"),
+            };
+            values.push(super::choice(
+                &choice.label,
+                &target,
+                choice.condition.as_deref(),
+                choice.once,
+            ));
+            if choice.target.as_ref() != Some(&target) {
+                bodies.push((target, choice));
+            }
+        }
+        self.builder.choices(values);
+
+        for (label, choice) in bodies {
+            self.builder.label(label);
+            if !choice.output.is_empty() {
+                self.content(choice.line, &choice.output);
+            }
+            for (line_num, line) in &choice.body {
+                if self.open_braces > 0 {
+                    self.open_braces = brace_depth(self.open_braces, line);
+                } else {
+                    self.content(*line_num, line);
+                }
+            }
+            match &choice.target {
+                Some(target) => self.builder.jump(target),
+                None if !self.builder.ends_with_jump() => {
+                    self.builder.jump(gather.as_deref().unwrap_or(END_LABEL));
+                }
+                None => {}
+            }
+        }
+        if let Some(gather) = gather {
+            self.builder.label(gather);
+        }
+    }
+
+    /// Emit a line of content: assignments, text and diverts.
+    fn content(&mut self, line_num: usize, line: &str) {
+        if line.starts_with("<-") {
+            self.builder
+                .warn(line_num, "Threads are not supported and were skipped");
+            return;
+        }
+        if let Some(logic) = line.strip_prefix('~') {
+            self.logic(line_num, logic.trim());
+            return;
+        }
+        if line.starts_with('{') && matching_brace(line).is_none() {
+            self.builder.warn(
+                line_num,
+                "Multi-line conditional blocks are not supported and were skipped",
+            );
+            self.open_braces = brace_depth(0, line);
+            return;
+        }
+
+        let line = self.strip_tags(line_num, line);
+        let (text, target) = self.split_divert(line_num, &line);
+        let text = collapse_spaces(&self.inline(line_num, &text.replace("<>", "")));
+        if !text.is_empty() {
+            self.builder.text(&text);
+        }
+        if let Some(target) = target {
+            self.builder.jump(&target);
+        }
+    }
+
+    /// Convert a `~` line: `x = e`, `x += e`, `x -= e`, `x++` or `x--`.
+    fn logic(&mut self, line_num: usize, logic: &str) {
+        let logic = logic.strip_prefix("temp ").unwrap_or(logic).trim();
+        let assignment = if let Some(name) = logic.strip_suffix("++") {
+            Some((name, "1".to_string(), Some("add")))
+        } else if let Some(name) = logic.strip_suffix("--") {
+            Some((name, "1".to_string(), Some("sub")))
+        } else if let Some((name, expr)) = logic.split_once("+=") {
+            Some((name, convert_expr(expr.trim()), Some("add")))
+        } else if let Some((name, expr)) = logic.split_once("-=") {
+            Some((name, convert_expr(expr.trim()), Some("sub")))
+        } else if let Some((name, expr)) = logic.split_once('=')
+            && !expr.starts_with('=')
+        {
+            Some((name, convert_expr(expr.trim()), None))
+        } else {
+            None
+        };
+
+        match assignment {
+            Some((name, expr, op)) if is_identifier(name.trim()) && !expr.is_empty() => {
+                self.builder.set(name.trim(), &expr, op);
+            }
+            _ => self
+                .builder
+                .warn(line_num, format!("Could not convert '~ {}'", logic)),
+        }
+    }
+
+    /// Remove `#` tags, reporting them.
+    fn strip_tags(&mut self, line_num: usize, line: &str) -> String {
+        match line
+            .find('#')
+            .filter(|&index| !line[..index].ends_with('\\'))
+        {
+            // Read before the story starts
+            Some(0) if line[1..].trim().starts_with("title:") => String::new(),
+            Some(index) => {
+                self.builder.warn(
+                    line_num,
+                    format!(
+                        "Tags are not supported; '{}' was dropped",
+                        line[index..].trim()
+                    ),
+                );
+                line[..index].trim_end().to_string()
+            }
+            None => line.to_string(),
+        }
+    }
+
+    /// Split a trailing `-> target` off a line, resolving the target.
+    fn split_divert(&mut self, line_num: usize, line: &str) -> (String, Option<String>) {
+        let Some(index) = line.find("->") else {
+            return (line.to_string(), None);
+        };
+        let (text, divert) = (&line[..index], line[index + 2..].trim());
+        if divert.starts_with('>') || divert.contains("->") {
+            self.builder
+                .warn(line_num, "Tunnels are not supported and were skipped");
+            return (text.to_string(), None);
+        }
+        let target = match divert.split_once('(') {
+            Some((target, _)) => {
+                self.builder.warn(
+                    line_num,
+                    format!("Divert arguments are not supported in '-> {}'", divert),
+                );
+                target.trim()
+            }
+            None => divert,
+        };
+        (text.to_string(), Some(self.resolve(target)))
+    }
+
+    /// Convert `{variable}` and `{condition: a|b}` in text to ivy tags,
+    /// dropping other inline logic.
+    fn inline(&mut self, line_num: usize, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(open) = rest.find('{') {
+            result.push_str(&rest[..open]);
+            let Some(len) = matching_brace(&rest[open..]) else {
+                result.push_str(&rest[open..]);
+                return result;
+            };
+            let inner = &rest[open + 1..open + len - 1];
+            rest = &rest[open + len..];
+
+            if is_identifier(inner.trim()) {
+                result.push_str(&format!("{{var:{}}}", inner.trim()));
+            } else if let Some((condition, branches)) = inner.split_once(':')
+                && !condition.starts_with(['&', '~', '!'])
+            {
+                let (then, otherwise) = branches.split_once('|').unwrap_or((branches, ""));
+                let (then, otherwise) = (then.trim(), otherwise.trim());
+                result.push_str(&format!(
+                    "{{if:{}}}{}",
+                    convert_expr(condition.trim()),
+                    then
+                ));
+                if !otherwise.is_empty() {
+                    result.push_str(&format!("{{else}}{}", otherwise));
+                }
+                result.push_str("{/if}");
+            } else {
+                self.builder.warn(
+                    line_num,
+                    format!(
+                        "Inline logic '{{{}}}' is not supported and was dropped",
+                        inner
+                    ),
+                );
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// Resolve a divert target to a label.
+    fn resolve(&self, target: &str) -> String {
+        match target {
+            "END" | "DONE" => END_LABEL.to_string(),
+            _ => self
+                .locals
+                .get(&self.knot)
+                .and_then(|locals| locals.get(target))
+                .cloned()
+                .unwrap_or_else(|| target.to_string()),
+        }
+    }
+
+    /// Full label of a gather or choice named in the current knot and stitch.
+    fn scoped(&self, name: &str) -> String {
+        scoped(&self.knot, self.stitch.as_deref(), name)
+    }
+
+    /// A new label for a choice body or gather.
+    fn generated_label(&mut self, kind: &str) -> String {
+        self.generated += 1;
+        let name = format!("{}_{}", kind, self.generated);
+        self.scoped(&name)
+    }
+}
+
+/// Length of the `{...}` group at the start of `text`.
+fn matching_brace(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Brace depth after a line, starting from `depth`.
+fn brace_depth(depth: usize, line: &str) -> usize {
+    line.chars().fold(depth, |depth, c| match c {
+        '{' => depth + 1,
+        '}' => depth.saturating_sub(1),
+        _ => depth,
+    })
+}
+
+fn is_identifier(text: &str) -> bool {
+    !text.is_empty()
+        && !text.starts_with(|c: char| c.is_ascii_digit())
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn collapse_spaces(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Convert an Ink expression to an ivy expression.
+fn convert_expr(expr: &str) -> String {
+    expr.split_whitespace()
+        .map(|word| match word {
+            "mod" => "%",
+            word => word,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
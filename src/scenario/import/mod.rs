@@ -0,0 +1,292 @@
+//! Importers converting stories written for other tools into a [`Scenario`].
+//!
+//! Supported sources are Twine stories in Twee 3 notation (`.twee`, `.tw`)
+//! and a practical subset of Ink (`.ink`). Content with no ivy equivalent is
+//! left out and reported as a warning, so nothing is dropped silently.
+
+pub mod ink;
+pub mod twee;
+
+use std::fmt::Display;
+use std::path::Path;
+
+use anyhow::Result;
+use serde_yaml::{Mapping, Value};
+
+use crate::scenario::types::{Command, Scenario};
+use crate::scenario::validator::ValidationIssue;
+
+/// Label of the command ending the story, the target of Ink's `-> END` and
+/// of Twine passages without links.
+pub const END_LABEL: &str = "END";
+
+/// Story formats that can be imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Twee,
+    Ink,
+}
+
+impl ImportFormat {
+    /// Guess the format from a file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "twee" | "tw" => Some(Self::Twee),
+            "ink" => Some(Self::Ink),
+            _ => None,
+        }
+    }
+
+    /// Parse a format name (`twee` or `ink`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "twee" | "twine" => Some(Self::Twee),
+            "ink" => Some(Self::Ink),
+            _ => None,
+        }
+    }
+}
+
+/// A converted story with warnings about what couldn't be converted.
+pub struct Imported {
+    pub scenario: Scenario,
+    pub issues: Vec<ValidationIssue>,
+}
+
+/// Convert a story in the given format.
+pub fn import_scenario(format: ImportFormat, text: &str) -> Result<Imported> {
+    match format {
+        ImportFormat::Twee => twee::import_twee(text),
+        ImportFormat::Ink => ink::import_ink(text),
+    }
+}
+
+/// Write a scenario as YAML, leaving out fields that are at their defaults.
+pub fn scenario_to_yaml(scenario: &Scenario) -> Result<String> {
+    let defaults = serde_yaml::to_value(serde_yaml::from_value::<Command>(Value::Mapping(
+        Mapping::new(),
+    ))?)?;
+    let mut value = serde_yaml::to_value(scenario)?;
+    if let Value::Mapping(map) = &mut value {
+        map.retain(|key, value| key.as_str() == Some("title") || !is_empty(value));
+        if let Some(Value::Sequence(script)) = map.get_mut("script") {
+            for command in script.iter_mut() {
+                if let Value::Mapping(command) = command {
+                    command.retain(|key, value| defaults.get(key) != Some(value));
+                }
+                prune(command);
+            }
+        }
+    }
+    Ok(serde_yaml::to_string(&value)?)
+}
+
+/// Whether a value would be left out of hand-written YAML.
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Sequence(items) => items.is_empty(),
+        Value::Mapping(map) => map.is_empty(),
+        _ => false,
+    }
+}
+
+/// Remove nulls, `default: false` and `op: assign` nested inside a command.
+fn prune(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            let is_default = |key: &Value, value: &Value| match key.as_str() {
+                Some("default") => value == &false,
+                Some("op") => value == "assign",
+                _ => value.is_null(),
+            };
+            map.retain(|key, value| !is_default(key, value));
+            map.values_mut().for_each(prune);
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(prune),
+        _ => {}
+    }
+}
+
+/// Accumulates converted commands, attaching labels and jumps to them.
+#[derive(Default)]
+struct ScriptBuilder {
+    commands: Vec<Mapping>,
+    /// Label waiting for the next command.
+    label: Option<String>,
+    /// Passage or knot being converted, named in warnings.
+    section: Option<String>,
+    issues: Vec<ValidationIssue>,
+    /// Whether anything jumps to [`END_LABEL`].
+    ends: bool,
+}
+
+impl ScriptBuilder {
+    /// Report content on a (0-indexed) source line that couldn't be converted.
+    fn warn(&mut self, line: usize, message: impl Display) {
+        let mut issue = ValidationIssue::warning(format!("line {}: {}", line + 1, message), None);
+        if let Some(section) = &self.section {
+            issue = issue.with_label(section.clone());
+        }
+        self.issues.push(issue);
+    }
+
+    /// Label the next command.
+    fn label(&mut self, name: impl Into<String>) {
+        if self.label.is_some() {
+            self.push(Mapping::new());
+        }
+        self.label = Some(name.into());
+    }
+
+    fn push(&mut self, fields: Mapping) {
+        let mut command = Mapping::new();
+        if let Some(label) = self.label.take() {
+            command.insert("label".into(), label.into());
+        }
+        command.extend(fields);
+        self.commands.push(command);
+    }
+
+    /// Add a line of text.
+    fn text(&mut self, text: &str) {
+        self.push(field("text", text.into()));
+    }
+
+    /// Add a variable assignment.
+    fn set(&mut self, name: &str, expr: &str, op: Option<&str>) {
+        let mut set = Mapping::new();
+        set.insert("name".into(), name.into());
+        set.insert("expr".into(), expr.into());
+        if let Some(op) = op {
+            set.insert("op".into(), op.into());
+        }
+        self.push(field("set", Value::Mapping(set)));
+    }
+
+    /// Jump to a label after the last command.
+    fn jump(&mut self, target: &str) {
+        self.ends |= target == END_LABEL;
+        match self.open_command() {
+            Some(command) => {
+                command.insert("jump".into(), target.into());
+            }
+            None => self.push(field("jump", target.into())),
+        }
+    }
+
+    /// Offer choices after the last command.
+    fn choices(&mut self, choices: Vec<Value>) {
+        let choices = Value::Sequence(choices);
+        match self.open_command() {
+            Some(command) if command.contains_key("text") => {
+                command.insert("choices".into(), choices);
+            }
+            _ => {
+                let mut fields = field("text", "".into());
+                fields.insert("choices".into(), choices);
+                self.push(fields);
+            }
+        }
+    }
+
+    /// Whether the last command jumps elsewhere, so nothing falls through it.
+    fn ends_with_jump(&self) -> bool {
+        self.label.is_none()
+            && self
+                .commands
+                .last()
+                .is_some_and(|command| command.contains_key("jump"))
+    }
+
+    /// The last command, if nothing has been labelled since and it doesn't
+    /// already move elsewhere.
+    fn open_command(&mut self) -> Option<&mut Mapping> {
+        if self.label.is_some() {
+            return None;
+        }
+        self.commands.last_mut().filter(|command| {
+            ["jump", "call", "return", "choices", "if"]
+                .iter()
+                .all(|key| !command.contains_key(*key))
+        })
+    }
+
+    /// Build the scenario.
+    fn finish(mut self, title: &str) -> Result<Imported> {
+        if self.ends {
+            self.label(END_LABEL);
+        }
+        if self.label.is_some() {
+            self.push(Mapping::new());
+        }
+
+        let mut scenario = Mapping::new();
+        scenario.insert("title".into(), title.into());
+        scenario.insert(
+            "script".into(),
+            Value::Sequence(self.commands.into_iter().map(Value::Mapping).collect()),
+        );
+        Ok(Imported {
+            scenario: serde_yaml::from_value(Value::Mapping(scenario))?,
+            issues: self.issues,
+        })
+    }
+}
+
+/// A mapping with a single field.
+fn field(key: &str, value: Value) -> Mapping {
+    let mut mapping = Mapping::new();
+    mapping.insert(key.into(), value);
+    mapping
+}
+
+/// A choice jumping to `target`, shown while `condition` holds.
+fn choice(text: &str, target: &str, condition: Option<&str>, once: bool) -> Value {
+    let mut choice = Mapping::new();
+    choice.insert("label".into(), text.into());
+    choice.insert("jump".into(), target.into());
+    if let Some(condition) = condition {
+        choice.insert("if".into(), condition.into());
+    }
+    if once {
+        choice.insert("once".into(), true.into());
+    }
+    Value::Mapping(choice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ImportFormat::from_path(Path::new("story.twee")),
+            Some(ImportFormat::Twee)
+        );
+        assert_eq!(
+            ImportFormat::from_path(Path::new("story.ink")),
+            Some(ImportFormat::Ink)
+        );
+        assert_eq!(ImportFormat::from_path(Path::new("story.yaml")), None);
+    }
+
+    #[test]
+    fn test_yaml_leaves_out_defaults() {
+        let mut builder = ScriptBuilder::default();
+        builder.label("start");
+        builder.text("Hello");
+        builder.choices(vec![choice("Go", "start", None, false)]);
+        let imported = builder.finish("Test").unwrap();
+
+        let yaml = scenario_to_yaml(&imported.scenario).unwrap();
+        assert_eq!(
+            yaml,
+            format!(
+                "format_version: {}\ntitle: Test\nscript:\n- label: start\n  text: Hello\n  choices:\n  - label: Go\n    jump: start\n",
+                crate::scenario::FORMAT_VERSION
+            )
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! Twine stories in Twee 3 notation.
+//!
+//! Every passage becomes a label and each of its lines a text command; its
+//! links become choices on the last line, and passages without links end
+//! the story. The `set` and `goto` macros of SugarCube (`<<set $gold to 5>>`)
+//! and Harlowe (`(set: $gold to 5)`) are converted, and `$gold` in text is
+//! shown with `{var:gold}`. Other macros, script and stylesheet passages are
+//! reported as warnings.
+
+use anyhow::Result;
+
+use super::{END_LABEL, Imported, ScriptBuilder, choice};
+
+/// Passage run once before the story starts (SugarCube).
+const INIT_PASSAGE: &str = "StoryInit";
+
+/// A passage header and its body lines.
+struct Passage<'a> {
+    name: String,
+    tags: Vec<&'a str>,
+    /// Line of the `::` header.
+    line: usize,
+    body: Vec<(usize, &'a str)>,
+}
+
+impl Passage<'_> {
+    fn text(&self) -> String {
+        let lines: Vec<&str> = self.body.iter().map(|(_, line)| *line).collect();
+        lines.join("\n").trim().to_string()
+    }
+}
+
+/// Convert a Twee 3 story.
+pub fn import_twee(text: &str) -> Result<Imported> {
+    let passages = parse_passages(text);
+    let mut builder = ScriptBuilder::default();
+
+    let mut title = String::new();
+    let mut start = None;
+    let mut story = Vec::new();
+    for passage in &passages {
+        match passage.name.as_str() {
+            "StoryTitle" => title = passage.text(),
+            "StoryData" => match serde_json::from_str::<serde_json::Value>(&passage.text()) {
+                Ok(data) => {
+                    start = data
+                        .get("start")
+                        .and_then(|start| start.as_str())
+                        .map(str::to_string);
+                }
+                Err(e) => builder.warn(passage.line, format!("Invalid StoryData: {}", e)),
+            },
+            _ if passage
+                .tags
+                .iter()
+                .any(|tag| matches!(*tag, "script" | "stylesheet")) =>
+            {
+                builder.warn(
+                    passage.line,
+                    format!(
+                        "Script and stylesheet passages are not supported; '{}' was skipped",
+                        passage.name
+                    ),
+                );
+            }
+            _ => story.push(passage),
+        }
+    }
+
+    // The script starts at the first command: StoryInit, then the start passage
+    let start = start.unwrap_or_else(|| "Start".to_string());
+    story.sort_by_key(|passage| match passage.name.as_str() {
+        INIT_PASSAGE => 0,
+        name if name == start => 1,
+        _ => 2,
+    });
+
+    for passage in story {
+        convert_passage(&mut builder, passage);
+    }
+    builder.finish(&title)
+}
+
+/// Split the story into passages at `::` header lines.
+fn parse_passages(text: &str) -> Vec<Passage<'_>> {
+    let mut passages: Vec<Passage> = Vec::new();
+    for (line_num, line) in text.lines().enumerate() {
+        if let Some(header) = line.strip_prefix("::") {
+            let (name, tags) = parse_header(header);
+            passages.push(Passage {
+                name,
+                tags,
+                line: line_num,
+                body: Vec::new(),
+            });
+        } else if let Some(passage) = passages.last_mut() {
+            passage.body.push((line_num, line));
+        }
+    }
+    passages
+}
+
+/// Parse `Name [tag tag] {"position":"..."}` into the name and tags.
+fn parse_header(header: &str) -> (String, Vec<&str>) {
+    let mut rest = header.trim();
+    if rest.ends_with('}')
+        && let Some(open) = unescaped_rfind(rest, '{')
+    {
+        rest = rest[..open].trim_end();
+    }
+    let mut tags = Vec::new();
+    if rest.ends_with(']')
+        && let Some(open) = unescaped_rfind(rest, '[')
+    {
+        tags = rest[open + 1..rest.len() - 1].split_whitespace().collect();
+        rest = rest[..open].trim_end();
+    }
+    (unescape(rest), tags)
+}
+
+/// Last position of `target` not escaped with a backslash.
+fn unescaped_rfind(text: &str, target: char) -> Option<usize> {
+    text.char_indices()
+        .filter(|&(index, c)| c == target && !text[..index].ends_with('\\'))
+        .map(|(index, _)| index)
+        .next_back()
+}
+
+/// Remove the backslashes escaping special characters in passage names.
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.extend(chars.next()),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Convert a passage into a label and its commands.
+fn convert_passage(builder: &mut ScriptBuilder, passage: &Passage) {
+    builder.section = Some(passage.name.clone());
+    builder.label(passage.name.clone());
+
+    let mut links = Vec::new();
+    let mut moved = false;
+    for &(line_num, line) in &passage.body {
+        let converted = convert_line(builder, line_num, line, &mut links);
+        if let Some(text) = converted.text {
+            builder.text(&text);
+        }
+        if let Some(target) = converted.goto {
+            builder.jump(&target);
+            moved = true;
+        }
+    }
+
+    if !links.is_empty() {
+        let choices = links
+            .iter()
+            .map(|(text, target)| choice(text, target, None, false))
+            .collect();
+        builder.choices(choices);
+    } else if !moved && passage.name != INIT_PASSAGE {
+        builder.jump(END_LABEL);
+    }
+}
+
+/// What a passage line turned into.
+#[derive(Default)]
+struct ConvertedLine {
+    text: Option<String>,
+    /// Target of a `goto` macro.
+    goto: Option<String>,
+}
+
+/// Convert one line, adding its assignments to the builder and its links to
+/// `links`.
+fn convert_line(
+    builder: &mut ScriptBuilder,
+    line_num: usize,
+    line: &str,
+    links: &mut Vec<(String, String)>,
+) -> ConvertedLine {
+    let mut converted = ConvertedLine::default();
+    // Text with links shown as their text, and without them
+    let mut text = String::new();
+    let mut rest = String::new();
+    let mut pos = 0;
+
+    while pos < line.len() {
+        let tail = &line[pos..];
+        if let Some(link) = tail.strip_prefix("[[")
+            && let Some(end) = link.find("]]")
+        {
+            let (label, target) = parse_link(&link[..end]);
+            text.push_str(&label);
+            links.push((label, target));
+            pos += 2 + end + 2;
+        } else if let Some(mac) = tail.strip_prefix("<<")
+            && let Some(end) = mac.find(">>")
+        {
+            let mac = mac[..end].trim();
+            let (name, args) = mac.split_once(char::is_whitespace).unwrap_or((mac, ""));
+            if !name.starts_with('/') {
+                macro_call(builder, line_num, name, args, "<<", &mut converted);
+            }
+            pos += 2 + end + 2;
+        } else if let Some((name, args, len)) = harlowe_macro(tail) {
+            pos += len;
+            if !macro_call(builder, line_num, name, args, "(", &mut converted)
+                && line[pos..].starts_with('[')
+            {
+                // The hook the macro would have shown
+                pos += matching(&line[pos..], '[', ']').unwrap_or(line.len() - pos);
+            }
+        } else if let Some(comment) = tail.strip_prefix("/*")
+            && let Some(end) = comment.find("*/")
+        {
+            pos += 2 + end + 2;
+        } else if let Some(comment) = tail.strip_prefix("<!--")
+            && let Some(end) = comment.find("-->")
+        {
+            pos += 4 + end + 3;
+        } else if let Some(name) = tail.strip_prefix('$').map(identifier)
+            && !name.is_empty()
+        {
+            let tag = format!("{{var:{}}}", name);
+            text.push_str(&tag);
+            rest.push_str(&tag);
+            pos += 1 + name.len();
+        } else {
+            let c = tail.chars().next().unwrap_or_default();
+            text.push(c);
+            rest.push(c);
+            pos += c.len_utf8();
+        }
+    }
+
+    // Lines holding nothing but links only offer them as choices
+    if !rest.trim().is_empty() {
+        converted.text = Some(text.trim().to_string());
+    }
+    converted
+}
+
+/// Handle a `set` or `goto` macro, reporting any other one.
+///
+/// Returns whether the macro was converted.
+fn macro_call(
+    builder: &mut ScriptBuilder,
+    line_num: usize,
+    name: &str,
+    args: &str,
+    open: &str,
+    converted: &mut ConvertedLine,
+) -> bool {
+    let shown = match open {
+        "(" => format!("({}:)", name),
+        _ => format!("<<{}>>", name),
+    };
+    match name {
+        "set" => match parse_set(args) {
+            Some((var, expr, op)) => {
+                builder.set(&var, &expr, op);
+                true
+            }
+            None => {
+                builder.warn(line_num, format!("Could not convert {} {}", shown, args));
+                false
+            }
+        },
+        "goto" | "go-to" => {
+            converted.goto = Some(unquote(args.trim()).to_string());
+            true
+        }
+        _ => {
+            builder.warn(
+                line_num,
+                format!("Macro {} is not supported and was dropped", shown),
+            );
+            false
+        }
+    }
+}
+
+/// Parse a link: `Target`, `Text|Target`, `Text->Target` or `Target<-Text`.
+fn parse_link(link: &str) -> (String, String) {
+    let (text, target) = if let Some((text, target)) = link.rsplit_once('|') {
+        (text, target)
+    } else if let Some((text, target)) = link.rsplit_once("->") {
+        (text, target)
+    } else if let Some((target, text)) = link.split_once("<-") {
+        (text, target)
+    } else {
+        (link, link)
+    };
+    (text.trim().to_string(), target.trim().to_string())
+}
+
+/// A Harlowe macro call `(name: args)` at the start of `text`, with its length.
+fn harlowe_macro(text: &str) -> Option<(&str, &str, usize)> {
+    let inner = text.strip_prefix('(')?;
+    let name_len = inner.find(':')?;
+    let name = &inner[..name_len];
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return None;
+    }
+    let len = matching(text, '(', ')')?;
+    Some((name, text[1 + name_len + 1..len - 1].trim(), len))
+}
+
+/// Length of the bracketed group at the start of `text`, skipping quoted
+/// strings.
+fn matching(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, c) if c == open => depth += 1,
+            (None, c) if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index + c.len_utf8());
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// A variable name at the start of `text`.
+fn identifier(text: &str) -> &str {
+    let end = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(text.len());
+    if text.starts_with(|c: char| c.is_ascii_digit()) {
+        ""
+    } else {
+        &text[..end]
+    }
+}
+
+/// Parse `$var to expr` (or `=`, `+=`, `-=`, `*=`) into the variable,
+/// expression and operation.
+fn parse_set(args: &str) -> Option<(String, String, Option<&'static str>)> {
+    let args = args.trim();
+    let var = identifier(args.strip_prefix(['$', '_'])?);
+    if var.is_empty() {
+        return None;
+    }
+    let rest = args[1 + var.len()..].trim_start();
+    let (op, expr) = if let Some(expr) = rest.strip_prefix("to ") {
+        (None, expr)
+    } else if let Some(expr) = rest.strip_prefix("+=") {
+        (Some("add"), expr)
+    } else if let Some(expr) = rest.strip_prefix("-=") {
+        (Some("sub"), expr)
+    } else if let Some(expr) = rest.strip_prefix("*=") {
+        (Some("mul"), expr)
+    } else {
+        (None, rest.strip_prefix('=')?)
+    };
+    // Harlowe's `it` is the variable being set
+    let expr = convert_expr(expr, var);
+    (!expr.is_empty()).then(|| (var.to_string(), expr, op))
+}
+
+/// Convert a SugarCube or Harlowe expression to an ivy expression.
+fn convert_expr(expr: &str, it: &str) -> String {
+    let mut words = Vec::new();
+    let mut tokens = expr.split_whitespace().peekable();
+    while let Some(token) = tokens.next() {
+        let word = match token {
+            "is" if tokens.peek() == Some(&"not") => {
+                tokens.next();
+                "!="
+            }
+            "is" | "eq" | "===" => "==",
+            "isnot" | "neq" | "!==" => "!=",
+            "gt" => ">",
+            "gte" => ">=",
+            "lt" => "<",
+            "lte" => "<=",
+            "it" => it,
+            _ => token,
+        };
+        words.push(word.replace('$', ""));
+    }
+    words.join(" ")
+}
+
+/// Strip one pair of matching quotes.
+fn unquote(text: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = text
+            .strip_prefix(quote)
+            .and_then(|text| text.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(
+            parse_header(r#" Dark Room [dark scary] {"position":"100,200"}"#),
+            ("Dark Room".to_string(), vec!["dark", "scary"])
+        );
+        assert_eq!(parse_header(r" A \[B\]"), ("A [B]".to_string(), vec![]));
+    }
+
+    #[test]
+    fn test_parse_link() {
+        let link = |text: &str, target: &str| (text.to_string(), target.to_string());
+        assert_eq!(parse_link("Cave"), link("Cave", "Cave"));
+        assert_eq!(parse_link("Go in|Cave"), link("Go in", "Cave"));
+        assert_eq!(parse_link("Go in->Cave"), link("Go in", "Cave"));
+        assert_eq!(parse_link("Cave<-Go in"), link("Go in", "Cave"));
+    }
+
+    #[test]
+    fn test_parse_set() {
+        assert_eq!(
+            parse_set("$gold to $gold + 5"),
+            Some(("gold".to_string(), "gold + 5".to_string(), None))
+        );
+        assert_eq!(
+            parse_set("$hp -= 1"),
+            Some(("hp".to_string(), "1".to_string(), Some("sub")))
+        );
+        assert_eq!(
+            parse_set("$met to it is not true"),
+            Some(("met".to_string(), "met != true".to_string(), None))
+        );
+        assert_eq!(parse_set("gold to 5"), None);
+    }
+}
@@ -0,0 +1,549 @@
+//! Expansion of the variable tags in text lines.
+//!
+//! Shared by the game, the preview server and the editor playtest so they all
+//! show the same text. Tags this module doesn't handle (`{color:red}`,
+//! `{ruby:...}`) are left in place for the renderer, as are tags that can't be
+//! resolved, such as a reference to an undefined variable.
+
+use crate::scenario::expr::{VariableLookup, parse_expr};
+use crate::types::Value;
+
+/// Expand the variable tags of a text line.
+///
+/// - `{var:name}` inserts a variable; `{var:name:spec}` formats it first (see
+///   [`format_value`]).
+/// - `{if:condition}...{else}...{/if}` keeps one branch. The condition is an
+///   expression as in `if:` commands; one that can't be evaluated (e.g. it
+///   names an undefined variable) counts as false.
+/// - `{plural:name|one|other}` or `{plural:name|zero|one|other}` picks a form
+///   by the variable's value, replacing `#` in it with the number.
+pub fn interpolate_variables(text: &str, variables: &impl VariableLookup) -> String {
+    let tokens = tokenize(text);
+    let mut pos = 0;
+    let (nodes, _) = parse_nodes(&tokens, &mut pos, false);
+
+    let mut result = String::with_capacity(text.len());
+    render(&nodes, variables, &mut result);
+    result
+}
+
+/// Format a value for `{var:name:spec}`.
+///
+/// The spec is `upper`, `lower`, `title`, or a number format made of an
+/// optional `0` (pad with zeros), a width, `,` (thousands separators) and a
+/// `.precision`, in that order: `03`, `,`, `.2`, `08,.2`. An empty spec uses
+/// the plain value. Returns `None` for an unknown spec or a number format
+/// applied to something that isn't a number.
+pub fn format_value(value: &Value, spec: &str) -> Option<String> {
+    match spec {
+        "" => Some(value.to_string()),
+        "upper" => Some(value.to_string().to_uppercase()),
+        "lower" => Some(value.to_string().to_lowercase()),
+        "title" => Some(title_case(&value.to_string())),
+        _ => NumberFormat::parse(spec)?.apply(value),
+    }
+}
+
+/// Check a tag handled by [`interpolate_variables`].
+///
+/// Returns `None` if the tag isn't an interpolation tag, otherwise whether it
+/// is well-formed. `{else}` and `{/if}` are accepted here; their nesting is
+/// checked with the rest of the markup.
+pub fn check_tag(tag: &str) -> Option<Result<(), String>> {
+    if tag == "else" || tag == "/if" {
+        return Some(Ok(()));
+    }
+    if let Some(var) = tag.strip_prefix("var:") {
+        let (name, spec) = var.split_once(':').unwrap_or((var, ""));
+        return Some(if name.is_empty() {
+            Err("'{var:...}' needs a variable name".to_string())
+        } else if !is_known_spec(spec) {
+            Err(format!("unknown format spec '{}' in '{{{}}}'", spec, tag))
+        } else {
+            Ok(())
+        });
+    }
+    if let Some(condition) = tag.strip_prefix("if:") {
+        return Some(
+            parse_expr(condition)
+                .map(|_| ())
+                .map_err(|e| format!("invalid condition in '{{{}}}': {}", tag, e)),
+        );
+    }
+    if let Some(plural) = tag.strip_prefix("plural:") {
+        let parts = plural.split('|').count();
+        return Some(if parts == 3 || parts == 4 {
+            Ok(())
+        } else {
+            Err(format!(
+                "'{{{}}}' needs a variable and two or three forms",
+                tag
+            ))
+        });
+    }
+    None
+}
+
+fn is_known_spec(spec: &str) -> bool {
+    matches!(spec, "" | "upper" | "lower" | "title") || NumberFormat::parse(spec).is_some()
+}
+
+/// A piece of text: literal text or the inside of a `{...}` tag.
+enum Token<'a> {
+    Text(&'a str),
+    Tag(&'a str),
+}
+
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        // An unterminated brace is plain text
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        if start > 0 {
+            tokens.push(Token::Text(&rest[..start]));
+        }
+        tokens.push(Token::Tag(&rest[start + 1..start + len]));
+        rest = &rest[start + len + 1..];
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest));
+    }
+    tokens
+}
+
+enum Node<'a> {
+    Text(&'a str),
+    Tag(&'a str),
+    If {
+        condition: &'a str,
+        then: Vec<Node<'a>>,
+        otherwise: Vec<Node<'a>>,
+    },
+}
+
+/// What stopped [`parse_nodes`].
+enum Boundary {
+    Else,
+    EndIf,
+    Eof,
+}
+
+/// Parse tokens up to the end of the text or, inside a conditional, up to its
+/// `{else}` or `{/if}`.
+fn parse_nodes<'a>(
+    tokens: &[Token<'a>],
+    pos: &mut usize,
+    in_if: bool,
+) -> (Vec<Node<'a>>, Boundary) {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.get(*pos) {
+        *pos += 1;
+        match *token {
+            Token::Text(text) => nodes.push(Node::Text(text)),
+            Token::Tag("else") if in_if => return (nodes, Boundary::Else),
+            Token::Tag("/if") if in_if => return (nodes, Boundary::EndIf),
+            Token::Tag(tag) => match tag.strip_prefix("if:") {
+                Some(condition) => nodes.extend(parse_if(tokens, pos, tag, condition)),
+                None => nodes.push(Node::Tag(tag)),
+            },
+        }
+    }
+    (nodes, Boundary::Eof)
+}
+
+/// Parse the branches of the `{if:...}` tag just read. A conditional without
+/// a `{/if}` is kept as literal text.
+fn parse_if<'a>(
+    tokens: &[Token<'a>],
+    pos: &mut usize,
+    tag: &'a str,
+    condition: &'a str,
+) -> Vec<Node<'a>> {
+    let (then, mut end) = parse_nodes(tokens, pos, true);
+    let has_else = matches!(end, Boundary::Else);
+    let mut otherwise = Vec::new();
+    if has_else {
+        loop {
+            let (nodes, next) = parse_nodes(tokens, pos, true);
+            otherwise.extend(nodes);
+            if let Boundary::Else = next {
+                // A second `{else}` is just text
+                otherwise.push(Node::Tag("else"));
+            } else {
+                end = next;
+                break;
+            }
+        }
+    }
+
+    if let Boundary::EndIf = end {
+        return vec![Node::If {
+            condition,
+            then,
+            otherwise,
+        }];
+    }
+    let mut nodes = vec![Node::Tag(tag)];
+    nodes.extend(then);
+    if has_else {
+        nodes.push(Node::Tag("else"));
+        nodes.extend(otherwise);
+    }
+    nodes
+}
+
+fn render(nodes: &[Node], variables: &impl VariableLookup, result: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => result.push_str(text),
+            Node::Tag(tag) => match resolve_tag(tag, variables) {
+                Some(text) => result.push_str(&text),
+                None => {
+                    result.push('{');
+                    result.push_str(tag);
+                    result.push('}');
+                }
+            },
+            Node::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let holds = parse_expr(condition)
+                    .and_then(|expr| expr.eval_bool(variables))
+                    .unwrap_or(false);
+                render(if holds { then } else { otherwise }, variables, result);
+            }
+        }
+    }
+}
+
+/// Text of a `{var:...}` or `{plural:...}` tag, or `None` to keep the tag.
+fn resolve_tag(tag: &str, variables: &impl VariableLookup) -> Option<String> {
+    if let Some(var) = tag.strip_prefix("var:") {
+        let (name, spec) = var.split_once(':').unwrap_or((var, ""));
+        return format_value(variables.lookup(name)?, spec);
+    }
+    if let Some(plural) = tag.strip_prefix("plural:") {
+        let mut parts = plural.split('|');
+        let value = variables.lookup(parts.next()?)?;
+        let count = value.as_float()?;
+        let forms: Vec<&str> = parts.collect();
+        let form = match forms.as_slice() {
+            [one, other] => {
+                if count == 1.0 {
+                    one
+                } else {
+                    other
+                }
+            }
+            [zero, one, other] => {
+                if count == 0.0 {
+                    zero
+                } else if count == 1.0 {
+                    one
+                } else {
+                    other
+                }
+            }
+            _ => return None,
+        };
+        return Some(form.replace('#', &value.to_string()));
+    }
+    None
+}
+
+fn title_case(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut word_start = true;
+    for ch in text.chars() {
+        if word_start {
+            result.extend(ch.to_uppercase());
+        } else {
+            result.push(ch);
+        }
+        word_start = ch.is_whitespace();
+    }
+    result
+}
+
+/// Number part of a format spec.
+struct NumberFormat {
+    zero_pad: bool,
+    width: usize,
+    grouping: bool,
+    precision: Option<usize>,
+}
+
+impl NumberFormat {
+    fn parse(spec: &str) -> Option<Self> {
+        let (spec, precision) = match spec.split_once('.') {
+            Some((spec, precision)) => (spec, Some(parse_digits(precision)?)),
+            None => (spec, None),
+        };
+        let (spec, grouping) = match spec.strip_suffix(',') {
+            Some(spec) => (spec, true),
+            None => (spec, false),
+        };
+        let width = if spec.is_empty() {
+            0
+        } else {
+            parse_digits(spec)?
+        };
+        Some(Self {
+            zero_pad: spec.starts_with('0'),
+            width,
+            grouping,
+            precision,
+        })
+    }
+
+    fn apply(&self, value: &Value) -> Option<String> {
+        let (negative, mut digits) = match value {
+            Value::Int(i) => {
+                let mut digits = i.unsigned_abs().to_string();
+                if let Some(precision) = self.precision.filter(|p| *p > 0) {
+                    digits.push('.');
+                    digits.extend(std::iter::repeat_n('0', precision));
+                }
+                (*i < 0, digits)
+            }
+            Value::Float(x) => {
+                let digits = match self.precision {
+                    Some(precision) => format!("{:.*}", precision, x.abs()),
+                    None => x.abs().to_string(),
+                };
+                (*x < 0.0, digits)
+            }
+            _ => return None,
+        };
+
+        if self.grouping {
+            let (whole, fraction) = match digits.find('.') {
+                Some(dot) => digits.split_at(dot),
+                None => (digits.as_str(), ""),
+            };
+            digits = format!("{}{}", group_thousands(whole), fraction);
+        }
+
+        let sign = if negative { "-" } else { "" };
+        let padding = self
+            .width
+            .saturating_sub(sign.len() + digits.chars().count());
+        Some(if self.zero_pad {
+            format!("{}{}{}", sign, "0".repeat(padding), digits)
+        } else {
+            format!("{}{}{}", " ".repeat(padding), sign, digits)
+        })
+    }
+}
+
+fn parse_digits(text: &str) -> Option<usize> {
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn group_thousands(digits: &str) -> String {
+    let mut result = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, ch) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            result.push(',');
+        }
+        result.push(ch);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{ScriptVariables, Variables};
+
+    fn vars(entries: &[(&str, Value)]) -> Variables {
+        let mut vars = Variables::new();
+        for (name, value) in entries {
+            vars.set(*name, value.clone());
+        }
+        vars
+    }
+
+    #[test]
+    fn test_interpolate_variables() {
+        let vars = vars(&[
+            ("name", Value::String("Alice".to_string())),
+            ("count", Value::Int(42)),
+            ("flag", Value::Bool(true)),
+        ]);
+
+        assert_eq!(
+            interpolate_variables("Hello {var:name}!", &vars),
+            "Hello Alice!"
+        );
+        assert_eq!(
+            interpolate_variables("Count: {var:count}", &vars),
+            "Count: 42"
+        );
+        assert_eq!(
+            interpolate_variables("Flag: {var:flag}", &vars),
+            "Flag: true"
+        );
+        assert_eq!(
+            interpolate_variables("{var:unknown}", &vars),
+            "{var:unknown}"
+        );
+    }
+
+    #[test]
+    fn test_interpolate_float_and_list_variables() {
+        let vars = vars(&[
+            ("progress", Value::Float(0.75)),
+            (
+                "items",
+                Value::List(vec![
+                    Value::String("key".to_string()),
+                    Value::String("map".to_string()),
+                ]),
+            ),
+        ]);
+
+        assert_eq!(interpolate_variables("{var:progress}", &vars), "0.75");
+        assert_eq!(
+            interpolate_variables("Items: {var:items}", &vars),
+            "Items: key, map"
+        );
+    }
+
+    #[test]
+    fn test_interpolate_persistent_variables() {
+        let vars = Variables::new();
+        let persistent = self::vars(&[("runs", Value::Int(3))]);
+        let lookup = ScriptVariables {
+            variables: &vars,
+            persistent: &persistent,
+        };
+
+        assert_eq!(
+            interpolate_variables("Run {var:persistent.runs}", &lookup),
+            "Run 3"
+        );
+    }
+
+    #[test]
+    fn test_interpolate_keeps_other_tags() {
+        let vars = vars(&[("name", Value::String("Alice".to_string()))]);
+
+        assert_eq!(
+            interpolate_variables("{color:blue}Hello {var:name}!{/color}", &vars),
+            "{color:blue}Hello Alice!{/color}"
+        );
+        assert_eq!(interpolate_variables("a {b", &vars), "a {b");
+    }
+
+    #[test]
+    fn test_interpolate_multiple_vars() {
+        let vars = vars(&[
+            ("a", Value::String("X".to_string())),
+            ("b", Value::String("Y".to_string())),
+            ("c", Value::String("Z".to_string())),
+        ]);
+
+        assert_eq!(
+            interpolate_variables("{var:a}-{var:b}-{var:c}", &vars),
+            "X-Y-Z"
+        );
+    }
+
+    #[test]
+    fn test_interpolate_empty_text() {
+        assert_eq!(interpolate_variables("", &Variables::new()), "");
+    }
+
+    #[test]
+    fn test_interpolate_no_tags() {
+        assert_eq!(
+            interpolate_variables("plain text without tags", &Variables::new()),
+            "plain text without tags"
+        );
+    }
+
+    #[test]
+    fn test_format_specs() {
+        let vars = vars(&[
+            ("gold", Value::Int(1234567)),
+            ("day", Value::Int(7)),
+            ("debt", Value::Int(-42)),
+            ("ratio", Value::Float(2.5)),
+            ("name", Value::String("alice liddell".to_string())),
+        ]);
+        let format = |text| interpolate_variables(text, &vars);
+
+        assert_eq!(format("{var:gold:,}"), "1,234,567");
+        assert_eq!(format("{var:day:03}"), "007");
+        assert_eq!(format("{var:day:3}"), "  7");
+        assert_eq!(format("{var:debt:05}"), "-0042");
+        assert_eq!(format("{var:ratio:.2}"), "2.50");
+        assert_eq!(format("{var:gold:,.2}"), "1,234,567.00");
+        assert_eq!(format("{var:name:upper}"), "ALICE LIDDELL");
+        assert_eq!(format("{var:name:title}"), "Alice Liddell");
+        // Unknown specs and number specs on text are kept as written
+        assert_eq!(format("{var:name:bold}"), "{var:name:bold}");
+        assert_eq!(format("{var:name:03}"), "{var:name:03}");
+    }
+
+    #[test]
+    fn test_inline_conditionals() {
+        let vars = vars(&[("met_alice", Value::Bool(true)), ("gold", Value::Int(3))]);
+        let format = |text| interpolate_variables(text, &vars);
+
+        assert_eq!(
+            format("Hi, {if:met_alice}Alice{else}stranger{/if}!"),
+            "Hi, Alice!"
+        );
+        assert_eq!(format("{if:gold > 5}Rich{else}Poor{/if}"), "Poor");
+        assert_eq!(format("{if:not met_alice}Who?{/if}"), "");
+        // Undefined variables make the condition false
+        assert_eq!(format("{if:met_bob}Bob{else}someone{/if}"), "someone");
+        assert_eq!(
+            format("{if:met_alice}A{if:gold == 3} has {var:gold}{/if}{else}B{/if}"),
+            "A has 3"
+        );
+        // Without {/if} the tags are kept
+        assert_eq!(format("{if:met_alice}A{else}B"), "{if:met_alice}A{else}B");
+        assert_eq!(format("{else}{/if}"), "{else}{/if}");
+    }
+
+    #[test]
+    fn test_check_tag() {
+        assert_eq!(check_tag("color:red"), None);
+        assert_eq!(check_tag("var:gold:,"), Some(Ok(())));
+        assert_eq!(check_tag("plural:n|a|b"), Some(Ok(())));
+        assert!(matches!(check_tag("var:gold:bold"), Some(Err(_))));
+        assert!(matches!(check_tag("if:gold >"), Some(Err(_))));
+        assert!(matches!(check_tag("plural:n|a"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_plurals() {
+        let vars = vars(&[
+            ("none", Value::Int(0)),
+            ("one", Value::Int(1)),
+            ("many", Value::Int(5)),
+        ]);
+        let format = |text| interpolate_variables(text, &vars);
+
+        assert_eq!(format("{plural:one|# apple|# apples}"), "1 apple");
+        assert_eq!(format("{plural:many|# apple|# apples}"), "5 apples");
+        assert_eq!(format("{plural:none|# apple|# apples}"), "0 apples");
+        assert_eq!(
+            format("{plural:none|no apples|an apple|# apples}"),
+            "no apples"
+        );
+        assert_eq!(format("{plural:missing|a|b}"), "{plural:missing|a|b}");
+        assert_eq!(format("{plural:one|only}"), "{plural:one|only}");
+    }
+}
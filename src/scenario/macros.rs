@@ -0,0 +1,378 @@
+//! Parse-time expansion of `- macro:` entries into the commands they stand for.
+
+use std::collections::HashMap;
+
+use serde_yaml::{Mapping, Value};
+
+use crate::scenario::markup;
+use crate::scenario::types::{CommandOrigin, MacroCommand, MacroDef};
+
+/// Maximum nesting of macros calling other macros.
+const MAX_MACRO_DEPTH: usize = 16;
+
+/// An error found while expanding a macro call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacroError {
+    /// Index of the offending `- macro:` entry in the `script:` list.
+    pub written_index: usize,
+    pub message: String,
+}
+
+/// Read the `macros:` section of a scenario document.
+///
+/// Returns `None` when the section is malformed; the typed parse that follows
+/// reports that with a line number.
+pub fn read_macros(doc: &Value) -> Option<HashMap<String, MacroDef>> {
+    match doc.get("macros") {
+        Some(section) => serde_yaml::from_value(section.clone()).ok(),
+        None => Some(HashMap::new()),
+    }
+}
+
+/// Expand the macro calls in a scenario document's `script:` list in place.
+///
+/// Returns the origin of every command of the expanded script, or `None` if
+/// the script contains no macro calls and was left untouched.
+pub fn expand_script(
+    doc: &mut Value,
+    macros: &HashMap<String, MacroDef>,
+) -> Result<Option<Vec<CommandOrigin>>, MacroError> {
+    let Some(Value::Sequence(script)) = doc.get_mut("script") else {
+        return Ok(None);
+    };
+    if !script.iter().any(is_macro_call) {
+        return Ok(None);
+    }
+
+    let mut expanded = Vec::with_capacity(script.len());
+    let mut origins = Vec::with_capacity(script.len());
+    for (written_index, entry) in std::mem::take(script).into_iter().enumerate() {
+        if !is_macro_call(&entry) {
+            expanded.push(entry);
+            origins.push(CommandOrigin {
+                written_index,
+                expanded_from: None,
+            });
+            continue;
+        }
+
+        let mut stack = Vec::new();
+        let commands = expand_call(&entry, macros, &mut stack).map_err(|message| MacroError {
+            written_index,
+            message,
+        })?;
+        for (command, macro_command) in commands {
+            expanded.push(command);
+            origins.push(CommandOrigin {
+                written_index,
+                expanded_from: Some(macro_command),
+            });
+        }
+    }
+
+    *script = expanded;
+    Ok(Some(origins))
+}
+
+/// Check whether a script entry is a `- macro:` call.
+fn is_macro_call(entry: &Value) -> bool {
+    entry.get("macro").is_some()
+}
+
+/// Expand one call, recursing into macros that call other macros.
+fn expand_call(
+    entry: &Value,
+    macros: &HashMap<String, MacroDef>,
+    stack: &mut Vec<String>,
+) -> Result<Vec<(Value, MacroCommand)>, String> {
+    let (name, args) = parse_call(entry)?;
+    let def = macros
+        .get(&name)
+        .ok_or_else(|| format!("Unknown macro '{}'", name))?;
+
+    if stack.contains(&name) {
+        return Err(format!("Macro '{}' expands itself", name));
+    }
+    if stack.len() >= MAX_MACRO_DEPTH {
+        return Err(format!(
+            "Macro '{}' is nested more than {} levels deep",
+            name, MAX_MACRO_DEPTH
+        ));
+    }
+
+    for param in &def.params {
+        if markup::is_tag(param) {
+            return Err(format!(
+                "Macro '{}' has a parameter named '{}', which is also a text tag",
+                name, param
+            ));
+        }
+        if !args.contains_key(param) {
+            return Err(format!("Macro '{}' is missing argument '{}'", name, param));
+        }
+    }
+    for arg in args.keys() {
+        if !def.params.contains(arg) {
+            return Err(format!("Macro '{}' has no parameter '{}'", name, arg));
+        }
+    }
+
+    stack.push(name.clone());
+    let mut commands = Vec::new();
+    for (index, command) in def.commands.iter().enumerate() {
+        let command = substitute(command, &args)
+            .map_err(|e| format!("In command {} of macro '{}': {}", index + 1, name, e))?;
+        if is_macro_call(&command) {
+            commands.extend(expand_call(&command, macros, stack)?);
+        } else {
+            commands.push((
+                command,
+                MacroCommand {
+                    name: name.clone(),
+                    index,
+                },
+            ));
+        }
+    }
+    stack.pop();
+
+    Ok(commands)
+}
+
+/// Read the macro name and arguments of a call, either `macro: name` or
+/// `macro: { name: ..., args: { ... } }`.
+fn parse_call(entry: &Value) -> Result<(String, HashMap<String, Value>), String> {
+    let Value::Mapping(mapping) = entry else {
+        return Err("A macro call must be a mapping".to_string());
+    };
+    if let Some(key) = mapping.keys().find(|key| key.as_str() != Some("macro")) {
+        return Err(format!(
+            "A macro call can't be combined with other keys (found '{}')",
+            key.as_str().unwrap_or("?")
+        ));
+    }
+
+    match &mapping["macro"] {
+        Value::String(name) => Ok((name.clone(), HashMap::new())),
+        Value::Mapping(call) => {
+            let name = call
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or("A macro call needs a 'name'")?;
+            let args = match call.get("args") {
+                Some(Value::Mapping(args)) => read_args(args)?,
+                Some(Value::Null) | None => HashMap::new(),
+                Some(_) => return Err("Macro 'args' must be a mapping".to_string()),
+            };
+            Ok((name.to_string(), args))
+        }
+        _ => Err("'macro' must be a macro name or { name, args }".to_string()),
+    }
+}
+
+/// Convert call arguments to a name -> value table.
+fn read_args(args: &Mapping) -> Result<HashMap<String, Value>, String> {
+    args.iter()
+        .map(|(key, value)| match key.as_str() {
+            Some(key) => Ok((key.to_string(), value.clone())),
+            None => Err("Macro argument names must be strings".to_string()),
+        })
+        .collect()
+}
+
+/// Replace `{param}` placeholders in every string of a YAML value.
+///
+/// A string that is exactly one placeholder takes the argument's value as is,
+/// so numbers and lists keep their type. Placeholders that don't name a
+/// parameter are left alone (they may be text tags), and inserted arguments
+/// aren't searched for placeholders again.
+fn substitute(value: &Value, args: &HashMap<String, Value>) -> Result<Value, String> {
+    match value {
+        Value::String(text) => {
+            if let Some(arg) = text
+                .strip_prefix('{')
+                .and_then(|rest| rest.strip_suffix('}'))
+                .and_then(|name| args.get(name))
+            {
+                return Ok(arg.clone());
+            }
+            let mut result = String::with_capacity(text.len());
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find('{') {
+                result.push_str(&rest[..start]);
+                let after = &rest[start + 1..];
+                let placeholder = after
+                    .find('}')
+                    .and_then(|len| Some((len, args.get_key_value(&after[..len])?)));
+                match placeholder {
+                    Some((len, (name, arg))) => {
+                        result.push_str(&scalar_text(name, arg)?);
+                        rest = &after[len + 1..];
+                    }
+                    None => {
+                        result.push('{');
+                        rest = after;
+                    }
+                }
+            }
+            result.push_str(rest);
+            Ok(Value::String(result))
+        }
+        Value::Sequence(items) => items
+            .iter()
+            .map(|item| substitute(item, args))
+            .collect::<Result<_, _>>()
+            .map(Value::Sequence),
+        Value::Mapping(mapping) => mapping
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), substitute(value, args)?)))
+            .collect::<Result<_, String>>()
+            .map(Value::Mapping),
+        other => Ok(other.clone()),
+    }
+}
+
+/// Text of an argument inserted into a longer string.
+fn scalar_text(name: &str, arg: &Value) -> Result<String, String> {
+    match arg {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Null => Ok(String::new()),
+        _ => Err(format!(
+            "argument '{}' is not a single value and can't be inserted into text",
+            name
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(yaml: &str) -> Result<(Value, Option<Vec<CommandOrigin>>), MacroError> {
+        let mut doc: Value = serde_yaml::from_str(yaml).unwrap();
+        let macros = read_macros(&doc).unwrap();
+        let origins = expand_script(&mut doc, &macros)?;
+        Ok((doc, origins))
+    }
+
+    #[test]
+    fn test_expand_with_arguments() {
+        let yaml = r#"
+macros:
+  greet:
+    params: [who, times]
+    commands:
+      - text: "Hello, {who}! {unknown}"
+      - wait: "{times}"
+script:
+  - text: "Start"
+  - macro:
+      name: greet
+      args: { who: Alice, times: 2 }
+"#;
+        let (doc, origins) = expand(yaml).unwrap();
+        let script = doc["script"].as_sequence().unwrap();
+
+        assert_eq!(script.len(), 3);
+        assert_eq!(script[1]["text"].as_str(), Some("Hello, Alice! {unknown}"));
+        assert_eq!(script[2]["wait"].as_u64(), Some(2));
+
+        let origins = origins.unwrap();
+        assert_eq!(origins[0].written_index, 0);
+        assert_eq!(origins[2].written_index, 1);
+        assert_eq!(
+            origins[2].expanded_from,
+            Some(MacroCommand {
+                name: "greet".to_string(),
+                index: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_script_without_calls_is_untouched() {
+        let (_, origins) = expand("script:\n  - text: \"Hi\"\n").unwrap();
+        assert!(origins.is_none());
+    }
+
+    #[test]
+    fn test_nested_macros() {
+        let yaml = r#"
+macros:
+  inner:
+    params: [n]
+    commands:
+      - text: "Inner {n}"
+  outer:
+    commands:
+      - text: "Outer"
+      - macro: { name: inner, args: { n: 1 } }
+script:
+  - macro: outer
+"#;
+        let (doc, origins) = expand(yaml).unwrap();
+
+        assert_eq!(doc["script"][1]["text"].as_str(), Some("Inner 1"));
+        let origin = origins.unwrap()[1].expanded_from.clone().unwrap();
+        assert_eq!(origin.name, "inner");
+        assert_eq!(origin.index, 0);
+    }
+
+    #[test]
+    fn test_expansion_errors() {
+        let unknown = expand("script:\n  - text: \"Hi\"\n  - macro: nope\n").unwrap_err();
+        assert_eq!(unknown.written_index, 1);
+        assert!(unknown.message.contains("Unknown macro 'nope'"));
+
+        let missing = "macros:\n  m:\n    params: [a]\n    commands: []\nscript:\n  - macro: m\n";
+        assert!(
+            expand(missing)
+                .unwrap_err()
+                .message
+                .contains("argument 'a'")
+        );
+
+        let recursive = "macros:\n  m:\n    commands:\n      - macro: m\nscript:\n  - macro: m\n";
+        assert!(
+            expand(recursive)
+                .unwrap_err()
+                .message
+                .contains("expands itself")
+        );
+
+        let mixed = "macros:\n  m:\n    commands: []\nscript:\n  - label: x\n    macro: m\n";
+        assert!(expand(mixed).unwrap_err().message.contains("other keys"));
+
+        let tag = "macros:\n  m:\n    params: [b]\n    commands: []\nscript:\n  - macro: { name: m, args: { b: 1 } }\n";
+        assert!(
+            expand(tag)
+                .unwrap_err()
+                .message
+                .contains("parameter named 'b', which is also a text tag")
+        );
+    }
+
+    #[test]
+    fn test_inserted_arguments_are_not_substituted_again() {
+        let yaml = r#"
+macros:
+  pair:
+    params: [first, second]
+    commands:
+      - text: "{b}{first}{/b} and {second}"
+script:
+  - macro:
+      name: pair
+      args: { first: "{second}", second: "{first}" }
+"#;
+        for _ in 0..8 {
+            let (doc, _) = expand(yaml).unwrap();
+            assert_eq!(
+                doc["script"][0]["text"].as_str(),
+                Some("{b}{second}{/b} and {first}")
+            );
+        }
+    }
+}
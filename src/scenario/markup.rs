@@ -0,0 +1,556 @@
+//! Rich text markup of text lines.
+//!
+//! Styling tags come in pairs: `{color:red}`/`{/color}`, `{b}`/`{/b}`,
+//! `{i}`/`{/i}`, `{size:1.4}`/`{/size}`, `{shake}`/`{/shake}` and
+//! `{wave}`/`{/wave}`. `{ruby:base:reading}` stands alone.
+//!
+//! Pacing tags control the typewriter instead: `{w=0.5}` pauses, `{speed=10}`
+//! changes the characters per second until `{/speed}`, and `{nw}` advances
+//! to the next line as soon as the text is shown.
+//!
+//! The text box renderer draws the spans parsed here, and
+//! [`count_visible_chars`], the typewriter and the validator use the same
+//! parser, so they agree with what is drawn.
+
+use crate::scenario::interpolate;
+
+/// Per-character animation of a span.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEffect {
+    /// Each character jitters around its position.
+    Shake,
+    /// Characters bob up and down in a travelling wave.
+    Wave,
+}
+
+/// Style of a run of text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextStyle {
+    /// Colour name or `#rrggbb` of the innermost `{color:...}`.
+    pub color: Option<String>,
+    pub bold: bool,
+    pub italic: bool,
+    /// Font size multiplier.
+    pub size: f32,
+    pub effect: Option<TextEffect>,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            color: None,
+            bold: false,
+            italic: false,
+            size: 1.0,
+            effect: None,
+        }
+    }
+}
+
+/// A run of text drawn with one style.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSpan {
+    pub text: String,
+    pub style: TextStyle,
+    /// Reading drawn above the text (`{ruby:base:reading}`).
+    pub ruby: Option<String>,
+}
+
+/// A change of typewriter pace inside a text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaceChange {
+    /// Pause for this many seconds (`{w=0.5}`).
+    Pause(f32),
+    /// Reveal this many characters per second (`{speed=10}`), or go back to
+    /// the player's text speed (`{/speed}`).
+    Speed(Option<f32>),
+}
+
+/// A pace change and the number of visible characters before it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaceMark {
+    pub at: usize,
+    pub change: PaceChange,
+}
+
+/// The pacing tags of a text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextPacing {
+    /// Pace changes in text order.
+    pub marks: Vec<PaceMark>,
+    /// Advance without waiting for the player once the text is shown (`{nw}`).
+    pub no_wait: bool,
+}
+
+impl TextPacing {
+    /// Move every mark by `offset` characters, for text drawn after other
+    /// text (NVL pages).
+    pub fn shifted(mut self, offset: usize) -> Self {
+        for mark in &mut self.marks {
+            mark.at += offset;
+        }
+        self
+    }
+}
+
+/// Parse the pacing tags of a text.
+pub fn text_pacing(text: &str) -> TextPacing {
+    let mut parser = Parser::default();
+    parser.run(text);
+    parser.pacing
+}
+
+/// Parse text into styled spans.
+///
+/// Unknown and malformed tags are kept as literal text. A `{` without a
+/// closing `}` swallows the rest of the text as a tag.
+pub fn parse_markup(text: &str) -> Vec<TextSpan> {
+    let mut parser = Parser::default();
+    parser.run(text);
+    parser.spans
+}
+
+/// Describe the problems in a text's markup: unknown or malformed tags,
+/// closing tags without an opening one and tags left open.
+///
+/// Interpolation tags (`{var:...}`, `{if:...}`, `{plural:...}`) are checked
+/// too, since they are written in the same text.
+pub fn check_markup(text: &str) -> Vec<String> {
+    let mut parser = Parser::default();
+    parser.run(text);
+    parser.issues
+}
+
+/// Check whether `{tag}` is read as a markup or interpolation tag, such as
+/// `{b}`, `{w=0.5}` or `{var:name}`.
+pub fn is_tag(tag: &str) -> bool {
+    let name = tag.strip_prefix('/').unwrap_or(tag);
+    is_styling_tag(name)
+        || matches!(tag, "nw" | "/speed")
+        || ["w=", "speed=", "color:", "size:", "ruby:"]
+            .iter()
+            .any(|prefix| tag.starts_with(prefix))
+        || interpolate::check_tag(tag).is_some()
+}
+
+/// Number of characters the typewriter reveals for a text: everything but
+/// the styling tags, with a ruby group counting as its base text.
+pub fn count_visible_chars(text: &str) -> usize {
+    parse_markup(text)
+        .iter()
+        .map(|span| span.text.chars().count())
+        .sum()
+}
+
+/// Text with the styling and pacing tags removed (ruby groups keep their
+/// base text), e.g. for text-to-speech.
+pub fn strip_markup(text: &str) -> String {
+    parse_markup(text)
+        .into_iter()
+        .map(|span| span.text)
+        .collect()
+}
+
+/// Attribute value saved when a tag opened, restored when it closes.
+#[derive(Debug)]
+enum Saved {
+    Color(Option<String>),
+    Bold(bool),
+    Italic(bool),
+    Size(f32),
+    Effect(Option<TextEffect>),
+}
+
+#[derive(Default)]
+struct Parser {
+    spans: Vec<TextSpan>,
+    issues: Vec<String>,
+    current: String,
+    style: TextStyle,
+    /// Open styling tags, innermost last.
+    open: Vec<(&'static str, Saved)>,
+    /// Depth of `{if:...}` conditionals (only seen before interpolation).
+    conditionals: usize,
+    pacing: TextPacing,
+    /// Visible characters in `spans`.
+    visible: usize,
+}
+
+impl Parser {
+    fn run(&mut self, text: &str) {
+        let mut rest = text;
+        while let Some(start) = rest.find('{') {
+            self.current.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            match after.find('}') {
+                Some(len) => {
+                    self.tag(&after[..len]);
+                    rest = &after[len + 1..];
+                }
+                None => {
+                    self.issues.push(format!("Unterminated tag '{{{}'", after));
+                    self.tag(after);
+                    rest = "";
+                }
+            }
+        }
+        self.current.push_str(rest);
+        self.flush();
+
+        for (name, _) in &self.open {
+            self.issues.push(format!("Unclosed '{{{}}}' tag", name));
+        }
+        if self.conditionals > 0 {
+            self.issues
+                .push("Unclosed '{if:...}' (missing '{/if}')".to_string());
+        }
+    }
+
+    fn tag(&mut self, tag: &str) {
+        // Text so far keeps the style it was written in
+        self.flush();
+
+        match tag {
+            "b" => {
+                let saved = Saved::Bold(std::mem::replace(&mut self.style.bold, true));
+                self.open_tag("b", saved);
+            }
+            "i" => {
+                let saved = Saved::Italic(std::mem::replace(&mut self.style.italic, true));
+                self.open_tag("i", saved);
+            }
+            "shake" => {
+                let saved = Saved::Effect(self.style.effect.replace(TextEffect::Shake));
+                self.open_tag("shake", saved);
+            }
+            "wave" => {
+                let saved = Saved::Effect(self.style.effect.replace(TextEffect::Wave));
+                self.open_tag("wave", saved);
+            }
+            "nw" => self.pacing.no_wait = true,
+            "/speed" => self.pace(PaceChange::Speed(None)),
+            _ => {
+                if let Some(seconds) = tag.strip_prefix("w=") {
+                    match seconds.parse::<f32>() {
+                        Ok(seconds) if seconds.is_finite() && seconds >= 0.0 => {
+                            self.pace(PaceChange::Pause(seconds));
+                        }
+                        _ => self.invalid_number(tag, "wait", "a number of seconds"),
+                    }
+                } else if let Some(cps) = tag.strip_prefix("speed=") {
+                    match cps.parse::<f32>() {
+                        Ok(cps) if cps.is_finite() && cps > 0.0 => {
+                            self.pace(PaceChange::Speed(Some(cps)));
+                        }
+                        _ => self.invalid_number(
+                            tag,
+                            "speed",
+                            "a positive number of characters per second",
+                        ),
+                    }
+                } else if let Some(color) = tag.strip_prefix("color:") {
+                    let saved = Saved::Color(self.style.color.replace(color.to_string()));
+                    self.open_tag("color", saved);
+                } else if let Some(size) = tag.strip_prefix("size:") {
+                    self.size_tag(tag, size);
+                } else if let Some(ruby) = tag.strip_prefix("ruby:") {
+                    self.ruby_tag(tag, ruby);
+                } else if let Some(name) = tag.strip_prefix('/').filter(|n| is_styling_tag(n)) {
+                    self.close_tag(name);
+                } else {
+                    self.other_tag(tag);
+                }
+            }
+        }
+    }
+
+    fn size_tag(&mut self, tag: &str, size: &str) {
+        match size.parse::<f32>() {
+            Ok(scale) if scale.is_finite() && scale > 0.0 => {
+                let saved = Saved::Size(std::mem::replace(&mut self.style.size, scale));
+                self.open_tag("size", saved);
+            }
+            _ => self.invalid_number(tag, "size", "a positive number"),
+        }
+    }
+
+    fn ruby_tag(&mut self, tag: &str, ruby: &str) {
+        match ruby.split_once(':') {
+            Some((base, reading)) => {
+                self.visible += base.chars().count();
+                self.spans.push(TextSpan {
+                    text: base.to_string(),
+                    style: self.style.clone(),
+                    ruby: Some(reading.to_string()),
+                });
+            }
+            None => {
+                self.issues.push(format!(
+                    "Malformed '{{{}}}' (expected '{{ruby:base:reading}}')",
+                    tag
+                ));
+                self.literal(tag);
+            }
+        }
+    }
+
+    /// Handle a tag that isn't a styling tag: interpolation tags left in the
+    /// text and unknown tags are kept as written.
+    fn other_tag(&mut self, tag: &str) {
+        match interpolate::check_tag(tag) {
+            Some(result) => {
+                if let Err(e) = result {
+                    self.issues.push(capitalize(&e));
+                }
+                if tag.starts_with("if:") {
+                    self.conditionals += 1;
+                } else if tag == "/if" || tag == "else" {
+                    if self.conditionals == 0 {
+                        self.issues
+                            .push(format!("'{{{}}}' without a matching '{{if:...}}'", tag));
+                    } else if tag == "/if" {
+                        self.conditionals -= 1;
+                    }
+                }
+            }
+            None => self.issues.push(format!("Unknown text tag '{{{}}}'", tag)),
+        }
+        self.literal(tag);
+    }
+
+    fn pace(&mut self, change: PaceChange) {
+        self.pacing.marks.push(PaceMark {
+            at: self.visible,
+            change,
+        });
+    }
+
+    /// Report a tag whose number doesn't parse; it is kept as written.
+    fn invalid_number(&mut self, tag: &str, what: &str, expected: &str) {
+        self.issues.push(format!(
+            "Invalid {} in '{{{}}}' (expected {})",
+            what, tag, expected
+        ));
+        self.literal(tag);
+    }
+
+    /// Record a styling tag whose attribute was just set.
+    fn open_tag(&mut self, name: &'static str, saved: Saved) {
+        self.open.push((name, saved));
+    }
+
+    fn close_tag(&mut self, name: &str) {
+        let Some(position) = self.open.iter().rposition(|(open, _)| *open == name) else {
+            self.issues
+                .push(format!("'{{/{}}}' without a matching '{{{}}}'", name, name));
+            return;
+        };
+        let (_, saved) = self.open.remove(position);
+        self.style = restore(std::mem::take(&mut self.style), &saved);
+    }
+
+    fn literal(&mut self, tag: &str) {
+        self.current.push('{');
+        self.current.push_str(tag);
+        self.current.push('}');
+    }
+
+    fn flush(&mut self) {
+        if !self.current.is_empty() {
+            self.visible += self.current.chars().count();
+            self.spans.push(TextSpan {
+                text: std::mem::take(&mut self.current),
+                style: self.style.clone(),
+                ruby: None,
+            });
+        }
+    }
+}
+
+/// Put back the attribute saved when a tag opened.
+fn restore(mut style: TextStyle, saved: &Saved) -> TextStyle {
+    match saved {
+        Saved::Color(color) => style.color = color.clone(),
+        Saved::Bold(bold) => style.bold = *bold,
+        Saved::Italic(italic) => style.italic = *italic,
+        Saved::Size(size) => style.size = *size,
+        Saved::Effect(effect) => style.effect = *effect,
+    }
+    style
+}
+
+fn is_styling_tag(name: &str) -> bool {
+    matches!(name, "color" | "b" | "i" | "size" | "shake" | "wave")
+}
+
+fn capitalize(message: &str) -> String {
+    let mut chars = message.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_visible_chars_plain_text() {
+        assert_eq!(count_visible_chars("hello"), 5);
+        assert_eq!(count_visible_chars("hello world"), 11);
+        assert_eq!(count_visible_chars(""), 0);
+    }
+
+    #[test]
+    fn test_count_visible_chars_with_color_tags() {
+        // Color tags should not be counted
+        assert_eq!(count_visible_chars("{color:red}hello{/color}"), 5);
+        assert_eq!(count_visible_chars("{color:#ff0000}test{/color}"), 4);
+        assert_eq!(count_visible_chars("{color:blue}hello{/color} world"), 11);
+    }
+
+    #[test]
+    fn test_count_visible_chars_nested_colors() {
+        assert_eq!(
+            count_visible_chars("{color:red}a{color:blue}b{/color}c{/color}"),
+            3
+        );
+        assert_eq!(
+            count_visible_chars("{color:red}{color:blue}{color:green}ABC{/color}{/color}{/color}"),
+            3
+        );
+    }
+
+    #[test]
+    fn test_count_visible_chars_unrecognized_tags() {
+        // Unrecognized tags are drawn as written (braces + content)
+        assert_eq!(count_visible_chars("{unknown}text"), 13);
+        // "hello" (5) + " " (1) + "{unknown}" (9) + " " (1) + "world" (5) = 21
+        assert_eq!(
+            count_visible_chars("{color:red}hello{/color} {unknown} {color:blue}world{/color}"),
+            21
+        );
+    }
+
+    #[test]
+    fn test_count_visible_chars_style_tags_and_ruby() {
+        assert_eq!(
+            count_visible_chars("{b}bold{/b} {i}it{/i} {size:1.4}big{/size}"),
+            11
+        );
+        assert_eq!(count_visible_chars("{shake}Ah{/shake}{wave}~{/wave}"), 3);
+        // A ruby group counts its base text, as the typewriter reveals it
+        assert_eq!(count_visible_chars("{ruby:漢字:かんじ}を"), 3);
+    }
+
+    #[test]
+    fn test_count_visible_chars_long_text() {
+        let long_text = "a".repeat(10000);
+        assert_eq!(count_visible_chars(&long_text), 10000);
+
+        let long_text = "{color:red}hello{/color}".repeat(1000);
+        assert_eq!(count_visible_chars(&long_text), 5000);
+    }
+
+    #[test]
+    fn test_count_visible_chars_malformed_tags() {
+        // An unterminated tag swallows the rest of the text
+        assert_eq!(count_visible_chars("{color:red"), 0);
+        // "{}" is not a recognized tag, so braces + empty content = 2
+        assert_eq!(count_visible_chars("{}text"), 6);
+    }
+
+    #[test]
+    fn test_strip_markup() {
+        assert_eq!(strip_markup("{color:red}hello{/color}"), "hello");
+        assert_eq!(strip_markup("{b}{i}Hi{/i}{/b}"), "Hi");
+        assert_eq!(
+            strip_markup("text {ruby:東京:とうきょう} more"),
+            "text 東京 more"
+        );
+        assert_eq!(
+            strip_markup("{color:red}{ruby:漢字:かんじ}{/color}"),
+            "漢字"
+        );
+        assert_eq!(strip_markup("{unknown}text"), "{unknown}text");
+    }
+
+    #[test]
+    fn test_parse_styles() {
+        let spans = parse_markup("a{b}b{i}c{/b}d{/i}{size:2}{wave}e{/wave}{/size}");
+        let texts: Vec<&str> = spans.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec!["a", "b", "c", "d", "e"]);
+
+        assert_eq!(spans[0].style, TextStyle::default());
+        assert!(spans[1].style.bold && !spans[1].style.italic);
+        assert!(spans[2].style.bold && spans[2].style.italic);
+        // Tags can be closed out of order
+        assert!(!spans[3].style.bold && spans[3].style.italic);
+        assert_eq!(spans[4].style.size, 2.0);
+        assert_eq!(spans[4].style.effect, Some(TextEffect::Wave));
+    }
+
+    #[test]
+    fn test_pacing_tags() {
+        let text = "Well{w=0.5}... {speed=5}{ruby:漢字:かんじ}slow{/speed} done{nw}";
+        let pacing = text_pacing(text);
+
+        assert_eq!(
+            pacing.marks,
+            vec![
+                PaceMark {
+                    at: 4,
+                    change: PaceChange::Pause(0.5),
+                },
+                PaceMark {
+                    at: 8,
+                    change: PaceChange::Speed(Some(5.0)),
+                },
+                PaceMark {
+                    at: 14,
+                    change: PaceChange::Speed(None),
+                },
+            ]
+        );
+        assert!(pacing.no_wait);
+        assert!(!text_pacing("Plain").no_wait);
+
+        // Pacing tags are never drawn or spoken
+        assert_eq!(strip_markup(text), "Well... 漢字slow done");
+        assert_eq!(count_visible_chars(text), 19);
+        assert!(check_markup(text).is_empty());
+    }
+
+    #[test]
+    fn test_parse_color_and_ruby() {
+        let spans = parse_markup("{color:red}A{color:#00ff00}B{/color}{ruby:漢:かん}{/color}C");
+
+        assert_eq!(spans[0].style.color.as_deref(), Some("red"));
+        assert_eq!(spans[1].style.color.as_deref(), Some("#00ff00"));
+        assert_eq!(spans[2].text, "漢");
+        assert_eq!(spans[2].ruby.as_deref(), Some("かん"));
+        assert_eq!(spans[2].style.color.as_deref(), Some("red"));
+        assert_eq!(spans[3].style.color, None);
+    }
+
+    #[test]
+    fn test_check_markup() {
+        assert!(check_markup("{b}Hi{/b} {var:name} {if:x}a{else}b{/if}").is_empty());
+
+        let issues = check_markup("{b}Hi {bold}");
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].contains("Unknown text tag '{bold}'"));
+        assert!(issues[1].contains("Unclosed '{b}'"));
+
+        assert!(check_markup("Hi{/i}")[0].contains("without a matching '{i}'"));
+        assert!(check_markup("{size:big}x{/size}")[0].contains("Invalid size"));
+        assert!(check_markup("{w=soon}")[0].contains("Invalid wait"));
+        assert!(check_markup("{speed=0}")[0].contains("Invalid speed"));
+        assert!(check_markup("{ruby:漢字}")[0].contains("Malformed"));
+        assert!(check_markup("{if:x}a")[0].contains("missing '{/if}'"));
+        assert!(check_markup("a{/if}")[0].contains("without a matching"));
+        assert!(check_markup("{var:n:bogus}")[0].contains("Unknown format spec"));
+        assert!(check_markup("Hi {color:red")[0].contains("Unterminated"));
+    }
+}
@@ -0,0 +1,236 @@
+//! Format versions of scenario files and the migrations between them.
+//!
+//! A layout change that would break existing files bumps the format version
+//! and adds a step to [`MIGRATIONS`] rewriting documents of the previous
+//! version. Older documents are upgraded one version at a time, in memory,
+//! before they are parsed; `ivy-validate --migrate` writes the result back.
+//!
+//! Files without a `format_version` predate versioning and are version 1.
+
+use anyhow::{Result, anyhow};
+use serde_yaml::{Mapping, Value};
+
+/// Key holding the format version of a scenario or save file.
+pub const VERSION_KEY: &str = "format_version";
+
+/// A step upgrading a document to the next format version.
+pub type Migration<T> = fn(&mut T);
+
+/// Migrations of scenario documents; entry `i` upgrades version `i + 1` to
+/// `i + 2`.
+pub const MIGRATIONS: &[Migration<Value>] = &[];
+
+/// Format version of scenario files written by this version of ivy.
+pub const FORMAT_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+/// Apply the `migrations` a document written in format `version` needs.
+///
+/// Fails for versions newer than the last one `migrations` lead to, which
+/// were written by a later version of ivy.
+pub fn upgrade<T>(doc: &mut T, version: u64, migrations: &[Migration<T>]) -> Result<()> {
+    let latest = migrations.len() as u64 + 1;
+    if version == 0 {
+        return Err(anyhow!("{} must be 1 or later", VERSION_KEY));
+    }
+    if version > latest {
+        return Err(anyhow!(
+            "{} {} is newer than this version of ivy supports ({}); update ivy to load it",
+            VERSION_KEY,
+            version,
+            latest
+        ));
+    }
+    for migrate in &migrations[version as usize - 1..] {
+        migrate(doc);
+    }
+    Ok(())
+}
+
+/// Read the format version of a scenario document (1 if it has none).
+pub fn document_version(doc: &Value) -> Result<u64> {
+    match doc.get(VERSION_KEY) {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| anyhow!("{} must be a whole number", VERSION_KEY)),
+    }
+}
+
+/// Upgrade a scenario document to [`FORMAT_VERSION`] in place.
+///
+/// Returns the version it was written in.
+pub fn migrate_document(doc: &mut Value) -> Result<u64> {
+    migrate_document_with(doc, MIGRATIONS)
+}
+
+fn migrate_document_with(doc: &mut Value, migrations: &[Migration<Value>]) -> Result<u64> {
+    if !doc.is_mapping() {
+        // Left for the typed parse to report.
+        return Ok(migrations.len() as u64 + 1);
+    }
+    let version = document_version(doc)?;
+    upgrade(doc, version, migrations)?;
+    if (version as usize) <= migrations.len() {
+        doc[VERSION_KEY] = Value::from(migrations.len() as u64 + 1);
+    }
+    Ok(version)
+}
+
+/// A scenario file rewritten in the current format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigratedSource {
+    /// Version the file was written in (1 for files without a version).
+    pub from: u64,
+    pub yaml: String,
+}
+
+/// Rewrite a scenario file in the current format, or `None` if it already
+/// declares it.
+///
+/// Files only missing the version get a `format_version:` line and keep
+/// their formatting and comments. Files whose layout changed are written
+/// out again from the upgraded document, which drops comments.
+pub fn migrate_source(yaml: &str) -> Result<Option<MigratedSource>> {
+    migrate_source_with(yaml, MIGRATIONS)
+}
+
+fn migrate_source_with(
+    yaml: &str,
+    migrations: &[Migration<Value>],
+) -> Result<Option<MigratedSource>> {
+    let mut doc: Value = serde_yaml::from_str(yaml)?;
+    if !doc.is_mapping() {
+        return Err(anyhow!("A scenario file must be a mapping"));
+    }
+    let declared = doc.get(VERSION_KEY).is_some();
+    let from = migrate_document_with(&mut doc, migrations)?;
+    let latest = migrations.len() as u64 + 1;
+    if declared && from == latest {
+        return Ok(None);
+    }
+
+    if from == latest
+        && let Some(stamped) = stamp_version(yaml, latest)
+    {
+        return Ok(Some(MigratedSource {
+            from,
+            yaml: stamped,
+        }));
+    }
+
+    // Write the version first, as `stamp_version` does.
+    let mut ordered = Mapping::new();
+    if let Value::Mapping(map) = doc {
+        ordered.insert(VERSION_KEY.into(), Value::from(latest));
+        ordered.extend(map.into_iter().filter(|(key, _)| key != VERSION_KEY));
+    }
+    Ok(Some(MigratedSource {
+        from,
+        yaml: serde_yaml::to_string(&ordered)?,
+    }))
+}
+
+/// Insert a `format_version:` line before the first key of a block-style
+/// document, after any leading comments.
+///
+/// Returns `None` if the result wouldn't read back with that version.
+fn stamp_version(yaml: &str, version: u64) -> Option<String> {
+    let mut offset = 0;
+    for line in yaml.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if !(trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("---")) {
+            break;
+        }
+        offset += line.len();
+    }
+
+    let stamped = format!(
+        "{}{}: {}\n{}",
+        &yaml[..offset],
+        VERSION_KEY,
+        version,
+        &yaml[offset..]
+    );
+    let doc: Value = serde_yaml::from_str(&stamped).ok()?;
+    (document_version(&doc).ok() == Some(version)).then_some(stamped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Version 2 renames `name` to `title`; version 3 wraps `script` in
+    /// `main`.
+    const TEST_MIGRATIONS: &[Migration<Value>] = &[
+        |doc| {
+            if let Some(map) = doc.as_mapping_mut()
+                && let Some(name) = map.remove("name")
+            {
+                map.insert("title".into(), name);
+            }
+        },
+        |doc| {
+            if let Some(map) = doc.as_mapping_mut()
+                && let Some(script) = map.remove("script")
+            {
+                let mut main = Mapping::new();
+                main.insert("main".into(), script);
+                map.insert("script".into(), Value::Mapping(main));
+            }
+        },
+    ];
+
+    #[test]
+    fn test_migrations_run_in_order() {
+        let mut doc: Value = serde_yaml::from_str("name: Old\nscript: []\n").unwrap();
+        assert_eq!(migrate_document_with(&mut doc, TEST_MIGRATIONS).unwrap(), 1);
+        assert_eq!(doc["title"], "Old");
+        assert!(doc["script"]["main"].is_sequence());
+        assert_eq!(doc[VERSION_KEY], 3);
+
+        // Only the steps after the declared version run
+        let mut doc: Value =
+            serde_yaml::from_str("format_version: 2\nname: Kept\nscript: []\n").unwrap();
+        assert_eq!(migrate_document_with(&mut doc, TEST_MIGRATIONS).unwrap(), 2);
+        assert_eq!(doc["name"], "Kept");
+        assert!(doc["script"]["main"].is_sequence());
+    }
+
+    #[test]
+    fn test_unsupported_versions() {
+        let mut doc: Value = serde_yaml::from_str("format_version: 4\n").unwrap();
+        let err = migrate_document_with(&mut doc, TEST_MIGRATIONS).unwrap_err();
+        assert!(err.to_string().contains("newer"), "{}", err);
+
+        let mut doc: Value = serde_yaml::from_str("format_version: 0\n").unwrap();
+        assert!(migrate_document_with(&mut doc, TEST_MIGRATIONS).is_err());
+
+        let mut doc: Value = serde_yaml::from_str("format_version: two\n").unwrap();
+        assert!(migrate_document_with(&mut doc, TEST_MIGRATIONS).is_err());
+    }
+
+    #[test]
+    fn test_migrate_source() {
+        // Layout changes rewrite the file, version first
+        let migrated = migrate_source_with("name: Old\nscript: []\n", TEST_MIGRATIONS)
+            .unwrap()
+            .unwrap();
+        assert_eq!(migrated.from, 1);
+        assert!(migrated.yaml.starts_with("format_version: 3\n"));
+
+        // Files only missing the version keep their comments
+        let yaml = "# A story\n\ntitle: Test # inline\nscript: []\n";
+        let migrated = migrate_source_with(yaml, &[]).unwrap().unwrap();
+        assert_eq!(
+            migrated.yaml,
+            "# A story\n\nformat_version: 1\ntitle: Test # inline\nscript: []\n"
+        );
+        assert_eq!(migrate_source_with(&migrated.yaml, &[]).unwrap(), None);
+
+        // Flow-style documents are written out again
+        let migrated = migrate_source_with("{title: Test, script: []}", &[])
+            .unwrap()
+            .unwrap();
+        assert!(migrated.yaml.starts_with("format_version: 1\n"));
+    }
+}
@@ -1,18 +1,54 @@
 pub mod easing;
+pub mod export;
+pub mod expr;
+pub mod import;
+pub mod interpolate;
+mod macros;
+pub mod markup;
+pub mod migrate;
 pub mod parser;
 pub mod position;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod screenplay;
+pub mod span;
+pub mod strict;
 pub mod types;
 pub mod validator;
 
 #[allow(unused_imports)]
-pub use parser::{load_scenario, parse_scenario};
+pub use export::{SpokenLine, fountain, html, lines_table, spoken_lines, text_languages};
+#[allow(unused_imports)]
+pub use expr::{Expr, ExprError, VariableLookup, parse_expr};
+#[allow(unused_imports)]
+pub use import::{ImportFormat, Imported, import_scenario, scenario_to_yaml};
+#[allow(unused_imports)]
+pub use interpolate::{format_value, interpolate_variables};
+#[allow(unused_imports)]
+pub use markup::{
+    PaceChange, PaceMark, TextEffect, TextPacing, TextSpan, TextStyle, check_markup,
+    count_visible_chars, parse_markup, strip_markup, text_pacing,
+};
+#[allow(unused_imports)]
+pub use migrate::{FORMAT_VERSION, MigratedSource, migrate_source};
+#[allow(unused_imports)]
+pub use parser::{
+    is_scenario_file, load_scenario, parse_scenario, parse_scenario_source, resolve_includes,
+};
+#[allow(unused_imports)]
+pub use screenplay::{SCREENPLAY_EXTENSION, is_screenplay, parse_screenplay};
+#[allow(unused_imports)]
+pub use span::{FieldSpan, ScenarioSpans, SourceLocation, Span};
 #[allow(unused_imports)]
 pub use types::{
     CameraFocus, CharAnimation, CharAnimationType, CharIdleAnimation, CharIdleType, CharPosition,
-    Choice, Easing, Input, ModularCharDef, Scenario, Shake, ShakeType, TransitionDirection,
-    TransitionType,
+    CharacterDef, CharacterMove, Choice, CommandOrigin, Easing, FILE_LABEL_SEPARATOR, Input,
+    LayerCommand, MacroCommand, MacroDef, ModularCharDef, RandomBranch, RandomCommand, Scenario,
+    Shake, ShakeType, SourceFile, Switch, SwitchCase, TransitionDirection, TransitionType,
+    split_qualified_label,
 };
 #[allow(unused_imports)]
 pub use validator::{
-    Severity, ValidationIssue, ValidationResult, detect_circular_paths, validate_scenario,
+    Severity, ValidationIssue, ValidationResult, describe_command, detect_circular_paths,
+    validate_scenario, validate_scenario_strict,
 };
@@ -1,17 +1,345 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
 use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
 
 use crate::platform;
-use crate::scenario::types::Scenario;
+use crate::scenario::macros::{self, MacroError};
+use crate::scenario::migrate;
+use crate::scenario::screenplay::{self, SCREENPLAY_EXTENSION};
+use crate::scenario::span::ScenarioSpans;
+use crate::scenario::strict::{self, UnknownField};
+use crate::scenario::types::{
+    ChapterDef, CharacterDef, Command, CommandOrigin, MacroDef, ModularCharDef, Scenario,
+    SourceFile,
+};
 
-/// Load a scenario from a YAML file (or localStorage on WASM).
+/// Load a scenario from a YAML or screenplay file (or localStorage on WASM).
+///
+/// Files listed under `includes:` are loaded recursively and their scripts
+/// appended in order, so all files share one label namespace.
 pub fn load_scenario(path: &str) -> Result<Scenario> {
     let content = platform::read_file(path)?;
-    parse_scenario(&content)
+    resolve_includes(parse_scenario_source(path, &content)?, path)
+}
+
+/// Parse a scenario read from `path`, as a screenplay if the path has the
+/// screenplay extension and as YAML otherwise.
+pub fn parse_scenario_source(path: &str, content: &str) -> Result<Scenario> {
+    if screenplay::is_screenplay(path) {
+        screenplay::parse_screenplay(content)
+    } else {
+        parse_scenario(content)
+    }
+}
+
+/// Check whether a path names a scenario file (YAML or screenplay).
+pub fn is_scenario_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "yaml" || ext == "yml" || ext == SCREENPLAY_EXTENSION)
+}
+
+/// Merge the files listed under `includes:` into a scenario read from `path`.
+///
+/// Also records `path` as the scenario's first source file, so that qualified
+/// jumps to the root file resolve.
+pub fn resolve_includes(mut scenario: Scenario, path: &str) -> Result<Scenario> {
+    let base_dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    let root_name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string());
+    scenario.sources = vec![SourceFile {
+        name: root_name.clone(),
+        path: path.to_string(),
+        start: 0,
+        len: scenario.script.len(),
+    }];
+
+    // The merged scenario is self-contained.
+    let includes = std::mem::take(&mut scenario.includes);
+    let mut loaded = HashSet::from([root_name]);
+    for include in &includes {
+        load_include(&mut scenario, base_dir, "", include, &mut loaded)?;
+    }
+
+    Ok(scenario)
+}
+
+/// An included scenario file. Unlike the root file it needs no title.
+#[derive(Default, Deserialize)]
+struct IncludedScenario {
+    /// Read when the file is migrated, before it is parsed.
+    #[serde(default, rename = "format_version")]
+    _format_version: u32,
+    #[serde(default)]
+    includes: Vec<String>,
+    #[serde(default)]
+    chapters: Vec<ChapterDef>,
+    #[serde(default)]
+    characters: HashMap<String, CharacterDef>,
+    #[serde(default)]
+    modular_characters: HashMap<String, ModularCharDef>,
+    #[serde(default)]
+    macros: HashMap<String, MacroDef>,
+    script: Vec<Command>,
+}
+
+/// Load an included file (relative to `parent_dir`) and append it to `scenario`.
+fn load_include(
+    scenario: &mut Scenario,
+    base_dir: &Path,
+    parent_dir: &str,
+    include: &str,
+    loaded: &mut HashSet<String>,
+) -> Result<()> {
+    let name = join_relative(parent_dir, include)?;
+    if !loaded.insert(name.clone()) {
+        return Err(anyhow!(
+            "'{}' is included more than once (or includes itself)",
+            name
+        ));
+    }
+
+    let path = base_dir.join(&name).to_string_lossy().into_owned();
+    let content = platform::read_file(&path)
+        .map_err(|e| anyhow!("Failed to read included file '{}': {}", name, e))?;
+    // Macros of the including files are available to included ones.
+    let parsed: Parsed<IncludedScenario> = if screenplay::is_screenplay(&name) {
+        screenplay::parse_screenplay(&content).map(|part| Parsed {
+            value: IncludedScenario {
+                includes: part.includes,
+                script: part.script,
+                ..IncludedScenario::default()
+            },
+            origins: Vec::new(),
+            unknown_fields: Vec::new(),
+        })
+    } else {
+        parse_with_macros(&content, &scenario.macros)
+    }
+    .map_err(|e| anyhow!("In included file '{}': {}", name, e))?;
+    let part = parsed.value;
+
+    append_origins(scenario, part.script.len(), parsed.origins);
+    let start = scenario.script.len();
+    scenario
+        .unknown_fields
+        .extend(parsed.unknown_fields.into_iter().map(|field| UnknownField {
+            command_index: field.command_index.map(|index| start + index),
+            file: Some(name.clone()),
+            ..field
+        }));
+    scenario.sources.push(SourceFile {
+        name: name.clone(),
+        path,
+        start: scenario.script.len(),
+        len: part.script.len(),
+    });
+    scenario.script.extend(part.script);
+    for (macro_name, def) in part.macros {
+        scenario.macros.entry(macro_name).or_insert(def);
+    }
+    scenario.chapters.extend(part.chapters);
+    for (id, def) in part.characters {
+        scenario.characters.entry(id).or_insert(def);
+    }
+    for (char_name, def) in part.modular_characters {
+        scenario.modular_characters.entry(char_name).or_insert(def);
+    }
+
+    let dir = name.rsplit_once('/').map_or("", |(dir, _)| dir);
+    for nested in &part.includes {
+        load_include(scenario, base_dir, dir, nested, loaded)?;
+    }
+
+    Ok(())
+}
+
+/// Record the origins of `len` commands about to be appended to a scenario.
+///
+/// Origins are only tracked once some file expanded a macro; from then on
+/// every command needs one, so earlier files are filled in one-to-one.
+fn append_origins(scenario: &mut Scenario, len: usize, origins: Vec<CommandOrigin>) {
+    if origins.is_empty() && scenario.origins.is_empty() {
+        return;
+    }
+    if scenario.origins.is_empty() {
+        scenario.origins = (0..scenario.script.len())
+            .map(|index| CommandOrigin {
+                written_index: scenario.written_index(index),
+                expanded_from: None,
+            })
+            .collect();
+    }
+    if origins.is_empty() {
+        scenario
+            .origins
+            .extend((0..len).map(|written_index| CommandOrigin {
+                written_index,
+                expanded_from: None,
+            }));
+    } else {
+        scenario.origins.extend(origins);
+    }
+}
+
+/// Join a relative path onto a `/`-separated directory, resolving `.` and `..`.
+///
+/// Fails if the path leads out of the directory `dir` is relative to.
+fn join_relative(dir: &str, path: &str) -> Result<String> {
+    let mut parts: Vec<&str> = dir.split('/').filter(|p| !p.is_empty()).collect();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if parts.pop().is_none() {
+                    return Err(anyhow!(
+                        "'{}' is outside the directory of the main scenario file",
+                        path
+                    ));
+                }
+            }
+            _ => parts.push(part),
+        }
+    }
+    Ok(parts.join("/"))
 }
 
 /// Parse a scenario from a YAML string.
+///
+/// `- macro:` entries in the script are expanded into the commands of the
+/// macro they name.
 pub fn parse_scenario(yaml: &str) -> Result<Scenario> {
-    serde_yaml::from_str(yaml).map_err(|e| format_yaml_error(yaml, e))
+    let parsed: Parsed<Scenario> = parse_with_macros(yaml, &HashMap::new())?;
+    let mut scenario = parsed.value;
+    scenario.origins = parsed.origins;
+    scenario.unknown_fields = parsed.unknown_fields;
+    Ok(scenario)
+}
+
+/// A parsed scenario document.
+struct Parsed<T> {
+    value: T,
+    /// Origin of every command, or empty if no macro was expanded.
+    origins: Vec<CommandOrigin>,
+    /// Keys that no field has the name of.
+    unknown_fields: Vec<UnknownField>,
+}
+
+/// Parse a scenario document, expanding macro calls in its script.
+///
+/// Documents written in an older format are migrated first. Macros declared
+/// in the document shadow `inherited` ones.
+fn parse_with_macros<T: DeserializeOwned>(
+    yaml: &str,
+    inherited: &HashMap<String, MacroDef>,
+) -> Result<Parsed<T>> {
+    let mut doc: serde_yaml::Value =
+        serde_yaml::from_str(yaml).map_err(|e| format_yaml_error(yaml, e))?;
+    let version = migrate::migrate_document(&mut doc)?;
+    let migrated = version < u64::from(migrate::FORMAT_VERSION);
+
+    let expanded = match macros::read_macros(&doc) {
+        Some(own) => {
+            let mut available = inherited.clone();
+            available.extend(own);
+            macros::expand_script(&mut doc, &available).map_err(|e| format_macro_error(yaml, e))?
+        }
+        None => None,
+    };
+
+    match strict::from_value(doc.clone()) {
+        Ok((value, unknown_fields)) => Ok(Parsed {
+            value,
+            origins: expanded.unwrap_or_default(),
+            unknown_fields,
+        }),
+        Err(e) if migrated => Err(anyhow!(
+            "YAML parse error after migrating from format_version {}: {}",
+            version,
+            e
+        )),
+        Err(e) => match expanded {
+            Some(origins) => Err(expansion_error::<T>(yaml, &doc, &origins, e)),
+            // Parse the text itself so the error carries a location.
+            None => match serde_yaml::from_str::<T>(yaml) {
+                Err(e) => Err(format_yaml_error(yaml, e)),
+                Ok(_) => Err(anyhow!("YAML parse error: {}", e)),
+            },
+        },
+    }
+}
+
+/// Format an error in a macro call, pointing at the call site.
+fn format_macro_error(yaml: &str, err: MacroError) -> anyhow::Error {
+    let spans = ScenarioSpans::parse(yaml);
+    match spans.command(err.written_index) {
+        Some(call) => anyhow!(
+            "Macro call at line {} failed: {}",
+            call.span.start.line + 1,
+            err.message
+        ),
+        None => anyhow!(
+            "Macro error in script command {}: {}",
+            err.written_index + 1,
+            err.message
+        ),
+    }
+}
+
+/// Explain why an expanded script failed to parse, pointing at both the
+/// macro definition and the call site of the offending command.
+fn expansion_error<T: DeserializeOwned>(
+    yaml: &str,
+    doc: &serde_yaml::Value,
+    origins: &[CommandOrigin],
+    err: serde_yaml::Error,
+) -> anyhow::Error {
+    // Errors outside the expanded commands are best reported with the
+    // location serde_yaml finds in the original text.
+    if let Err(e) = serde_yaml::from_str::<T>(yaml) {
+        return format_yaml_error(yaml, e);
+    }
+
+    let script = doc["script"]
+        .as_sequence()
+        .map(Vec::as_slice)
+        .unwrap_or(&[]);
+    let failed = script.iter().enumerate().find_map(|(index, command)| {
+        serde_yaml::from_value::<Command>(command.clone())
+            .err()
+            .map(|e| (index, e))
+    });
+    let Some((index, cause)) = failed else {
+        return anyhow!("YAML parse error: {}", err);
+    };
+    let Some(origin) = origins.get(index) else {
+        return anyhow!("YAML parse error: {}", cause);
+    };
+    let Some(expanded_from) = &origin.expanded_from else {
+        return anyhow!("YAML parse error: {}", cause);
+    };
+
+    let spans = ScenarioSpans::parse(yaml);
+    let definition = spans
+        .macro_command(&expanded_from.name, expanded_from.index)
+        .map(|command| format!(" at line {}", command.span.start.line + 1))
+        .unwrap_or_default();
+    let call_site = spans
+        .command(origin.written_index)
+        .map(|call| format!("line {}", call.span.start.line + 1))
+        .unwrap_or_else(|| format!("script command {}", origin.written_index + 1));
+    anyhow!(
+        "Invalid command {} of macro '{}'{} (called at {}): {}",
+        expanded_from.index + 1,
+        expanded_from.name,
+        definition,
+        call_site,
+        cause
+    )
 }
 
 /// Common error patterns and their helpful hints.
@@ -210,3 +538,27 @@ fn format_yaml_error(yaml: &str, err: serde_yaml::Error) -> anyhow::Error {
         None => anyhow!("YAML parse error: {}", err),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_relative() {
+        assert_eq!(join_relative("", "chapter2.yaml").unwrap(), "chapter2.yaml");
+        assert_eq!(
+            join_relative("chapters", "./intro.yaml").unwrap(),
+            "chapters/intro.yaml"
+        );
+        assert_eq!(
+            join_relative("chapters/a", "../b.yaml").unwrap(),
+            "chapters/b.yaml"
+        );
+        assert_eq!(
+            join_relative("", "chapters\\c.yaml").unwrap(),
+            "chapters/c.yaml"
+        );
+        assert!(join_relative("chapters", "../../shared.yaml").is_err());
+        assert!(join_relative("", "../shared.yaml").is_err());
+    }
+}
@@ -1,9 +1,17 @@
 //! Position utilities for mapping command indices to line numbers.
+//!
+//! Positions of YAML files come from their [`ScenarioSpans`]; screenplays
+//! record theirs as they are compiled.
 
 use std::collections::HashMap;
 
+use crate::platform;
+use crate::scenario::screenplay::{compile_screenplay, is_screenplay};
+use crate::scenario::span::ScenarioSpans;
+use crate::scenario::types::{CommandOrigin, Scenario, SourceFile, split_qualified_label};
+
 /// Line position information for a YAML element.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct LinePosition {
     /// 0-indexed line number.
     pub line: u32,
@@ -25,6 +33,12 @@ pub struct PositionMap {
     pub labels: HashMap<String, LinePosition>,
     /// Label references (jump targets) -> list of line positions.
     pub label_references: HashMap<String, Vec<LinePosition>>,
+    /// Macro name -> line position of its definition.
+    pub macros: HashMap<String, LinePosition>,
+    /// (macro name, command index within the macro) -> line position.
+    pub macro_commands: HashMap<(String, usize), LinePosition>,
+    /// Spans the positions were taken from (empty for screenplays).
+    pub spans: ScenarioSpans,
 }
 
 impl PositionMap {
@@ -33,78 +47,66 @@ impl PositionMap {
             commands: HashMap::new(),
             labels: HashMap::new(),
             label_references: HashMap::new(),
+            macros: HashMap::new(),
+            macro_commands: HashMap::new(),
+            spans: ScenarioSpans::default(),
+        }
+    }
+
+    /// Build a position map for a file read from `path`: a screenplay if the
+    /// path has the screenplay extension, YAML otherwise.
+    ///
+    /// Screenplays that don't compile have no positions.
+    pub fn from_source(path: &str, text: &str) -> Self {
+        if is_screenplay(path) {
+            compile_screenplay(text)
+                .map(|compiled| compiled.positions)
+                .unwrap_or_default()
+        } else {
+            Self::from_yaml(text)
         }
     }
 
     /// Build a position map from YAML text.
     pub fn from_yaml(yaml: &str) -> Self {
-        let mut map = Self::new();
-        let lines: Vec<&str> = yaml.lines().collect();
-
-        let mut in_script = false;
-        let mut command_index = 0;
-
-        for (line_num, line) in lines.iter().enumerate() {
-            let trimmed = line.trim();
-
-            // Detect script section start.
-            if trimmed == "script:" {
-                in_script = true;
-                continue;
-            }
-
-            // Exit script section when encountering another top-level key.
-            if in_script
-                && !line.starts_with(' ')
-                && !line.starts_with('\t')
-                && !trimmed.is_empty()
-                && !trimmed.starts_with('-')
-                && !trimmed.starts_with('#')
-            {
-                in_script = false;
-                continue;
-            }
-
-            if !in_script {
-                continue;
-            }
-
-            // Detect command start (list item).
-            if trimmed.starts_with("- ") || trimmed == "-" {
-                let column = line.find('-').unwrap_or(0) as u32;
-                map.commands
-                    .insert(command_index, LinePosition::new(line_num as u32, column));
-                command_index += 1;
-            }
-
-            // Detect label definition.
-            if let Some(label) = extract_label_definition(trimmed) {
-                let column = line.find("label:").unwrap_or(0) as u32;
-                map.labels.insert(
-                    label.to_string(),
-                    LinePosition::new(line_num as u32, column),
-                );
-            }
+        Self::from_spans(ScenarioSpans::parse(yaml))
+    }
 
-            // Detect jump references.
-            if let Some(target) = extract_jump_target(trimmed) {
-                let column = line.find("jump:").unwrap_or(0) as u32;
-                map.label_references
-                    .entry(target.to_string())
-                    .or_default()
-                    .push(LinePosition::new(line_num as u32, column));
-            }
+    /// Build a position map from the spans of a YAML file.
+    ///
+    /// Commands are at their first key, labels, references and macros at
+    /// their keys.
+    pub fn from_spans(spans: ScenarioSpans) -> Self {
+        let mut map = Self::index(&spans);
+        map.spans = spans;
+        map
+    }
 
-            // Detect choice jump references.
-            if let Some(target) = extract_choice_jump(trimmed) {
-                let column = line.find("jump:").unwrap_or(0) as u32;
-                map.label_references
-                    .entry(target.to_string())
-                    .or_default()
-                    .push(LinePosition::new(line_num as u32, column));
+    /// Positions of the parts of a YAML file, without its spans.
+    fn index(spans: &ScenarioSpans) -> Self {
+        let mut map = Self::new();
+        for (index, command) in spans.commands().iter().enumerate() {
+            map.commands.insert(index, command.span.start);
+        }
+        for label in spans.labels() {
+            map.labels.entry(label.label).or_insert(label.field.start());
+        }
+        for reference in spans.label_references() {
+            map.label_references
+                .entry(reference.label)
+                .or_default()
+                .push(reference.field.start());
+        }
+        for (name, span, def) in spans.macros() {
+            map.macros.insert(name.to_string(), span.start);
+            let commands = def
+                .get("commands")
+                .map_or(&[][..], |commands| commands.items());
+            for (index, command) in commands.iter().enumerate() {
+                map.macro_commands
+                    .insert((name.to_string(), index), command.span.start);
             }
         }
-
         map
     }
 
@@ -122,6 +124,33 @@ impl PositionMap {
     pub fn get_label_references(&self, label: &str) -> Option<&Vec<LinePosition>> {
         self.label_references.get(label)
     }
+
+    /// Get the line position of a macro definition.
+    pub fn get_macro_position(&self, name: &str) -> Option<LinePosition> {
+        self.macros.get(name).copied()
+    }
+
+    /// Get the line position of a command inside a macro definition.
+    pub fn get_macro_command_position(&self, name: &str, index: usize) -> Option<LinePosition> {
+        self.macro_commands.get(&(name.to_string(), index)).copied()
+    }
+
+    /// Get the line position of a command of a parsed scenario, which may
+    /// differ from its index in the file once macros are expanded.
+    ///
+    /// Returns the position of the written entry (the macro call for expanded
+    /// commands) and, for expanded commands, the position within the macro.
+    pub fn locate_command(
+        &self,
+        scenario: &Scenario,
+        index: usize,
+    ) -> (Option<LinePosition>, Option<LinePosition>) {
+        let written = self.get_command_position(scenario.written_index(index));
+        let definition = scenario
+            .macro_origin(index)
+            .and_then(|origin| self.get_macro_command_position(&origin.name, origin.index));
+        (written, definition)
+    }
 }
 
 impl Default for PositionMap {
@@ -130,45 +159,105 @@ impl Default for PositionMap {
     }
 }
 
-/// Extract label name from a line like "label: foo" or "- label: foo".
-fn extract_label_definition(line: &str) -> Option<&str> {
-    let trimmed = line.trim_start_matches("- ").trim();
-    if trimmed.starts_with("label:") {
-        let value = trimmed.strip_prefix("label:")?.trim();
-        // Handle quoted and unquoted values.
-        Some(value.trim_matches(|c| c == '"' || c == '\''))
-    } else {
-        None
-    }
+/// Position maps for every file of a scenario loaded with `includes`.
+///
+/// Command indices are indices into the merged script; positions are
+/// reported together with the name of the file they belong to.
+#[derive(Default)]
+pub struct ProjectPositionMap {
+    files: Vec<(SourceFile, PositionMap)>,
+    origins: Vec<CommandOrigin>,
+    /// Spans of each source file, in the order of the scenario's `sources`.
+    spans: Vec<ScenarioSpans>,
 }
 
-/// Extract jump target from a line like "jump: foo".
-fn extract_jump_target(line: &str) -> Option<&str> {
-    let trimmed = line.trim_start_matches("- ").trim();
-    // Skip if this is inside a choices block (indented jump).
-    if line.starts_with("        ") || line.starts_with("\t\t") {
-        return None;
+impl ProjectPositionMap {
+    pub fn new() -> Self {
+        Self::default()
     }
-    if trimmed.starts_with("jump:") {
-        let value = trimmed.strip_prefix("jump:")?.trim();
-        Some(value.trim_matches(|c| c == '"' || c == '\''))
-    } else {
-        None
+
+    /// Build position maps by reading each source file of a scenario again.
+    ///
+    /// Files that can no longer be read are skipped.
+    pub fn from_scenario(scenario: &Scenario) -> Self {
+        let mut map = Self::new();
+        for source in &scenario.sources {
+            let Ok(content) = platform::read_file(&source.path) else {
+                map.spans.push(ScenarioSpans::default());
+                continue;
+            };
+            if is_screenplay(&source.path) {
+                map.add_file(source.clone(), &content);
+                map.spans.push(ScenarioSpans::default());
+            } else {
+                let spans = ScenarioSpans::parse(&content);
+                map.files.push((source.clone(), PositionMap::index(&spans)));
+                map.spans.push(spans);
+            }
+        }
+        map.origins = scenario.origins.clone();
+        map
+    }
+
+    /// Spans of each source file read by [`ProjectPositionMap::from_scenario`],
+    /// to locate fields with (see [`Scenario::locate_field`]).
+    pub fn spans(&self) -> &[ScenarioSpans] {
+        &self.spans
     }
-}
 
-/// Extract choice jump target from an indented line like "  jump: foo".
-fn extract_choice_jump(line: &str) -> Option<&str> {
-    // Choice jumps are typically more indented.
-    if !line.starts_with("        ") && !line.starts_with("\t\t") {
-        return None;
+    /// Add a source file with its text (YAML or screenplay, by its path).
+    pub fn add_file(&mut self, source: SourceFile, text: &str) {
+        let map = PositionMap::from_source(&source.path, text);
+        self.files.push((source, map));
     }
-    let trimmed = line.trim();
-    if trimmed.starts_with("jump:") {
-        let value = trimmed.strip_prefix("jump:")?.trim();
-        Some(value.trim_matches(|c| c == '"' || c == '\''))
-    } else {
-        None
+
+    /// Get the file name and line position for a merged command index.
+    ///
+    /// Commands expanded from a macro report the position of the macro call.
+    pub fn get_command_position(&self, index: usize) -> Option<(&str, LinePosition)> {
+        self.files.iter().find_map(|(source, map)| {
+            if source.contains(index) {
+                let written = self
+                    .origins
+                    .get(index)
+                    .map_or(index - source.start, |origin| origin.written_index);
+                map.get_command_position(written)
+                    .map(|pos| (source.name.as_str(), pos))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Get the file name and line position of the macro command a merged
+    /// command was expanded from.
+    pub fn get_macro_command_position(&self, index: usize) -> Option<(&str, LinePosition)> {
+        let origin = self.origins.get(index)?.expanded_from.as_ref()?;
+        // Prefer the calling file, whose own macros shadow inherited ones.
+        let calling = self
+            .files
+            .iter()
+            .filter(|(source, _)| source.contains(index));
+        let others = self
+            .files
+            .iter()
+            .filter(|(source, _)| !source.contains(index));
+        calling.chain(others).find_map(|(source, map)| {
+            map.get_macro_command_position(&origin.name, origin.index)
+                .map(|pos| (source.name.as_str(), pos))
+        })
+    }
+
+    /// Get the file name and line position of a label, bare or `file#label`.
+    pub fn get_label_position(&self, label: &str) -> Option<(&str, LinePosition)> {
+        let (file, label) = split_qualified_label(label);
+        self.files
+            .iter()
+            .filter(|(source, _)| file.is_none_or(|file| file == source.name))
+            .find_map(|(source, map)| {
+                map.get_label_position(label)
+                    .map(|pos| (source.name.as_str(), pos))
+            })
     }
 }
 
@@ -206,17 +295,120 @@ script:
     }
 
     #[test]
-    fn test_extract_label_definition() {
-        assert_eq!(extract_label_definition("label: start"), Some("start"));
-        assert_eq!(extract_label_definition("- label: start"), Some("start"));
-        assert_eq!(extract_label_definition("label: \"start\""), Some("start"));
-        assert_eq!(extract_label_definition("text: hello"), None);
+    fn test_branch_references() {
+        let yaml = r#"
+script:
+  - if:
+      var: met
+      is: true
+      jump: friend
+      else: stranger
+  - switch:
+      var: day
+      cases:
+        - is: 1
+          jump: monday
+      default: weekend
+  - text: "Pick"
+    choices:
+      - label: "A"
+        jump: friend
+        default: true
+"#;
+        let map = PositionMap::from_yaml(yaml);
+
+        let line_of = |label| map.get_label_references(label).map(|refs| refs[0].line);
+        assert_eq!(line_of("stranger"), Some(6));
+        assert_eq!(line_of("monday"), Some(11));
+        assert_eq!(line_of("weekend"), Some(12));
+        assert_eq!(line_of("true"), None);
     }
 
     #[test]
-    fn test_extract_jump_target() {
-        assert_eq!(extract_jump_target("jump: ending"), Some("ending"));
-        assert_eq!(extract_jump_target("- jump: ending"), Some("ending"));
-        assert_eq!(extract_jump_target("label: start"), None);
+    fn test_position_map_flow_style_and_multi_line() {
+        let yaml = r#"title: Test
+script:
+  - {label: start, text: "Hello"}
+  - text: |
+      - label: fake
+        jump: fake
+    choices: [{label: "Go", jump: start}]
+  -
+    label: end
+"#;
+        let map = PositionMap::from_yaml(yaml);
+
+        assert_eq!(map.commands.len(), 3);
+        assert_eq!(map.get_command_position(1).unwrap().line, 3);
+        assert_eq!(map.get_command_position(2).unwrap().line, 8);
+        assert_eq!(map.get_label_position("start").unwrap().column, 5);
+        assert!(map.get_label_position("fake").is_none());
+        assert!(map.get_label_references("fake").is_none());
+        let refs = map.get_label_references("start").unwrap();
+        assert_eq!((refs[0].line, refs[0].column), (6, 28));
+    }
+
+    #[test]
+    fn test_project_position_map() {
+        let main = "title: Main\nscript:\n  - label: start\n    jump: chapter2.yaml#intro\n";
+        let chapter2 = "script:\n  - text: \"Filler\"\n  - label: intro\n    text: \"Hi\"\n";
+
+        let mut map = ProjectPositionMap::new();
+        map.add_file(
+            SourceFile {
+                name: "main.yaml".to_string(),
+                path: "main.yaml".to_string(),
+                start: 0,
+                len: 1,
+            },
+            main,
+        );
+        map.add_file(
+            SourceFile {
+                name: "chapter2.yaml".to_string(),
+                path: "chapter2.yaml".to_string(),
+                start: 1,
+                len: 2,
+            },
+            chapter2,
+        );
+
+        let (file, pos) = map.get_command_position(2).unwrap();
+        assert_eq!(file, "chapter2.yaml");
+        assert_eq!(pos.line, 2);
+
+        let (file, pos) = map.get_label_position("chapter2.yaml#intro").unwrap();
+        assert_eq!(file, "chapter2.yaml");
+        assert_eq!(pos.line, 2);
+        assert!(map.get_label_position("main.yaml#intro").is_none());
+        assert!(map.get_command_position(3).is_none());
+    }
+
+    #[test]
+    fn test_position_map_skips_nested_items_and_scans_macros() {
+        let yaml = r#"title: Test
+macros:
+  greet:
+    params: [who]
+    commands:
+      - text: "Hi {who}"
+      - choices:
+          - label: "Wave"
+            jump: wave
+script:
+  - text: "Pick"
+    choices:
+      - label: "A"
+        jump: a
+  - label: a
+    text: "A"
+"#;
+        let map = PositionMap::from_yaml(yaml);
+
+        assert_eq!(map.commands.len(), 2);
+        assert_eq!(map.get_command_position(1).unwrap().line, 14);
+        assert_eq!(map.get_macro_position("greet").unwrap().line, 2);
+        assert_eq!(map.get_macro_command_position("greet", 1).unwrap().line, 6);
+        assert!(map.get_macro_command_position("greet", 2).is_none());
     }
 }
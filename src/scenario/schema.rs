@@ -0,0 +1,30 @@
+//! JSON Schema of the scenario format, generated from the Rust types.
+//!
+//! Field docs become descriptions, enums such as [`TransitionType`] list
+//! their values and `#[serde(default)]` fields carry their default. The
+//! schema is written by `ivy-schema` to [`SCHEMA_PATH`], where a test keeps
+//! it in sync with the types.
+//!
+//! [`TransitionType`]: crate::scenario::TransitionType
+
+use schemars::generate::SchemaSettings;
+
+use crate::scenario::types::Scenario;
+
+/// Where the generated schema is kept in the repository.
+pub const SCHEMA_PATH: &str = "editors/schema/scenario.schema.json";
+
+/// JSON Schema (draft-07, as read by yaml-language-server) of scenario files.
+pub fn scenario_schema() -> serde_json::Value {
+    let mut schema = SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<Scenario>();
+    schema.insert("title".into(), "ivy scenario".into());
+    schema.to_value()
+}
+
+/// [`scenario_schema`] as pretty-printed JSON, as written to [`SCHEMA_PATH`].
+pub fn scenario_schema_json() -> String {
+    let json = serde_json::to_string_pretty(&scenario_schema()).unwrap_or_default();
+    format!("{}\n", json)
+}
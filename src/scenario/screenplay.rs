@@ -0,0 +1,564 @@
+//! Plain-text screenplay format, compiled to a [`Scenario`].
+//!
+//! A lighter alternative to YAML for dialogue-heavy scripts, read from files
+//! with the `.ivys` extension:
+//!
+//! ```text
+//! @title The Forest
+//!
+//! # start
+//! @bg forest.png
+//! @char alice smile left
+//! alice "Where are we?"
+//! "The trees close in around them."
+//! alice (worried) "Which way?"
+//! > Left -> left_path
+//! > Right -> right_path if courage >= 3
+//! ```
+//!
+//! Lines are dialogue (`speaker "text"`, optionally with an `(expression)`),
+//! narration (`"text"` or any other plain line), labels (`# name`), choices
+//! (`> text -> label [if condition]`, added to the line above) or `@`
+//! directives. Directives such as `@bg` apply to the next line; `@jump`,
+//! `@call`, `@return` and `@wait` end the current command. `//` starts a
+//! comment line.
+
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use serde_yaml::{Mapping, Value};
+
+use crate::scenario::position::{LinePosition, PositionMap};
+use crate::scenario::types::{Command, Scenario};
+
+/// File extension of screenplay files.
+pub const SCREENPLAY_EXTENSION: &str = "ivys";
+
+/// Check whether a path names a screenplay file (by its extension).
+pub fn is_screenplay(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext == SCREENPLAY_EXTENSION)
+}
+
+/// A compiled screenplay with the line positions of its commands and labels.
+pub struct CompiledScreenplay {
+    pub scenario: Scenario,
+    pub positions: PositionMap,
+}
+
+/// Parse a scenario from screenplay text.
+pub fn parse_screenplay(text: &str) -> Result<Scenario> {
+    Ok(compile_screenplay(text)?.scenario)
+}
+
+/// Compile screenplay text into a scenario and its position map.
+pub fn compile_screenplay(text: &str) -> Result<CompiledScreenplay> {
+    let mut compiler = Compiler::default();
+    for (line_num, line) in text.lines().enumerate() {
+        compiler
+            .line(line_num, line)
+            .map_err(|e| e.at(line_num, line))?;
+    }
+    compiler
+        .flush()
+        .map_err(|e| e.at(compiler.pending_line, ""))?;
+
+    let mut header = Mapping::new();
+    header.insert("title".into(), compiler.title.unwrap_or_default().into());
+    header.insert("includes".into(), compiler.includes.into());
+    header.insert("script".into(), Value::Sequence(Vec::new()));
+    let mut scenario: Scenario = serde_yaml::from_value(Value::Mapping(header))?;
+    scenario.script = compiler.commands;
+
+    Ok(CompiledScreenplay {
+        scenario,
+        positions: compiler.positions,
+    })
+}
+
+/// An error on a screenplay line, with the column it was found at.
+struct LineError {
+    /// Line of the error, if not the line being read.
+    line: Option<usize>,
+    /// Byte offset of the error in the line.
+    column: usize,
+    message: String,
+}
+
+impl LineError {
+    fn new(column: usize, message: impl Into<String>) -> Self {
+        Self {
+            line: None,
+            column,
+            message: message.into(),
+        }
+    }
+
+    /// Report the error, counting its column in characters of `line`.
+    fn at(self, line_num: usize, line: &str) -> anyhow::Error {
+        anyhow!(
+            "Screenplay error at line {} column {}: {}",
+            self.line.unwrap_or(line_num) + 1,
+            char_column(line, self.column) + 1,
+            self.message
+        )
+    }
+}
+
+/// Count a byte offset into a line in characters, as positions are.
+fn char_column(line: &str, byte: usize) -> usize {
+    line.get(..byte)
+        .map_or(byte, |before| before.chars().count())
+}
+
+/// Directives that end the command they are added to.
+const CLOSING_DIRECTIVES: &[&str] = &["jump", "call", "return", "wait"];
+
+/// Compiler state while reading a screenplay line by line.
+#[derive(Default)]
+struct Compiler {
+    title: Option<String>,
+    includes: Vec<String>,
+    commands: Vec<Command>,
+    positions: PositionMap,
+    /// Fields of the command being built.
+    pending: Mapping,
+    /// Line the pending command started at.
+    pending_line: usize,
+    /// Text of the line being read, whose byte offsets positions are
+    /// recorded from.
+    text: String,
+}
+
+impl Compiler {
+    fn line(&mut self, line_num: usize, line: &str) -> Result<(), LineError> {
+        self.text = line.to_string();
+        let trimmed = line.trim();
+        let indent = line.len() - line.trim_start().len();
+        if trimmed.is_empty() || trimmed.starts_with("//") {
+            return Ok(());
+        }
+
+        if let Some(name) = trimmed.strip_prefix('#') {
+            let name = name.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(LineError::new(
+                    indent,
+                    "A label is '# name', without spaces in the name",
+                ));
+            }
+            if !self.pending.is_empty() {
+                self.flush()?;
+            }
+            self.start(line_num, indent);
+            self.set("label", name.into());
+            let position = self.position(line_num, indent);
+            self.positions.labels.insert(name.to_string(), position);
+            return Ok(());
+        }
+
+        if let Some(choice) = trimmed.strip_prefix('>') {
+            let column = indent + 1;
+            let choice = self.choice(line_num, column, choice)?;
+            if self.pending.is_empty() {
+                self.start(line_num, indent);
+            }
+            let choices = self
+                .pending
+                .entry("choices".into())
+                .or_insert_with(|| Value::Sequence(Vec::new()));
+            if let Value::Sequence(choices) = choices {
+                choices.push(choice);
+            }
+            return Ok(());
+        }
+
+        if let Some(directive) = trimmed.strip_prefix('@') {
+            return self.directive(line_num, indent, directive);
+        }
+
+        // Dialogue or narration
+        let (speaker, expression, text) = split_dialogue(trimmed)
+            .map_err(|(column, message)| LineError::new(indent + column, message))?;
+        if self.has_line() {
+            self.flush()?;
+        }
+        if self.pending.is_empty() {
+            self.start(line_num, indent);
+        }
+        if let Some(speaker) = speaker {
+            self.set("speaker", speaker.into());
+        }
+        if let Some(expression) = expression {
+            self.set("expression", expression.into());
+        }
+        self.set("text", text.into());
+        Ok(())
+    }
+
+    /// Handle an `@` directive (without the `@`).
+    fn directive(
+        &mut self,
+        line_num: usize,
+        indent: usize,
+        directive: &str,
+    ) -> Result<(), LineError> {
+        let (name, args) = directive
+            .split_once(char::is_whitespace)
+            .map_or((directive, ""), |(name, args)| (name, args.trim()));
+        let args_column = indent + 1 + name.len() + 1;
+        let error = |message: String| LineError::new(indent, message);
+
+        let (key, value) = match name {
+            "title" => {
+                self.title = Some(args.to_string());
+                return Ok(());
+            }
+            "include" => {
+                let path = required(args, name).map_err(error)?;
+                self.includes.push(unquote(path));
+                return Ok(());
+            }
+            "bg" => ("background", unquote(args).into()),
+            "char" => ("character", Value::Null),
+            "bgm" => ("bgm", unquote(args).into()),
+            "se" => ("se", unquote(required(args, name).map_err(error)?).into()),
+            "voice" => (
+                "voice",
+                unquote(required(args, name).map_err(error)?).into(),
+            ),
+            "jump" | "call" => {
+                let target = label_arg(args, name).map_err(error)?;
+                self.reference(target, line_num, args_column);
+                (name, target.into())
+            }
+            "return" => ("return", true.into()),
+            "wait" => ("wait", number(args, name).map_err(error)?.into()),
+            "transition" => ("transition", transition(args).map_err(error)?),
+            "nvl" => match args {
+                "on" => ("nvl", true.into()),
+                "off" => ("nvl", false.into()),
+                "clear" => ("nvl_clear", true.into()),
+                _ => return Err(error("'@nvl' takes on, off or clear".to_string())),
+            },
+            "set" => ("set", set_var(args).map_err(error)?),
+            "if" => {
+                let (condition, target, else_target) = if_jump(args).map_err(error)?;
+                let mut cond = Mapping::new();
+                cond.insert("expr".into(), condition.into());
+                cond.insert("jump".into(), target.into());
+                // Targets follow the last arrow
+                let targets = args.rfind("->").map_or(0, |arrow| arrow + 2);
+                let column =
+                    |label: &str| args_column + targets + args[targets..].find(label).unwrap_or(0);
+                self.reference(target, line_num, column(target));
+                if let Some(else_target) = else_target {
+                    cond.insert("else".into(), else_target.into());
+                    let else_column =
+                        args_column + targets + args[targets..].rfind(else_target).unwrap_or(0);
+                    self.reference(else_target, line_num, else_column);
+                }
+                ("if", Value::Mapping(cond))
+            }
+            _ => return Err(error(format!("Unknown directive '@{}'", name))),
+        };
+
+        let closing = CLOSING_DIRECTIVES.contains(&name);
+        // Other directives apply to the next line
+        if self.pending.contains_key(key) || (!closing && self.has_line()) {
+            self.flush()?;
+        }
+        if self.pending.is_empty() {
+            self.start(line_num, indent);
+        }
+
+        if name == "char" {
+            self.character(args);
+        } else {
+            self.set(key, value);
+        }
+        if closing {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// `@char image [expression] [left|center|right]`; no image clears it.
+    fn character(&mut self, args: &str) {
+        let mut words: Vec<&str> = args.split_whitespace().collect();
+        if let Some(&pos) = words.last()
+            && words.len() > 1
+            && matches!(pos, "left" | "center" | "right")
+        {
+            self.set("char_pos", pos.into());
+            words.pop();
+        }
+        self.set("character", words.first().copied().unwrap_or("").into());
+        if let Some(expression) = words.get(1) {
+            self.set("expression", (*expression).into());
+        }
+    }
+
+    /// Parse a choice line after the `>`.
+    fn choice(&mut self, line_num: usize, column: usize, choice: &str) -> Result<Value, LineError> {
+        let Some((label, rest)) = choice.rsplit_once("->") else {
+            return Err(LineError::new(column, "A choice is '> text -> label'"));
+        };
+        let rest_column = column + choice.len() - rest.trim_start().len();
+        let rest = rest.trim_start();
+        let (target, condition) = match rest.split_once(char::is_whitespace) {
+            Some((target, condition)) => {
+                let Some(condition) = condition.trim().strip_prefix("if ") else {
+                    return Err(LineError::new(
+                        rest_column + target.len(),
+                        "Only 'if condition' may follow a choice's label",
+                    ));
+                };
+                (target, Some(condition.trim()))
+            }
+            None => (rest.trim(), None),
+        };
+        let label = unquote(label.trim());
+        if label.is_empty() || target.is_empty() {
+            return Err(LineError::new(column, "A choice is '> text -> label'"));
+        }
+        self.reference(target, line_num, rest_column);
+
+        let mut value = Mapping::new();
+        value.insert("label".into(), label.into());
+        value.insert("jump".into(), target.into());
+        if let Some(condition) = condition {
+            value.insert("if".into(), condition.into());
+        }
+        Ok(Value::Mapping(value))
+    }
+
+    /// Whether the pending command already shows a line or choices.
+    fn has_line(&self) -> bool {
+        self.pending.contains_key("text") || self.pending.contains_key("choices")
+    }
+
+    /// Position of a byte offset into the line being read.
+    fn position(&self, line_num: usize, column: usize) -> LinePosition {
+        LinePosition::new(line_num as u32, char_column(&self.text, column) as u32)
+    }
+
+    /// Start a command at a line.
+    fn start(&mut self, line_num: usize, indent: usize) {
+        self.pending_line = line_num;
+        let position = self.position(line_num, indent);
+        self.positions
+            .commands
+            .insert(self.commands.len(), position);
+    }
+
+    fn set(&mut self, key: &str, value: Value) {
+        self.pending.insert(key.into(), value);
+    }
+
+    /// Record a label reference.
+    fn reference(&mut self, label: &str, line_num: usize, column: usize) {
+        let position = self.position(line_num, column);
+        self.positions
+            .label_references
+            .entry(label.to_string())
+            .or_default()
+            .push(position);
+    }
+
+    /// Finish the pending command.
+    fn flush(&mut self) -> Result<(), LineError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let fields = std::mem::take(&mut self.pending);
+        let command = serde_yaml::from_value(Value::Mapping(fields)).map_err(|e| LineError {
+            line: Some(self.pending_line),
+            ..LineError::new(0, e.to_string())
+        })?;
+        self.commands.push(command);
+        Ok(())
+    }
+}
+
+/// Speaker, expression and text of a dialogue line.
+type Dialogue<'a> = (Option<&'a str>, Option<&'a str>, String);
+
+/// Split a dialogue line into speaker, expression and text.
+///
+/// `speaker (expression) "text"`, `"text"` and unquoted narration are
+/// accepted. Errors carry the column they were found at.
+fn split_dialogue(line: &str) -> Result<Dialogue<'_>, (usize, String)> {
+    if line.starts_with('"') {
+        return quoted(line, 0).map(|text| (None, None, text));
+    }
+    let Some(quote) = line.find('"') else {
+        return Ok((None, None, line.to_string()));
+    };
+    let head = line[..quote].trim_end();
+    let (speaker, expression) = match head.split_once(char::is_whitespace) {
+        Some((speaker, expression)) => (speaker, Some(expression.trim())),
+        None => (head, None),
+    };
+    if !speaker
+        .chars()
+        .all(|c| c.is_alphanumeric() || "_-.".contains(c))
+    {
+        // Narration that happens to contain quotes
+        return Ok((None, None, line.to_string()));
+    }
+    let expression = match expression {
+        Some(expression) => match expression
+            .strip_prefix('(')
+            .and_then(|e| e.strip_suffix(')'))
+        {
+            Some(expression) if !expression.trim().is_empty() => Some(expression.trim()),
+            _ => return Ok((None, None, line.to_string())),
+        },
+        None => None,
+    };
+    let text = quoted(&line[quote..], quote)?;
+    Ok((Some(speaker), expression, text))
+}
+
+/// Read a `"..."` string that ends the line, with `\"` and `\\` escapes.
+fn quoted(text: &str, column: usize) -> Result<String, (usize, String)> {
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .filter(|inner| !inner.ends_with('\\') || inner.ends_with("\\\\"))
+        .ok_or((column, "Unterminated quoted text".to_string()))?;
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => result.push('\n'),
+                Some(escaped) => result.push(escaped),
+                None => result.push('\\'),
+            },
+            c => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+/// Strip optional quotes around a choice label or path.
+fn unquote(text: &str) -> String {
+    quoted(text, 0).unwrap_or_else(|_| text.to_string())
+}
+
+fn required<'a>(args: &'a str, name: &str) -> Result<&'a str, String> {
+    if args.is_empty() {
+        Err(format!("'@{}' needs a value", name))
+    } else {
+        Ok(args)
+    }
+}
+
+fn label_arg<'a>(args: &'a str, name: &str) -> Result<&'a str, String> {
+    let label = required(args, name)?;
+    if label.contains(char::is_whitespace) {
+        return Err(format!("'@{}' takes a single label", name));
+    }
+    Ok(label)
+}
+
+fn number(args: &str, name: &str) -> Result<f64, String> {
+    required(args, name)?
+        .parse()
+        .map_err(|_| format!("'@{}' needs a number of seconds", name))
+}
+
+/// `@transition type [duration]`.
+fn transition(args: &str) -> Result<Value, String> {
+    let mut words = args.split_whitespace();
+    let kind = words
+        .next()
+        .ok_or_else(|| "'@transition' needs a type".to_string())?;
+    let mut value = Mapping::new();
+    value.insert("type".into(), kind.into());
+    if let Some(duration) = words.next() {
+        value.insert("duration".into(), number(duration, "transition")?.into());
+    }
+    Ok(Value::Mapping(value))
+}
+
+/// `@set name = expr`, or `+=`, `-=`, `*=` to update the current value.
+fn set_var(args: &str) -> Result<Value, String> {
+    let usage = || "A set is '@set name = expression' (or +=, -=, *=)".to_string();
+    let (lhs, expr) = args.split_once('=').ok_or_else(usage)?;
+    let lhs = lhs.trim();
+    let (name, op) = match lhs.char_indices().last() {
+        Some((index, '+')) => (&lhs[..index], Some("add")),
+        Some((index, '-')) => (&lhs[..index], Some("sub")),
+        Some((index, '*')) => (&lhs[..index], Some("mul")),
+        _ => (lhs, None),
+    };
+    let (name, expr) = (name.trim(), expr.trim());
+    if name.is_empty() || expr.is_empty() || name.contains(char::is_whitespace) {
+        return Err(usage());
+    }
+    let mut value = Mapping::new();
+    value.insert("name".into(), name.into());
+    value.insert("expr".into(), expr.into());
+    if let Some(op) = op {
+        value.insert("op".into(), op.into());
+    }
+    Ok(Value::Mapping(value))
+}
+
+/// `@if condition -> label [else label]`.
+fn if_jump(args: &str) -> Result<(&str, &str, Option<&str>), String> {
+    let usage = || "A conditional jump is '@if condition -> label [else label]'".to_string();
+    let (condition, targets) = args.rsplit_once("->").ok_or_else(usage)?;
+    let mut words = targets.split_whitespace();
+    let target = words.next().ok_or_else(usage)?;
+    let else_target = match (words.next(), words.next(), words.next()) {
+        (None, _, _) => None,
+        (Some("else"), Some(label), None) => Some(label),
+        _ => return Err(usage()),
+    };
+    let condition = condition.trim();
+    if condition.is_empty() {
+        return Err(usage());
+    }
+    Ok((condition, target, else_target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_dialogue() {
+        assert_eq!(
+            split_dialogue(r#"alice "Hello""#).unwrap(),
+            (Some("alice"), None, "Hello".to_string())
+        );
+        assert_eq!(
+            split_dialogue(r#"alice (smile) "Say \"hi\"""#).unwrap(),
+            (Some("alice"), Some("smile"), "Say \"hi\"".to_string())
+        );
+        assert_eq!(
+            split_dialogue(r#""Quiet.""#).unwrap(),
+            (None, None, "Quiet.".to_string())
+        );
+        assert_eq!(
+            split_dialogue(r#"She said "no" twice."#).unwrap(),
+            (None, None, r#"She said "no" twice."#.to_string())
+        );
+        assert_eq!(split_dialogue(r#"bob "Oops"#).unwrap_err().0, 4);
+    }
+
+    #[test]
+    fn test_set_var() {
+        let value = set_var("gold += 5").unwrap();
+        assert_eq!(value["name"], "gold");
+        assert_eq!(value["op"], "add");
+        assert_eq!(value["expr"], "5");
+        assert_eq!(set_var("flag = a == b").unwrap()["expr"], "a == b");
+        assert!(set_var("gold").is_err());
+    }
+}
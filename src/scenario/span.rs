@@ -0,0 +1,869 @@
+//! Source spans of YAML scenario files.
+//!
+//! Scenario files are read a second time with an event parser into a tree
+//! recording where every node starts and ends. It sees flow-style
+//! collections, multi-line strings and nested lists as the scenario parser
+//! does, so commands, fields and labels can be pointed at precisely.
+//!
+//! Spans aren't kept with a parsed [`Scenario`]: tools that report
+//! positions read them when they need them, with [`ScenarioSpans::load`].
+
+use std::collections::HashMap;
+use std::fmt;
+
+use yaml_rust2::parser::{Event as YamlEvent, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+
+use crate::platform;
+use crate::scenario::position::LinePosition;
+use crate::scenario::screenplay::is_screenplay;
+use crate::scenario::types::{Scenario, SourceFile};
+
+/// A range of source text, from the first character of a node to just past
+/// its last.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: LinePosition,
+    pub end: LinePosition,
+}
+
+impl Span {
+    pub fn new(start: LinePosition, end: LinePosition) -> Self {
+        Self { start, end }
+    }
+
+    /// Check whether a position is inside the span (or just past its end).
+    pub fn contains(&self, pos: LinePosition) -> bool {
+        self.start <= pos && pos <= self.end
+    }
+}
+
+/// A YAML node with its span.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub span: Span,
+    pub kind: NodeKind,
+}
+
+/// Contents of a node. Scalars keep their text; aliases keep the span of the
+/// node they name rather than a copy of it, so that nested aliases can't
+/// blow up the tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    Scalar(String),
+    Sequence(Vec<Node>),
+    Mapping(Vec<(Node, Node)>),
+    Alias(Span),
+}
+
+/// Where a field was written: its key (none for list items) and its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSpan {
+    pub key: Option<Span>,
+    pub value: Span,
+}
+
+impl FieldSpan {
+    /// Position of the key, or of the value for list items.
+    pub fn start(&self) -> LinePosition {
+        self.key.unwrap_or(self.value).start
+    }
+
+    /// Span of the key and its value, or of the key alone if the value
+    /// continues on later lines.
+    pub fn span(&self) -> Span {
+        match self.key {
+            Some(key) if key.start.line != self.value.end.line => key,
+            Some(key) => Span::new(key.start, self.value.end),
+            None => self.value,
+        }
+    }
+}
+
+impl Node {
+    /// Text of a scalar node.
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            NodeKind::Scalar(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Items of a sequence node (none for other nodes).
+    pub fn items(&self) -> &[Node] {
+        match &self.kind {
+            NodeKind::Sequence(items) => items,
+            _ => &[],
+        }
+    }
+
+    /// Entries of a mapping node (none for other nodes).
+    pub fn entries(&self) -> &[(Node, Node)] {
+        match &self.kind {
+            NodeKind::Mapping(entries) => entries,
+            _ => &[],
+        }
+    }
+
+    /// Key and value nodes of a mapping entry.
+    pub fn entry(&self, key: &str) -> Option<(&Node, &Node)> {
+        self.entries()
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(k, v)| (k, v))
+    }
+
+    /// Value node of a mapping entry.
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        self.entry(key).map(|(_, value)| value)
+    }
+
+    /// Find a field below this node by path, such as `transition.duration`
+    /// or `choices[1].jump`. An empty path is the node itself.
+    pub fn field(&self, path: &str) -> Option<FieldSpan> {
+        let mut node = self;
+        let mut field = FieldSpan {
+            key: None,
+            value: self.span,
+        };
+        for segment in path.split('.').filter(|segment| !segment.is_empty()) {
+            let name_end = segment.find('[').unwrap_or(segment.len());
+            let (name, mut indices) = segment.split_at(name_end);
+            if !name.is_empty() {
+                let (key, value) = node.entry(name)?;
+                field = FieldSpan {
+                    key: Some(key.span),
+                    value: value.span,
+                };
+                node = value;
+            }
+            while let Some(rest) = indices.strip_prefix('[') {
+                let (index, rest) = rest.split_once(']')?;
+                node = node.items().get(index.parse::<usize>().ok()?)?;
+                field = FieldSpan {
+                    key: None,
+                    value: node.span,
+                };
+                indices = rest;
+            }
+        }
+        Some(field)
+    }
+}
+
+/// A YAML syntax error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub message: String,
+    pub position: LinePosition,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {} column {}",
+            self.message,
+            self.position.line + 1,
+            self.position.column + 1
+        )
+    }
+}
+
+/// The first document of a YAML text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Document {
+    /// Nodes read before the end of the document or the first syntax error.
+    pub root: Option<Node>,
+    pub error: Option<SyntaxError>,
+}
+
+/// Parse the first document of a YAML text into a tree of nodes with spans.
+///
+/// A syntax error ends the tree where it occurs; the nodes read before it
+/// are kept, so a file being edited still has spans up to the error.
+pub fn parse_document(yaml: &str) -> Document {
+    let mut tree = TreeBuilder::default();
+    let error = read_events(yaml, |event, span| tree.push(event, span)).err();
+    Document {
+        root: tree.finish(),
+        error,
+    }
+}
+
+/// A parser event, with anchors and styles where they matter for spans.
+enum Event {
+    Scalar {
+        value: String,
+        anchor: Option<usize>,
+    },
+    /// A value left out, such as `key:` with nothing after it.
+    EmptyScalar {
+        anchor: Option<usize>,
+    },
+    Alias(usize),
+    SequenceStart {
+        anchor: Option<usize>,
+        flow: bool,
+    },
+    SequenceEnd,
+    MappingStart {
+        anchor: Option<usize>,
+        flow: bool,
+    },
+    MappingEnd,
+    /// Stream and document starts.
+    Other,
+}
+
+/// Read the events of the first document of a YAML text, up to its end or
+/// the first syntax error.
+///
+/// The parser only gives where each event starts, so the ends of scalars
+/// and flow collections are found in the text from there.
+fn read_events(yaml: &str, mut emit: impl FnMut(Event, Span)) -> Result<(), SyntaxError> {
+    let source = Source::new(yaml);
+    let mut parser = Parser::new_from_str(yaml);
+    loop {
+        let (event, mark) = parser.next_token().map_err(|e| SyntaxError {
+            message: e.info().to_string(),
+            position: source.position(*e.marker()),
+        })?;
+        let start = source.position(mark);
+        let at_start = Span::new(start, start);
+        let anchor = |id: usize| (id != 0).then_some(id);
+        let (event, span) = match event {
+            YamlEvent::Scalar(value, style, id, _)
+                if value.is_empty() && style == TScalarStyle::Plain =>
+            {
+                (Event::EmptyScalar { anchor: anchor(id) }, at_start)
+            }
+            YamlEvent::Scalar(value, style, id, _) => {
+                let end = source.scalar_end(mark, &value, style);
+                (
+                    Event::Scalar {
+                        value,
+                        anchor: anchor(id),
+                    },
+                    Span::new(start, end),
+                )
+            }
+            YamlEvent::Alias(id) => (Event::Alias(id), Span::new(start, source.token_end(mark))),
+            YamlEvent::SequenceStart(id, _) => (
+                Event::SequenceStart {
+                    anchor: anchor(id),
+                    flow: source.char_at(mark) == Some('['),
+                },
+                at_start,
+            ),
+            YamlEvent::SequenceEnd => (Event::SequenceEnd, source.closing_bracket(mark, ']')),
+            YamlEvent::MappingStart(id, _) => (
+                Event::MappingStart {
+                    anchor: anchor(id),
+                    flow: source.char_at(mark) == Some('{'),
+                },
+                at_start,
+            ),
+            YamlEvent::MappingEnd => (Event::MappingEnd, source.closing_bracket(mark, '}')),
+            // The end of the first document, or of an empty stream
+            YamlEvent::DocumentEnd | YamlEvent::StreamEnd => return Ok(()),
+            YamlEvent::StreamStart | YamlEvent::DocumentStart | YamlEvent::Nothing => {
+                (Event::Other, at_start)
+            }
+        };
+        emit(event, span);
+    }
+}
+
+/// The text being parsed, indexed by character as the parser's marks are.
+struct Source {
+    chars: Vec<char>,
+}
+
+impl Source {
+    fn new(yaml: &str) -> Self {
+        Self {
+            chars: yaml.chars().collect(),
+        }
+    }
+
+    /// Position of a parser mark (whose lines count from 1).
+    fn position(&self, mark: Marker) -> LinePosition {
+        LinePosition::new(mark.line().saturating_sub(1) as u32, mark.col() as u32)
+    }
+
+    fn char_at(&self, mark: Marker) -> Option<char> {
+        self.chars.get(mark.index()).copied()
+    }
+
+    /// Span of a closing bracket at a mark, or an empty span if the
+    /// collection is a block one, which has no bracket.
+    fn closing_bracket(&self, mark: Marker, bracket: char) -> Span {
+        let start = self.position(mark);
+        let end = match self.char_at(mark) {
+            Some(c) if c == bracket => LinePosition::new(start.line, start.column + 1),
+            _ => start,
+        };
+        Span::new(start, end)
+    }
+
+    /// End of a token without spaces starting at a mark, such as an alias.
+    fn token_end(&self, mark: Marker) -> LinePosition {
+        let start = self.position(mark);
+        let length = self.chars[mark.index().min(self.chars.len())..]
+            .iter()
+            .take_while(|c| !c.is_whitespace() && !matches!(c, ',' | ']' | '}'))
+            .count();
+        LinePosition::new(start.line, start.column + length as u32)
+    }
+
+    /// End of a scalar starting at a mark: past its closing quote, or past
+    /// the last character of its value as written.
+    fn scalar_end(&self, mark: Marker, value: &str, style: TScalarStyle) -> LinePosition {
+        let mut pos = self.position(mark);
+        let mut end = pos;
+        let mut chars = self.chars[mark.index().min(self.chars.len())..].iter();
+        let advance = |pos: &mut LinePosition, c: char| {
+            if c == '\n' {
+                *pos = LinePosition::new(pos.line + 1, 0);
+            } else {
+                pos.column += 1;
+            }
+        };
+        match style {
+            TScalarStyle::SingleQuoted | TScalarStyle::DoubleQuoted => {
+                let quote = if style == TScalarStyle::SingleQuoted {
+                    '\''
+                } else {
+                    '"'
+                };
+                // The opening quote
+                if let Some(&c) = chars.next() {
+                    advance(&mut pos, c);
+                }
+                let mut chars = chars.peekable();
+                while let Some(&c) = chars.next() {
+                    advance(&mut pos, c);
+                    match c {
+                        '\\' if quote == '"' => {
+                            if let Some(&escaped) = chars.next() {
+                                advance(&mut pos, escaped);
+                            }
+                        }
+                        '\'' if quote == '\'' && chars.peek() == Some(&&'\'') => {
+                            chars.next();
+                            advance(&mut pos, '\'');
+                        }
+                        c if c == quote => return pos,
+                        _ => {}
+                    }
+                }
+                pos
+            }
+            // Plain and block scalars are their value as written, except
+            // for the indentation and line breaks folded away
+            _ => {
+                let mut value = value.chars().peekable();
+                let mut chars = chars.peekable();
+                while let (Some(&expected), Some(&&c)) = (value.peek(), chars.peek()) {
+                    if c == expected {
+                        value.next();
+                        chars.next();
+                        advance(&mut pos, c);
+                        if !c.is_whitespace() {
+                            end = pos;
+                        }
+                    } else if c.is_whitespace() {
+                        chars.next();
+                        advance(&mut pos, c);
+                    } else if expected.is_whitespace() {
+                        value.next();
+                    } else {
+                        break;
+                    }
+                }
+                end
+            }
+        }
+    }
+}
+
+/// A collection whose end hasn't been read yet.
+struct Open {
+    start: LinePosition,
+    anchor: Option<usize>,
+    /// Flow collections start and end at their brackets; block collections
+    /// at their first and last items.
+    flow: bool,
+    contents: OpenContents,
+}
+
+enum OpenContents {
+    Sequence(Vec<Node>),
+    /// Entries, and the key of the entry whose value is being read.
+    Mapping(Vec<(Node, Node)>, Option<Node>),
+}
+
+impl Open {
+    fn close(self, end: Option<LinePosition>) -> Node {
+        let first_start = match &self.contents {
+            OpenContents::Sequence(items) => items.first().map(|item| item.span.start),
+            OpenContents::Mapping(entries, key) => entries
+                .first()
+                .map(|(key, _)| key)
+                .or(key.as_ref())
+                .map(|key| key.span.start),
+        };
+        let start = match first_start {
+            Some(first_start) if !self.flow => first_start.min(self.start),
+            _ => self.start,
+        };
+        let last_end = match &self.contents {
+            OpenContents::Sequence(items) => items.last().map(|item| item.span.end),
+            OpenContents::Mapping(entries, _) => entries.last().map(|(_, value)| value.span.end),
+        };
+        let end = match end {
+            Some(end) if self.flow => end,
+            _ => last_end.unwrap_or(start),
+        };
+        let kind = match self.contents {
+            OpenContents::Sequence(items) => NodeKind::Sequence(items),
+            OpenContents::Mapping(entries, _) => NodeKind::Mapping(entries),
+        };
+        Node {
+            span: Span::new(start, end),
+            kind,
+        }
+    }
+}
+
+/// Builds a node tree from parser events.
+#[derive(Default)]
+struct TreeBuilder {
+    open: Vec<Open>,
+    /// Spans of the anchored nodes, by anchor id.
+    anchors: HashMap<usize, Span>,
+    root: Option<Node>,
+}
+
+impl TreeBuilder {
+    fn push(&mut self, event: Event, span: Span) {
+        match event {
+            Event::Scalar { value, anchor } => {
+                let node = Node {
+                    span,
+                    kind: NodeKind::Scalar(value),
+                };
+                self.add(node, anchor);
+            }
+            Event::EmptyScalar { anchor } => {
+                // Placed just past the key it's the value of, if any
+                let at = match self.open.last().map(|open| &open.contents) {
+                    Some(OpenContents::Mapping(_, Some(key))) => key.span.end,
+                    _ => span.start,
+                };
+                let node = Node {
+                    span: Span::new(at, at),
+                    kind: NodeKind::Scalar(String::new()),
+                };
+                self.add(node, anchor);
+            }
+            Event::Alias(id) => {
+                if let Some(&target) = self.anchors.get(&id) {
+                    let node = Node {
+                        span,
+                        kind: NodeKind::Alias(target),
+                    };
+                    self.add(node, None);
+                }
+            }
+            Event::SequenceStart { anchor, flow } => self.open.push(Open {
+                start: span.start,
+                anchor,
+                flow,
+                contents: OpenContents::Sequence(Vec::new()),
+            }),
+            Event::MappingStart { anchor, flow } => self.open.push(Open {
+                start: span.start,
+                anchor,
+                flow,
+                contents: OpenContents::Mapping(Vec::new(), None),
+            }),
+            Event::SequenceEnd | Event::MappingEnd => {
+                if let Some(mut open) = self.open.pop() {
+                    let anchor = open.anchor.take();
+                    self.add(open.close(Some(span.end)), anchor);
+                }
+            }
+            Event::Other => {}
+        }
+    }
+
+    /// Add a finished node to the collection being read.
+    fn add(&mut self, node: Node, anchor: Option<usize>) {
+        if let Some(anchor) = anchor {
+            self.anchors.insert(anchor, node.span);
+        }
+        match self.open.last_mut().map(|open| &mut open.contents) {
+            None => {
+                self.root.get_or_insert(node);
+            }
+            Some(OpenContents::Sequence(items)) => items.push(node),
+            Some(OpenContents::Mapping(entries, key)) => match key.take() {
+                Some(key) => entries.push((key, node)),
+                None => *key = Some(node),
+            },
+        }
+    }
+
+    /// Close the collections left open by a syntax error and return the root.
+    fn finish(mut self) -> Option<Node> {
+        while let Some(open) = self.open.pop() {
+            self.add(open.close(None), None);
+        }
+        self.root
+    }
+}
+
+/// A label defined or referenced by a command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelSpan {
+    pub label: String,
+    /// Index of the command's entry in the file's `script:` list.
+    pub command: usize,
+    pub field: FieldSpan,
+}
+
+/// Spans of a scenario file: its script entries, macros and labels.
+///
+/// Written indices count the entries of the file's `script:` list, where a
+/// macro call is one entry.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScenarioSpans {
+    root: Option<Node>,
+}
+
+impl ScenarioSpans {
+    /// Parse the spans of a YAML scenario file. Files with a syntax error
+    /// have spans up to the error.
+    pub fn parse(yaml: &str) -> Self {
+        Self {
+            root: parse_document(yaml).root,
+        }
+    }
+
+    /// Read the spans of a source file from disk. Screenplays and files that
+    /// can no longer be read have none.
+    pub fn read(source: &SourceFile) -> Self {
+        if is_screenplay(&source.path) {
+            return Self::default();
+        }
+        platform::read_file(&source.path)
+            .map(|yaml| Self::parse(&yaml))
+            .unwrap_or_default()
+    }
+
+    /// Read the spans of every source file of a scenario, in the order of
+    /// its `sources`. Empty for scenarios parsed from a string, whose spans
+    /// are those of the string.
+    pub fn load(scenario: &Scenario) -> Vec<Self> {
+        scenario.sources.iter().map(Self::read).collect()
+    }
+
+    /// The root node, unless the file is empty (or not YAML).
+    pub fn root(&self) -> Option<&Node> {
+        self.root.as_ref()
+    }
+
+    /// Find a top-level field by path, such as `chapters[0].start_label`.
+    pub fn field(&self, path: &str) -> Option<FieldSpan> {
+        self.root.as_ref()?.field(path)
+    }
+
+    /// The entries of the `script:` list.
+    pub fn commands(&self) -> &[Node] {
+        self.root
+            .as_ref()
+            .and_then(|root| root.get("script"))
+            .map_or(&[], Node::items)
+    }
+
+    /// The script entry at a written index.
+    pub fn command(&self, written_index: usize) -> Option<&Node> {
+        self.commands().get(written_index)
+    }
+
+    /// Find a field of a script entry by path (empty for the entry itself).
+    pub fn command_field(&self, written_index: usize, path: &str) -> Option<FieldSpan> {
+        self.command(written_index)?.field(path)
+    }
+
+    /// The macros declared under `macros:`, with the span of their names.
+    pub fn macros(&self) -> impl Iterator<Item = (&str, Span, &Node)> {
+        self.root
+            .as_ref()
+            .and_then(|root| root.get("macros"))
+            .map_or(&[][..], Node::entries)
+            .iter()
+            .filter_map(|(name, def)| Some((name.as_str()?, name.span, def)))
+    }
+
+    /// The command at an index of a macro's `commands:`.
+    pub fn macro_command(&self, name: &str, index: usize) -> Option<&Node> {
+        let (_, _, def) = self.macros().find(|(macro_name, ..)| *macro_name == name)?;
+        def.get("commands")?.items().get(index)
+    }
+
+    /// Find a field of a macro command by path (empty for the command itself).
+    pub fn macro_field(&self, name: &str, index: usize, path: &str) -> Option<FieldSpan> {
+        self.macro_command(name, index)?.field(path)
+    }
+
+    /// The labels defined by script entries.
+    pub fn labels(&self) -> Vec<LabelSpan> {
+        let mut labels = Vec::new();
+        for (command, node) in self.commands().iter().enumerate() {
+            if let Some((key, value)) = node.entry("label")
+                && let Some(label) = value.as_str()
+            {
+                labels.push(LabelSpan {
+                    label: label.to_string(),
+                    command,
+                    field: FieldSpan {
+                        key: Some(key.span),
+                        value: value.span,
+                    },
+                });
+            }
+        }
+        labels
+    }
+
+    /// The labels script entries jump to or call, in the order written.
+    pub fn label_references(&self) -> Vec<LabelSpan> {
+        let mut references = Vec::new();
+        for (command, node) in self.commands().iter().enumerate() {
+            for (key, value) in label_fields(node) {
+                if let Some(label) = value.as_str() {
+                    references.push(LabelSpan {
+                        label: label.to_string(),
+                        command,
+                        field: FieldSpan {
+                            key: Some(key.span),
+                            value: value.span,
+                        },
+                    });
+                }
+            }
+        }
+        references
+    }
+
+    /// Find the field of a script entry that defines or references a label.
+    pub fn label_field(&self, written_index: usize, label: &str) -> Option<FieldSpan> {
+        find_label_field(self.command(written_index)?, label)
+    }
+
+    /// Find the field of a macro command that defines or references a label.
+    pub fn macro_label_field(&self, name: &str, index: usize, label: &str) -> Option<FieldSpan> {
+        find_label_field(self.macro_command(name, index)?, label)
+    }
+
+    /// Find the label defined or referenced at a position.
+    pub fn label_at(&self, pos: LinePosition) -> Option<LabelSpan> {
+        self.labels()
+            .into_iter()
+            .chain(self.label_references())
+            .find(|label| Span::new(label.field.start(), label.field.value.end).contains(pos))
+    }
+}
+
+/// Find the `label` entry or jump or call target of a command naming a label.
+fn find_label_field(command: &Node, label: &str) -> Option<FieldSpan> {
+    command
+        .entry("label")
+        .into_iter()
+        .chain(label_fields(command))
+        .find(|(_, value)| value.as_str() == Some(label))
+        .map(|(key, value)| FieldSpan {
+            key: Some(key.span),
+            value: value.span,
+        })
+}
+
+/// The entries of a command whose values are jump or call targets.
+fn label_fields(command: &Node) -> Vec<(&Node, &Node)> {
+    let mut fields: Vec<(&Node, &Node)> = Vec::new();
+    fields.extend(command.entry("jump"));
+    fields.extend(command.entry("call"));
+    for choice in command.get("choices").map_or(&[][..], Node::items) {
+        fields.extend(choice.entry("jump"));
+    }
+    if let Some(if_cond) = command.get("if") {
+        fields.extend(if_cond.entry("jump"));
+        fields.extend(if_cond.entry("else"));
+    }
+    if let Some(switch) = command.get("switch") {
+        for case in switch.get("cases").map_or(&[][..], Node::items) {
+            fields.extend(case.entry("jump"));
+        }
+        fields.extend(switch.entry("default"));
+    }
+    if let Some(random) = command.get("random") {
+        for branch in random.get("branches").map_or(&[][..], Node::items) {
+            fields.extend(branch.entry("jump"));
+        }
+    }
+    fields
+}
+
+/// Where a field of a scenario was written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLocation {
+    /// File the field is in, for scenarios loaded from a file.
+    pub file: Option<String>,
+    pub field: FieldSpan,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let start = self.field.start();
+        match &self.file {
+            Some(file) => write!(f, "{}:{}:{}", file, start.line + 1, start.column + 1),
+            None => write!(f, "line {}, column {}", start.line + 1, start.column + 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(line: u32, column: u32) -> LinePosition {
+        LinePosition::new(line, column)
+    }
+
+    #[test]
+    fn test_block_and_flow_spans() {
+        let yaml = "script:\n  - text: \"Hello\"\n    jump: end\n  - {label: end, text: Bye}\n";
+        let spans = ScenarioSpans::parse(yaml);
+
+        let first = spans.command(0).unwrap();
+        assert_eq!(first.span, Span::new(at(1, 4), at(2, 13)));
+        let second = spans.command(1).unwrap();
+        assert_eq!(second.span, Span::new(at(3, 4), at(3, 27)));
+
+        let jump = spans.command_field(0, "jump").unwrap();
+        assert_eq!(jump.key, Some(Span::new(at(2, 4), at(2, 8))));
+        assert_eq!(jump.value, Span::new(at(2, 10), at(2, 13)));
+        assert_eq!(
+            spans.command_field(1, "label").unwrap().value,
+            Span::new(at(3, 12), at(3, 15))
+        );
+    }
+
+    #[test]
+    fn test_multi_line_values_and_nested_paths() {
+        let yaml = r#"script:
+  - text: |
+      label: not a label
+      jump: nowhere
+    choices:
+      - label: "Go"
+        jump: go
+      - { label: "Stay", jump: stay }
+  - label: go
+"#;
+        let spans = ScenarioSpans::parse(yaml);
+
+        assert_eq!(spans.commands().len(), 2);
+        let labels: Vec<String> = spans.labels().into_iter().map(|l| l.label).collect();
+        assert_eq!(labels, ["go"]);
+        let references: Vec<(String, u32)> = spans
+            .label_references()
+            .into_iter()
+            .map(|r| (r.label, r.field.start().line))
+            .collect();
+        assert_eq!(references, [("go".to_string(), 6), ("stay".to_string(), 7)]);
+
+        let stay = spans.command_field(0, "choices[1].jump").unwrap();
+        assert_eq!(stay.start(), at(7, 25));
+        assert_eq!(spans.command_field(0, "choices[1]").unwrap().key, None);
+        assert!(spans.command_field(0, "choices[2]").is_none());
+        assert_eq!(
+            spans.label_at(at(7, 33)).map(|label| label.label),
+            Some("stay".to_string())
+        );
+    }
+
+    #[test]
+    fn test_syntax_error_keeps_earlier_nodes() {
+        let yaml = "script:\n  - label: start\n  - text: [unclosed\n";
+        let document = parse_document(yaml);
+
+        let error = document.error.unwrap();
+        assert_eq!(error.position.line, 3);
+        let root = document.root.unwrap();
+        let commands = root.get("script").unwrap().items();
+        assert_eq!(commands[0].get("label").unwrap().as_str(), Some("start"));
+    }
+
+    #[test]
+    fn test_aliases_and_macros() {
+        let yaml = r#"macros:
+  greet:
+    commands:
+      - text: &hello "Hello"
+      - text: *hello
+script:
+  - macro: greet
+"#;
+        let spans = ScenarioSpans::parse(yaml);
+
+        let (name, span, _) = spans.macros().next().unwrap();
+        assert_eq!((name, span.start), ("greet", at(1, 2)));
+        let alias = spans.macro_command("greet", 1).unwrap();
+        assert_eq!(
+            alias.get("text").unwrap().kind,
+            NodeKind::Alias(Span::new(at(3, 21), at(3, 28)))
+        );
+        assert_eq!(
+            spans.macro_field("greet", 1, "text").unwrap().value,
+            Span::new(at(4, 14), at(4, 20))
+        );
+    }
+
+    #[test]
+    fn test_nested_aliases_are_not_expanded() {
+        let mut yaml = "a0: &a0 [x, x]\n".to_string();
+        for level in 1..40 {
+            yaml.push_str(&format!(
+                "a{level}: &a{level} [*a{prev}, *a{prev}]\n",
+                prev = level - 1
+            ));
+        }
+        let root = parse_document(&yaml).root.unwrap();
+
+        let last = root.get("a39").unwrap();
+        assert_eq!(last.items().len(), 2);
+        assert_eq!(
+            last.items()[0].kind,
+            NodeKind::Alias(root.get("a38").unwrap().span)
+        );
+    }
+
+    #[test]
+    fn test_scalar_spans() {
+        let yaml = "a: 'it''s'\nb: \"say \\\"hi\\\"\"\nc: plain # note\nd: folded\n  on\ne:\nf: |\n  one\n  two\n";
+        let root = parse_document(yaml).root.unwrap();
+        let span = |key: &str| root.get(key).unwrap().span;
+
+        assert_eq!(span("a"), Span::new(at(0, 3), at(0, 10)));
+        assert_eq!(span("b"), Span::new(at(1, 3), at(1, 15)));
+        assert_eq!(span("c"), Span::new(at(2, 3), at(2, 8)));
+        assert_eq!(span("d"), Span::new(at(3, 3), at(4, 4)));
+        assert_eq!(span("e"), Span::new(at(5, 1), at(5, 1)));
+        assert_eq!(span("f"), Span::new(at(7, 2), at(8, 5)));
+        assert_eq!(root.span, Span::new(at(0, 0), at(8, 5)));
+    }
+}
@@ -0,0 +1,427 @@
+//! Detection of keys the scenario types don't know, for strict mode.
+//!
+//! Serde skips unknown keys, so a typo such as `charcter:` silently does
+//! nothing. [`from_value`] deserializes a document through a wrapper that
+//! compares the keys of every struct with the fields serde asks for, and
+//! suggests the closest real field for each unknown one.
+
+use std::cell::RefCell;
+use std::fmt;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde_yaml::{Error, Mapping, Value};
+
+/// A key no field of its struct is named after.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownField {
+    /// Index of the command in the (expanded) script, for keys inside one.
+    pub command_index: Option<usize>,
+    /// Where the key is: relative to its command for keys inside one (such
+    /// as `choices[1]`), from the top of the file otherwise (such as
+    /// `characters.alice`). Empty for keys of the command or file itself.
+    pub path: String,
+    pub key: String,
+    /// Closest field name of the struct, if any is close.
+    pub suggestion: Option<String>,
+    /// File the key is in, for keys in included files.
+    pub file: Option<String>,
+}
+
+impl UnknownField {
+    /// Path of the key itself, such as `choices[1].defualt`.
+    pub fn field_path(&self) -> String {
+        if self.path.is_empty() {
+            self.key.clone()
+        } else {
+            format!("{}.{}", self.path, self.key)
+        }
+    }
+}
+
+impl fmt::Display for UnknownField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown field '{}'", self.key)?;
+        if !self.path.is_empty() {
+            write!(f, " in '{}'", self.path)?;
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean '{}'?)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Deserialize a document, collecting the keys that were ignored because no
+/// field has their name.
+pub fn from_value<T: DeserializeOwned>(doc: Value) -> Result<(T, Vec<UnknownField>), Error> {
+    let found = RefCell::new(Vec::new());
+    let parsed = T::deserialize(Strict {
+        value: doc,
+        path: Vec::new(),
+        found: &found,
+    })?;
+    Ok((parsed, found.into_inner()))
+}
+
+/// Find the field closest to an unknown key, allowing roughly one typo per
+/// three characters.
+pub fn suggest_field<'a>(key: &str, fields: &[&'a str]) -> Option<&'a str> {
+    let key = key.to_lowercase();
+    let max_distance = (key.chars().count() / 3).max(1);
+    fields
+        .iter()
+        .map(|field| (edit_distance(&key, field), *field))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, field)| field)
+}
+
+/// Levenshtein distance, counting a swap of adjacent characters as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Rows for the previous two prefixes of `a` and the current one.
+    let mut before: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut previous, current);
+    }
+    previous[b.len()]
+}
+
+/// A step of the path to a value.
+#[derive(Debug, Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Record the unknown keys of a struct found at `path`.
+fn record_unknown(
+    map: &Mapping,
+    fields: &'static [&'static str],
+    path: &[Segment],
+    found: &RefCell<Vec<UnknownField>>,
+) {
+    for key in map.keys().filter_map(Value::as_str) {
+        if fields.contains(&key) {
+            continue;
+        }
+        // Keys inside the script are reported relative to their command.
+        let (command_index, rest) = match path {
+            [Segment::Key(script), Segment::Index(index), rest @ ..] if script == "script" => {
+                (Some(*index), rest)
+            }
+            _ => (None, path),
+        };
+        let mut location = String::new();
+        for segment in rest {
+            match segment {
+                Segment::Key(key) if location.is_empty() => location.push_str(key),
+                Segment::Key(key) => {
+                    location.push('.');
+                    location.push_str(key);
+                }
+                Segment::Index(index) => location.push_str(&format!("[{}]", index)),
+            }
+        }
+        found.borrow_mut().push(UnknownField {
+            command_index,
+            path: location,
+            key: key.to_string(),
+            suggestion: suggest_field(key, fields).map(str::to_string),
+            file: None,
+        });
+    }
+}
+
+/// A value being deserialized, with where it is in the document.
+struct Strict<'a> {
+    value: Value,
+    path: Vec<Segment>,
+    found: &'a RefCell<Vec<UnknownField>>,
+}
+
+/// Deserialize primitives straight from the YAML value.
+macro_rules! forward_to_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                self.value.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Strict<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Sequence(items) => visitor.visit_seq(StrictSeq {
+                items: items.into_iter().enumerate(),
+                path: self.path,
+                found: self.found,
+            }),
+            Value::Mapping(map) => visitor.visit_map(StrictMap {
+                entries: map.into_iter(),
+                value: None,
+                path: self.path,
+                found: self.found,
+            }),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match &self.value {
+            Value::Mapping(map) => {
+                record_unknown(map, fields, &self.path, self.found);
+                self.deserialize_any(visitor)
+            }
+            _ => self.value.deserialize_struct(name, fields, visitor),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Sequence(_) => self.deserialize_any(visitor),
+            value => value.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Sequence(_) => self.deserialize_any(visitor),
+            value => value.deserialize_tuple(len, visitor),
+        }
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Sequence(_) => self.deserialize_any(visitor),
+            value => value.deserialize_tuple_struct(name, len, visitor),
+        }
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Mapping(_) => self.deserialize_any(visitor),
+            value => value.deserialize_map(visitor),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.value.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.value.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_unit
+        deserialize_identifier
+    }
+}
+
+struct StrictSeq<'a> {
+    items: std::iter::Enumerate<std::vec::IntoIter<Value>>,
+    path: Vec<Segment>,
+    found: &'a RefCell<Vec<UnknownField>>,
+}
+
+impl<'de> SeqAccess<'de> for StrictSeq<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        let Some((index, value)) = self.items.next() else {
+            return Ok(None);
+        };
+        let mut path = self.path.clone();
+        path.push(Segment::Index(index));
+        seed.deserialize(Strict {
+            value,
+            path,
+            found: self.found,
+        })
+        .map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct StrictMap<'a> {
+    entries: serde_yaml::mapping::IntoIter,
+    /// Value of the key last returned, with the key.
+    value: Option<(String, Value)>,
+    path: Vec<Segment>,
+    found: &'a RefCell<Vec<UnknownField>>,
+}
+
+impl<'de> MapAccess<'de> for StrictMap<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        let name = match &key {
+            Value::String(name) => name.clone(),
+            other => serde_yaml::to_string(other)
+                .map(|name| name.trim_end().to_string())
+                .unwrap_or_default(),
+        };
+        self.value = Some((name, value));
+        seed.deserialize(key).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (key, value) = self
+            .value
+            .take()
+            .ok_or_else(|| <Error as de::Error>::custom("value is missing"))?;
+        let mut path = self.path.clone();
+        path.push(Segment::Key(key));
+        seed.deserialize(Strict {
+            value,
+            path,
+            found: self.found,
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::types::Scenario;
+
+    #[test]
+    fn test_suggest_field() {
+        let fields = ["character", "char_pos", "choices", "background"];
+        assert_eq!(suggest_field("charcter", &fields), Some("character"));
+        assert_eq!(suggest_field("chioces", &fields), Some("choices"));
+        assert_eq!(suggest_field("Background", &fields), Some("background"));
+        assert_eq!(suggest_field("music", &fields), None);
+    }
+
+    #[test]
+    fn test_unknown_fields_are_located() {
+        let yaml = r##"
+title: Test
+titel: Typo
+characters:
+  alice:
+    name: Alice
+    colr: "#ff0000"
+script:
+  - text: Hello
+    charcter: alice.png
+  - text: Pick
+    choices:
+      - label: A
+        jump: end
+        jupm: end
+  - label: end
+    transition:
+      tpye: fade
+"##;
+        let doc: Value = serde_yaml::from_str(yaml).unwrap();
+        let (scenario, unknown): (Scenario, _) = from_value(doc).unwrap();
+        assert_eq!(scenario.script.len(), 3);
+
+        let found: Vec<(Option<usize>, String)> = unknown
+            .iter()
+            .map(|field| (field.command_index, field.to_string()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    None,
+                    "Unknown field 'titel' (did you mean 'title'?)".to_string()
+                ),
+                (
+                    None,
+                    "Unknown field 'colr' in 'characters.alice' (did you mean 'color'?)"
+                        .to_string()
+                ),
+                (
+                    Some(0),
+                    "Unknown field 'charcter' (did you mean 'character'?)".to_string()
+                ),
+                (
+                    Some(1),
+                    "Unknown field 'jupm' in 'choices[0]' (did you mean 'jump'?)".to_string()
+                ),
+                (
+                    Some(2),
+                    "Unknown field 'tpye' in 'transition' (did you mean 'type'?)".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -3,10 +3,17 @@ use serde::{Deserialize, Serialize};
 use crate::i18n::LocalizedString;
 
 pub use super::easing::Easing;
+use super::expr::{
+    BinaryOp, Expr, ExprError, VariableLookup, apply_binary, call_function, parse_expr,
+    values_equal,
+};
+use super::span::{FieldSpan, ScenarioSpans, SourceLocation};
+use crate::types::Value;
 
 /// Character sprite position on screen.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum CharPosition {
     Left,
@@ -15,9 +22,21 @@ pub enum CharPosition {
     Right,
 }
 
+impl CharPosition {
+    /// Horizontal centre of this preset, as a fraction of the screen width.
+    pub fn anchor_x(self) -> f32 {
+        match self {
+            CharPosition::Left => 0.2,
+            CharPosition::Center => 0.5,
+            CharPosition::Right => 0.8,
+        }
+    }
+}
+
 /// Transition effect type.
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum TransitionType {
     #[default]
@@ -40,6 +59,7 @@ pub enum TransitionType {
 /// Direction for directional transitions.
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum TransitionDirection {
     /// Left to right (default for Wipe).
@@ -72,6 +92,7 @@ pub enum TransitionDirection {
 /// Transition configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Transition {
     #[serde(rename = "type", default)]
     pub transition_type: TransitionType,
@@ -102,17 +123,190 @@ fn default_duration() -> f32 {
     0.5
 }
 
+/// Operation applied by a `set` command to the variable's current value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SetOp {
+    /// Replace the current value.
+    #[default]
+    Assign,
+    /// Add to an integer.
+    Add,
+    /// Subtract from an integer.
+    Sub,
+    /// Multiply an integer.
+    Mul,
+    /// Keep the smaller of the current value and the operand (upper clamp).
+    Min,
+    /// Keep the larger of the current value and the operand (lower clamp).
+    Max,
+    /// Append to a string.
+    Concat,
+    /// Append an item to a list.
+    Push,
+    /// Remove an item from a list (every equal element) or a key from a map.
+    Remove,
+}
+
 /// Variable assignment command.
+///
+/// The new value is `value`, the result of `expr`, or, when `op` is set,
+/// the current value combined with that operand.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SetVar {
     pub name: String,
-    pub value: crate::types::Value,
+    /// Literal value (or operand for `op`).
+    pub value: Option<crate::types::Value>,
+    /// Expression evaluated against the current variables (e.g. `gold - price`).
+    pub expr: Option<String>,
+    /// Operation to apply to the current value.
+    #[serde(default)]
+    pub op: SetOp,
+}
+
+impl SetVar {
+    /// Check the command is well-formed without evaluating it.
+    pub fn check(&self) -> Result<(), ExprError> {
+        match (&self.expr, &self.value) {
+            (Some(source), None) => parse_expr(source).map(|_| ()),
+            (None, Some(_)) => Ok(()),
+            (Some(_), Some(_)) => Err(ExprError::new(
+                "set accepts either 'value' or 'expr', not both",
+                None,
+            )),
+            (None, None) => Err(ExprError::new("set needs either 'value' or 'expr'", None)),
+        }
+    }
+
+    /// Compute the value to assign, type-checked against the current value.
+    ///
+    /// Literal assignments are unrestricted; computed results (`op` or `expr`)
+    /// must keep the type of an existing variable.
+    pub fn evaluate(&self, vars: &impl VariableLookup) -> Result<Value, ExprError> {
+        self.check()?;
+        let operand = match (&self.expr, &self.value) {
+            (Some(source), _) => parse_expr(source)?.eval(vars)?,
+            (None, Some(value)) => value.clone(),
+            (None, None) => unreachable!("rejected by check"),
+        };
+
+        if self.op == SetOp::Assign && self.expr.is_none() {
+            return Ok(operand);
+        }
+
+        let result = match self.op {
+            SetOp::Assign => operand,
+            op => {
+                let current = vars.lookup(&self.name).ok_or_else(|| {
+                    ExprError::new(format!("undefined variable '{}'", self.name), None)
+                })?;
+                match op {
+                    SetOp::Add => apply_binary(BinaryOp::Add, current, &operand)?,
+                    SetOp::Sub => apply_binary(BinaryOp::Sub, current, &operand)?,
+                    SetOp::Mul => apply_binary(BinaryOp::Mul, current, &operand)?,
+                    SetOp::Min => call_function("min", &[current.clone(), operand])?,
+                    SetOp::Max => call_function("max", &[current.clone(), operand])?,
+                    SetOp::Concat => match (current, &operand) {
+                        (Value::String(a), Value::String(b)) => {
+                            Value::String(format!("{}{}", a, b))
+                        }
+                        _ => {
+                            return Err(ExprError::new(
+                                format!(
+                                    "cannot concat {} to {}",
+                                    operand.type_name(),
+                                    current.type_name()
+                                ),
+                                None,
+                            ));
+                        }
+                    },
+                    SetOp::Push => match current {
+                        Value::List(items) => {
+                            let mut items = items.clone();
+                            items.push(operand);
+                            Value::List(items)
+                        }
+                        _ => {
+                            return Err(ExprError::new(
+                                format!("cannot push to {}", current.type_name()),
+                                None,
+                            ));
+                        }
+                    },
+                    SetOp::Remove => match (current, &operand) {
+                        (Value::List(items), _) => {
+                            let items = items.iter().filter(|x| !values_equal(x, &operand));
+                            Value::List(items.cloned().collect())
+                        }
+                        (Value::Map(map), Value::String(key)) => {
+                            let mut map = map.clone();
+                            map.remove(key);
+                            Value::Map(map)
+                        }
+                        _ => {
+                            return Err(ExprError::new(
+                                format!(
+                                    "cannot remove {} from {}",
+                                    operand.type_name(),
+                                    current.type_name()
+                                ),
+                                None,
+                            ));
+                        }
+                    },
+                    SetOp::Assign => unreachable!("handled above"),
+                }
+            }
+        };
+
+        if let Some(current) = vars.lookup(&self.name)
+            && std::mem::discriminant(current) != std::mem::discriminant(&result)
+        {
+            return Err(ExprError::new(
+                format!(
+                    "type mismatch: '{}' is {} but the result is {}",
+                    self.name,
+                    current.type_name(),
+                    result.type_name()
+                ),
+                None,
+            ));
+        }
+
+        Ok(result)
+    }
+
+    /// Short human-readable description, e.g. `gold -= 10`.
+    pub fn describe(&self) -> String {
+        let operand = match (&self.expr, &self.value) {
+            (Some(source), _) => source.clone(),
+            (None, Some(Value::String(s))) => format!("{:?}", s),
+            (None, Some(value)) => value.to_string(),
+            (None, None) => String::new(),
+        };
+        let name = &self.name;
+        match self.op {
+            SetOp::Assign => format!("{} = {}", name, operand),
+            SetOp::Add | SetOp::Concat => format!("{} += {}", name, operand),
+            SetOp::Sub => format!("{} -= {}", name, operand),
+            SetOp::Mul => format!("{} *= {}", name, operand),
+            SetOp::Min => format!("{} = min({}, {})", name, name, operand),
+            SetOp::Max => format!("{} = max({}, {})", name, name, operand),
+            SetOp::Push => format!("push {} to {}", operand, name),
+            SetOp::Remove => format!("remove {} from {}", operand, name),
+        }
+    }
 }
 
 /// Text input command for player input.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Input {
     /// Variable name to store the input.
     pub var: String,
@@ -125,26 +319,216 @@ pub struct Input {
 }
 
 /// Conditional jump command.
+///
+/// The condition is either `var`/`is` (equality check) or an `expr`
+/// such as `affection_alice > affection_bob and met_alice`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct IfCondition {
     /// Variable name to check.
-    pub var: String,
+    pub var: Option<String>,
     /// Expected value.
-    pub is: crate::types::Value,
+    pub is: Option<crate::types::Value>,
+    /// Condition expression (see `scenario::expr` for the grammar).
+    pub expr: Option<String>,
     /// Label to jump to if condition is true.
     pub jump: String,
+    /// Label to jump to if condition is false (falls through when unset).
+    #[serde(default, rename = "else", skip_serializing_if = "Option::is_none")]
+    pub else_jump: Option<String>,
+}
+
+impl IfCondition {
+    /// Parse the condition into an expression (`var`/`is` becomes `var == is`).
+    pub fn condition(&self) -> Result<Expr, ExprError> {
+        if let Some(source) = &self.expr {
+            return parse_expr(source);
+        }
+        match (&self.var, &self.is) {
+            (Some(var), Some(is)) => Ok(Expr::Binary {
+                op: BinaryOp::Eq,
+                lhs: Box::new(Expr::Var(var.clone())),
+                rhs: Box::new(Expr::Literal(is.clone())),
+            }),
+            _ => Err(ExprError::new(
+                "condition needs either 'expr' or both 'var' and 'is'".to_string(),
+                None,
+            )),
+        }
+    }
+
+    /// Short human-readable description of the condition.
+    pub fn describe(&self) -> String {
+        match (&self.expr, &self.var, &self.is) {
+            (Some(source), _, _) => source.clone(),
+            (None, Some(var), Some(is)) => format!("{} == {:?}", var, is),
+            (None, Some(var), None) => var.clone(),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Multi-way jump on the value of a variable or expression.
+///
+/// Cases are tried in order; the first match wins. When nothing matches the
+/// `default` label is used, or execution falls through if there is none.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Switch {
+    /// Variable to switch on.
+    pub var: Option<String>,
+    /// Expression to switch on (instead of `var`).
+    pub expr: Option<String>,
+    /// Cases tried in order.
+    #[serde(default)]
+    pub cases: Vec<SwitchCase>,
+    /// Label to jump to if no case matches.
+    pub default: Option<String>,
+}
+
+/// One arm of a `switch:`, matching an exact value or an inclusive range.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SwitchCase {
+    /// Exact value to match.
+    pub is: Option<crate::types::Value>,
+    /// Lower bound of a numeric range (inclusive).
+    pub min: Option<crate::types::Value>,
+    /// Upper bound of a numeric range (inclusive).
+    pub max: Option<crate::types::Value>,
+    /// Label to jump to if this case matches.
+    pub jump: String,
+}
+
+impl Switch {
+    /// Parse the value being switched on into an expression.
+    pub fn subject(&self) -> Result<Expr, ExprError> {
+        match (&self.var, &self.expr) {
+            (Some(var), None) => Ok(Expr::Var(var.clone())),
+            (None, Some(source)) => parse_expr(source),
+            _ => Err(ExprError::new(
+                "switch needs exactly one of 'var' or 'expr'".to_string(),
+                None,
+            )),
+        }
+    }
+
+    /// Pick the jump target for the current variables.
+    ///
+    /// An undefined `var` selects the default. Returns `None` when nothing
+    /// matches and there is no default.
+    pub fn select(&self, vars: &impl VariableLookup) -> Result<Option<&str>, ExprError> {
+        let value = match (&self.var, &self.expr) {
+            (Some(var), None) => match vars.lookup(var) {
+                Some(value) => value.clone(),
+                None => return Ok(self.default.as_deref()),
+            },
+            _ => self.subject()?.eval(vars)?,
+        };
+
+        for case in &self.cases {
+            if case.matches(&value)? {
+                return Ok(Some(&case.jump));
+            }
+        }
+        Ok(self.default.as_deref())
+    }
+
+    /// Short human-readable description of the value being switched on.
+    pub fn describe(&self) -> String {
+        self.expr
+            .clone()
+            .or_else(|| self.var.clone())
+            .unwrap_or_default()
+    }
+
+    /// All labels this switch can jump to (cases, then default).
+    pub fn targets(&self) -> impl Iterator<Item = &str> {
+        self.cases
+            .iter()
+            .map(|case| case.jump.as_str())
+            .chain(self.default.as_deref())
+    }
+}
+
+impl SwitchCase {
+    /// Check whether the case is well-formed (`is`, or a `min`/`max` range).
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.is, &self.min, &self.max) {
+            (Some(_), None, None) => Ok(()),
+            (None, Some(_), _) | (None, _, Some(_)) => Ok(()),
+            (None, None, None) => Err("case needs 'is', 'min' or 'max'".to_string()),
+            (Some(_), _, _) => Err("case can't combine 'is' with 'min'/'max'".to_string()),
+        }
+    }
+
+    /// Check whether the value matches this case.
+    pub fn matches(&self, value: &crate::types::Value) -> Result<bool, ExprError> {
+        let check = |op, bound: &crate::types::Value| {
+            apply_binary(op, value, bound).map(|result| result == crate::types::Value::Bool(true))
+        };
+        if let Some(is) = &self.is {
+            return check(BinaryOp::Eq, is);
+        }
+        if self.min.is_none() && self.max.is_none() {
+            return Ok(false);
+        }
+        if let Some(min) = &self.min
+            && !check(BinaryOp::Ge, min)?
+        {
+            return Ok(false);
+        }
+        if let Some(max) = &self.max
+            && !check(BinaryOp::Le, max)?
+        {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Short human-readable description of the case (`== 1`, `10..20`).
+    pub fn describe(&self) -> String {
+        match (&self.is, &self.min, &self.max) {
+            (Some(is), _, _) => format!("== {}", is),
+            (None, Some(min), Some(max)) => format!("{}..{}", min, max),
+            (None, Some(min), None) => format!(">= {}", min),
+            (None, None, Some(max)) => format!("<= {}", max),
+            (None, None, None) => String::new(),
+        }
+    }
 }
 
 /// Character display configuration for multiple characters.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CharacterDisplay {
-    /// Character image path.
+    /// Character image path, or the id of a character under `characters:`.
     pub image: String,
-    /// Position on screen.
+    /// Expression of the character named by `image`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
+    /// Position on screen (a preset for `x`).
     #[serde(default)]
     pub pos: CharPosition,
+    /// Horizontal centre as a fraction of the screen width, overriding `pos`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<f32>,
+    /// Bottom edge as a fraction of the screen height (1.0 = screen bottom).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<f32>,
+    /// Size relative to the default sprite height.
+    #[serde(default = "default_char_scale")]
+    pub scale: f32,
+    /// Mirror the sprite horizontally.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub flip: bool,
+    /// Drawing order; higher is drawn in front, ties keep list order.
+    #[serde(default)]
+    pub z: i32,
     /// Enter animation (optional).
     pub enter: Option<CharAnimation>,
     /// Exit animation (optional).
@@ -153,9 +537,14 @@ pub struct CharacterDisplay {
     pub idle: Option<CharIdleAnimation>,
 }
 
+fn default_char_scale() -> f32 {
+    1.0
+}
+
 /// Character animation type.
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum CharAnimationType {
     /// No animation (instant).
@@ -172,6 +561,7 @@ pub enum CharAnimationType {
 /// Character enter/exit animation configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CharAnimation {
     #[serde(rename = "type", default)]
     pub animation_type: CharAnimationType,
@@ -188,6 +578,7 @@ fn default_char_animation_duration() -> f32 {
 /// Character idle animation type (looping animations).
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum CharIdleType {
     /// No idle animation.
@@ -206,6 +597,7 @@ pub enum CharIdleType {
 /// Character idle animation configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CharIdleAnimation {
     #[serde(rename = "type", default)]
     pub idle_type: CharIdleType,
@@ -230,6 +622,7 @@ fn default_char_idle_intensity() -> f32 {
 /// Shake effect type.
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum ShakeType {
     /// Horizontal shake (left-right).
@@ -244,6 +637,7 @@ pub enum ShakeType {
 /// Shake effect configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Shake {
     /// Shake type (horizontal, vertical, both).
     #[serde(rename = "type", default)]
@@ -269,6 +663,7 @@ fn default_shake_duration() -> f32 {
 /// Camera focus point.
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum CameraFocus {
     /// Center of screen (default).
@@ -295,6 +690,7 @@ pub enum CameraFocus {
 /// Camera pan offset.
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CameraPan {
     /// Horizontal offset in pixels.
     #[serde(default)]
@@ -307,6 +703,7 @@ pub struct CameraPan {
 /// Camera command for dynamic camera effects.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CameraCommand {
     /// Pan offset (horizontal and vertical movement).
     pub pan: Option<CameraPan>,
@@ -329,9 +726,72 @@ fn default_camera_duration() -> f32 {
     0.5
 }
 
+/// Tween of an on-screen character's placement, opacity and tint.
+///
+/// Omitted properties keep their current value. The end result stays until
+/// the character is replaced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CharacterMove {
+    /// Character to move: an index into `characters`, an image path or a
+    /// character id. Omit to move the single `character`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Horizontal centre as a fraction of the screen width.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<f32>,
+    /// Bottom edge as a fraction of the screen height.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<f32>,
+    /// Size relative to the default sprite height.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f32>,
+    /// Opacity (0.0 = invisible, 1.0 = opaque).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opacity: Option<f32>,
+    /// Colour multiplied into the sprite, as a color name or `#rrggbb`
+    /// (`white` for none).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tint: Option<String>,
+    /// Animation duration in seconds.
+    #[serde(default = "default_move_duration")]
+    pub duration: f32,
+    /// Easing function for animation.
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+fn default_move_duration() -> f32 {
+    0.5
+}
+
+/// Show, change or hide a named image layer (event CG, overlay, foreground).
+///
+/// A layer stays on screen until a later command with the same `name` hides
+/// it with an empty `image`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LayerCommand {
+    /// Layer name.
+    pub name: String,
+    /// Image path, drawn over the whole screen (empty = hide the layer).
+    pub image: String,
+    /// Drawing order (None = keep the layer's current order, or 0). Layers
+    /// below 0 are drawn behind the characters, the rest in front of them;
+    /// higher values are drawn on top.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub z: Option<i32>,
+    /// Show/hide transition (`fade`, `slide_left` or `slide_right`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transition: Option<CharAnimation>,
+}
+
 /// Video playback command.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct VideoCommand {
     /// Video file path.
     pub path: String,
@@ -360,6 +820,7 @@ fn default_video_bgm_fade() -> f32 {
 /// Ambient audio track for layered soundscapes.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AmbientTrack {
     /// Unique identifier for this ambient track (used for stopping).
     pub id: String,
@@ -387,6 +848,7 @@ fn default_ambient_loop() -> bool {
 /// Stop an ambient track by ID.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AmbientStop {
     /// ID of the ambient track to stop.
     pub id: String,
@@ -398,6 +860,7 @@ pub struct AmbientStop {
 /// Video background configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct VideoBackground {
     /// Video file path.
     pub path: String,
@@ -415,6 +878,7 @@ fn default_video_bg_loop() -> bool {
 /// A single choice option that branches the story.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Choice {
     /// Display text for this choice (supports localization).
     pub label: LocalizedString,
@@ -423,15 +887,37 @@ pub struct Choice {
     /// Whether this is the default choice when timeout expires.
     #[serde(default)]
     pub default: bool,
+    /// Condition expression; the choice is only available while it holds.
+    #[serde(default, rename = "if", skip_serializing_if = "Option::is_none")]
+    pub if_cond: Option<String>,
+    /// Reason shown on the greyed-out choice when `if` doesn't hold. Without
+    /// it, the choice is hidden instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disabled_reason: Option<LocalizedString>,
+    /// Hide this choice after it has been picked once.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub once: bool,
+    /// Variable assignments applied, in order, when this choice is picked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub set: Vec<SetVar>,
+}
+
+impl Choice {
+    /// Parse the `if` condition, if any.
+    pub fn condition(&self) -> Option<Result<Expr, ExprError>> {
+        self.if_cond.as_deref().map(parse_expr)
+    }
 }
 
 /// A single command in the scenario script.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Command {
     /// Optional label for this command (used as jump target).
     pub label: Option<String>,
-    /// Speaker name to display (supports localization).
+    /// Speaker name to display (supports localization), or the id of a
+    /// character under `characters:`.
     pub speaker: Option<LocalizedString>,
     /// Text to display (supports localization).
     pub text: Option<LocalizedString>,
@@ -439,12 +925,21 @@ pub struct Command {
     pub choices: Option<Vec<Choice>>,
     /// Unconditional jump to another label.
     pub jump: Option<String>,
+    /// Call a label as a subroutine; `return` resumes after this command.
+    pub call: Option<String>,
+    /// Return to the command after the most recent `call`.
+    #[serde(default, rename = "return")]
+    pub return_: bool,
     /// Background image path (None = keep previous, Some("") = clear).
     pub background: Option<String>,
     /// Video background (replaces static background with looping video).
     pub video_bg: Option<VideoBackground>,
-    /// Character sprite image path (None = keep previous, Some("") = clear).
+    /// Character sprite image path or character id (None = keep previous,
+    /// Some("") = clear).
     pub character: Option<String>,
+    /// Expression of `character` (or, without one, of the speaker) to show.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
     /// Character sprite position.
     pub char_pos: Option<CharPosition>,
     /// Character entrance animation.
@@ -455,11 +950,17 @@ pub struct Command {
     pub char_idle: Option<CharIdleAnimation>,
     /// Multiple characters to display.
     pub characters: Option<Vec<CharacterDisplay>>,
+    /// Tweens of characters already on screen.
+    #[serde(default, rename = "move", skip_serializing_if = "Vec::is_empty")]
+    pub move_: Vec<CharacterMove>,
+    /// Named image layers to show, change or hide.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<LayerCommand>,
     /// BGM file path (None = keep previous, Some("") = stop).
     pub bgm: Option<String>,
     /// Sound effect file path (plays once).
     pub se: Option<String>,
-    /// Voice file path (plays once).
+    /// Voice file path (plays once), after the speaker's `voice_prefix`.
     pub voice: Option<String>,
     /// Ambient audio tracks to start.
     #[serde(default)]
@@ -476,6 +977,10 @@ pub struct Command {
     /// Conditional jump.
     #[serde(rename = "if")]
     pub if_cond: Option<IfCondition>,
+    /// Multi-way jump on a value.
+    pub switch: Option<Switch>,
+    /// Random branch or random number.
+    pub random: Option<RandomCommand>,
     /// Wait duration in seconds.
     pub wait: Option<f32>,
     /// Timeout for choices in seconds (auto-selects default choice).
@@ -507,9 +1012,126 @@ pub struct Command {
     pub modular_char: Option<ModularCharRef>,
 }
 
+/// Random branch or random number.
+///
+/// Either `var` with `min`/`max` (store a random integer in `min..=max`) or
+/// `branches` (jump to one label, picked by weight).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RandomCommand {
+    /// Variable to store the random integer in.
+    pub var: Option<String>,
+    /// Smallest value (inclusive).
+    pub min: Option<i64>,
+    /// Largest value (inclusive).
+    pub max: Option<i64>,
+    /// Labels to pick from.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub branches: Vec<RandomBranch>,
+}
+
+/// One weighted label of a `random:` command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RandomBranch {
+    /// Label to jump to.
+    pub jump: String,
+    /// Relative chance of being picked.
+    #[serde(default = "default_random_weight")]
+    pub weight: f64,
+}
+
+fn default_random_weight() -> f64 {
+    1.0
+}
+
+impl RandomCommand {
+    /// Check that the command is either a number or a branch, and well-formed.
+    pub fn check(&self) -> Result<(), String> {
+        match (&self.var, self.branches.is_empty()) {
+            (Some(_), false) => Err("random can't have both 'var' and 'branches'".to_string()),
+            (None, true) => {
+                Err("random needs 'var' (with 'min' and 'max') or 'branches'".to_string())
+            }
+            (Some(_), true) => match (self.min, self.max) {
+                (Some(min), Some(max)) if min <= max => Ok(()),
+                (Some(min), Some(max)) => {
+                    Err(format!("'min' ({}) is greater than 'max' ({})", min, max))
+                }
+                _ => Err("random 'var' needs both 'min' and 'max'".to_string()),
+            },
+            (None, false) => {
+                if let Some(branch) = self
+                    .branches
+                    .iter()
+                    .find(|b| b.weight < 0.0 || b.weight.is_nan())
+                {
+                    return Err(format!(
+                        "weight of '{}' must be a non-negative number",
+                        branch.jump
+                    ));
+                }
+                if !self.branches.iter().any(|b| b.weight > 0.0) {
+                    return Err("at least one branch needs a positive weight".to_string());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Short human-readable description of a branch's chance (`75%`).
+    pub fn describe_branch(&self, index: usize) -> String {
+        let total: f64 = self.branches.iter().map(|b| b.weight.max(0.0)).sum();
+        let weight = self.branches.get(index).map_or(0.0, |b| b.weight.max(0.0));
+        if total > 0.0 {
+            format!("{:.0}%", weight / total * 100.0)
+        } else {
+            String::new()
+        }
+    }
+}
+
+impl Command {
+    /// Labels the `if` (both arms), `switch` (every case and the default) and
+    /// `random` (every branch) of this command can jump to.
+    pub fn branch_targets(&self) -> Vec<&str> {
+        let mut targets = Vec::new();
+        if let Some(if_cond) = &self.if_cond {
+            targets.push(if_cond.jump.as_str());
+            targets.extend(if_cond.else_jump.as_deref());
+        }
+        if let Some(switch) = &self.switch {
+            targets.extend(switch.targets());
+        }
+        if let Some(random) = &self.random {
+            targets.extend(random.branches.iter().map(|b| b.jump.as_str()));
+        }
+        targets
+    }
+
+    /// Whether the branches of this command always jump away (an `if` with
+    /// `else`, a `switch` with `default`, or a `random` with `branches`).
+    pub fn always_branches(&self) -> bool {
+        self.if_cond
+            .as_ref()
+            .is_some_and(|if_cond| if_cond.else_jump.is_some())
+            || self
+                .switch
+                .as_ref()
+                .is_some_and(|switch| switch.default.is_some())
+            || self
+                .random
+                .as_ref()
+                .is_some_and(|random| !random.branches.is_empty())
+    }
+}
+
 /// Achievement unlock command.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Achievement {
     /// Achievement ID.
     pub id: String,
@@ -531,6 +1153,7 @@ fn default_particle_intensity() -> f32 {
 /// Chapter definition in scenario YAML.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ChapterDef {
     /// Unique chapter ID.
     pub id: String,
@@ -543,9 +1166,49 @@ pub struct ChapterDef {
     pub description: String,
 }
 
+/// A character declared under `characters:`, referred to by its id from
+/// `speaker`, `character` and `characters`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CharacterDef {
+    /// Display name (supports localization). Defaults to the id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<LocalizedString>,
+    /// Name colour, as a color name or `#rrggbb`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Sprite shown when no expression is given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sprite: Option<String>,
+    /// Sprites by expression name (e.g., "smile" -> "assets/alice_smile.png").
+    #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub expressions: std::collections::HashMap<String, String>,
+    /// Prepended to the `voice` of lines this character speaks
+    /// (e.g., "assets/voice/alice/").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice_prefix: Option<String>,
+}
+
+impl CharacterDef {
+    /// Sprite for an expression, or the default sprite without one.
+    pub fn sprite_for(&self, expression: Option<&str>) -> Option<&str> {
+        match expression {
+            Some(expression) => self.expressions.get(expression).map(String::as_str),
+            None => self.sprite.as_deref(),
+        }
+    }
+
+    /// Check whether an image is one of this character's sprites.
+    pub fn owns_sprite(&self, path: &str) -> bool {
+        self.sprite.as_deref() == Some(path) || self.expressions.values().any(|p| p == path)
+    }
+}
+
 /// Layer definition for modular characters.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct LayerDef {
     /// Layer name (e.g., "hair", "expression", "outfit").
     pub name: String,
@@ -556,6 +1219,7 @@ pub struct LayerDef {
 /// Modular character definition for layered sprite compositing.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ModularCharDef {
     /// Base image path (body silhouette).
     pub base: String,
@@ -567,6 +1231,7 @@ pub struct ModularCharDef {
 /// Modular character reference in commands.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ModularCharRef {
     /// Character definition name.
     pub name: String,
@@ -578,15 +1243,272 @@ pub struct ModularCharRef {
 /// A complete scenario loaded from YAML.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Scenario {
+    /// Version of the file format (see `scenario::migrate`). Files without
+    /// one are version 1 and are migrated when loaded.
+    #[serde(default = "default_format_version")]
+    pub format_version: u32,
     /// Title of this scenario.
     pub title: String,
     /// Optional chapter definitions.
     #[serde(default)]
     pub chapters: Vec<ChapterDef>,
+    /// Character definitions (id -> definition).
+    #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub characters: std::collections::HashMap<String, CharacterDef>,
     /// Modular character definitions (name -> definition).
     #[serde(default)]
     pub modular_characters: std::collections::HashMap<String, ModularCharDef>,
+    /// Other scenario files whose scripts are appended to this one, relative
+    /// to this file. Resolved by `load_scenario`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub includes: Vec<String>,
+    /// Reusable command lists (name -> definition), expanded into the script
+    /// by `- macro:` entries when the scenario is parsed.
+    #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub macros: std::collections::HashMap<String, MacroDef>,
     /// List of commands that make up the script.
     pub script: Vec<Command>,
+    /// Files the script was loaded from, in script order. Empty when the
+    /// scenario was parsed from a string.
+    #[serde(skip)]
+    pub sources: Vec<SourceFile>,
+    /// Where each command was written, one entry per command. Empty when no
+    /// macro was expanded, in which case commands map one-to-one to entries.
+    #[serde(skip)]
+    pub origins: Vec<CommandOrigin>,
+    /// Keys of the files that no field is named after, reported in strict
+    /// mode.
+    #[serde(skip)]
+    pub unknown_fields: Vec<super::strict::UnknownField>,
+}
+
+fn default_format_version() -> u32 {
+    super::migrate::FORMAT_VERSION
+}
+
+/// A macro declared under `macros:`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MacroDef {
+    /// Parameter names, substituted wherever `{name}` appears in the commands.
+    #[serde(default)]
+    pub params: Vec<String>,
+    /// Commands inserted at each call site. Kept as YAML until expanded,
+    /// since parameters may stand in for values of any type.
+    #[cfg_attr(feature = "editor-types", ts(type = "Array<unknown>"))]
+    #[cfg_attr(feature = "schema", schemars(with = "Vec<serde_json::Value>"))]
+    pub commands: Vec<serde_yaml::Value>,
+}
+
+/// Where a command of the (expanded) script was written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandOrigin {
+    /// Index of the entry in its file's `script:` list. For commands expanded
+    /// from a macro, this is the `- macro:` call site.
+    pub written_index: usize,
+    /// The macro command this command was expanded from, if any.
+    pub expanded_from: Option<MacroCommand>,
+}
+
+/// A command inside a macro definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroCommand {
+    /// Macro name.
+    pub name: String,
+    /// Index of the command within the macro's `commands:`.
+    pub index: usize,
+}
+
+/// Separator between file and label in a qualified jump (`chapter2.yaml#intro`).
+pub const FILE_LABEL_SEPARATOR: char = '#';
+
+/// Split a jump target into an optional file name and the label.
+pub fn split_qualified_label(target: &str) -> (Option<&str>, &str) {
+    match target.split_once(FILE_LABEL_SEPARATOR) {
+        Some((file, label)) => (Some(file), label),
+        None => (None, target),
+    }
+}
+
+/// A file that contributed a contiguous range of commands to a scenario.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    /// Name used in qualified jumps, relative to the root scenario's directory.
+    pub name: String,
+    /// Path the file was read from.
+    pub path: String,
+    /// Index of the file's first command in the merged script.
+    pub start: usize,
+    /// Number of commands from this file.
+    pub len: usize,
+}
+
+impl SourceFile {
+    /// Check whether a merged command index belongs to this file.
+    pub fn contains(&self, index: usize) -> bool {
+        index >= self.start && index < self.start + self.len
+    }
+}
+
+impl Scenario {
+    /// Find the file a command came from and its index within that file.
+    pub fn source_of(&self, index: usize) -> Option<(&SourceFile, usize)> {
+        self.sources
+            .iter()
+            .find(|source| source.contains(index))
+            .map(|source| (source, index - source.start))
+    }
+
+    /// Index of the `script:` entry a command was written as, within its file.
+    ///
+    /// Commands expanded from a macro report the index of the macro call.
+    pub fn written_index(&self, index: usize) -> usize {
+        match self.origins.get(index) {
+            Some(origin) => origin.written_index,
+            None => self.source_of(index).map_or(index, |(_, local)| local),
+        }
+    }
+
+    /// The macro command a command was expanded from, if any.
+    pub fn macro_origin(&self, index: usize) -> Option<&MacroCommand> {
+        self.origins.get(index)?.expanded_from.as_ref()
+    }
+
+    /// Find where a field of a command was written, by a path such as `if`
+    /// or `choices[1].jump` (empty for the command itself).
+    ///
+    /// `spans` are those of each source file (see [`ScenarioSpans::load`]),
+    /// or of the string the scenario was parsed from. Fields of commands
+    /// expanded from a macro are found in the macro definition.
+    pub fn locate_field(
+        &self,
+        spans: &[ScenarioSpans],
+        index: usize,
+        path: &str,
+    ) -> Option<SourceLocation> {
+        self.locate(
+            spans,
+            index,
+            |spans, written| spans.command_field(written, path),
+            |spans, origin| spans.macro_field(&origin.name, origin.index, path),
+        )
+    }
+
+    /// Find where a command defines or references a label.
+    pub fn locate_label(
+        &self,
+        spans: &[ScenarioSpans],
+        index: usize,
+        label: &str,
+    ) -> Option<SourceLocation> {
+        self.locate(
+            spans,
+            index,
+            |spans, written| spans.label_field(written, label),
+            |spans, origin| spans.macro_label_field(&origin.name, origin.index, label),
+        )
+    }
+
+    /// Find where a top-level field of a file was written, by its name as
+    /// included (`None` for the root file).
+    pub fn locate_file_field(
+        &self,
+        spans: &[ScenarioSpans],
+        file: Option<&str>,
+        path: &str,
+    ) -> Option<SourceLocation> {
+        let file = match file {
+            Some(name) => self.sources.iter().position(|source| source.name == name)?,
+            None => 0,
+        };
+        let field = spans.get(file)?.field(path)?;
+        Some(self.location(file, field))
+    }
+
+    fn locate(
+        &self,
+        spans: &[ScenarioSpans],
+        index: usize,
+        in_command: impl Fn(&ScenarioSpans, usize) -> Option<FieldSpan>,
+        in_macro: impl Fn(&ScenarioSpans, &MacroCommand) -> Option<FieldSpan>,
+    ) -> Option<SourceLocation> {
+        let file = if self.sources.is_empty() {
+            (index < self.script.len()).then_some(0)?
+        } else {
+            self.sources
+                .iter()
+                .position(|source| source.contains(index))?
+        };
+        let (file, field) = match self.macro_origin(index) {
+            // Prefer the calling file, whose own macros shadow inherited ones.
+            Some(origin) => std::iter::once(file)
+                .chain((0..spans.len()).filter(|&other| other != file))
+                .find_map(|file| Some((file, in_macro(spans.get(file)?, origin)?)))?,
+            None => (
+                file,
+                in_command(spans.get(file)?, self.written_index(index))?,
+            ),
+        };
+        Some(self.location(file, field))
+    }
+
+    fn location(&self, file: usize, field: FieldSpan) -> SourceLocation {
+        SourceLocation {
+            file: self.sources.get(file).map(|source| source.name.clone()),
+            field,
+        }
+    }
+
+    /// The character a speaker name refers to, with its id.
+    pub fn speaker_character(&self, speaker: &LocalizedString) -> Option<(&str, &CharacterDef)> {
+        let id = speaker.as_plain()?;
+        self.characters
+            .get_key_value(id)
+            .map(|(id, def)| (id.as_str(), def))
+    }
+
+    /// Name to display for a speaker: the character's name for a character
+    /// id (its id if it has none), otherwise the speaker as written.
+    pub fn speaker_name(&self, speaker: &LocalizedString) -> LocalizedString {
+        match self.speaker_character(speaker) {
+            Some((id, def)) => def.name.clone().unwrap_or_else(|| id.into()),
+            None => speaker.clone(),
+        }
+    }
+
+    /// Image for a `character`/`image` value: the sprite of a character id
+    /// (for the given expression), otherwise the value as a path.
+    ///
+    /// Returns `None` for a character without a matching sprite.
+    pub fn character_sprite(&self, image: &str, expression: Option<&str>) -> Option<String> {
+        match self.characters.get(image) {
+            Some(def) => def.sprite_for(expression).map(str::to_string),
+            None => Some(image.to_string()),
+        }
+    }
+
+    /// Build the label lookup table.
+    ///
+    /// Every label is reachable by its bare name and, for scenarios loaded
+    /// from files, as `file#label`.
+    pub fn label_index(&self) -> std::collections::HashMap<String, usize> {
+        let mut index = std::collections::HashMap::new();
+        for (i, cmd) in self.script.iter().enumerate() {
+            let Some(label) = &cmd.label else {
+                continue;
+            };
+            // A bare label defined in several files is the first one.
+            index.entry(label.clone()).or_insert(i);
+            if let Some((source, _)) = self.source_of(i) {
+                index.insert(
+                    format!("{}{}{}", source.name, FILE_LABEL_SEPARATOR, label),
+                    i,
+                );
+            }
+        }
+        index
+    }
 }
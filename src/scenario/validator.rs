@@ -4,7 +4,10 @@ use std::collections::{HashMap, HashSet};
 
 use serde::Serialize;
 
-use crate::scenario::types::Scenario;
+use crate::i18n::LocalizedString;
+use crate::scenario::markup::check_markup;
+use crate::scenario::span::{ScenarioSpans, SourceLocation};
+use crate::scenario::types::{FILE_LABEL_SEPARATOR, Scenario, split_qualified_label};
 
 /// Severity level for validation issues.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
@@ -23,6 +26,13 @@ pub struct ValidationIssue {
     pub message: String,
     pub command_index: Option<usize>,
     pub label: Option<String>,
+    /// File the command came from, for scenarios loaded with `includes`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Field the issue is about, as a path such as `choices[1].if` (from the
+    /// command, or the top of the file for issues outside commands).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
 }
 
 impl ValidationIssue {
@@ -32,22 +42,47 @@ impl ValidationIssue {
             message: message.into(),
             command_index,
             label: None,
+            file: None,
+            field: None,
         }
     }
 
-    fn warning(message: impl Into<String>, command_index: Option<usize>) -> Self {
+    pub(crate) fn warning(message: impl Into<String>, command_index: Option<usize>) -> Self {
         Self {
             severity: Severity::Warning,
             message: message.into(),
             command_index,
             label: None,
+            file: None,
+            field: None,
         }
     }
 
-    fn with_label(mut self, label: impl Into<String>) -> Self {
+    pub(crate) fn with_label(mut self, label: impl Into<String>) -> Self {
         self.label = Some(label.into());
         self
     }
+
+    pub(crate) fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+
+    /// Find where the field the issue is about was written: its `field`, or
+    /// for issues about a label, where the command names it.
+    ///
+    /// `spans` are those of the scenario's files, as for
+    /// [`Scenario::locate_field`].
+    pub fn locate(&self, scenario: &Scenario, spans: &[ScenarioSpans]) -> Option<SourceLocation> {
+        match (self.command_index, &self.field, &self.label) {
+            (Some(index), Some(field), _) => scenario.locate_field(spans, index, field),
+            (Some(index), None, Some(label)) => scenario.locate_label(spans, index, label),
+            (None, Some(field), _) => {
+                scenario.locate_file_field(spans, self.file.as_deref(), field)
+            }
+            _ => None,
+        }
+    }
 }
 
 /// Result of scenario validation.
@@ -94,15 +129,12 @@ impl ValidationResult {
 pub fn validate_scenario(scenario: &Scenario) -> ValidationResult {
     let mut result = ValidationResult::new();
 
-    // Collect all defined labels
-    let defined_labels: HashSet<&str> = scenario
-        .script
-        .iter()
-        .filter_map(|cmd| cmd.label.as_deref())
-        .collect();
+    // Collect all defined labels (bare and file-qualified)
+    let label_index = scenario.label_index();
 
-    // Collect all referenced labels (jumps)
+    // Collect all referenced labels (jumps) and subroutine calls
     let mut referenced_labels: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut called_labels: HashMap<&str, Vec<usize>> = HashMap::new();
 
     for (index, cmd) in scenario.script.iter().enumerate() {
         // Check jump references
@@ -123,10 +155,15 @@ pub fn validate_scenario(scenario: &Scenario) -> ValidationResult {
             }
         }
 
-        // Check conditional jump references
-        if let Some(ref if_cond) = cmd.if_cond {
-            referenced_labels
-                .entry(if_cond.jump.as_str())
+        // Check conditional and switch jump references
+        for target in cmd.branch_targets() {
+            referenced_labels.entry(target).or_default().push(index);
+        }
+
+        // Check call references
+        if let Some(ref call_target) = cmd.call {
+            called_labels
+                .entry(call_target.as_str())
                 .or_default()
                 .push(index);
         }
@@ -134,7 +171,7 @@ pub fn validate_scenario(scenario: &Scenario) -> ValidationResult {
 
     // Check for undefined label references
     for (label, indices) in &referenced_labels {
-        if !defined_labels.contains(label) {
+        if !label_index.contains_key(*label) {
             for &index in indices {
                 result.issues.push(
                     ValidationIssue::error(
@@ -147,11 +184,56 @@ pub fn validate_scenario(scenario: &Scenario) -> ValidationResult {
         }
     }
 
+    // Check for undefined call targets
+    for (label, indices) in &called_labels {
+        if !label_index.contains_key(*label) {
+            for &index in indices {
+                result.issues.push(
+                    ValidationIssue::error(
+                        format!("Call to undefined label '{}'", label),
+                        Some(index),
+                    )
+                    .with_label(label.to_string()),
+                );
+            }
+        }
+    }
+
+    // Check for returns that no call can reach
+    let in_subroutine = subroutine_commands(scenario, &label_index);
+    for (index, cmd) in scenario.script.iter().enumerate() {
+        if cmd.return_ && !in_subroutine.contains(&index) {
+            result.issues.push(
+                ValidationIssue::error(
+                    "Return without a caller (not reachable from any 'call')",
+                    Some(index),
+                )
+                .with_field("return"),
+            );
+        }
+        if [cmd.jump.is_some(), cmd.call.is_some(), cmd.return_]
+            .iter()
+            .filter(|&&set| set)
+            .count()
+            > 1
+        {
+            result.issues.push(ValidationIssue::warning(
+                "Command has more than one of 'call', 'return' and 'jump'; only the first (in that order) is followed",
+                Some(index),
+            ));
+        }
+    }
+
     // Check for unused labels
+    let referenced_names: HashSet<&str> = referenced_labels
+        .keys()
+        .chain(called_labels.keys())
+        .map(|target| split_qualified_label(target).1)
+        .collect();
     for (index, cmd) in scenario.script.iter().enumerate() {
         if let Some(ref label) = cmd.label {
             // "start" is special and doesn't need to be referenced
-            if label != "start" && !referenced_labels.contains_key(label.as_str()) {
+            if label != "start" && !referenced_names.contains(label.as_str()) {
                 result.issues.push(
                     ValidationIssue::warning(format!("Unused label '{}'", label), Some(index))
                         .with_label(label.clone()),
@@ -164,7 +246,7 @@ pub fn validate_scenario(scenario: &Scenario) -> ValidationResult {
     for (index, cmd) in scenario.script.iter().enumerate() {
         if let Some(ref label) = cmd.label
             && let Some(ref jump) = cmd.jump
-            && label == jump
+            && (label == jump || label_index.get(jump.as_str()) == Some(&index))
         {
             result.issues.push(
                 ValidationIssue::error(
@@ -176,22 +258,33 @@ pub fn validate_scenario(scenario: &Scenario) -> ValidationResult {
         }
     }
 
-    // Check for duplicate labels
+    // Check for duplicate labels. Labels of different files can be told
+    // apart with `file#label`, but a bare jump goes to the first one.
     let mut seen_labels: HashMap<&str, usize> = HashMap::new();
     for (index, cmd) in scenario.script.iter().enumerate() {
         if let Some(ref label) = cmd.label {
-            if let Some(first_index) = seen_labels.get(label.as_str()) {
-                result.issues.push(
+            if let Some(&first_index) = seen_labels.get(label.as_str()) {
+                let file_of = |index| scenario.source_of(index).map(|(source, _)| &source.name);
+                let issue = if file_of(first_index) == file_of(index) {
                     ValidationIssue::error(
                         format!(
-                            "Duplicate label '{}' (first defined at command {})",
+                            "Duplicate label '{}' (first defined at {})",
                             label,
-                            first_index + 1
+                            describe_command(scenario, first_index)
                         ),
                         Some(index),
                     )
-                    .with_label(label.clone()),
-                );
+                } else {
+                    ValidationIssue::warning(
+                        format!(
+                            "Label '{}' is also defined at {}; bare jumps go there (use 'file#label')",
+                            label,
+                            describe_command(scenario, first_index)
+                        ),
+                        Some(index),
+                    )
+                };
+                result.issues.push(issue.with_label(label.clone()));
             } else {
                 seen_labels.insert(label.as_str(), index);
             }
@@ -208,96 +301,462 @@ pub fn validate_scenario(scenario: &Scenario) -> ValidationResult {
     // Check for commands with choices but no text
     for (index, cmd) in scenario.script.iter().enumerate() {
         if cmd.choices.is_some() && cmd.text.is_none() {
-            result.issues.push(ValidationIssue::warning(
-                "Choice command without display text",
-                Some(index),
-            ));
+            result.issues.push(
+                ValidationIssue::warning("Choice command without display text", Some(index))
+                    .with_field("choices"),
+            );
+        }
+    }
+
+    // Check text tags
+    for (index, cmd) in scenario.script.iter().enumerate() {
+        let texts: Vec<(Option<&str>, &str)> = match &cmd.text {
+            Some(LocalizedString::Plain(text)) => vec![(None, text.as_str())],
+            Some(LocalizedString::Localized(map)) => {
+                let mut texts: Vec<_> = map
+                    .iter()
+                    .map(|(lang, text)| (Some(lang.as_str()), text.as_str()))
+                    .collect();
+                texts.sort();
+                texts
+            }
+            Some(LocalizedString::Key(_)) | None => Vec::new(),
+        };
+        for (lang, text) in texts {
+            for issue in check_markup(text) {
+                let (message, field) = match lang {
+                    Some(lang) => (
+                        format!("Text tag problem ({}): {}", lang, issue),
+                        format!("text.{}", lang),
+                    ),
+                    None => (format!("Text tag problem: {}", issue), "text".to_string()),
+                };
+                result
+                    .issues
+                    .push(ValidationIssue::warning(message, Some(index)).with_field(field));
+            }
+        }
+    }
+
+    // Everything a `move` can target by name: character ids and the sprites
+    // of characters and commands
+    let mut shown: HashSet<&str> = HashSet::new();
+    for (id, def) in &scenario.characters {
+        shown.insert(id);
+        shown.extend(def.sprite.as_deref());
+        shown.extend(def.expressions.values().map(String::as_str));
+    }
+    for cmd in &scenario.script {
+        shown.extend(cmd.character.as_deref());
+        shown.extend(cmd.characters.iter().flatten().map(|c| c.image.as_str()));
+    }
+
+    // Check character sprites and expressions
+    for (index, cmd) in scenario.script.iter().enumerate() {
+        // Problems with the field they are about
+        let mut problems: Vec<(String, String)> = Vec::new();
+        match (&cmd.character, &cmd.expression) {
+            (Some(ch), expression) if !ch.is_empty() => {
+                let field = if expression.is_some() {
+                    "expression"
+                } else {
+                    "character"
+                };
+                problems.extend(
+                    check_character_sprite(scenario, ch, expression.as_deref())
+                        .map(|problem| (field.to_string(), problem)),
+                );
+            }
+            (None, Some(expression)) => {
+                match cmd
+                    .speaker
+                    .as_ref()
+                    .and_then(|speaker| scenario.speaker_character(speaker))
+                {
+                    Some((id, def)) if def.sprite_for(Some(expression)).is_none() => {
+                        problems.push((
+                            "expression".to_string(),
+                            format!("Character '{}' has no expression '{}'", id, expression),
+                        ))
+                    }
+                    Some(_) => {}
+                    None => problems.push((
+                        "expression".to_string(),
+                        format!(
+                            "Expression '{}' needs a character id in 'character' or 'speaker'",
+                            expression
+                        ),
+                    )),
+                }
+            }
+            _ => {}
+        }
+        for (i, display) in cmd.characters.iter().flatten().enumerate() {
+            problems.extend(
+                check_character_sprite(scenario, &display.image, display.expression.as_deref())
+                    .map(|problem| (format!("characters[{}]", i), problem)),
+            );
+            if !(display.scale.is_finite() && display.scale > 0.0) {
+                problems.push((
+                    format!("characters[{}].scale", i),
+                    format!("Scale of '{}' must be a positive number", display.image),
+                ));
+            }
+        }
+        for (i, layer) in cmd.layers.iter().enumerate() {
+            if layer.name.is_empty() {
+                problems.push((
+                    format!("layers[{}].name", i),
+                    "Layer name must not be empty".to_string(),
+                ));
+            }
+        }
+        for (i, mv) in cmd.move_.iter().enumerate() {
+            let target = mv.target.as_deref().unwrap_or("character");
+            if let Some(name) = &mv.target
+                && name.parse::<usize>().is_err()
+                && !shown.contains(name.as_str())
+            {
+                problems.push((
+                    format!("move[{}].target", i),
+                    format!("Move target '{}' is never shown", name),
+                ));
+            }
+            if mv
+                .scale
+                .is_some_and(|scale| !(scale.is_finite() && scale > 0.0))
+            {
+                problems.push((
+                    format!("move[{}].scale", i),
+                    format!("Move scale of '{}' must be a positive number", target),
+                ));
+            }
+            if mv
+                .opacity
+                .is_some_and(|opacity| !(0.0..=1.0).contains(&opacity))
+            {
+                problems.push((
+                    format!("move[{}].opacity", i),
+                    format!("Move opacity of '{}' must be between 0.0 and 1.0", target),
+                ));
+            }
+            if !(mv.duration.is_finite() && mv.duration >= 0.0) {
+                problems.push((
+                    format!("move[{}].duration", i),
+                    format!("Move duration of '{}' must not be negative", target),
+                ));
+            }
+        }
+        for (field, problem) in problems {
+            result
+                .issues
+                .push(ValidationIssue::warning(problem, Some(index)).with_field(field));
+        }
+    }
+
+    // Check conditions and set expressions parse
+    for (index, cmd) in scenario.script.iter().enumerate() {
+        if let Some(ref if_cond) = cmd.if_cond
+            && let Err(e) = if_cond.condition()
+        {
+            result.issues.push(
+                ValidationIssue::error(format!("Invalid condition: {}", e), Some(index))
+                    .with_field("if"),
+            );
+        }
+        if let Some(ref switch) = cmd.switch {
+            if let Err(e) = switch.subject() {
+                result.issues.push(
+                    ValidationIssue::error(format!("Invalid switch: {}", e), Some(index))
+                        .with_field("switch"),
+                );
+            }
+            for (number, case) in (1..).zip(&switch.cases) {
+                if let Err(e) = case.validate() {
+                    result.issues.push(
+                        ValidationIssue::error(
+                            format!("Invalid switch case {}: {}", number, e),
+                            Some(index),
+                        )
+                        .with_field(format!("switch.cases[{}]", number - 1)),
+                    );
+                }
+            }
+            if cmd.if_cond.is_some() {
+                result.issues.push(
+                    ValidationIssue::warning(
+                        "Command has both 'if' and 'switch'; the switch is only used when the 'if' doesn't jump",
+                        Some(index),
+                    )
+                    .with_field("switch"),
+                );
+            }
+        }
+        if let Some(ref random) = cmd.random
+            && let Err(e) = random.check()
+        {
+            result.issues.push(
+                ValidationIssue::error(format!("Invalid random: {}", e), Some(index))
+                    .with_field("random"),
+            );
+        }
+        if cmd.always_branches() && (cmd.text.is_some() || cmd.choices.is_some()) {
+            result.issues.push(
+                ValidationIssue::warning(
+                    "Command always jumps away ('if' with 'else', 'switch' with 'default' or 'random' with 'branches'), so its text is never shown",
+                    Some(index),
+                )
+                .with_field(if cmd.text.is_some() { "text" } else { "choices" }),
+            );
+        }
+        for (number, choice) in (1..).zip(cmd.choices.iter().flatten()) {
+            let field = |name: &str| format!("choices[{}].{}", number - 1, name);
+            if let Some(Err(e)) = choice.condition() {
+                result.issues.push(
+                    ValidationIssue::error(
+                        format!("Invalid condition on choice {}: {}", number, e),
+                        Some(index),
+                    )
+                    .with_field(field("if")),
+                );
+            }
+            for (i, set) in choice.set.iter().enumerate() {
+                if let Err(e) = set.check() {
+                    result.issues.push(
+                        ValidationIssue::error(
+                            format!("Invalid set for '{}' on choice {}: {}", set.name, number, e),
+                            Some(index),
+                        )
+                        .with_field(field(&format!("set[{}]", i))),
+                    );
+                }
+            }
+            if choice.disabled_reason.is_some() && choice.if_cond.is_none() {
+                result.issues.push(
+                    ValidationIssue::warning(
+                        format!(
+                            "Choice {} has a 'disabled_reason' but no 'if' condition",
+                            number
+                        ),
+                        Some(index),
+                    )
+                    .with_field(field("disabled_reason")),
+                );
+            }
+        }
+        if let Some(ref set) = cmd.set
+            && let Err(e) = set.check()
+        {
+            result.issues.push(
+                ValidationIssue::error(
+                    format!("Invalid set for '{}': {}", set.name, e),
+                    Some(index),
+                )
+                .with_field("set"),
+            );
         }
     }
 
     // Check chapter definitions
-    for chapter in &scenario.chapters {
-        if !defined_labels.contains(chapter.start_label.as_str()) {
-            result.issues.push(ValidationIssue::error(
-                format!(
-                    "Chapter '{}' references undefined start label '{}'",
-                    chapter.id, chapter.start_label
-                ),
-                None,
-            ));
+    for (i, chapter) in scenario.chapters.iter().enumerate() {
+        if !label_index.contains_key(&chapter.start_label) {
+            result.issues.push(
+                ValidationIssue::error(
+                    format!(
+                        "Chapter '{}' references undefined start label '{}'",
+                        chapter.id, chapter.start_label
+                    ),
+                    None,
+                )
+                .with_field(format!("chapters[{}].start_label", i)),
+            );
+        }
+    }
+
+    // Record which file each command came from
+    for issue in &mut result.issues {
+        if let Some(index) = issue.command_index {
+            issue.file = scenario
+                .source_of(index)
+                .map(|(source, _)| source.name.clone());
         }
     }
 
     result
 }
 
-/// Detect potential circular jump paths (more thorough analysis).
-pub fn detect_circular_paths(scenario: &Scenario) -> Vec<Vec<String>> {
-    let mut label_to_index: HashMap<&str, usize> = HashMap::new();
-    let mut index_to_label: HashMap<usize, &str> = HashMap::new();
+/// Validate a scenario in strict mode: like [`validate_scenario`], but keys
+/// that no field is named after, which are otherwise ignored, are errors.
+pub fn validate_scenario_strict(scenario: &Scenario) -> ValidationResult {
+    let mut result = ValidationResult::new();
+    for field in &scenario.unknown_fields {
+        let mut issue = ValidationIssue::error(field.to_string(), field.command_index)
+            .with_field(field.field_path());
+        issue.file = field.file.clone().or_else(|| {
+            field
+                .command_index
+                .and_then(|index| scenario.source_of(index))
+                .map(|(source, _)| source.name.clone())
+        });
+        result.issues.push(issue);
+    }
+    result.issues.extend(validate_scenario(scenario).issues);
+    result
+}
 
-    // Build label index maps
-    for (index, cmd) in scenario.script.iter().enumerate() {
-        if let Some(ref label) = cmd.label {
-            label_to_index.insert(label.as_str(), index);
-            index_to_label.insert(index, label.as_str());
+/// Check that a `character`/`image` value with an expression resolves to a
+/// sprite.
+fn check_character_sprite(
+    scenario: &Scenario,
+    image: &str,
+    expression: Option<&str>,
+) -> Option<String> {
+    let Some(def) = scenario.characters.get(image) else {
+        return expression.map(|expression| {
+            format!(
+                "Expression '{}' needs a character id, but '{}' is not one",
+                expression, image
+            )
+        });
+    };
+    if def.sprite_for(expression).is_some() {
+        return None;
+    }
+    Some(match expression {
+        Some(expression) => format!("Character '{}' has no expression '{}'", image, expression),
+        None => format!("Character '{}' has no default sprite", image),
+    })
+}
+
+/// Collect the commands reachable from any `call` target before its `return`.
+fn subroutine_commands(
+    scenario: &Scenario,
+    label_index: &HashMap<String, usize>,
+) -> HashSet<usize> {
+    let mut reachable = HashSet::new();
+    let mut stack: Vec<usize> = scenario
+        .script
+        .iter()
+        .filter_map(|cmd| cmd.call.as_ref())
+        .filter_map(|target| label_index.get(target).copied())
+        .collect();
+
+    while let Some(index) = stack.pop() {
+        let Some(cmd) = scenario.script.get(index) else {
+            continue;
+        };
+        if !reachable.insert(index) || (cmd.return_ && cmd.call.is_none()) {
+            continue;
+        }
+
+        let resolve = |target: &str| label_index.get(target).copied();
+        stack.extend(cmd.branch_targets().into_iter().filter_map(resolve));
+        if let Some(ref choices) = cmd.choices {
+            stack.extend(choices.iter().filter_map(|c| resolve(&c.jump)));
+            continue;
+        }
+        if cmd.always_branches() {
+            continue;
+        }
+        match (&cmd.call, &cmd.jump) {
+            // The callee returns here, so keep walking after the call
+            (Some(_), _) => stack.push(index + 1),
+            (None, Some(jump)) => stack.extend(resolve(jump)),
+            (None, None) => stack.push(index + 1),
         }
     }
 
-    // Build adjacency list (label -> labels it can jump to)
-    let mut graph: HashMap<&str, HashSet<&str>> = HashMap::new();
+    reachable
+}
 
-    for cmd in &scenario.script {
-        let source_label = cmd.label.as_deref();
+/// Describe a command position as "command N", naming its file when the
+/// scenario was loaded from several files and the macro it was expanded from.
+///
+/// N counts the entries as written, so commands expanded from a macro share
+/// the number of the macro call.
+pub fn describe_command(scenario: &Scenario, index: usize) -> String {
+    let written = scenario.written_index(index) + 1;
+    let mut description = match scenario.source_of(index) {
+        Some((source, _)) if scenario.sources.len() > 1 => {
+            format!("command {} of {}", written, source.name)
+        }
+        _ => format!("command {}", written),
+    };
+    if let Some(origin) = scenario.macro_origin(index) {
+        description.push_str(&format!(
+            " (command {} of macro '{}')",
+            origin.index + 1,
+            origin.name
+        ));
+    }
+    description
+}
+
+/// Detect potential circular jump paths (more thorough analysis).
+pub fn detect_circular_paths(scenario: &Scenario) -> Vec<Vec<String>> {
+    let label_index = scenario.label_index();
+
+    // Labels are named with their file when the scenario has several, so
+    // that labels of the same name in different files stay apart.
+    let name_of = |index: usize| {
+        let label = scenario.script[index].label.as_deref().unwrap_or_default();
+        match scenario.source_of(index) {
+            Some((source, _)) if scenario.sources.len() > 1 => {
+                format!("{}{}{}", source.name, FILE_LABEL_SEPARATOR, label)
+            }
+            _ => label.to_string(),
+        }
+    };
+
+    // Build adjacency list (label command -> label commands it can jump to)
+    let mut graph: HashMap<usize, HashSet<usize>> = HashMap::new();
 
+    for (index, cmd) in scenario.script.iter().enumerate() {
         // Get all jump targets from this command
         let mut targets: Vec<&str> = Vec::new();
 
         if let Some(ref jump) = cmd.jump {
-            targets.push(jump.as_str());
+            targets.push(jump);
         }
         if let Some(ref choices) = cmd.choices {
             for choice in choices {
-                targets.push(choice.jump.as_str());
+                targets.push(&choice.jump);
             }
         }
-        if let Some(ref if_cond) = cmd.if_cond {
-            targets.push(if_cond.jump.as_str());
-        }
+        targets.extend(cmd.branch_targets());
 
         // Associate jumps with the most recent label
-        if let Some(label) = source_label {
-            graph.entry(label).or_default().extend(targets);
+        if cmd.label.is_some() {
+            graph
+                .entry(index)
+                .or_default()
+                .extend(targets.iter().filter_map(|target| label_index.get(*target)));
         }
     }
 
     // Find cycles using DFS
-    let mut cycles: Vec<Vec<String>> = Vec::new();
-    let mut visited: HashSet<&str> = HashSet::new();
-    let mut rec_stack: Vec<&str> = Vec::new();
-
-    fn dfs<'a>(
-        node: &'a str,
-        graph: &HashMap<&'a str, HashSet<&'a str>>,
-        visited: &mut HashSet<&'a str>,
-        rec_stack: &mut Vec<&'a str>,
-        cycles: &mut Vec<Vec<String>>,
+    let mut cycles: Vec<Vec<usize>> = Vec::new();
+    let mut visited: HashSet<usize> = HashSet::new();
+    let mut rec_stack: Vec<usize> = Vec::new();
+
+    fn dfs(
+        node: usize,
+        graph: &HashMap<usize, HashSet<usize>>,
+        visited: &mut HashSet<usize>,
+        rec_stack: &mut Vec<usize>,
+        cycles: &mut Vec<Vec<usize>>,
     ) {
         visited.insert(node);
         rec_stack.push(node);
 
-        if let Some(neighbors) = graph.get(node) {
+        if let Some(neighbors) = graph.get(&node) {
             for &neighbor in neighbors {
-                if !visited.contains(neighbor) {
+                if !visited.contains(&neighbor) {
                     dfs(neighbor, graph, visited, rec_stack, cycles);
                 } else if rec_stack.contains(&neighbor) {
                     // Found a cycle
                     let cycle_start = rec_stack.iter().position(|&n| n == neighbor).unwrap();
-                    let cycle: Vec<String> = rec_stack[cycle_start..]
-                        .iter()
-                        .map(|s| s.to_string())
-                        .collect();
-                    cycles.push(cycle);
+                    cycles.push(rec_stack[cycle_start..].to_vec());
                 }
             }
         }
@@ -305,13 +764,18 @@ pub fn detect_circular_paths(scenario: &Scenario) -> Vec<Vec<String>> {
         rec_stack.pop();
     }
 
-    for label in graph.keys() {
-        if !visited.contains(label) {
+    let mut labels: Vec<usize> = graph.keys().copied().collect();
+    labels.sort_unstable();
+    for label in labels {
+        if !visited.contains(&label) {
             dfs(label, &graph, &mut visited, &mut rec_stack, &mut cycles);
         }
     }
 
     cycles
+        .into_iter()
+        .map(|cycle| cycle.into_iter().map(name_of).collect())
+        .collect()
 }
 
 #[cfg(test)]
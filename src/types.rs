@@ -1,13 +1,23 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
 /// Variable value types used across scenario and runtime modules.
+///
+/// Serialized untagged, so `Int` must stay ahead of `Float` for whole
+/// numbers to keep deserializing as integers.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[cfg_attr(feature = "editor-types", derive(ts_rs::TS))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum Value {
     Bool(bool),
     Int(i64),
+    Float(f64),
     String(String),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
 }
 
 impl Value {
@@ -27,6 +37,15 @@ impl Value {
         }
     }
 
+    /// Get as float (integers are widened).
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Float(f) => Some(*f),
+            Value::Int(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
     /// Get as string.
     pub fn as_string(&self) -> Option<&str> {
         match self {
@@ -34,6 +53,65 @@ impl Value {
             _ => None,
         }
     }
+
+    /// Get as list.
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Get as map.
+    pub fn as_map(&self) -> Option<&BTreeMap<String, Value>> {
+        match self {
+            Value::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Name of the variant, for error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+        }
+    }
+}
+
+/// Player-facing text: strings are unquoted, lists are comma separated and
+/// maps are `key: value` pairs.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::String(s) => write!(f, "{}", s),
+            Value::List(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                Ok(())
+            }
+            Value::Map(map) => {
+                for (i, (key, value)) in map.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -97,4 +175,54 @@ mod tests {
         assert_eq!(int_val, Value::Int(42));
         assert_eq!(str_val, Value::String("hello".to_string()));
     }
+
+    #[test]
+    fn test_value_float() {
+        let value = Value::Float(0.75);
+        assert_eq!(value.as_float(), Some(0.75));
+        assert_eq!(value.as_int(), None);
+        assert_eq!(Value::Int(2).as_float(), Some(2.0));
+    }
+
+    #[test]
+    fn test_value_collection_roundtrip() {
+        let mut map = BTreeMap::new();
+        map.insert("potion".to_string(), Value::Int(3));
+        let value = Value::List(vec![
+            Value::Float(1.5),
+            Value::String("beach".to_string()),
+            Value::Map(map),
+        ]);
+
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, r#"[1.5,"beach",{"potion":3}]"#);
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+    }
+
+    #[test]
+    fn test_value_whole_numbers_stay_int() {
+        assert_eq!(serde_json::from_str::<Value>("7").unwrap(), Value::Int(7));
+        assert_eq!(
+            serde_json::from_str::<Value>("7.0").unwrap(),
+            Value::Float(7.0)
+        );
+    }
+
+    #[test]
+    fn test_value_display() {
+        let mut map = BTreeMap::new();
+        map.insert("a".to_string(), Value::Int(1));
+        map.insert("b".to_string(), Value::Bool(false));
+
+        assert_eq!(Value::Float(0.5).to_string(), "0.5");
+        assert_eq!(
+            Value::List(vec![
+                Value::String("sword".to_string()),
+                Value::String("shield".to_string())
+            ])
+            .to_string(),
+            "sword, shield"
+        );
+        assert_eq!(Value::Map(map).to_string(), "a: 1, b: false");
+    }
 }
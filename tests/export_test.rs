@@ -0,0 +1,172 @@
+use ivy::i18n::Translations;
+use ivy::scenario::{
+    fountain, html, lines_table, load_scenario, parse_scenario, spoken_lines, text_languages,
+};
+
+const SCRIPT: &str = "tests/fixtures/export/script.yaml";
+
+#[test]
+fn test_spoken_lines() {
+    let scenario = load_scenario(SCRIPT).unwrap();
+    let lines = spoken_lines(&scenario, &Translations::new());
+
+    let summary: Vec<(&str, Option<&str>, usize)> = lines
+        .iter()
+        .map(|line| (line.character.as_str(), line.label.as_deref(), line.index))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("alice", Some("start"), 1),
+            ("bob", Some("start"), 2),
+            ("alice", Some("walk"), 3),
+            ("Station master", Some("wait"), 4),
+        ]
+    );
+
+    // Existing voice paths get the character's prefix; missing ones are generated
+    assert_eq!(lines[0].voice, "voice/alice/start_01.ogg");
+    assert!(!lines[0].generated_voice);
+    assert_eq!(lines[1].voice, "bob/start_0002.ogg");
+    assert!(lines[1].generated_voice);
+    assert_eq!(lines[2].voice, "voice/alice/walk_0000.ogg");
+    assert_eq!(lines[3].voice, "Station_master/wait_0000.ogg");
+}
+
+#[test]
+fn test_generated_voice_paths_are_stable() {
+    let voices = |yaml: &str| -> Vec<String> {
+        let scenario = parse_scenario(yaml).unwrap();
+        spoken_lines(&scenario, &Translations::new())
+            .into_iter()
+            .map(|line| line.voice)
+            .collect()
+    };
+    let before = voices(
+        "title: T\nscript:\n  - label: a\n    speaker: bob\n    text: Hi\n  - label: b\n    text: Hm\n  - speaker: bob\n    text: Bye\n",
+    );
+    // A line added under another label doesn't rename the later ones
+    let after = voices(
+        "title: T\nscript:\n  - label: a\n    speaker: bob\n    text: Hi\n  - speaker: bob\n    text: New\n  - label: b\n    text: Hm\n  - speaker: bob\n    text: Bye\n",
+    );
+    assert_eq!(before, ["bob/a_0000.ogg", "bob/b_0001.ogg"]);
+    assert_eq!(
+        after,
+        ["bob/a_0000.ogg", "bob/a_0001.ogg", "bob/b_0001.ogg"]
+    );
+}
+
+#[test]
+fn test_generated_voice_paths_of_included_files_stay_apart() {
+    let dir = std::env::temp_dir().join(format!("ivy_export_includes_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("main.yaml"),
+        "title: T\nincludes:\n  - a.yaml\n  - b.yaml\nscript:\n  - speaker: bob\n    text: Main\n  - label: loop\n    speaker: bob\n    text: Loop\n",
+    )
+    .unwrap();
+    for file in ["a.yaml", "b.yaml"] {
+        std::fs::write(
+            dir.join(file),
+            "script:\n  - speaker: bob\n    text: Hi\n  - label: loop\n    speaker: bob\n    text: Loop\n",
+        )
+        .unwrap();
+    }
+
+    let scenario = load_scenario(&dir.join("main.yaml").to_string_lossy()).unwrap();
+    let voices: Vec<String> = spoken_lines(&scenario, &Translations::new())
+        .into_iter()
+        .map(|line| line.voice)
+        .collect();
+    assert_eq!(
+        voices,
+        [
+            "bob/line_0000.ogg",
+            "bob/loop_0000.ogg",
+            "bob/a/line_0000.ogg",
+            "bob/a/loop_0000.ogg",
+            "bob/b/line_0000.ogg",
+            "bob/b/loop_0000.ogg",
+        ]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_lines_table() {
+    let scenario = load_scenario(SCRIPT).unwrap();
+    let lines = spoken_lines(&scenario, &Translations::new());
+    let languages = text_languages(&lines, &Translations::new());
+    assert_eq!(languages, ["en", "ja"]);
+
+    let alice: Vec<_> = lines
+        .into_iter()
+        .filter(|line| line.character == "alice")
+        .collect();
+    assert_eq!(
+        lines_table(&alice, &languages, ',', &Translations::new()),
+        "character,label,index,text_en,text_ja,voice,voice_generated\n\
+         alice,start,1,\"We missed it, didn't we?\",乗り遅れたね。,voice/alice/start_01.ogg,false\n\
+         alice,walk,3,\"Let's walk, then.\",じゃあ歩こう。,voice/alice/walk_0000.ogg,true\n"
+    );
+
+    // Markup is removed and plain text is shown in every language column
+    let tsv = lines_table(
+        &spoken_lines(&scenario, &Translations::new()),
+        &languages,
+        '\t',
+        &Translations::new(),
+    );
+    assert!(tsv.contains("bob\tstart\t2\tWalk or wait?\t歩く？待つ？\t"));
+    assert!(tsv.contains(
+        "Station master\twait\t4\tNo more trains tonight, folks.\tNo more trains tonight, folks.\t"
+    ));
+}
+
+#[test]
+fn test_fountain_screenplay() {
+    let scenario = load_scenario(SCRIPT).unwrap();
+    let screenplay = fountain(&scenario, "ja", &Translations::new());
+
+    assert!(screenplay.starts_with("Title: The Station\n"));
+    assert!(screenplay.contains(".START\n\n!終電は行ってしまった。\n"));
+    assert!(screenplay.contains("@アリス\n乗り遅れたね。\n"));
+    assert!(
+        screenplay.contains("> CHOICE <\n> 歩く -> WALK <\n> 待つ -> WAIT [[if patience > 1]] <\n")
+    );
+    assert!(screenplay.contains("> JUMP ENDING\n"));
+}
+
+#[test]
+fn test_html_screenplay() {
+    let scenario = load_scenario(SCRIPT).unwrap();
+    let page = html(&scenario, "en", &Translations::new());
+
+    assert!(page.contains("<h2 id=\"walk\">walk</h2>"));
+    assert!(page.contains(
+        "<p class=\"line\"><span class=\"speaker\">Alice</span>We missed it, didn't we?</p>"
+    ));
+    assert!(page.contains(
+        "<li>Wait &rarr; <a href=\"#wait\">wait</a> <span class=\"condition\">if patience &gt; 1</span></li>"
+    ));
+    assert!(page.contains("<p class=\"branch\">jump &rarr; <a href=\"#ending\">ending</a></p>"));
+}
+
+#[test]
+fn test_key_text_is_resolved_from_language_files() {
+    let scenario = load_scenario("tests/fixtures/export/keys/script.yaml").unwrap();
+    let translations =
+        Translations::load_dir(std::path::Path::new("tests/fixtures/export/keys/i18n")).unwrap();
+    let lines = spoken_lines(&scenario, &translations);
+    let languages = text_languages(&lines, &translations);
+    assert_eq!(languages, ["en", "ja"]);
+
+    assert_eq!(
+        lines_table(&lines, &languages, ',', &translations),
+        "character,label,index,text_en,text_ja,voice,voice_generated\n\
+         Guard,gate,0,Halt!,止まれ！,Guard/gate_0000.ogg,true\n"
+    );
+    let screenplay = fountain(&scenario, "ja", &translations);
+    assert!(screenplay.contains("@門番\n止まれ！\n"));
+}
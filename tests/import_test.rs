@@ -0,0 +1,175 @@
+use std::fs;
+use std::path::Path;
+
+use ivy::runtime::{DisplayState, GameState};
+use ivy::scenario::{
+    ImportFormat, Imported, Severity, import_scenario, parse_scenario, scenario_to_yaml,
+    validate_scenario,
+};
+
+const TWEE: &str = "tests/fixtures/import/story.twee";
+const INK: &str = "tests/fixtures/import/story.ink";
+
+fn import(path: &str) -> Imported {
+    let format = ImportFormat::from_path(Path::new(path)).unwrap();
+    import_scenario(format, &fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn plain(text: &Option<ivy::i18n::LocalizedString>) -> &str {
+    text.as_ref()
+        .and_then(|text| text.as_plain())
+        .unwrap_or_default()
+}
+
+fn messages(imported: &Imported) -> Vec<String> {
+    imported
+        .issues
+        .iter()
+        .map(|issue| {
+            assert!(matches!(issue.severity, Severity::Warning));
+            issue.message.clone()
+        })
+        .collect()
+}
+
+#[test]
+fn test_import_twee() {
+    let imported = import(TWEE);
+    let scenario = &imported.scenario;
+    assert_eq!(scenario.title, "The Cave");
+
+    // StoryInit runs first, then the start passage from StoryData
+    assert_eq!(scenario.script[0].label.as_deref(), Some("StoryInit"));
+    assert_eq!(scenario.script[1].label.as_deref(), Some("Entrance"));
+    assert_eq!(
+        plain(&scenario.script[2].text),
+        "You carry {var:torches} torches."
+    );
+    let choices = scenario.script[2].choices.as_ref().unwrap();
+    let links: Vec<(&str, &str)> = choices
+        .iter()
+        .map(|c| (c.label.as_plain().unwrap(), c.jump.as_str()))
+        .collect();
+    assert_eq!(links, [("Go inside", "Tunnel"), ("Leave", "Home")]);
+
+    // Passages without links end the story
+    let home = scenario
+        .script
+        .iter()
+        .find(|c| c.label.as_deref() == Some("Home"))
+        .unwrap();
+    assert_eq!(home.jump.as_deref(), Some("END"));
+
+    assert_eq!(
+        messages(&imported),
+        [
+            "line 17: Script and stylesheet passages are not supported; 'Style' was skipped",
+            "line 30: Macro <<if>> is not supported and was dropped",
+            "line 31: Macro (if:) is not supported and was dropped",
+        ]
+    );
+    assert_eq!(imported.issues[1].label.as_deref(), Some("Tunnel"));
+}
+
+#[test]
+fn test_import_ink() {
+    let imported = import(INK);
+    let scenario = &imported.scenario;
+    assert_eq!(scenario.title, "The Cave");
+
+    let script = &scenario.script;
+    assert_eq!(script[0].set.as_ref().unwrap().name, "torches");
+    assert_eq!(script[0].jump.as_deref(), Some("entrance"));
+
+    let choices = script[2].choices.as_ref().unwrap();
+    let summary: Vec<(&str, &str, Option<&str>, bool)> = choices
+        .iter()
+        .map(|c| {
+            (
+                c.label.as_plain().unwrap(),
+                c.jump.as_str(),
+                c.if_cond.as_deref(),
+                c.once,
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("Go inside", "tunnel", None, true),
+            ("Leave.", "entrance.choice_1", None, true),
+            (
+                "Light a torch",
+                "entrance.choice_2",
+                Some("torches > 1"),
+                false
+            ),
+        ]
+    );
+
+    // Choice text outside the brackets is printed after picking it
+    assert_eq!(script[3].label.as_deref(), Some("entrance.choice_1"));
+    assert_eq!(plain(&script[3].text), "Leave for home.");
+    assert_eq!(script[4].jump.as_deref(), Some("entrance.after"));
+    assert_eq!(
+        plain(&script[5].text),
+        "You light a torch. {if:torches > 2}It is bright.{else}It is dim.{/if}"
+    );
+
+    assert_eq!(
+        messages(&imported),
+        [
+            "line 7: Tags are not supported; '# mood: dark' was dropped",
+            "line 19: Inline logic '{&forever|and on}' is not supported and was dropped",
+            "line 23: Functions are not supported; 'double(x)' was skipped",
+        ]
+    );
+}
+
+#[test]
+fn test_imported_stories_are_valid() {
+    for path in [TWEE, INK] {
+        let imported = import(path);
+        let result = validate_scenario(&imported.scenario);
+        assert!(!result.has_errors(), "{}: {:?}", path, result.issues);
+
+        // The YAML written by ivy-import reads back as the same script
+        let yaml = scenario_to_yaml(&imported.scenario).unwrap();
+        let reparsed = parse_scenario(&yaml).unwrap();
+        assert_eq!(reparsed.script.len(), imported.scenario.script.len());
+    }
+}
+
+#[test]
+fn test_imported_ink_plays() {
+    let mut state = GameState::new(import(INK).scenario);
+
+    match state.display_state() {
+        DisplayState::Text { text, .. } => {
+            assert_eq!(text.as_plain(), Some("A dark cave opens before you."))
+        }
+        other => panic!("Expected text, got {:?}", other),
+    }
+    state.advance();
+    match state.display_state() {
+        // `torches > 1` holds with the initial value of 2
+        DisplayState::Choices { choices, .. } => {
+            assert_eq!(choices.len(), 3);
+            state.select_choice(1);
+        }
+        other => panic!("Expected choices, got {:?}", other),
+    }
+    match state.display_state() {
+        DisplayState::Text { text, .. } => assert_eq!(text.as_plain(), Some("Leave for home.")),
+        other => panic!("Expected text, got {:?}", other),
+    }
+    state.advance();
+    match state.display_state() {
+        DisplayState::Text { text, .. } => assert_eq!(text.as_plain(), Some("You hesitate.")),
+        other => panic!("Expected text, got {:?}", other),
+    }
+    assert_eq!(
+        state.variables().get("torches"),
+        Some(&ivy::types::Value::Int(1))
+    );
+}
@@ -0,0 +1,218 @@
+use ivy::runtime::{DisplayState, GameState};
+use ivy::scenario::{describe_command, detect_circular_paths, load_scenario, validate_scenario};
+
+const MAIN: &str = "tests/fixtures/includes/main.yaml";
+const BROKEN: &str = "tests/fixtures/includes/broken.yaml";
+
+fn current_text(state: &mut GameState) -> String {
+    match state.display_state() {
+        DisplayState::Text { text, .. } => text.as_plain().unwrap_or_default().to_string(),
+        other => panic!("Expected text display, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_includes_are_merged_in_order() {
+    let scenario = load_scenario(MAIN).unwrap();
+
+    assert_eq!(scenario.title, "Multi-file Scenario");
+    assert_eq!(scenario.script.len(), 5);
+    assert!(scenario.includes.is_empty());
+
+    let names: Vec<&str> = scenario.sources.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "main.yaml",
+            "chapter2.yaml",
+            "chapters/epilogue.yaml",
+            "chapters/credits.yaml"
+        ]
+    );
+
+    let (source, local) = scenario.source_of(3).unwrap();
+    assert_eq!(source.name, "chapters/epilogue.yaml");
+    assert_eq!(local, 0);
+}
+
+#[test]
+fn test_qualified_and_cross_file_jumps() {
+    let scenario = load_scenario(MAIN).unwrap();
+    let mut state = GameState::new(scenario);
+
+    assert_eq!(current_text(&mut state), "Chapter 1");
+    state.advance();
+    assert_eq!(current_text(&mut state), "Chapter 2");
+    state.advance();
+    state.advance();
+    assert_eq!(current_text(&mut state), "Epilogue");
+    state.advance();
+    assert_eq!(current_text(&mut state), "Credits");
+}
+
+#[test]
+fn test_label_index_has_qualified_keys() {
+    let scenario = load_scenario(MAIN).unwrap();
+    let index = scenario.label_index();
+
+    assert_eq!(index.get("intro"), Some(&1));
+    assert_eq!(index.get("chapter2.yaml#intro"), Some(&1));
+    assert_eq!(index.get("chapters/credits.yaml#credits"), Some(&4));
+    assert_eq!(index.get("main.yaml#intro"), None);
+}
+
+#[test]
+fn test_validate_multi_file_scenario() {
+    let scenario = load_scenario(MAIN).unwrap();
+    let result = validate_scenario(&scenario);
+
+    assert!(result.is_valid());
+    assert!(!result.has_warnings());
+}
+
+#[test]
+fn test_validate_reports_file_of_issue() {
+    let scenario = load_scenario(BROKEN).unwrap();
+    let result = validate_scenario(&scenario);
+
+    let missing = result
+        .errors()
+        .find(|e| e.message.contains("chapter2.yaml#missing"))
+        .unwrap();
+    assert_eq!(missing.file.as_deref(), Some("broken.yaml"));
+
+    assert!(
+        result
+            .errors()
+            .any(|e| e.message.contains("nowhere.yaml#intro"))
+    );
+
+    // `ending` lives in the epilogue, which broken.yaml doesn't include
+    let ending = result
+        .errors()
+        .find(|e| e.message.contains("'ending'"))
+        .unwrap();
+    assert_eq!(ending.file.as_deref(), Some("chapter2.yaml"));
+    assert_eq!(
+        describe_command(&scenario, ending.command_index.unwrap()),
+        "command 2 of chapter2.yaml"
+    );
+}
+
+#[test]
+fn test_missing_include_is_an_error() {
+    let dir = std::env::temp_dir().join(format!("ivy_includes_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let main = dir.join("main.yaml");
+    std::fs::write(
+        &main,
+        "title: Test\nincludes:\n  - missing.yaml\nscript:\n  - text: \"Hi\"\n",
+    )
+    .unwrap();
+
+    let err = load_scenario(&main.to_string_lossy()).unwrap_err();
+    assert!(err.to_string().contains("missing.yaml"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_include_cycle_is_an_error() {
+    let dir = std::env::temp_dir().join(format!("ivy_include_cycle_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("main.yaml"),
+        "title: Test\nincludes:\n  - a.yaml\nscript:\n  - text: \"Hi\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("a.yaml"),
+        "includes:\n  - a.yaml\nscript:\n  - text: \"A\"\n",
+    )
+    .unwrap();
+
+    let err = load_scenario(&dir.join("main.yaml").to_string_lossy()).unwrap_err();
+    assert!(err.to_string().contains("more than once"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_included_files_use_root_macros() {
+    let dir = std::env::temp_dir().join(format!("ivy_include_macros_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("main.yaml"),
+        "title: Test\nincludes:\n  - a.yaml\nmacros:\n  say:\n    params: [line]\n    commands:\n      - text: \"{line}\"\nscript:\n  - text: \"Main\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("a.yaml"),
+        "script:\n  - text: \"A\"\n  - macro: { name: say, args: { line: \"From A\" } }\n",
+    )
+    .unwrap();
+
+    let scenario = load_scenario(&dir.join("main.yaml").to_string_lossy()).unwrap();
+    assert_eq!(scenario.script.len(), 3);
+    assert_eq!(scenario.origins.len(), 3);
+    assert_eq!(scenario.written_index(2), 1);
+    assert_eq!(
+        describe_command(&scenario, 2),
+        "command 2 of a.yaml (command 1 of macro 'say')"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_include_outside_the_scenario_directory_is_an_error() {
+    let dir = std::env::temp_dir().join(format!("ivy_include_outside_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("main.yaml"),
+        "title: Test\nincludes:\n  - ../shared.yaml\nscript:\n  - text: \"Hi\"\n",
+    )
+    .unwrap();
+
+    let err = load_scenario(&dir.join("main.yaml").to_string_lossy()).unwrap_err();
+    assert!(err.to_string().contains("'../shared.yaml' is outside"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_labels_shared_by_files_stay_apart() {
+    let dir = std::env::temp_dir().join(format!("ivy_include_labels_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("main.yaml"),
+        "title: Test\nincludes:\n  - a.yaml\nscript:\n  - label: start\n    text: \"Start\"\n    jump: loop\n  - label: loop\n    text: \"Main\"\n    jump: a.yaml#loop\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("a.yaml"),
+        "script:\n  - label: loop\n    text: \"A\"\n    jump: main.yaml#loop\n",
+    )
+    .unwrap();
+
+    let scenario = load_scenario(&dir.join("main.yaml").to_string_lossy()).unwrap();
+    // A bare jump goes to the first definition
+    assert_eq!(scenario.label_index().get("loop"), Some(&1));
+
+    let result = validate_scenario(&scenario);
+    assert!(result.is_valid());
+    let warnings: Vec<&str> = result.warnings().map(|w| w.message.as_str()).collect();
+    assert_eq!(
+        warnings,
+        [
+            "Label 'loop' is also defined at command 2 of main.yaml; bare jumps go there (use 'file#label')"
+        ]
+    );
+
+    assert_eq!(
+        detect_circular_paths(&scenario),
+        [["main.yaml#loop", "a.yaml#loop"]]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
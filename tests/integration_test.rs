@@ -664,7 +664,7 @@ script:
 /// Test save/restore roundtrip with characters.
 #[test]
 fn test_save_restore_with_characters() {
-    use ivy::runtime::{CharacterState, SaveData, VisualState};
+    use ivy::runtime::{CharacterState, SAVE_FORMAT_VERSION, SaveData, VisualState};
     use ivy::scenario::CharPosition;
 
     let yaml = r#"
@@ -681,6 +681,7 @@ script:
         background: Some("bg.png".to_string()),
         character: None,
         char_pos: CharPosition::Center,
+        char_transform: Default::default(),
         characters: vec![
             CharacterState {
                 path: "a.png".to_string(),
@@ -688,6 +689,7 @@ script:
                 enter: None,
                 exit: None,
                 idle: None,
+                ..Default::default()
             },
             CharacterState {
                 path: "b.png".to_string(),
@@ -695,18 +697,24 @@ script:
                 enter: None,
                 exit: None,
                 idle: None,
+                ..Default::default()
             },
         ],
         nvl_mode: false,
         modular_char: None,
+        layers: Vec::new(),
     };
 
     let save = SaveData {
+        format_version: SAVE_FORMAT_VERSION,
         scenario_path: "test.yaml".to_string(),
         current_index: 1,
         visual,
         timestamp: 0,
         variables: Variables::new(),
+        call_stack: Vec::new(),
+        picked_choices: Default::default(),
+        rng: None,
     };
 
     let scenario2 = parse_scenario(yaml).unwrap();
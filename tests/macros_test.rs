@@ -0,0 +1,143 @@
+use ivy::scenario::position::PositionMap;
+use ivy::scenario::{describe_command, parse_scenario, validate_scenario};
+
+const SCENARIO: &str = r#"title: Macros
+
+macros:
+  scene_change:
+    params: [bg, music]
+    commands:
+      - background: "{bg}"
+        bgm: "{music}"
+      - wait: 0.5
+  chat:
+    params: [who, line]
+    commands:
+      - speaker: "{who}"
+        text: "{who}: {line}"
+
+script:
+  - text: "Start"
+  - macro:
+      name: scene_change
+      args:
+        bg: "assets/room.png"
+        music: "assets/calm.ogg"
+  - macro:
+      name: chat
+      args: { who: Alice, line: "Hello!" }
+  - text: "End"
+"#;
+
+#[test]
+fn test_macros_expand_into_commands() {
+    let scenario = parse_scenario(SCENARIO).unwrap();
+
+    assert_eq!(scenario.macros.len(), 2);
+    assert_eq!(scenario.script.len(), 5);
+    assert_eq!(
+        scenario.script[1].background.as_deref(),
+        Some("assets/room.png")
+    );
+    assert_eq!(scenario.script[1].bgm.as_deref(), Some("assets/calm.ogg"));
+    assert_eq!(scenario.script[2].wait, Some(0.5));
+    assert_eq!(
+        scenario.script[3].text.as_ref().and_then(|t| t.as_plain()),
+        Some("Alice: Hello!")
+    );
+    assert_eq!(
+        scenario.script[4].text.as_ref().and_then(|t| t.as_plain()),
+        Some("End")
+    );
+}
+
+#[test]
+fn test_expanded_commands_know_their_origin() {
+    let scenario = parse_scenario(SCENARIO).unwrap();
+
+    assert_eq!(scenario.written_index(2), 1);
+    assert_eq!(scenario.written_index(4), 3);
+    let origin = scenario.macro_origin(2).unwrap();
+    assert_eq!(origin.name, "scene_change");
+    assert_eq!(origin.index, 1);
+    assert!(scenario.macro_origin(4).is_none());
+
+    assert_eq!(
+        describe_command(&scenario, 2),
+        "command 2 (command 2 of macro 'scene_change')"
+    );
+}
+
+#[test]
+fn test_position_map_locates_call_site_and_definition() {
+    let scenario = parse_scenario(SCENARIO).unwrap();
+    let positions = PositionMap::from_yaml(SCENARIO);
+
+    let (call_site, definition) = positions.locate_command(&scenario, 2);
+    assert_eq!(call_site.unwrap().line, 17);
+    assert_eq!(definition.unwrap().line, 8);
+
+    let (call_site, definition) = positions.locate_command(&scenario, 4);
+    assert_eq!(call_site.unwrap().line, 25);
+    assert!(definition.is_none());
+}
+
+#[test]
+fn test_validation_issue_inside_macro() {
+    let yaml = r#"title: Test
+macros:
+  go:
+    params: [target]
+    commands:
+      - text: "Going"
+        jump: "{target}"
+script:
+  - macro: { name: go, args: { target: nowhere } }
+"#;
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    let issue = result
+        .errors()
+        .find(|e| e.message.contains("nowhere"))
+        .unwrap();
+    let index = issue.command_index.unwrap();
+    let (call_site, definition) = PositionMap::from_yaml(yaml).locate_command(&scenario, index);
+    assert_eq!(call_site.unwrap().line, 8);
+    assert_eq!(definition.unwrap().line, 5);
+}
+
+#[test]
+fn test_invalid_expansion_points_to_definition_and_call() {
+    let yaml = r#"title: Test
+macros:
+  pause:
+    params: [seconds]
+    commands:
+      - wait: "{seconds}"
+script:
+  - text: "Hi"
+  - macro: { name: pause, args: { seconds: "soon" } }
+"#;
+    let err = parse_scenario(yaml).unwrap_err().to_string();
+
+    assert!(err.contains("macro 'pause' at line 6"), "{}", err);
+    assert!(err.contains("called at line 9"), "{}", err);
+}
+
+#[test]
+fn test_unknown_macro_points_to_call() {
+    let yaml = "title: Test\nscript:\n  - text: \"Hi\"\n  - macro: missing\n";
+    let err = parse_scenario(yaml).unwrap_err().to_string();
+
+    assert!(err.contains("line 4"), "{}", err);
+    assert!(err.contains("Unknown macro 'missing'"), "{}", err);
+}
+
+#[test]
+fn test_scenario_without_macros_keeps_yaml_errors() {
+    let yaml = "title: Test\nscript:\n  - wait: soon\n";
+    let err = parse_scenario(yaml).unwrap_err().to_string();
+
+    assert!(err.contains("YAML parse error at line 3"), "{}", err);
+}
@@ -0,0 +1,97 @@
+use std::fs;
+
+use ivy::runtime::{DisplayState, GameState, SAVE_FORMAT_VERSION, SaveData};
+use ivy::scenario::{
+    FORMAT_VERSION, load_scenario, migrate_source, parse_scenario, validate_scenario,
+};
+use ivy::types::Value;
+
+fn scenario_fixture(version: u32) -> String {
+    format!("tests/fixtures/migrate/scenario_v{}.yaml", version)
+}
+
+fn save_fixture(version: u32) -> String {
+    format!("tests/fixtures/migrate/save_v{}.json", version)
+}
+
+#[test]
+fn test_load_scenarios_of_every_version() {
+    for version in 1..=FORMAT_VERSION {
+        let path = scenario_fixture(version);
+        let scenario = load_scenario(&path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        assert_eq!(scenario.format_version, FORMAT_VERSION);
+        assert_eq!(scenario.title, format!("Version {}", version));
+
+        let result = validate_scenario(&scenario);
+        assert!(!result.has_errors(), "{}: {:?}", path, result.issues);
+    }
+}
+
+#[test]
+fn test_load_saves_of_every_version() {
+    for version in 1..=SAVE_FORMAT_VERSION {
+        let path = save_fixture(version);
+        let save = SaveData::from_json(&fs::read_to_string(&path).unwrap())
+            .unwrap_or_else(|e| panic!("{}: {}", path, e));
+        assert_eq!(save.format_version, SAVE_FORMAT_VERSION);
+
+        // Saves resume the scenario of the same version
+        let scenario = load_scenario(&save.scenario_path).unwrap();
+        let mut state = GameState::from_save_data(&save, scenario);
+        assert_eq!(state.variables().get("has_key"), Some(&Value::Bool(true)));
+        match state.display_state() {
+            DisplayState::Text { text, .. } => {
+                assert_eq!(text.as_plain(), Some("The door opens."))
+            }
+            other => panic!("{}: expected text, got {:?}", path, other),
+        }
+    }
+}
+
+#[test]
+fn test_migrate_source_is_idempotent() {
+    for version in 1..=FORMAT_VERSION {
+        let yaml = fs::read_to_string(scenario_fixture(version)).unwrap();
+        let Some(migrated) = migrate_source(&yaml).unwrap() else {
+            continue;
+        };
+        assert_eq!(migrated.from, u64::from(version));
+        assert!(
+            migrated
+                .yaml
+                .contains(&format!("format_version: {}", FORMAT_VERSION))
+        );
+
+        // The rewritten file loads the same script and needs no more changes
+        let original = parse_scenario(&yaml).unwrap();
+        let rewritten = parse_scenario(&migrated.yaml).unwrap();
+        assert_eq!(rewritten.script.len(), original.script.len());
+        assert_eq!(migrate_source(&migrated.yaml).unwrap(), None);
+    }
+}
+
+#[test]
+fn test_newer_versions_are_rejected() {
+    let yaml = format!(
+        "format_version: {}\ntitle: Future\nscript: []\n",
+        FORMAT_VERSION + 1
+    );
+    let err = parse_scenario(&yaml).unwrap_err();
+    assert!(err.to_string().contains("newer"), "{}", err);
+
+    let json = format!(
+        r#"{{"format_version": {}, "scenario_path": "a.yaml", "current_index": 0, "visual": {{}}}}"#,
+        SAVE_FORMAT_VERSION + 1
+    );
+    let err = SaveData::from_json(&json).unwrap_err();
+    assert!(err.to_string().contains("newer"), "{}", err);
+}
+
+#[test]
+fn test_saves_record_their_version() {
+    let scenario = load_scenario(&scenario_fixture(FORMAT_VERSION)).unwrap();
+    let state = GameState::new(scenario);
+    let json = serde_json::to_string(&state.to_save_data("story.yaml")).unwrap();
+    let doc: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(doc["format_version"], SAVE_FORMAT_VERSION);
+}
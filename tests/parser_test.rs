@@ -127,7 +127,7 @@ script:
     let scenario = parse_scenario(yaml).unwrap();
     let if_cond = scenario.script[0].if_cond.as_ref().unwrap();
 
-    assert_eq!(if_cond.var, "has_key");
+    assert_eq!(if_cond.var.as_deref(), Some("has_key"));
     assert_eq!(if_cond.jump, "with_key");
 }
 
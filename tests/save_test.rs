@@ -1,16 +1,22 @@
 mod common;
 
-use ivy::runtime::{SaveData, Value, Variables, VisualState};
+use std::collections::BTreeMap;
+
+use ivy::runtime::{SAVE_FORMAT_VERSION, SaveData, Value, Variables, VisualState};
 use ivy::scenario::CharPosition;
 
 #[test]
 fn test_save_data_serialization_roundtrip() {
     let save = SaveData {
+        format_version: SAVE_FORMAT_VERSION,
         scenario_path: "test.yaml".to_string(),
         current_index: 5,
         visual: VisualState::default(),
         timestamp: 1234567890,
         variables: Variables::new(),
+        call_stack: Vec::new(),
+        picked_choices: Default::default(),
+        rng: None,
     };
 
     let json = serde_json::to_string(&save).unwrap();
@@ -29,11 +35,15 @@ fn test_save_data_with_variables() {
     variables.set("has_key", Value::Bool(true));
 
     let save = SaveData {
+        format_version: SAVE_FORMAT_VERSION,
         scenario_path: "test.yaml".to_string(),
         current_index: 0,
         visual: VisualState::default(),
         timestamp: 0,
         variables,
+        call_stack: Vec::new(),
+        picked_choices: Default::default(),
+        rng: None,
     };
 
     let json = serde_json::to_string(&save).unwrap();
@@ -47,23 +57,88 @@ fn test_save_data_with_variables() {
     assert_eq!(restored.variables.get("has_key"), Some(&Value::Bool(true)));
 }
 
+#[test]
+fn test_save_data_with_collection_variables() {
+    let mut inventory = BTreeMap::new();
+    inventory.insert("potion".to_string(), Value::Int(3));
+
+    let mut variables = Variables::new();
+    variables.set("progress", Value::Float(0.5));
+    variables.set(
+        "visited",
+        Value::List(vec![Value::String("beach".to_string())]),
+    );
+    variables.set("inventory", Value::Map(inventory.clone()));
+
+    let save = SaveData {
+        format_version: SAVE_FORMAT_VERSION,
+        scenario_path: "test.yaml".to_string(),
+        current_index: 0,
+        visual: VisualState::default(),
+        timestamp: 0,
+        variables,
+        call_stack: Vec::new(),
+        picked_choices: Default::default(),
+        rng: None,
+    };
+
+    let json = serde_json::to_string(&save).unwrap();
+    let restored: SaveData = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(restored.variables.get("progress"), Some(&Value::Float(0.5)));
+    assert_eq!(
+        restored.variables.get("visited"),
+        Some(&Value::List(vec![Value::String("beach".to_string())]))
+    );
+    assert_eq!(
+        restored.variables.get("inventory"),
+        Some(&Value::Map(inventory))
+    );
+}
+
+#[test]
+fn test_deserialize_legacy_variables() {
+    // Saves written before float/list/map support
+    let json = r#"{
+        "scenario_path": "old.yaml",
+        "current_index": 3,
+        "visual": {},
+        "variables": {"data": {"score": 10, "name": "Alice", "met": true}}
+    }"#;
+
+    let save: SaveData = serde_json::from_str(json).unwrap();
+
+    assert_eq!(save.variables.get("score"), Some(&Value::Int(10)));
+    assert_eq!(
+        save.variables.get("name"),
+        Some(&Value::String("Alice".to_string()))
+    );
+    assert_eq!(save.variables.get("met"), Some(&Value::Bool(true)));
+}
+
 #[test]
 fn test_save_data_with_visual_state() {
     let visual = VisualState {
         background: Some("bg.png".to_string()),
         character: Some("char.png".to_string()),
         char_pos: CharPosition::Center,
+        char_transform: Default::default(),
         characters: vec![],
         nvl_mode: false,
         modular_char: None,
+        layers: Vec::new(),
     };
 
     let save = SaveData {
+        format_version: SAVE_FORMAT_VERSION,
         scenario_path: "test.yaml".to_string(),
         current_index: 0,
         visual,
         timestamp: 0,
         variables: Variables::new(),
+        call_stack: Vec::new(),
+        picked_choices: Default::default(),
+        rng: None,
     };
 
     let json = serde_json::to_string(&save).unwrap();
@@ -82,6 +157,7 @@ fn test_save_data_with_characters() {
         background: None,
         character: None,
         char_pos: CharPosition::Center,
+        char_transform: Default::default(),
         characters: vec![
             CharacterState {
                 path: "char_a.png".to_string(),
@@ -89,6 +165,7 @@ fn test_save_data_with_characters() {
                 enter: None,
                 exit: None,
                 idle: None,
+                ..Default::default()
             },
             CharacterState {
                 path: "char_b.png".to_string(),
@@ -96,18 +173,24 @@ fn test_save_data_with_characters() {
                 enter: None,
                 exit: None,
                 idle: None,
+                ..Default::default()
             },
         ],
         nvl_mode: false,
         modular_char: None,
+        layers: Vec::new(),
     };
 
     let save = SaveData {
+        format_version: SAVE_FORMAT_VERSION,
         scenario_path: "test.yaml".to_string(),
         current_index: 0,
         visual,
         timestamp: 0,
         variables: Variables::new(),
+        call_stack: Vec::new(),
+        picked_choices: Default::default(),
+        rng: None,
     };
 
     let json = serde_json::to_string(&save).unwrap();
@@ -129,11 +212,15 @@ fn test_save_data_with_characters() {
 #[test]
 fn test_save_data_timestamp() {
     let save = SaveData {
+        format_version: SAVE_FORMAT_VERSION,
         scenario_path: "test.yaml".to_string(),
         current_index: 0,
         visual: VisualState::default(),
         timestamp: 1700000000,
         variables: Variables::new(),
+        call_stack: Vec::new(),
+        picked_choices: Default::default(),
+        rng: None,
     };
 
     let json = serde_json::to_string(&save).unwrap();
@@ -192,6 +279,26 @@ fn test_deserialize_with_defaults() {
     assert_eq!(save.timestamp, 0);
 }
 
+#[test]
+fn test_deserialize_characters_without_placement() {
+    let json = r#"{
+        "scenario_path": "test.yaml",
+        "current_index": 0,
+        "visual": {
+            "characters": [{ "path": "a.png", "position": "right" }]
+        }
+    }"#;
+
+    let save: SaveData = serde_json::from_str(json).unwrap();
+
+    let character = &save.visual.characters[0];
+    assert_eq!(character.transform.scale, 1.0);
+    assert!(!character.flip);
+    assert_eq!(character.z, 0);
+    assert_eq!(character.anchor_x(), 0.8);
+    assert_eq!(character.anchor_y(), 1.0);
+}
+
 #[test]
 fn test_save_data_from_game_state() {
     let yaml = r#"
@@ -227,11 +334,15 @@ script:
 "#;
 
     let save = SaveData {
+        format_version: SAVE_FORMAT_VERSION,
         scenario_path: "test.yaml".to_string(),
         current_index: 2,
         visual: VisualState::default(),
         timestamp: 0,
         variables: Variables::new(),
+        call_stack: Vec::new(),
+        picked_choices: Default::default(),
+        rng: None,
     };
 
     let scenario = parse_scenario(yaml).unwrap();
@@ -247,11 +358,15 @@ script:
 #[test]
 fn test_save_data_preserves_index() {
     let save = SaveData {
+        format_version: SAVE_FORMAT_VERSION,
         scenario_path: "test.yaml".to_string(),
         current_index: 42,
         visual: VisualState::default(),
         timestamp: 0,
         variables: Variables::new(),
+        call_stack: Vec::new(),
+        picked_choices: Default::default(),
+        rng: None,
     };
 
     let json = serde_json::to_string(&save).unwrap();
@@ -0,0 +1,44 @@
+#![cfg(feature = "schema")]
+
+use std::fs;
+
+use ivy::scenario::schema::{SCHEMA_PATH, scenario_schema, scenario_schema_json};
+
+#[test]
+fn test_schema_is_up_to_date() {
+    let committed = fs::read_to_string(SCHEMA_PATH).unwrap();
+    assert!(
+        committed == scenario_schema_json(),
+        "{} is out of date; run `cargo run --features schema --bin ivy-schema -- --update`",
+        SCHEMA_PATH
+    );
+}
+
+#[test]
+fn test_schema_describes_commands() {
+    let schema = scenario_schema();
+    let definitions = &schema["definitions"];
+
+    // Field docs become descriptions, and optional fields aren't required
+    let command = &definitions["Command"];
+    assert_eq!(
+        command["properties"]["text"]["description"],
+        "Text to display (supports localization)."
+    );
+    assert!(command.get("required").is_none());
+    assert_eq!(schema["required"], serde_json::json!(["title", "script"]));
+
+    // Enums list their values
+    let easing = serde_json::to_string(&definitions["Easing"]).unwrap();
+    for name in ["\"linear\"", "\"ease_in_out_cubic\"", "\"ease_out_bounce\""] {
+        assert!(easing.contains(name), "{} missing from Easing", name);
+    }
+    let transition = serde_json::to_string(&definitions["TransitionType"]).unwrap();
+    assert!(transition.contains("\"dissolve\""));
+
+    // `#[serde(default)]` fields carry their default
+    assert_eq!(
+        definitions["Choice"]["properties"]["default"]["default"],
+        false
+    );
+}
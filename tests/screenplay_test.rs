@@ -0,0 +1,117 @@
+use ivy::runtime::{DisplayState, GameState};
+use ivy::scenario::position::{PositionMap, ProjectPositionMap};
+use ivy::scenario::{load_scenario, parse_screenplay, validate_scenario};
+
+const STORY: &str = "tests/fixtures/screenplay/story.ivys";
+const MAIN: &str = "tests/fixtures/screenplay/main.yaml";
+
+fn plain(text: &Option<ivy::i18n::LocalizedString>) -> &str {
+    text.as_ref()
+        .and_then(|text| text.as_plain())
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_screenplay_compiles_to_commands() {
+    let scenario = load_scenario(STORY).unwrap();
+
+    assert_eq!(scenario.title, "The Forest");
+    assert_eq!(scenario.script.len(), 6);
+    let names: Vec<&str> = scenario.sources.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["story.ivys", "ending.ivys"]);
+
+    // Directives apply to the next line
+    let first = &scenario.script[0];
+    assert_eq!(first.label.as_deref(), Some("start"));
+    assert_eq!(first.background.as_deref(), Some("forest.png"));
+    assert_eq!(first.character.as_deref(), Some("alice"));
+    assert_eq!(first.expression.as_deref(), Some("smile"));
+    assert_eq!(first.bgm.as_deref(), Some("forest.ogg"));
+    assert_eq!(plain(&first.text), "Where are we?");
+    assert!(scenario.script[1].speaker.is_none());
+
+    let choice = &scenario.script[2];
+    assert_eq!(choice.expression.as_deref(), Some("worried"));
+    assert_eq!(choice.set.as_ref().unwrap().name, "courage");
+    let choices = choice.choices.as_ref().unwrap();
+    assert_eq!(choices.len(), 2);
+    assert_eq!(choices[1].jump, "right_path");
+    assert_eq!(choices[1].if_cond.as_deref(), Some("courage >= 2"));
+
+    // A closing directive ends the line it follows
+    assert_eq!(plain(&scenario.script[3].text), "Narration without quotes.");
+    assert_eq!(scenario.script[3].jump.as_deref(), Some("ending"));
+
+    let ending = &scenario.script[5];
+    assert_eq!(ending.background.as_deref(), Some(""));
+    assert_eq!(ending.transition.as_ref().unwrap().duration, 1.0);
+
+    let result = validate_scenario(&scenario);
+    assert!(result.is_valid(), "{:?}", result.issues);
+}
+
+#[test]
+fn test_screenplay_included_from_yaml() {
+    let scenario = load_scenario(MAIN).unwrap();
+    let mut state = GameState::new(scenario);
+
+    state.advance();
+    match state.display_state() {
+        DisplayState::Text { speaker, text, .. } => {
+            assert_eq!(speaker.as_ref().unwrap(), "Alice");
+            assert_eq!(text.as_plain(), Some("Where are we?"));
+        }
+        other => panic!("Expected text, got {:?}", other),
+    }
+    state.advance();
+    state.advance();
+    match state.display_state() {
+        DisplayState::Choices { choices, .. } => {
+            assert!(choices.iter().all(|choice| choice.is_enabled()));
+            state.select_choice(1);
+        }
+        other => panic!("Expected choices, got {:?}", other),
+    }
+    match state.display_state() {
+        DisplayState::Text { text, .. } => assert_eq!(text.as_plain(), Some("You're brave.")),
+        other => panic!("Expected text, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_screenplay_positions() {
+    let scenario = load_scenario(STORY).unwrap();
+    let positions = ProjectPositionMap::from_scenario(&scenario);
+
+    // Commands start at their first line, including directives and labels
+    let (file, pos) = positions.get_command_position(0).unwrap();
+    assert_eq!((file, pos.line), ("story.ivys", 4));
+    assert_eq!(positions.get_command_position(2).unwrap().1.line, 10);
+    let (file, pos) = positions.get_command_position(5).unwrap();
+    assert_eq!((file, pos.line), ("ending.ivys", 0));
+    assert_eq!(
+        positions.get_label_position("right_path").unwrap().1.line,
+        19
+    );
+
+    let text = std::fs::read_to_string(STORY).unwrap();
+    let map = PositionMap::from_source(STORY, &text);
+    let refs = map.get_label_references("ending").unwrap();
+    assert_eq!(refs.len(), 2);
+    assert_eq!((refs[0].line, refs[0].column), (17, 6));
+    let choice_ref = map.get_label_references("right_path").unwrap()[0];
+    assert_eq!((choice_ref.line, choice_ref.column), (13, 11));
+}
+
+#[test]
+fn test_screenplay_errors_have_lines() {
+    let err = parse_screenplay("# start\n\"Hi.\"\n@dance now\n").unwrap_err();
+    assert!(err.to_string().contains("line 3"), "{}", err);
+    assert!(err.to_string().contains("Unknown directive '@dance'"));
+
+    let err = parse_screenplay("alice \"Unclosed\n").unwrap_err();
+    assert!(err.to_string().contains("line 1 column 7"), "{}", err);
+
+    let err = parse_screenplay("@transition spin\n\"Whee.\"\n").unwrap_err();
+    assert!(err.to_string().contains("line 1"), "{}", err);
+}
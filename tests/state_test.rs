@@ -1,4 +1,5 @@
-use ivy::runtime::{DisplayState, GameState, Value};
+use ivy::i18n::Translations;
+use ivy::runtime::{CharacterSlot, DisplayState, GameState, Value};
 use ivy::scenario::parse_scenario;
 
 fn create_minimal_state() -> GameState {
@@ -98,6 +99,153 @@ fn test_rollback_returns_to_previous_state() {
     assert_eq!(state.current_index(), 1);
 }
 
+const CALL_SCENARIO: &str = r#"
+title: Call Test
+
+script:
+  - text: "Town"
+    call: shop
+  - text: "Back in town"
+    call: shop
+  - text: "Done"
+    jump: finish
+
+  - label: shop
+    text: "Welcome to the shop"
+    call: greeting
+  - return: true
+
+  - label: greeting
+    text: "Hello!"
+    return: true
+
+  - label: finish
+    text: "The end"
+"#;
+
+fn text_of(state: &mut GameState) -> String {
+    match state.display_state() {
+        DisplayState::Text { text, .. } => text.as_plain().unwrap_or_default().to_string(),
+        other => panic!("Expected DisplayState::Text, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_call_and_return() {
+    let mut state = GameState::new(parse_scenario(CALL_SCENARIO).unwrap());
+
+    let mut seen = vec![text_of(&mut state)];
+    while !state.is_ended() {
+        state.advance();
+        if !state.is_ended() {
+            seen.push(text_of(&mut state));
+        }
+    }
+
+    assert_eq!(
+        seen,
+        vec![
+            "Town",
+            "Welcome to the shop",
+            "Hello!",
+            "Back in town",
+            "Welcome to the shop",
+            "Hello!",
+            "Done",
+            "The end"
+        ]
+    );
+    assert!(state.call_stack().is_empty());
+}
+
+#[test]
+fn test_call_past_the_depth_limit_is_skipped() {
+    let yaml = r#"
+title: Recursion
+
+script:
+  - text: "Start"
+    call: dive
+  - text: "Surfaced"
+    jump: finish
+
+  - label: dive
+    text: "Deeper"
+    call: dive
+  - text: "Bottom"
+    return: true
+
+  - label: finish
+    text: "The end"
+"#;
+    let mut state = GameState::new(parse_scenario(yaml).unwrap());
+
+    let mut seen = vec![text_of(&mut state)];
+    while !state.is_ended() && seen.len() < 1000 {
+        state.advance();
+        if !state.is_ended() {
+            seen.push(text_of(&mut state));
+        }
+    }
+
+    // The call past the limit continues with the next command, and every
+    // return goes back to the command after its own call
+    let count = |text: &str| seen.iter().filter(|seen| *seen == text).count();
+    assert_eq!(count("Deeper"), 64);
+    assert_eq!(count("Bottom"), 64);
+    assert_eq!(seen[seen.len() - 2..], ["Surfaced", "The end"]);
+    assert!(state.call_stack().is_empty());
+}
+
+#[test]
+fn test_rollback_restores_call_stack() {
+    let mut state = GameState::new(parse_scenario(CALL_SCENARIO).unwrap());
+
+    state.advance(); // into shop
+    state.advance(); // into greeting
+    assert_eq!(state.call_stack(), &[1, 4]);
+
+    state.advance(); // return through shop back to town
+    assert_eq!(text_of(&mut state), "Back in town");
+    assert!(state.call_stack().is_empty());
+
+    assert!(state.rollback());
+    assert_eq!(text_of(&mut state), "Hello!");
+    assert_eq!(state.call_stack(), &[1, 4]);
+}
+
+#[test]
+fn test_call_stack_survives_save_and_load() {
+    let scenario = parse_scenario(CALL_SCENARIO).unwrap();
+    let mut state = GameState::new(scenario.clone());
+    state.advance();
+
+    let save = state.to_save_data("test.yaml");
+    assert_eq!(save.call_stack, vec![1]);
+
+    let mut restored = GameState::from_save_data(&save, scenario);
+    assert_eq!(text_of(&mut restored), "Welcome to the shop");
+    restored.advance();
+    restored.advance();
+    assert_eq!(text_of(&mut restored), "Back in town");
+}
+
+#[test]
+fn test_return_without_call_continues() {
+    let yaml = r#"
+title: Stray Return
+
+script:
+  - text: "First"
+    return: true
+  - text: "Second"
+"#;
+    let mut state = GameState::new(parse_scenario(yaml).unwrap());
+    state.advance();
+
+    assert_eq!(text_of(&mut state), "Second");
+}
+
 #[test]
 fn test_rollback_when_empty_returns_false() {
     let mut state = create_minimal_state();
@@ -179,8 +327,8 @@ script:
         DisplayState::Choices { text, choices, .. } => {
             assert_eq!(text, "What do you want?");
             assert_eq!(choices.len(), 2);
-            assert_eq!(choices[0].label, "Option A");
-            assert_eq!(choices[1].label, "Option B");
+            assert_eq!(choices[0].choice.label, "Option A");
+            assert_eq!(choices[1].choice.label, "Option B");
         }
         _ => panic!("Expected DisplayState::Choices"),
     }
@@ -294,6 +442,178 @@ script:
     }
 }
 
+#[test]
+fn test_conditional_jump_with_expression() {
+    let yaml = r#"
+title: Expression Condition Test
+
+script:
+  - text: "Start"
+
+  - if:
+      expr: "affection_alice > affection_bob and met_alice"
+      jump: alice_route
+    text: "Bob route"
+
+  - label: alice_route
+    text: "Alice route"
+"#;
+    let scenario = parse_scenario(yaml).unwrap();
+    let mut state = GameState::new(scenario);
+
+    state.set_variable("affection_alice", Value::Int(5));
+    state.set_variable("affection_bob", Value::Int(3));
+    state.set_variable("met_alice", Value::Bool(true));
+    state.advance();
+
+    match state.display_state() {
+        DisplayState::Text { text, .. } => {
+            assert_eq!(text, "Alice route");
+        }
+        _ => panic!("Expected DisplayState::Text at alice_route"),
+    }
+}
+
+#[test]
+fn test_conditional_expression_error_does_not_jump() {
+    let yaml = r#"
+title: Expression Error Test
+
+script:
+  - text: "Start"
+
+  - if:
+      expr: "undefined_var >= 10"
+      jump: success
+    text: "Fell through"
+
+  - label: success
+    text: "Success!"
+"#;
+    let scenario = parse_scenario(yaml).unwrap();
+    let mut state = GameState::new(scenario);
+
+    state.advance();
+
+    match state.display_state() {
+        DisplayState::Text { text, .. } => {
+            assert_eq!(text, "Fell through");
+        }
+        _ => panic!("Expected DisplayState::Text"),
+    }
+}
+
+#[test]
+fn test_conditional_else_jump() {
+    let yaml = r#"
+title: Else Test
+
+script:
+  - set:
+      name: has_key
+      value: false
+
+  - if:
+      var: has_key
+      is: true
+      jump: open
+      else: locked
+
+  - text: "Fell through"
+
+  - label: open
+    text: "Open"
+
+  - label: locked
+    text: "Locked"
+"#;
+    let scenario = parse_scenario(yaml).unwrap();
+    let mut state = GameState::new(scenario);
+
+    match state.display_state() {
+        DisplayState::Text { text, .. } => {
+            assert_eq!(text, "Locked");
+        }
+        _ => panic!("Expected DisplayState::Text"),
+    }
+}
+
+const SWITCH_SCRIPT: &str = r#"
+  - switch:
+      var: affection
+      cases:
+        - is: 0
+          jump: bad
+        - min: 5
+          max: 9
+          jump: good
+        - min: 10
+          jump: best
+      default: normal
+
+  - label: bad
+    text: "Bad"
+  - label: good
+    text: "Good"
+  - label: best
+    text: "Best"
+  - label: normal
+    text: "Normal"
+"#;
+
+fn switch_result(affection: Option<i64>) -> String {
+    let set = affection
+        .map(|value| format!("  - set: {{ name: affection, value: {} }}\n", value))
+        .unwrap_or_default();
+    let yaml = format!("title: Switch Test\nscript:\n{}{}", set, SWITCH_SCRIPT);
+    let scenario = parse_scenario(&yaml).unwrap();
+    text_of(&mut GameState::new(scenario))
+}
+
+#[test]
+fn test_switch_selects_case() {
+    assert_eq!(switch_result(Some(0)), "Bad");
+    assert_eq!(switch_result(Some(5)), "Good");
+    assert_eq!(switch_result(Some(9)), "Good");
+    assert_eq!(switch_result(Some(42)), "Best");
+}
+
+#[test]
+fn test_switch_default() {
+    assert_eq!(switch_result(Some(3)), "Normal");
+    // An unset variable takes the default
+    assert_eq!(switch_result(None), "Normal");
+}
+
+#[test]
+fn test_switch_without_default_falls_through() {
+    let yaml = r#"
+title: Switch Fall-through
+
+script:
+  - set:
+      name: day
+      value: 3
+  - switch:
+      expr: "day % 7"
+      cases:
+        - is: 0
+          jump: sunday
+  - text: "Weekday"
+  - label: sunday
+    text: "Sunday"
+"#;
+    let scenario = parse_scenario(yaml).unwrap();
+    let mut state = GameState::new(scenario);
+
+    match state.display_state() {
+        DisplayState::Text { text, .. } => {
+            assert_eq!(text, "Weekday");
+        }
+        _ => panic!("Expected DisplayState::Text"),
+    }
+}
+
 #[test]
 fn test_set_variable_during_advance() {
     let yaml = r#"
@@ -316,6 +636,167 @@ script:
     assert!(value.is_some());
 }
 
+#[test]
+fn test_set_variable_with_op() {
+    let yaml = r#"
+title: Op Test
+
+script:
+  - set:
+      name: affection
+      value: 10
+  - set:
+      name: affection
+      op: add
+      value: 5
+  - set:
+      name: affection
+      op: mul
+      value: 10
+  - set:
+      name: affection
+      op: min
+      value: 100
+  - set:
+      name: greeting
+      value: "Hello"
+  - set:
+      name: greeting
+      op: concat
+      value: ", Alice"
+    text: "Done"
+"#;
+    let scenario = parse_scenario(yaml).unwrap();
+    let mut state = GameState::new(scenario);
+    let _ = state.display_state();
+
+    assert_eq!(state.variables().get("affection"), Some(&Value::Int(100)));
+    assert_eq!(
+        state.variables().get("greeting"),
+        Some(&Value::String("Hello, Alice".to_string()))
+    );
+}
+
+#[test]
+fn test_set_variable_with_expression() {
+    let yaml = r#"
+title: Expr Test
+
+script:
+  - set:
+      name: gold
+      value: 30
+  - set:
+      name: price
+      value: 50
+  - set:
+      name: gold
+      expr: "max(gold - price, 0)"
+    text: "Bought"
+"#;
+    let scenario = parse_scenario(yaml).unwrap();
+    let mut state = GameState::new(scenario);
+    let _ = state.display_state();
+
+    assert_eq!(state.variables().get("gold"), Some(&Value::Int(0)));
+}
+
+#[test]
+fn test_set_variable_type_mismatch_keeps_value() {
+    let yaml = r#"
+title: Mismatch Test
+
+script:
+  - set:
+      name: count
+      value: 1
+  - set:
+      name: count
+      op: add
+      value: "one"
+  - set:
+      name: count
+      expr: "\"text\""
+    text: "Done"
+"#;
+    let scenario = parse_scenario(yaml).unwrap();
+    let mut state = GameState::new(scenario);
+    let _ = state.display_state();
+
+    assert_eq!(state.variables().get("count"), Some(&Value::Int(1)));
+}
+
+#[test]
+fn test_list_push_remove_and_contains() {
+    let yaml = r#"
+title: List Test
+
+script:
+  - set:
+      name: visited
+      value: []
+  - set:
+      name: visited
+      op: push
+      value: "beach"
+  - set:
+      name: visited
+      op: push
+      value: "forest"
+  - set:
+      name: visited
+      op: remove
+      value: "beach"
+  - if:
+      expr: "contains(visited, 'forest') and not contains(visited, 'beach')"
+      jump: ok
+    text: "Wrong branch"
+  - label: ok
+    text: "Correct branch"
+"#;
+    let scenario = parse_scenario(yaml).unwrap();
+    let mut state = GameState::new(scenario);
+
+    assert_eq!(
+        state.variables().get("visited"),
+        Some(&Value::List(vec![Value::String("forest".to_string())]))
+    );
+    if let DisplayState::Text { text, .. } = state.display_state() {
+        assert_eq!(text, "Correct branch");
+    } else {
+        panic!("Expected text display");
+    }
+}
+
+#[test]
+fn test_list_remove_compares_numbers_like_contains() {
+    let yaml = r#"
+title: List Test
+
+script:
+  - set:
+      name: scores
+      value: [1.0, 2.5, 1]
+  - if:
+      expr: "contains(scores, 1)"
+      jump: remove
+    text: "Not found"
+  - label: remove
+    set:
+      name: scores
+      op: remove
+      value: 1
+    text: "Removed"
+"#;
+    let mut state = GameState::new(parse_scenario(yaml).unwrap());
+
+    assert_eq!(text_of(&mut state), "Removed");
+    assert_eq!(
+        state.variables().get("scores"),
+        Some(&Value::List(vec![Value::Float(2.5)]))
+    );
+}
+
 #[test]
 fn test_set_variable_via_api() {
     let mut state = create_minimal_state();
@@ -758,6 +1239,269 @@ script:
     }
 }
 
+const SHOP_SCENARIO: &str = r#"
+title: Shop
+
+script:
+  - label: shop
+    text: "What will you buy?"
+    timeout: 5.0
+    choices:
+      - label: "Sword"
+        jump: sword
+        if: "gold >= 10"
+        disabled_reason: "Not enough gold"
+        default: true
+      - label: "Secret"
+        jump: secret
+        if: "has_map"
+      - label: "Potion"
+        jump: potion
+        once: true
+      - label: "Leave"
+        jump: leave
+  - label: sword
+    text: "Sword"
+    jump: shop
+  - label: secret
+    text: "Secret"
+    jump: shop
+  - label: potion
+    text: "Potion"
+    jump: shop
+  - label: leave
+    text: "Bye"
+"#;
+
+/// Labels of the offered choices, with `!` marking disabled ones.
+fn offered_choices(state: &mut GameState) -> (Vec<String>, Option<usize>) {
+    match state.display_state() {
+        DisplayState::Choices {
+            choices,
+            default_choice,
+            ..
+        } => (
+            choices
+                .iter()
+                .map(|c| {
+                    let label = c.choice.label.as_plain().unwrap_or_default();
+                    if c.is_enabled() {
+                        label.to_string()
+                    } else {
+                        format!("!{}", label)
+                    }
+                })
+                .collect(),
+            default_choice,
+        ),
+        other => panic!("Expected DisplayState::Choices, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_conditional_choices_are_hidden_or_disabled() {
+    let mut state = GameState::new(parse_scenario(SHOP_SCENARIO).unwrap());
+    state.set_variable("gold", Value::Int(3));
+
+    let (labels, default_choice) = offered_choices(&mut state);
+    assert_eq!(labels, vec!["!Sword", "Potion", "Leave"]);
+    // The marked default is disabled, so the first enabled choice is used
+    assert_eq!(default_choice, Some(1));
+
+    // Disabled choices can't be selected
+    state.select_choice(0);
+    assert_eq!(state.current_index(), 0);
+    assert!(state.history().is_empty());
+
+    state.set_variable("gold", Value::Int(10));
+    state.set_variable("has_map", Value::Bool(true));
+    let (labels, default_choice) = offered_choices(&mut state);
+    assert_eq!(labels, vec!["Sword", "Secret", "Potion", "Leave"]);
+    assert_eq!(default_choice, Some(0));
+
+    // Indices refer to the offered choices
+    state.select_choice(1);
+    assert_eq!(text_of(&mut state), "Secret");
+}
+
+#[test]
+fn test_once_choice_disappears_after_pick() {
+    let mut state = GameState::new(parse_scenario(SHOP_SCENARIO).unwrap());
+
+    state.select_choice(1); // Potion
+    assert_eq!(text_of(&mut state), "Potion");
+    state.advance();
+
+    let (labels, _) = offered_choices(&mut state);
+    assert_eq!(labels, vec!["!Sword", "Leave"]);
+
+    // Rolling back before the pick brings it back
+    state.rollback();
+    state.rollback();
+    let (labels, _) = offered_choices(&mut state);
+    assert_eq!(labels, vec!["!Sword", "Potion", "Leave"]);
+}
+
+#[test]
+fn test_picked_choices_survive_save_and_load() {
+    let scenario = parse_scenario(SHOP_SCENARIO).unwrap();
+    let mut state = GameState::new(scenario.clone());
+    state.select_choice(1);
+    state.advance();
+
+    let save = state.to_save_data("test.yaml");
+    assert!(save.picked_choices.contains(&(0, 2)));
+
+    let mut restored = GameState::from_save_data(&save, scenario);
+    let (labels, _) = offered_choices(&mut restored);
+    assert_eq!(labels, vec!["!Sword", "Leave"]);
+}
+
+const CHOICE_SET_SCENARIO: &str = r#"
+title: Choice Sets
+
+script:
+  - set: { name: gold, value: 20 }
+    text: "Buy the sword?"
+    choices:
+      - label: "Buy"
+        jump: after
+        set:
+          - { name: gold, op: sub, value: 15 }
+          - { name: inventory, value: ["sword"] }
+      - label: "Buy with a bad coupon"
+        jump: after
+        set:
+          - { name: gold, op: sub, value: 5 }
+          - { name: gold, op: concat, value: "!" }
+      - label: "Leave"
+        jump: after
+  - label: after
+    text: "Done"
+"#;
+
+#[test]
+fn test_choice_set_applied_on_selection() {
+    let mut state = GameState::new(parse_scenario(CHOICE_SET_SCENARIO).unwrap());
+    state.select_choice(0);
+
+    assert_eq!(text_of(&mut state), "Done");
+    assert_eq!(state.variables().get("gold"), Some(&Value::Int(5)));
+    assert_eq!(
+        state.variables().get("inventory"),
+        Some(&Value::List(vec![Value::String("sword".to_string())]))
+    );
+}
+
+#[test]
+fn test_choice_set_is_all_or_nothing() {
+    let mut state = GameState::new(parse_scenario(CHOICE_SET_SCENARIO).unwrap());
+    // The second assignment fails, so the first is not applied either
+    state.select_choice(1);
+
+    assert_eq!(text_of(&mut state), "Done");
+    assert_eq!(state.variables().get("gold"), Some(&Value::Int(20)));
+}
+
+#[test]
+fn test_rollback_undoes_choice_set() {
+    let mut state = GameState::new(parse_scenario(CHOICE_SET_SCENARIO).unwrap());
+    state.select_choice(0);
+    assert_eq!(state.variables().get("gold"), Some(&Value::Int(5)));
+
+    assert!(state.rollback());
+    assert_eq!(state.variables().get("gold"), Some(&Value::Int(20)));
+    assert_eq!(state.variables().get("inventory"), None);
+
+    // Picking again applies the assignments once more
+    state.select_choice(0);
+    assert_eq!(state.variables().get("gold"), Some(&Value::Int(5)));
+}
+
+#[test]
+fn test_all_choices_hidden_shows_text() {
+    let yaml = r#"
+title: Test
+
+script:
+  - text: "Nothing to do"
+    choices:
+      - label: "Secret"
+        jump: secret
+        if: "has_map"
+  - text: "Next"
+  - label: secret
+    text: "Secret"
+"#;
+    let mut state = GameState::new(parse_scenario(yaml).unwrap());
+
+    assert_eq!(text_of(&mut state), "Nothing to do");
+    state.advance();
+    assert_eq!(text_of(&mut state), "Next");
+}
+
+#[test]
+fn test_all_choices_picked_without_text_shows_empty_text() {
+    let yaml = r#"
+title: Test
+
+script:
+  - label: menu
+    choices:
+      - label: "Look around"
+        jump: look
+        once: true
+  - text: "Next"
+  - label: look
+    text: "Nothing here"
+    jump: menu
+"#;
+    let mut state = GameState::new(parse_scenario(yaml).unwrap());
+
+    assert!(matches!(
+        state.display_state(),
+        DisplayState::Choices { .. }
+    ));
+    state.select_choice(0);
+    assert_eq!(text_of(&mut state), "Nothing here");
+    state.advance();
+
+    // The menu has nothing left to offer, but the story doesn't end there
+    assert_eq!(text_of(&mut state), "");
+    state.advance();
+    assert_eq!(text_of(&mut state), "Next");
+}
+
+#[test]
+fn test_all_choices_disabled_shows_text() {
+    let yaml = r#"
+title: Test
+
+script:
+  - text: "The doors are locked"
+    choices:
+      - label: "Red door"
+        jump: secret
+        if: "has_red_key"
+        disabled_reason: "Needs the red key"
+      - label: "Blue door"
+        jump: secret
+        if: "has_blue_key"
+        disabled_reason: "Needs the blue key"
+  - text: "Next"
+  - label: secret
+    text: "Secret"
+"#;
+    let mut state = GameState::new(parse_scenario(yaml).unwrap());
+
+    // Nothing could be clicked, so the story goes on instead of getting stuck
+    assert_eq!(text_of(&mut state), "The doors are locked");
+    state.select_choice(0);
+    assert_eq!(text_of(&mut state), "The doors are locked");
+    state.advance();
+    assert_eq!(text_of(&mut state), "Next");
+}
+
 #[test]
 fn test_transition_command() {
     let yaml = r#"
@@ -992,3 +1736,570 @@ script:
     assert_eq!(idle.duration, 2.0); // default
     assert_eq!(idle.intensity, 0.3); // default
 }
+
+const RANDOM_SCENARIO: &str = r#"
+title: Random
+
+script:
+  - text: "Start"
+  - random:
+      var: roll
+      min: 1
+      max: 1000000
+    text: "Rolled"
+  - random:
+      branches:
+        - jump: heads
+        - jump: tails
+  - label: heads
+    text: "Heads"
+  - label: tails
+    text: "Tails"
+"#;
+
+/// Advance from "Start" and return the roll and the branch taken.
+fn play_random(state: &mut GameState) -> (Value, String) {
+    state.advance();
+    assert_eq!(text_of(state), "Rolled");
+    let roll = state.variables().get("roll").unwrap().clone();
+    state.advance();
+    (roll, text_of(state))
+}
+
+#[test]
+fn test_random_is_deterministic_for_a_seed() {
+    let scenario = parse_scenario(RANDOM_SCENARIO).unwrap();
+    let first = play_random(&mut GameState::with_seed(scenario.clone(), 7));
+    let second = play_random(&mut GameState::with_seed(scenario.clone(), 7));
+
+    assert_eq!(first, second);
+    match first.0 {
+        Value::Int(n) => assert!((1..=1_000_000).contains(&n)),
+        other => panic!("Expected an int roll, got {:?}", other),
+    }
+    assert!(first.1 == "Heads" || first.1 == "Tails");
+    assert_ne!(
+        first.0,
+        play_random(&mut GameState::with_seed(scenario, 8)).0
+    );
+}
+
+#[test]
+fn test_rollback_replays_random_outcome() {
+    let scenario = parse_scenario(RANDOM_SCENARIO).unwrap();
+    let mut state = GameState::with_seed(scenario, 42);
+    let first = play_random(&mut state);
+
+    state.rollback();
+    state.rollback();
+    assert_eq!(text_of(&mut state), "Start");
+
+    assert_eq!(play_random(&mut state), first);
+}
+
+#[test]
+fn test_loading_save_replays_random_outcome() {
+    let scenario = parse_scenario(RANDOM_SCENARIO).unwrap();
+    let state = GameState::with_seed(scenario.clone(), 42);
+    let save = state.to_save_data("test.yaml");
+    assert!(save.rng.is_some());
+
+    let mut original = GameState::with_seed(scenario.clone(), 42);
+    let mut restored = GameState::from_save_data(&save, scenario);
+    assert_eq!(play_random(&mut restored), play_random(&mut original));
+}
+
+#[test]
+fn test_random_branch_skips_zero_weight() {
+    let yaml = r#"
+title: Weighted
+
+script:
+  - random:
+      branches:
+        - jump: never
+          weight: 0
+        - jump: always
+  - label: never
+    text: "Never"
+  - label: always
+    text: "Always"
+"#;
+    let scenario = parse_scenario(yaml).unwrap();
+    for seed in 0..20 {
+        let mut state = GameState::with_seed(scenario.clone(), seed);
+        assert_eq!(text_of(&mut state), "Always");
+    }
+}
+
+const PERSISTENT_SCENARIO: &str = r#"
+title: Persistent
+
+script:
+  - if:
+      expr: "persistent.runs >= 1"
+      jump: again
+  - text: "First time"
+    jump: finish
+  - label: again
+    text: "Welcome back"
+  - label: finish
+    set:
+      name: persistent.runs
+      op: add
+      value: 1
+    text: "End"
+"#;
+
+#[test]
+fn test_persistent_variables_carry_over_to_a_new_game() {
+    let scenario = parse_scenario(PERSISTENT_SCENARIO).unwrap();
+    let mut first = GameState::new(scenario.clone());
+    first.set_variable("persistent.runs", Value::Int(0));
+    assert_eq!(text_of(&mut first), "First time");
+    first.advance();
+    assert_eq!(text_of(&mut first), "End");
+    assert_eq!(first.persistent().get("runs"), Some(&Value::Int(1)));
+    assert!(first.variables().get("persistent.runs").is_none());
+
+    let mut second = GameState::with_persistent(scenario, first.persistent().clone());
+    assert_eq!(text_of(&mut second), "Welcome back");
+}
+
+#[test]
+fn test_rollback_keeps_persistent_variables() {
+    let scenario = parse_scenario(PERSISTENT_SCENARIO).unwrap();
+    let mut state = GameState::new(scenario);
+    state.set_variable("persistent.runs", Value::Int(0));
+    state.advance();
+    assert_eq!(text_of(&mut state), "End");
+
+    state.rollback();
+    assert_eq!(text_of(&mut state), "First time");
+    assert_eq!(state.persistent().get("runs"), Some(&Value::Int(1)));
+
+    // Playing the same command again doesn't count it twice
+    state.advance();
+    assert_eq!(text_of(&mut state), "End");
+    assert_eq!(state.persistent().get("runs"), Some(&Value::Int(1)));
+    state.rollback();
+    state.advance();
+    assert_eq!(state.persistent().get("runs"), Some(&Value::Int(1)));
+}
+
+const PERSISTENT_LOOP_SCENARIO: &str = r#"
+title: Persistent Loop
+
+script:
+  - text: "Start"
+  - label: again
+    set:
+      name: persistent.count
+      op: add
+      value: 1
+  - if:
+      expr: "persistent.count < 3"
+      jump: again
+  - text: "Done"
+"#;
+
+#[test]
+fn test_loop_over_a_persistent_variable_counts_every_pass() {
+    let mut state = GameState::new(parse_scenario(PERSISTENT_LOOP_SCENARIO).unwrap());
+    state.set_variable("persistent.count", Value::Int(0));
+    state.advance();
+    assert_eq!(text_of(&mut state), "Done");
+    assert_eq!(state.persistent().get("count"), Some(&Value::Int(3)));
+}
+
+#[test]
+fn test_rollback_over_a_persistent_loop_does_not_repeat_it() {
+    let mut state = GameState::new(parse_scenario(PERSISTENT_LOOP_SCENARIO).unwrap());
+    state.set_variable("persistent.count", Value::Int(0));
+    state.advance();
+    assert_eq!(text_of(&mut state), "Done");
+    assert_eq!(state.persistent().get("count"), Some(&Value::Int(3)));
+
+    state.rollback();
+    state.advance();
+    assert_eq!(text_of(&mut state), "Done");
+    assert_eq!(state.persistent().get("count"), Some(&Value::Int(3)));
+}
+
+#[test]
+fn test_rollback_to_a_different_choice_applies_its_persistent_set() {
+    let yaml = r#"
+title: Persistent Choices
+
+script:
+  - text: "Which?"
+    choices:
+      - label: "Small"
+        jump: done
+        set:
+          - name: persistent.score
+            op: add
+            value: 1
+      - label: "Big"
+        jump: done
+        set:
+          - name: persistent.score
+            op: add
+            value: 10
+  - label: done
+    text: "Done"
+"#;
+    let mut state = GameState::new(parse_scenario(yaml).unwrap());
+    state.set_variable("persistent.score", Value::Int(0));
+
+    state.select_choice(0);
+    assert_eq!(state.persistent().get("score"), Some(&Value::Int(1)));
+    state.rollback();
+    state.select_choice(0);
+    assert_eq!(state.persistent().get("score"), Some(&Value::Int(1)));
+
+    // Another choice is a new decision
+    state.rollback();
+    state.select_choice(1);
+    assert_eq!(state.persistent().get("score"), Some(&Value::Int(11)));
+}
+
+const CHARACTERS_SCENARIO: &str = r##"
+title: Characters
+
+characters:
+  alice:
+    name:
+      en: "Alice"
+      ja: "アリス"
+    color: "#ff8080"
+    sprite: "assets/alice.png"
+    expressions:
+      smile: "assets/alice_smile.png"
+    voice_prefix: "assets/voice/alice/"
+  bob:
+    sprite: "assets/bob.png"
+
+script:
+  - speaker: alice
+    character: alice
+    voice: "001.ogg"
+    text: "Hello."
+  - speaker: alice
+    expression: smile
+    text: "Nice to meet you."
+  - speaker: "Narrator"
+    characters:
+      - image: alice
+        expression: smile
+        pos: left
+      - image: bob
+        pos: right
+    text: "They met."
+  - speaker: bob
+    text: "Hi."
+"##;
+
+#[test]
+fn test_character_speaker_and_expression() {
+    let scenario = parse_scenario(CHARACTERS_SCENARIO).unwrap();
+    let mut state = GameState::new(scenario);
+
+    match state.display_state() {
+        DisplayState::Text {
+            speaker, visual, ..
+        } => {
+            let speaker = speaker.unwrap();
+            let translations = Translations::new();
+            assert_eq!(speaker.resolve("en", &translations), "Alice");
+            assert_eq!(speaker.resolve("ja", &translations), "アリス");
+            assert_eq!(visual.character.as_deref(), Some("assets/alice.png"));
+        }
+        other => panic!("Expected DisplayState::Text, got {:?}", other),
+    }
+    assert_eq!(state.current_speaker_color(), Some("#ff8080"));
+    assert_eq!(
+        state.current_voice().as_deref(),
+        Some("assets/voice/alice/001.ogg")
+    );
+
+    // An expression alone swaps the speaker's sprite
+    state.advance();
+    match state.display_state() {
+        DisplayState::Text { visual, .. } => {
+            assert_eq!(visual.character.as_deref(), Some("assets/alice_smile.png"));
+        }
+        other => panic!("Expected DisplayState::Text, got {:?}", other),
+    }
+
+    state.advance();
+    match state.display_state() {
+        DisplayState::Text {
+            speaker, visual, ..
+        } => {
+            assert_eq!(speaker.unwrap(), "Narrator");
+            let paths: Vec<_> = visual.characters.iter().map(|c| c.path.as_str()).collect();
+            assert_eq!(paths, ["assets/alice_smile.png", "assets/bob.png"]);
+        }
+        other => panic!("Expected DisplayState::Text, got {:?}", other),
+    }
+    assert_eq!(state.current_speaker_color(), None);
+
+    // A character without a name is shown by its id
+    state.advance();
+    match state.display_state() {
+        DisplayState::Text { speaker, .. } => assert_eq!(speaker.unwrap(), "bob"),
+        other => panic!("Expected DisplayState::Text, got {:?}", other),
+    }
+
+    let history = state.history();
+    assert_eq!(history[0].speaker.as_ref().unwrap(), "Alice");
+    assert_eq!(history[0].speaker_color.as_deref(), Some("#ff8080"));
+    assert_eq!(history[2].speaker.as_ref().unwrap(), "Narrator");
+}
+
+#[test]
+fn test_character_placement_and_z_order() {
+    let yaml = r#"
+title: Crowd
+
+script:
+  - characters:
+      - image: "a.png"
+        pos: left
+        z: 1
+      - image: "b.png"
+        x: 0.35
+        y: 0.9
+        scale: 0.6
+        flip: true
+      - image: "c.png"
+        pos: left
+        z: -1
+    text: "A crowd."
+"#;
+    let scenario = parse_scenario(yaml).unwrap();
+    let mut state = GameState::new(scenario);
+
+    match state.display_state() {
+        DisplayState::Text { visual, .. } => {
+            let [a, b, c] = &visual.characters[..] else {
+                panic!("Expected three characters");
+            };
+            assert_eq!(
+                (a.anchor_x(), a.anchor_y(), a.transform.scale),
+                (0.2, 1.0, 1.0)
+            );
+            assert_eq!(
+                (b.anchor_x(), b.anchor_y(), b.transform.scale),
+                (0.35, 0.9, 0.6)
+            );
+            assert!(b.flip && !a.flip);
+            // Two characters can share a preset
+            assert_eq!(c.anchor_x(), a.anchor_x());
+            assert_eq!(visual.character_draw_order(), [2, 1, 0]);
+        }
+        other => panic!("Expected DisplayState::Text, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_character_keys_follow_sprites() {
+    let yaml = r#"
+title: Crowd Changes
+
+script:
+  - characters:
+      - image: "alice.png"
+        exit: { type: fade, duration: 0.3 }
+      - image: "bob.png"
+    text: "Alice leaves."
+  - characters:
+      - image: "bob.png"
+    text: "Bob stays."
+  - characters:
+      - image: "carol.png"
+      - image: "bob.png"
+      - image: "carol.png"
+    text: "Two Carols."
+"#;
+    let scenario = parse_scenario(yaml).unwrap();
+    let mut state = GameState::new(scenario);
+    let keys = |state: &mut GameState| match state.display_state() {
+        DisplayState::Text { visual, .. } => visual
+            .character_keys()
+            .into_iter()
+            .map(|key| format!("{}#{}", key.path, key.occurrence))
+            .collect::<Vec<_>>(),
+        other => panic!("Expected DisplayState::Text, got {:?}", other),
+    };
+
+    assert_eq!(keys(&mut state), ["alice.png#0", "bob.png#0"]);
+    // Bob keeps his identity, not Alice's old place in the list
+    state.advance();
+    assert_eq!(keys(&mut state), ["bob.png#0"]);
+    // Reordered and repeated sprites are told apart
+    state.advance();
+    assert_eq!(
+        keys(&mut state),
+        ["carol.png#0", "bob.png#0", "carol.png#1"]
+    );
+}
+
+#[test]
+fn test_move_persists_and_survives_rollback_and_save() {
+    let yaml = r##"
+title: Move
+
+characters:
+  bob:
+    sprite: "bob.png"
+
+script:
+  - character: "alice.png"
+    text: "Alone."
+  - move:
+      - x: 0.3
+        opacity: 0.5
+        tint: "#ff8080"
+        duration: 1.0
+        easing: ease_in_out
+    text: "Step aside."
+  - text: "Still there."
+  - characters:
+      - image: "alice.png"
+        pos: left
+      - image: "bob.png"
+        pos: right
+    text: "Together."
+  - move:
+      - target: bob
+        scale: 1.2
+      - target: "0"
+        y: 0.9
+      - target: nobody
+        x: 0.1
+    text: "Closer."
+"##;
+    let scenario = parse_scenario(yaml).unwrap();
+    let mut state = GameState::new(scenario.clone());
+    assert!(state.current_moves().is_empty());
+
+    state.advance();
+    let moves = state.current_moves();
+    assert_eq!(moves.len(), 1);
+    assert_eq!(moves[0].slot, CharacterSlot::Single);
+    assert_eq!(moves[0].from.anchor_x(), 0.5);
+    assert_eq!(moves[0].to.anchor_x(), 0.3);
+    assert_eq!(moves[0].to.transform.opacity, 0.5);
+    assert_eq!(moves[0].duration, 1.0);
+
+    // The end result persists on later lines and in saves
+    state.advance();
+    assert!(state.current_moves().is_empty());
+    let save = state.to_save_data("test.yaml");
+    assert_eq!(save.visual.char_transform.x, Some(0.3));
+    assert_eq!(save.visual.char_transform.tint.as_deref(), Some("#ff8080"));
+    let restored = GameState::from_save_data(&save, scenario);
+    assert_eq!(
+        restored.to_save_data("test.yaml").visual.char_transform,
+        save.visual.char_transform
+    );
+
+    // Rolling back before the move puts the character back
+    state.rollback();
+    state.rollback();
+    assert!(
+        state
+            .to_save_data("test.yaml")
+            .visual
+            .char_transform
+            .is_default()
+    );
+
+    for _ in 0..4 {
+        state.advance();
+    }
+    let moves = state.current_moves();
+    assert_eq!(moves.len(), 2);
+    assert_eq!(moves[0].slot, CharacterSlot::Index(1));
+    assert_eq!(moves[0].to.transform.scale, 1.2);
+    assert_eq!(moves[1].slot, CharacterSlot::Index(0));
+    assert_eq!(moves[1].to.anchor_y(), 0.9);
+    // Replacing the single character resets its placement
+    let visual = state.to_save_data("test.yaml").visual;
+    assert!(visual.char_transform.is_default());
+    assert_eq!(visual.characters[1].transform.scale, 1.2);
+}
+
+#[test]
+fn test_image_layers() {
+    let yaml = r#"
+title: Layers
+
+script:
+  - background: "room.png"
+    layers:
+      - name: cg
+        image: "cg/kiss.png"
+        transition:
+          type: fade
+          duration: 1.0
+      - name: dust
+        image: "fx/dust.png"
+        z: -1
+    text: "Show."
+  - layers:
+      - name: cg
+        image: "cg/kiss_2.png"
+      - name: frame
+        image: "ui/frame.png"
+        z: 5
+    text: "Change."
+  - layers:
+      - name: cg
+        image: ""
+        transition:
+          type: fade
+    text: "Hide."
+"#;
+    let scenario = parse_scenario(yaml).unwrap();
+    let mut state = GameState::new(scenario.clone());
+
+    let changes = state.current_layer_changes();
+    assert_eq!(changes.len(), 2);
+    assert!(changes[0].from.is_none());
+    assert_eq!(changes[0].to.as_ref().unwrap().path, "cg/kiss.png");
+    assert_eq!(changes[0].transition.as_ref().unwrap().duration, 1.0);
+
+    state.advance();
+    let visual = state.to_save_data("test.yaml").visual;
+    let order: Vec<_> = visual
+        .layer_draw_order()
+        .iter()
+        .map(|layer| layer.path.as_str())
+        .collect();
+    assert_eq!(order, ["fx/dust.png", "cg/kiss_2.png", "ui/frame.png"]);
+    assert!(visual.layer("dust").unwrap().is_behind_characters());
+    let changes = state.current_layer_changes();
+    assert_eq!(changes[0].from.as_ref().unwrap().path, "cg/kiss.png");
+
+    // Layers persist in saves
+    let save = state.to_save_data("test.yaml");
+    let restored = GameState::from_save_data(&save, scenario);
+    assert_eq!(
+        restored.to_save_data("test.yaml").visual.layers,
+        visual.layers
+    );
+
+    state.advance();
+    let visual = state.to_save_data("test.yaml").visual;
+    assert!(visual.layer("cg").is_none());
+    assert_eq!(visual.layers.len(), 2);
+    let changes = state.current_layer_changes();
+    assert!(changes[0].from.is_some() && changes[0].to.is_none());
+
+    // Rolling back shows the layer again
+    state.rollback();
+    assert!(state.to_save_data("test.yaml").visual.layer("cg").is_some());
+}
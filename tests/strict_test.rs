@@ -0,0 +1,112 @@
+use ivy::scenario::{
+    ScenarioSpans, Severity, describe_command, load_scenario, parse_scenario, validate_scenario,
+    validate_scenario_strict,
+};
+
+const MAIN: &str = "tests/fixtures/strict/main.yaml";
+
+#[test]
+fn test_unknown_fields_are_errors_in_strict_mode() {
+    let scenario = load_scenario(MAIN).unwrap();
+
+    // Without strict mode the typos are silently ignored
+    assert!(validate_scenario(&scenario).is_valid());
+
+    let result = validate_scenario_strict(&scenario);
+    let errors: Vec<(String, String)> = result
+        .errors()
+        .map(|issue| {
+            let location = match issue.command_index {
+                Some(index) => describe_command(&scenario, index),
+                None => issue.file.clone().unwrap_or_default(),
+            };
+            (location, issue.message.clone())
+        })
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            (
+                "command 1 of main.yaml".to_string(),
+                "Unknown field 'charcter' (did you mean 'character'?)".to_string()
+            ),
+            (
+                "command 2 of main.yaml (command 1 of macro 'greet')".to_string(),
+                "Unknown field 'txet' (did you mean 'text'?)".to_string()
+            ),
+            (
+                "command 3 of main.yaml".to_string(),
+                "Unknown field 'defualt' in 'choices[0]' (did you mean 'default'?)".to_string()
+            ),
+            (
+                "part.yaml".to_string(),
+                "Unknown field 'charaters' (did you mean 'characters'?)".to_string()
+            ),
+            (
+                "command 1 of part.yaml".to_string(),
+                "Unknown field 'durration' in 'transition' (did you mean 'duration'?)".to_string()
+            ),
+        ]
+    );
+    assert!(
+        result
+            .issues
+            .iter()
+            .all(|issue| issue.severity == Severity::Error)
+    );
+}
+
+#[test]
+fn test_unknown_fields_are_located_at_their_key() {
+    let scenario = load_scenario(MAIN).unwrap();
+    let spans = ScenarioSpans::load(&scenario);
+    let located: Vec<String> = validate_scenario_strict(&scenario)
+        .errors()
+        .map(|issue| issue.locate(&scenario, &spans).unwrap().to_string())
+        .collect();
+    // The typo in the macro is in its definition, not at the call
+    assert_eq!(
+        located,
+        [
+            "main.yaml:16:5",
+            "main.yaml:11:9",
+            "main.yaml:22:9",
+            "part.yaml:1:1",
+            "part.yaml:10:7",
+        ]
+    );
+
+    // Labels are found where commands name them
+    let location = scenario.locate_label(&spans, 2, "part.yaml#intro").unwrap();
+    assert_eq!(location.to_string(), "main.yaml:21:9");
+    let location = scenario.locate_label(&spans, 3, "intro").unwrap();
+    assert_eq!(location.to_string(), "part.yaml:6:5");
+}
+
+#[test]
+fn test_unknown_fields_without_suggestion() {
+    let yaml = r#"
+title: No Suggestion
+script:
+  - text: "Hello"
+    music: "assets/bgm.ogg"
+"#;
+    let scenario = parse_scenario(yaml).unwrap();
+    let field = &scenario.unknown_fields[0];
+    assert_eq!(field.command_index, Some(0));
+    assert_eq!(field.key, "music");
+    assert_eq!(field.suggestion, None);
+    assert_eq!(field.to_string(), "Unknown field 'music'");
+}
+
+#[test]
+fn test_known_fields_pass_strict_mode() {
+    for path in [
+        "tests/fixtures/full_scenario.yaml",
+        "tests/fixtures/effects.yaml",
+        "tests/fixtures/includes/main.yaml",
+    ] {
+        let scenario = load_scenario(path).unwrap();
+        assert_eq!(scenario.unknown_fields, [], "{}", path);
+    }
+}
@@ -1,4 +1,6 @@
-use ivy::scenario::{Severity, detect_circular_paths, parse_scenario, validate_scenario};
+use ivy::scenario::{
+    ScenarioSpans, Severity, detect_circular_paths, parse_scenario, validate_scenario,
+};
 
 #[test]
 fn test_validate_valid_scenario() {
@@ -81,6 +83,311 @@ script:
     assert_eq!(result.errors().count(), 1);
 }
 
+#[test]
+fn test_validate_invalid_condition_expression() {
+    let yaml = r#"
+title: Invalid Expression
+
+script:
+  - label: start
+    text: "Hello"
+  - if:
+      expr: "affection = 3"
+      jump: start
+    text: "Test"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    assert!(!result.is_valid());
+    assert_eq!(result.errors().count(), 1);
+    let error = result.errors().next().unwrap();
+    assert_eq!(error.command_index, Some(1));
+    assert!(error.message.contains("column 11"));
+}
+
+#[test]
+fn test_validate_condition_missing_expression() {
+    let yaml = r#"
+title: Empty Condition
+
+script:
+  - label: start
+    if:
+      jump: start
+    text: "Test"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    assert!(!result.is_valid());
+    assert!(result.errors().any(|e| e.message.contains("expr")));
+}
+
+#[test]
+fn test_validate_invalid_set_expression() {
+    let yaml = r#"
+title: Bad Set
+
+script:
+  - set:
+      name: gold
+      expr: "gold -"
+    text: "Test"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    assert!(!result.is_valid());
+    assert!(
+        result
+            .errors()
+            .any(|e| e.command_index == Some(0) && e.message.contains("Invalid set for 'gold'"))
+    );
+}
+
+#[test]
+fn test_validate_choice_conditions() {
+    let yaml = r#"
+title: Choice Conditions
+
+script:
+  - text: "Pick"
+    choices:
+      - label: "Broken"
+        jump: end
+        if: "gold >="
+      - label: "Pointless"
+        jump: end
+        disabled_reason: "Never shown"
+      - label: "Fine"
+        jump: end
+        if: "gold >= 10"
+        once: true
+  - label: end
+    text: "End"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    assert!(
+        result
+            .errors()
+            .any(|e| e.message.contains("Invalid condition on choice 1"))
+    );
+    assert!(
+        result
+            .warnings()
+            .any(|w| w.message.contains("Choice 2 has a 'disabled_reason'"))
+    );
+    assert_eq!(result.errors().count(), 1);
+}
+
+#[test]
+fn test_validate_choice_set() {
+    let yaml = r#"
+title: Choice Set
+
+script:
+  - text: "Pick"
+    choices:
+      - label: "Pay"
+        jump: end
+        set:
+          - { name: gold, op: sub, expr: "price +" }
+  - label: end
+    text: "End"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    assert!(
+        result
+            .errors()
+            .any(|e| e.message.contains("Invalid set for 'gold' on choice 1"))
+    );
+}
+
+#[test]
+fn test_validate_branch_targets() {
+    let yaml = r#"
+title: Branches
+
+script:
+  - if:
+      var: met
+      is: true
+      jump: friend
+      else: nowhere
+  - switch:
+      var: day
+      cases:
+        - is: 1
+          jump: friend
+        - jump: missing_case
+      default: weekend
+    text: "Never shown"
+  - label: friend
+    text: "Friend"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    assert!(result.errors().any(|e| e.message.contains("'nowhere'")));
+    assert!(result.errors().any(|e| e.message.contains("'weekend'")));
+    assert!(
+        result
+            .errors()
+            .any(|e| e.message.contains("Invalid switch case 2"))
+    );
+    assert!(
+        result
+            .warnings()
+            .any(|w| w.message.contains("always jumps away") && w.command_index == Some(1))
+    );
+}
+
+#[test]
+fn test_validate_invalid_switch() {
+    let yaml = r#"
+title: Bad Switch
+
+script:
+  - switch:
+      cases: []
+  - text: "End"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    assert!(
+        result
+            .errors()
+            .any(|e| e.message.contains("Invalid switch:"))
+    );
+}
+
+#[test]
+fn test_validate_random() {
+    let yaml = r#"
+title: Random
+
+script:
+  - random:
+      var: roll
+      min: 6
+      max: 1
+  - random:
+      branches:
+        - jump: fight
+          weight: 0
+  - random:
+      branches:
+        - jump: fight
+        - jump: nowhere
+  - label: fight
+    text: "Fight"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    let invalid: Vec<Option<usize>> = result
+        .errors()
+        .filter(|e| e.message.contains("Invalid random"))
+        .map(|e| e.command_index)
+        .collect();
+    assert_eq!(invalid, vec![Some(0), Some(1)]);
+    assert!(result.errors().any(|e| e.message.contains("'nowhere'")));
+}
+
+#[test]
+fn test_validate_call_and_return() {
+    let yaml = r#"
+title: Calls
+
+script:
+  - label: start
+    text: "Hi"
+    call: shop
+  - text: "Bye"
+    jump: finish
+
+  - label: shop
+    if:
+      var: closed
+      is: true
+      jump: shop_closed
+    text: "Shop"
+  - return: true
+  - label: shop_closed
+    text: "Closed"
+    return: true
+
+  - label: finish
+    text: "End"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    assert!(result.is_valid(), "{:?}", result.issues);
+    assert!(
+        !result
+            .warnings()
+            .any(|w| w.message.contains("Unused label"))
+    );
+}
+
+#[test]
+fn test_validate_return_without_caller() {
+    let yaml = r#"
+title: Stray Return
+
+script:
+  - label: start
+    text: "Hi"
+  - return: true
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    assert!(
+        result
+            .errors()
+            .any(|e| e.command_index == Some(1) && e.message.contains("Return without a caller"))
+    );
+}
+
+#[test]
+fn test_validate_call_to_undefined_label() {
+    let yaml = r#"
+title: Bad Call
+
+script:
+  - label: start
+    text: "Hi"
+    call: nowhere
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    assert!(
+        result
+            .errors()
+            .any(|e| e.message.contains("Call to undefined label 'nowhere'"))
+    );
+}
+
 #[test]
 fn test_validate_unused_label_warning() {
     let yaml = r#"
@@ -250,6 +557,38 @@ script:
     assert!(cycle.contains(&"c".to_string()));
 }
 
+#[test]
+fn test_detect_circular_paths_through_branches() {
+    let yaml = r#"
+title: Branch Loop
+
+script:
+  - label: a
+    if:
+      var: done
+      is: true
+      jump: end
+      else: b
+  - label: b
+    switch:
+      var: n
+      cases:
+        - is: 1
+          jump: end
+      default: a
+  - label: end
+    text: "End"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let cycles = detect_circular_paths(&scenario);
+
+    assert!(!cycles.is_empty());
+    let cycle = &cycles[0];
+    assert!(cycle.contains(&"a".to_string()));
+    assert!(cycle.contains(&"b".to_string()));
+}
+
 #[test]
 fn test_detect_circular_paths_none() {
     let yaml = r#"
@@ -323,3 +662,235 @@ script:
     assert!(!errors.is_empty());
     assert!(!warnings.is_empty());
 }
+
+#[test]
+fn test_validate_text_tags() {
+    let yaml = r#"
+title: Tags
+
+script:
+  - text: "{b}Bold{/b}, {i}italic{/i}, {size:1.4}big{/size} and {wave}wavy{/wave}"
+  - text: "{shake}Unclosed"
+  - text: "{blink}Unknown{/blink}"
+  - text:
+      en: "Fine {if:flag}yes{else}no{/if}"
+      ja: "{b}閉じていない"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    assert!(result.is_valid());
+    let warnings: Vec<_> = result.warnings().collect();
+    assert!(!warnings.iter().any(|w| w.command_index == Some(0)));
+    assert!(
+        warnings
+            .iter()
+            .any(|w| w.command_index == Some(1) && w.message.contains("Unclosed '{shake}'"))
+    );
+    assert!(warnings.iter().any(|w| w.command_index == Some(2)
+        && w.message.contains("Unknown text tag '{blink}'")));
+    assert!(warnings.iter().any(|w| w.command_index == Some(3)
+        && w.message.contains("(ja)")
+        && w.message.contains("Unclosed '{b}'")));
+    assert!(!warnings.iter().any(|w| w.message.contains("(en)")));
+}
+
+#[test]
+fn test_validate_character_expressions() {
+    let yaml = r#"
+title: Characters
+
+characters:
+  alice:
+    sprite: "assets/alice.png"
+    expressions:
+      smile: "assets/alice_smile.png"
+  ghost:
+    name: "???"
+
+script:
+  - speaker: alice
+    expression: smile
+    text: "Fine"
+  - speaker: alice
+    expression: cry
+    text: "Unknown expression"
+  - speaker: "Narrator"
+    expression: smile
+    text: "Not a character"
+  - character: ghost
+    text: "No sprite"
+  - characters:
+      - image: "assets/bob.png"
+        expression: smile
+    text: "Path with an expression"
+  - characters:
+      - image: alice
+        scale: 0
+    text: "Invisible"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    assert!(result.is_valid());
+    let warnings: Vec<_> = result.warnings().collect();
+    assert!(!warnings.iter().any(|w| w.command_index == Some(0)));
+    assert!(warnings.iter().any(|w| {
+        w.command_index == Some(1)
+            && w.message
+                .contains("Character 'alice' has no expression 'cry'")
+    }));
+    assert!(
+        warnings
+            .iter()
+            .any(|w| w.command_index == Some(2) && w.message.contains("needs a character id"))
+    );
+    assert!(warnings.iter().any(|w| {
+        w.command_index == Some(3)
+            && w.message
+                .contains("Character 'ghost' has no default sprite")
+    }));
+    assert!(
+        warnings
+            .iter()
+            .any(|w| w.command_index == Some(4) && w.message.contains("'assets/bob.png'"))
+    );
+    assert!(warnings.iter().any(|w| {
+        w.command_index == Some(5)
+            && w.message
+                .contains("Scale of 'alice' must be a positive number")
+    }));
+}
+
+#[test]
+fn test_validate_move() {
+    let yaml = r#"
+title: Move
+
+script:
+  - character: "alice.png"
+    text: "Hello"
+  - move:
+      - x: 0.3
+        opacity: 0.5
+    text: "Fine"
+  - move:
+      - scale: 0
+        opacity: 2.0
+        duration: -1
+    text: "Broken"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    assert!(result.is_valid());
+    let warnings: Vec<_> = result.warnings().collect();
+    assert!(!warnings.iter().any(|w| w.command_index == Some(1)));
+    let broken: Vec<_> = warnings
+        .iter()
+        .filter(|w| w.command_index == Some(2))
+        .map(|w| w.message.as_str())
+        .collect();
+    assert_eq!(broken.len(), 3);
+    assert!(broken[0].contains("Move scale of 'character'"));
+    assert!(broken[1].contains("between 0.0 and 1.0"));
+    assert!(broken[2].contains("must not be negative"));
+}
+
+#[test]
+fn test_validate_move_target_never_shown() {
+    let yaml = r#"
+title: Move Targets
+
+characters:
+  alice:
+    sprite: "alice.png"
+
+script:
+  - characters:
+      - image: alice
+      - image: "bob.png"
+    text: "Hello"
+  - move:
+      - target: alice
+        x: 0.3
+      - target: "bob.png"
+        x: 0.7
+      - target: "1"
+        y: 0.9
+      - target: carol
+        x: 0.5
+    text: "Moving"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    let warnings: Vec<_> = result
+        .warnings()
+        .map(|w| (w.command_index, w.field.as_deref(), w.message.as_str()))
+        .collect();
+    assert_eq!(
+        warnings,
+        [(
+            Some(1),
+            Some("move[3].target"),
+            "Move target 'carol' is never shown"
+        )]
+    );
+}
+
+#[test]
+fn test_validate_layer_name() {
+    let yaml = r#"
+title: Layers
+
+script:
+  - layers:
+      - name: cg
+        image: "cg.png"
+      - name: ""
+        image: "rain.png"
+    text: "Hello"
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+
+    let warnings: Vec<_> = result.warnings().collect();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].message.contains("Layer name must not be empty"));
+}
+
+#[test]
+fn test_validate_issues_locate_their_field() {
+    let yaml = r#"title: Issue Positions
+script:
+  - text: "Pick"
+    choices: [{ label: A, jump: end }, { label: B, jump: nowhere, if: "x >" }]
+  - label: end
+    text: |
+      Multi-line
+      text
+    set: { name: x, expr: "1 +" }
+"#;
+
+    let scenario = parse_scenario(yaml).unwrap();
+    let result = validate_scenario(&scenario);
+    let spans = [ScenarioSpans::parse(yaml)];
+
+    let located: Vec<String> = result
+        .issues
+        .iter()
+        .map(|issue| issue.locate(&scenario, &spans).unwrap().to_string())
+        .collect();
+    // The undefined label at its `jump:`, the choice condition at its `if:`
+    // and the set after a multi-line value
+    assert_eq!(
+        located,
+        ["line 4, column 52", "line 4, column 67", "line 9, column 5"]
+    );
+}